@@ -1,6 +1,6 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use rug::{rand::RandState, Integer};
-use rug_gmpmee::fpowm::FPowmTable;
+use rug_gmpmee::fpowm::{CombTable, FPowmTable};
 
 fn bench_fpowns(c: &mut Criterion) {
     let mut group = c.benchmark_group("fpown");
@@ -11,11 +11,13 @@ fn bench_fpowns(c: &mut Criterion) {
     let base = Integer::from(Integer::random_bits(2048, &mut rand));
     let exp = Integer::from(Integer::random_bits(1024, &mut rand));
     let tab = FPowmTable::init_precomp(&base, &p, 16, 1024).unwrap();
+    let comb = CombTable::init_precomp(&base, &p, 16, 1024).unwrap();
 
     group.bench_function("rug", |b| {
         b.iter(|| Integer::from(base.pow_mod_ref(&exp, &p).unwrap()))
     });
     group.bench_function("gmpmee", |b| b.iter(|| tab.fpowm(&exp)));
+    group.bench_function("comb", |b| b.iter(|| comb.fpowm(&exp)));
 
     group.finish();
 }
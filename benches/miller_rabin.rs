@@ -0,0 +1,66 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rug::{integer::IsPrime, rand::RandState, Integer};
+use rug_gmpmee::{
+    miller_rabin::{miller_rabin, miller_rabin_safe, miller_rabin_safe_parallel},
+    standard_groups::StandardGroup,
+};
+
+const REPS: i32 = 40;
+
+fn random_prime(bits: u32, rand: &mut RandState) -> Integer {
+    let top = Integer::from(1) << (bits - 1);
+    (Integer::from(Integer::random_bits(bits - 1, rand)) + top).next_prime()
+}
+
+fn bench_primes(c: &mut Criterion) {
+    let mut rand = RandState::new();
+    for bits in [1024, 2048, 3072] {
+        let prime = random_prime(bits, &mut rand);
+        // Composite without small factors, which passes the trial division
+        let composite = random_prime(bits / 2, &mut rand) * random_prime(bits / 2, &mut rand);
+        for (kind, n) in [("prime", prime), ("composite", composite)] {
+            let mut group = c.benchmark_group(format!("miller_rabin_{kind}"));
+            group.bench_with_input(BenchmarkId::new("gmpmee", bits), &n, |b, n| {
+                b.iter(|| miller_rabin(n, REPS))
+            });
+            group.bench_with_input(BenchmarkId::new("rug", bits), &n, |b, n| {
+                b.iter(|| n.is_probably_prime(REPS as u32))
+            });
+            group.bench_with_input(BenchmarkId::new("rug_miller_rabin", bits), &n, |b, n| {
+                b.iter(|| rug_miller_rabin::is_prime(n, REPS as usize))
+            });
+            group.finish();
+        }
+    }
+}
+
+fn bench_safe_primes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("miller_rabin_safe");
+    for group_params in [StandardGroup::Modp2048, StandardGroup::Modp3072] {
+        let p = group_params.modulus();
+        let bits = p.significant_bits();
+        let q = group_params.order();
+        group.bench_with_input(BenchmarkId::new("gmpmee", bits), p, |b, p| {
+            b.iter(|| miller_rabin_safe(p, REPS))
+        });
+        group.bench_with_input(BenchmarkId::new("gmpmee_parallel", bits), p, |b, p| {
+            b.iter(|| miller_rabin_safe_parallel(p, REPS))
+        });
+        group.bench_with_input(BenchmarkId::new("rug", bits), p, |b, p| {
+            b.iter(|| {
+                p.is_probably_prime(REPS as u32) != IsPrime::No
+                    && q.is_probably_prime(REPS as u32) != IsPrime::No
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("rug_miller_rabin", bits), p, |b, p| {
+            b.iter(|| {
+                rug_miller_rabin::is_prime(p, REPS as usize)
+                    && rug_miller_rabin::is_prime(q, REPS as usize)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_primes, bench_safe_primes);
+criterion_main!(benches);
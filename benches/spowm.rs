@@ -23,10 +23,10 @@ fn bench_spowns(c: &mut Criterion) {
     let mut exponents = vec![];
     (0..len).for_each(|_| exponents.push(Integer::from(Integer::random_bits(3072, &mut rand))));
 
-    group.bench_with_input(BenchmarkId::new("rug", &len), &len, |b, _| {
+    group.bench_with_input(BenchmarkId::new("rug", len), &len, |b, _| {
         b.iter(|| rug_spown(&bases, &exponents, &p))
     });
-    group.bench_with_input(BenchmarkId::new("gmpmee", &len), &len, |b, _| {
+    group.bench_with_input(BenchmarkId::new("gmpmee", len), &len, |b, _| {
         b.iter(|| spowm(&bases, &exponents, &p).unwrap())
     });
 
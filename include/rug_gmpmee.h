@@ -0,0 +1,50 @@
+/*
+ * C API of rug-gmpmee (feature `capi`)
+ *
+ * The integers are passed as their absolute value in unsigned big endian, and the arrays of
+ * integers as the concatenation of integers of the same number of bytes. The results are written
+ * in a buffer of the caller, and their number of bytes in `out_len`. A buffer of the size of the
+ * modulus is always large enough.
+ */
+
+#ifndef RUG_GMPMEE_H
+#define RUG_GMPMEE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RUG_GMPMEE_OK 0
+#define RUG_GMPMEE_NULL_POINTER -1
+#define RUG_GMPMEE_INVALID_ARGUMENT -2
+#define RUG_GMPMEE_BUFFER_TOO_SMALL -3
+#define RUG_GMPMEE_INTERNAL_ERROR -4
+
+/* Opaque handle of a precomputed table */
+typedef struct FPowmTable rug_gmpmee_table;
+
+int32_t rug_gmpmee_spowm(const uint8_t *bases, size_t base_len, const uint8_t *exponents,
+                         size_t exponent_len, size_t count, const uint8_t *modulus,
+                         size_t modulus_len, uint8_t *out, size_t out_cap, size_t *out_len);
+
+int32_t rug_gmpmee_table_new(const uint8_t *base, size_t base_len, const uint8_t *modulus,
+                             size_t modulus_len, size_t block_width, size_t exponent_bitlen,
+                             rug_gmpmee_table **out_table);
+
+int32_t rug_gmpmee_table_from_bytes(const uint8_t *data, size_t len,
+                                    rug_gmpmee_table **out_table);
+
+int32_t rug_gmpmee_table_fpowm(const rug_gmpmee_table *table, const uint8_t *exponent,
+                               size_t exponent_len, uint8_t *out, size_t out_cap,
+                               size_t *out_len);
+
+void rug_gmpmee_table_free(rug_gmpmee_table *table);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RUG_GMPMEE_H */
@@ -0,0 +1,160 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module for the interoperability with [num-bigint](https://docs.rs/num-bigint) (only with the
+//! feature `num-bigint`)
+//!
+//! The rules of coherence do not allow to implement `From` between [Integer] and the types of
+//! num-bigint outside of the two crates, so that the conversions are given by the trait
+//! [ToInteger] and the functions [to_biguint] and [to_bigint]. The exponentiations can be
+//! calculated directly from [BigUint] with [spowm_biguint] and [fpowm_biguint].
+//! ```
+//! use num_bigint::BigUint;
+//! use rug_gmpmee::bigint::{ToInteger, spowm_biguint, to_biguint};
+//! let modulus = BigUint::from(1019u32);
+//! let bases = [BigUint::from(2u32), BigUint::from(3u32)];
+//! let exponents = [BigUint::from(10u32), BigUint::from(5u32)];
+//! let res = spowm_biguint(&bases, &exponents, &modulus).unwrap();
+//! assert_eq!(res, BigUint::from(1024u32 * 243 % 1019));
+//! assert_eq!(to_biguint(&res.to_integer()).unwrap(), res);
+//! ```
+
+use crate::{GmpMEEError, fpowm::FixedBaseExp, spown::spowm};
+use num_bigint::{BigInt, BigUint, Sign};
+use rug::{Integer, integer::Order};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BigIntError {
+    #[error("The negative integer {0} cannot be converted to BigUint")]
+    Negative(Integer),
+}
+
+/// Conversion of the integers of num-bigint to [Integer]
+pub trait ToInteger {
+    /// Convert to [Integer]
+    fn to_integer(&self) -> Integer;
+}
+
+impl ToInteger for BigUint {
+    fn to_integer(&self) -> Integer {
+        Integer::from_digits(&self.to_u64_digits(), Order::Lsf)
+    }
+}
+
+impl ToInteger for BigInt {
+    fn to_integer(&self) -> Integer {
+        let magnitude = self.magnitude().to_integer();
+        match self.sign() {
+            Sign::Minus => -magnitude,
+            Sign::NoSign | Sign::Plus => magnitude,
+        }
+    }
+}
+
+/// Convert the integer to [BigUint]
+///
+/// Return an error if the integer is negative.
+pub fn to_biguint(value: &Integer) -> Result<BigUint, GmpMEEError> {
+    if *value < 0 {
+        return Err(BigIntError::Negative(value.clone()).into());
+    }
+    Ok(BigUint::new(value.to_digits::<u32>(Order::Lsf)))
+}
+
+/// Convert the integer to [BigInt]
+pub fn to_bigint(value: &Integer) -> BigInt {
+    let sign = match *value < 0 {
+        true => Sign::Minus,
+        false => Sign::Plus,
+    };
+    // the digits ignore the sign, and the sign of zero is normalized by num-bigint
+    BigInt::from_biguint(sign, BigUint::new(value.to_digits::<u32>(Order::Lsf)))
+}
+
+/// Multi exponentiation of [BigUint] (see [spowm])
+pub fn spowm_biguint(
+    bases: &[BigUint],
+    exponents: &[BigUint],
+    modulus: &BigUint,
+) -> Result<BigUint, GmpMEEError> {
+    let bases = bases.iter().map(ToInteger::to_integer).collect::<Vec<_>>();
+    let exponents = exponents
+        .iter()
+        .map(ToInteger::to_integer)
+        .collect::<Vec<_>>();
+    to_biguint(&spowm(&bases, &exponents, &modulus.to_integer())?)
+}
+
+/// Fixed base exponentiation of a [BigUint] exponent with a precomputed table
+pub fn fpowm_biguint(table: &impl FixedBaseExp, exponent: &BigUint) -> BigUint {
+    // the result is reduced modulo the modulus, hence not negative
+    to_biguint(&table.fpowm(&exponent.to_integer())).expect("the result must not be negative")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fpowm::CombTable;
+
+    #[test]
+    fn test_conversions() {
+        let big: Integer = (Integer::from(1) << 300) + 12345;
+        for value in [
+            Integer::ZERO,
+            Integer::from(1),
+            Integer::from(u64::MAX),
+            big,
+        ] {
+            let biguint = to_biguint(&value).unwrap();
+            assert_eq!(biguint.to_string(), value.to_string());
+            assert_eq!(biguint.to_integer(), value);
+            let neg = Integer::from(-&value);
+            for v in [&value, &neg] {
+                let bigint = to_bigint(v);
+                assert_eq!(bigint.to_string(), v.to_string());
+                assert_eq!(bigint.to_integer(), *v);
+            }
+        }
+        assert_eq!(
+            to_biguint(&Integer::from(-3)),
+            Err(BigIntError::Negative(Integer::from(-3)).into())
+        );
+    }
+
+    #[test]
+    fn test_exponentiations() {
+        let modulus = BigUint::from(1019u32);
+        let bases = [2u32, 3, 5].map(BigUint::from);
+        let exponents = [100u32, 200, 300].map(BigUint::from);
+        let expected = bases
+            .iter()
+            .zip(exponents.iter())
+            .fold(BigUint::from(1u32), |acc, (b, e)| {
+                acc * b.modpow(e, &modulus) % &modulus
+            });
+        assert_eq!(
+            spowm_biguint(&bases, &exponents, &modulus).unwrap(),
+            expected
+        );
+        let table =
+            CombTable::init_precomp(&Integer::from(2), &Integer::from(1019), 4, 16).unwrap();
+        assert_eq!(
+            fpowm_biguint(&table, &exponents[0]),
+            bases[0].modpow(&exponents[0], &modulus)
+        );
+    }
+}
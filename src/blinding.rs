@@ -0,0 +1,181 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module for the additive blinding of secret exponents
+//!
+//! The exponent `x` of an element of order `q` is replaced by `x + r*q` for a random `r` of
+//! [BLINDING_BITS] bits. The result of the exponentiation is unchanged, but the bits of the
+//! exponent processed by the exponentiation change at each call, which mitigates the timing leakage
+//! of the exponentiations that are not constant-time (see `fpowm::ExponentMode` with the feature
+//! `constant-time`). With the feature `zeroize`, the blinded exponents are wiped from memory after
+//! the exponentiation.
+//! ```
+//! use rug::{Integer, rand::RandState};
+//! use rug_gmpmee::blinding::powm_blinded;
+//! let (p, q, g) = (Integer::from(23), Integer::from(11), Integer::from(4));
+//! let mut rand = RandState::new();
+//! let x = Integer::from(7);
+//! assert_eq!(powm_blinded(&g, &x, &q, &p, &mut rand).unwrap(), g.pow_mod(&x, &p).unwrap());
+//! ```
+
+use crate::GmpMEEError;
+use crate::fpowm::FPowmTable;
+use rug::{Integer, rand::RandState};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BlindingError {
+    #[error("The exponent must not be negative")]
+    NegativeExponent,
+    #[error("The exponent must be in [0, {order}[")]
+    ExponentOutOfRange { order: Integer },
+    #[error("The table covers exponents of {exponent_bitlen} bits instead of {required} bits")]
+    TableTooShort {
+        exponent_bitlen: usize,
+        required: usize,
+    },
+}
+
+/// Bit length of the random multiple of the order added to the exponents
+pub const BLINDING_BITS: u32 = 64;
+
+/// Blind the exponent with a random multiple of the order, i.e. return `exponent + r*order` for a
+/// random `r` of [BLINDING_BITS] bits drawn from `rand`
+pub fn blind_exponent(exponent: &Integer, order: &Integer, rand: &mut RandState<'_>) -> Integer {
+    let r = Integer::from(Integer::random_bits(BLINDING_BITS, rand));
+    r * order + exponent
+}
+
+/// Remove the blinding of the exponent, i.e. reduce it modulo the order
+pub fn unblind_exponent(blinded: &Integer, order: &Integer) -> Integer {
+    Integer::from(blinded % order)
+}
+
+/// Bit length of the blinded exponents of an exponent in `[0, order[`
+///
+/// A table used with [fpowm_blinded] must cover this bit length (see
+/// [FPowmTable::extend_bitlen]).
+pub fn blinded_bitlen(order: &Integer) -> usize {
+    (order.significant_bits() + BLINDING_BITS) as usize
+}
+
+/// Wipe the blinded exponent from memory (only with the feature `zeroize`)
+#[cfg_attr(not(feature = "zeroize"), allow(unused_variables))]
+fn wipe(blinded: &mut Integer) {
+    #[cfg(feature = "zeroize")]
+    crate::secret::zeroize_integer(blinded);
+}
+
+/// `base^exponent mod modulus` computed with the exponent blinded by the order of the base (see
+/// [blind_exponent])
+///
+/// The order must be the order of the base modulo `modulus`, otherwise the result is wrong.
+/// Return an error if the exponent is negative.
+pub fn powm_blinded(
+    base: &Integer,
+    exponent: &Integer,
+    order: &Integer,
+    modulus: &Integer,
+    rand: &mut RandState<'_>,
+) -> Result<Integer, GmpMEEError> {
+    if *exponent < 0 {
+        return Err(BlindingError::NegativeExponent.into());
+    }
+    let mut blinded = blind_exponent(exponent, order, rand);
+    let res = Integer::from(
+        base.pow_mod_ref(&blinded, modulus)
+            .expect("the blinded exponent is non-negative"),
+    );
+    wipe(&mut blinded);
+    Ok(res)
+}
+
+/// Fixed base exponentiation with the exponent blinded by the order of the base of the table
+/// (see [blind_exponent])
+///
+/// Return an error if the exponent is not in `[0, order[`, or if the table does not cover the
+/// exponents of [blinded_bitlen] bits (see [FPowmTable::extend_bitlen]).
+pub fn fpowm_blinded(
+    table: &FPowmTable,
+    exponent: &Integer,
+    order: &Integer,
+    rand: &mut RandState<'_>,
+) -> Result<Integer, GmpMEEError> {
+    if *exponent < 0 || exponent >= order {
+        return Err(BlindingError::ExponentOutOfRange {
+            order: order.clone(),
+        }
+        .into());
+    }
+    let required = blinded_bitlen(order);
+    if table.exponent_bitlen() < required {
+        return Err(BlindingError::TableTooShort {
+            exponent_bitlen: table.exponent_bitlen(),
+            required,
+        }
+        .into());
+    }
+    let mut blinded = blind_exponent(exponent, order, rand);
+    // The blinded exponent must not be kept in the cache of the results of the table
+    let res = table.fpowm_uncached(&blinded);
+    wipe(&mut blinded);
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::groups::GroupParams;
+
+    #[test]
+    fn test_blinding() {
+        let mut rand = RandState::new();
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        let (p, q, g) = (&group.p, &group.q, &group.g);
+        let mut table = group.table().unwrap();
+        let x = group.random_exponent(&mut rand);
+        assert_eq!(
+            fpowm_blinded(&table, &x, q, &mut rand),
+            Err(BlindingError::TableTooShort {
+                exponent_bitlen: table.exponent_bitlen(),
+                required: blinded_bitlen(q)
+            }
+            .into())
+        );
+        table.extend_bitlen(blinded_bitlen(q)).unwrap();
+        for _ in 0..10 {
+            let x = group.random_exponent(&mut rand);
+            let expected = g.clone().pow_mod(&x, p).unwrap();
+            let blinded = blind_exponent(&x, q, &mut rand);
+            assert!(blinded.significant_bits() as usize <= blinded_bitlen(q));
+            assert_ne!(blinded, blind_exponent(&x, q, &mut rand));
+            assert_eq!(unblind_exponent(&blinded, q), x);
+            assert_eq!(powm_blinded(g, &x, q, p, &mut rand).unwrap(), expected);
+            assert_eq!(fpowm_blinded(&table, &x, q, &mut rand).unwrap(), expected);
+        }
+        let minus_one = Integer::from(-1);
+        assert_eq!(
+            powm_blinded(g, &minus_one, q, p, &mut rand),
+            Err(BlindingError::NegativeExponent.into())
+        );
+        for x in [minus_one, q.clone()] {
+            assert_eq!(
+                fpowm_blinded(&table, &x, q, &mut rand),
+                Err(BlindingError::ExponentOutOfRange { order: q.clone() }.into())
+            );
+        }
+    }
+}
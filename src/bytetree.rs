@@ -0,0 +1,624 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module for the byte-tree format of Verificatum (see [ByteTree])
+//!
+//! A byte tree is either a leaf, encoded as the byte `1`, the number of bytes of the data as a
+//! 32-bit big-endian integer and the data, or a node, encoded as the byte `0`, the number of
+//! children as a 32-bit big-endian integer and the encodings of the children. The integers are
+//! leaves containing their big-endian two's complement representation, and the arrays of integers
+//! are nodes of such leaves.
+//!
+//! The files of large integer arrays are read and written as streams with [IntegerArrayReader]
+//! and [IntegerArrayWriter].
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::bytetree::ByteTree;
+//! let values = [1, 255, -2].map(Integer::from);
+//! let tree = ByteTree::from_integers(&values);
+//! assert_eq!(tree.to_hex(), "0000000003010000000101010000000200ff0100000001fe");
+//! assert_eq!(ByteTree::from_hex(&tree.to_hex()).unwrap().to_integers().unwrap(), values);
+//! ```
+
+use crate::GmpMEEError;
+use rug::{Integer, integer::Order};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ByteTreeError {
+    #[error("The byte tree is truncated")]
+    Truncated,
+    #[error("Unknown tag {0} (0 for a node and 1 for a leaf)")]
+    InvalidTag(u8),
+    #[error("{0} bytes follow the byte tree")]
+    TrailingBytes(usize),
+    #[error("The byte tree is nested deeper than {MAX_DEPTH} levels")]
+    TooDeep,
+    #[error("A leaf was expected")]
+    NotLeaf,
+    #[error("A node was expected")]
+    NotNode,
+    #[error("An empty leaf does not encode an integer")]
+    EmptyInteger,
+    #[error("The integer does not fit in {len} bytes")]
+    IntegerTooLong { len: usize },
+    #[error("The number of bytes or of children {0} does not fit in 32 bits")]
+    TooLong(usize),
+    #[error("Invalid hexadecimal string")]
+    InvalidHex,
+    #[error("All the integers of the array are already written")]
+    TooManyIntegers,
+    #[error("{0} integers of the array are not written")]
+    MissingIntegers(usize),
+    #[error("I/O error: {0}")]
+    Io(String),
+}
+
+/// Maximal depth of the byte trees that are decoded
+pub const MAX_DEPTH: usize = 256;
+
+const NODE: u8 = 0;
+const LEAF: u8 = 1;
+
+/// Byte tree of Verificatum
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ByteTree {
+    /// Leaf containing data
+    Leaf(Vec<u8>),
+    /// Node containing children
+    Node(Vec<ByteTree>),
+}
+
+/// Big-endian two's complement representation of the integer with the minimal number of bytes, as
+/// `BigInteger.toByteArray` of Java
+fn twos_complement(value: &Integer) -> Vec<u8> {
+    let negative = *value < 0;
+    // the complement of the bytes of -value-1 for a negative value
+    let magnitude = match negative {
+        true => Integer::from(-value) - 1u32,
+        false => value.clone(),
+    };
+    let mut bytes = magnitude.to_digits::<u8>(Order::Msf);
+    if bytes.first().is_none_or(|&b| b & 0x80 != 0) {
+        bytes.insert(0, 0);
+    }
+    if negative {
+        bytes.iter_mut().for_each(|b| *b = !*b);
+    }
+    bytes
+}
+
+fn length(len: usize) -> Result<[u8; 4], GmpMEEError> {
+    u32::try_from(len)
+        .map(u32::to_be_bytes)
+        .map_err(|_| ByteTreeError::TooLong(len).into())
+}
+
+impl ByteTree {
+    /// Leaf of the integer, in the minimal two's complement representation
+    pub fn from_integer(value: &Integer) -> Self {
+        Self::Leaf(twos_complement(value))
+    }
+
+    /// Leaf of the integer in a two's complement representation of exactly `len` bytes, as the
+    /// elements of a group of Verificatum whose length is given by the modulus
+    ///
+    /// Return an error if the integer does not fit in `len` bytes.
+    pub fn from_integer_with_len(value: &Integer, len: usize) -> Result<Self, GmpMEEError> {
+        let bytes = twos_complement(value);
+        if bytes.len() > len {
+            return Err(ByteTreeError::IntegerTooLong { len }.into());
+        }
+        let fill = match *value < 0 {
+            true => 0xff,
+            false => 0,
+        };
+        let mut res = vec![fill; len - bytes.len()];
+        res.extend(bytes);
+        Ok(Self::Leaf(res))
+    }
+
+    /// Node of the leaves of the integers (see [Self::from_integer])
+    pub fn from_integers(values: &[Integer]) -> Self {
+        Self::Node(values.iter().map(Self::from_integer).collect())
+    }
+
+    /// Integer of the leaf
+    ///
+    /// Return an error if the tree is not a leaf or if the leaf is empty.
+    pub fn to_integer(&self) -> Result<Integer, GmpMEEError> {
+        let Self::Leaf(bytes) = self else {
+            return Err(ByteTreeError::NotLeaf.into());
+        };
+        let first = *bytes.first().ok_or(ByteTreeError::EmptyInteger)?;
+        Ok(match first & 0x80 != 0 {
+            true => {
+                let complement = bytes.iter().map(|b| !b).collect::<Vec<_>>();
+                -Integer::from_digits(&complement, Order::Msf) - 1u32
+            }
+            false => Integer::from_digits(bytes, Order::Msf),
+        })
+    }
+
+    /// Integers of the leaves of the node
+    ///
+    /// Return an error if the tree is not a node of leaves encoding integers.
+    pub fn to_integers(&self) -> Result<Vec<Integer>, GmpMEEError> {
+        self.children()?.iter().map(Self::to_integer).collect()
+    }
+
+    /// Children of the node
+    ///
+    /// Return an error if the tree is not a node.
+    pub fn children(&self) -> Result<&[ByteTree], GmpMEEError> {
+        match self {
+            Self::Node(children) => Ok(children),
+            Self::Leaf(_) => Err(ByteTreeError::NotNode.into()),
+        }
+    }
+
+    /// Encode the byte tree
+    ///
+    /// Return an error if a leaf or a node is longer than `u32::MAX`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GmpMEEError> {
+        let mut res = Vec::new();
+        self.write_bytes(&mut res)?;
+        Ok(res)
+    }
+
+    fn write_bytes(&self, out: &mut Vec<u8>) -> Result<(), GmpMEEError> {
+        match self {
+            Self::Leaf(data) => {
+                out.push(LEAF);
+                out.extend(length(data.len())?);
+                out.extend_from_slice(data);
+            }
+            Self::Node(children) => {
+                out.push(NODE);
+                out.extend(length(children.len())?);
+                for child in children {
+                    child.write_bytes(out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Decode the byte tree, which must use all the bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GmpMEEError> {
+        let (tree, rest) = Self::parse(bytes, 0)?;
+        match rest.len() {
+            0 => Ok(tree),
+            len => Err(ByteTreeError::TrailingBytes(len).into()),
+        }
+    }
+
+    /// Decode the byte tree at the start of the bytes, returning the remaining bytes
+    fn parse(bytes: &[u8], depth: usize) -> Result<(Self, &[u8]), GmpMEEError> {
+        if depth > MAX_DEPTH {
+            return Err(ByteTreeError::TooDeep.into());
+        }
+        let (&tag, rest) = bytes.split_first().ok_or(ByteTreeError::Truncated)?;
+        let (len, mut rest) = rest
+            .split_first_chunk::<4>()
+            .ok_or(ByteTreeError::Truncated)?;
+        let len = u32::from_be_bytes(*len) as usize;
+        match tag {
+            LEAF => {
+                let (data, rest) = rest.split_at_checked(len).ok_or(ByteTreeError::Truncated)?;
+                Ok((Self::Leaf(data.to_vec()), rest))
+            }
+            NODE => {
+                // each child needs at least 5 bytes, which bounds the allocation
+                let mut children = Vec::with_capacity(len.min(rest.len() / 5));
+                for _ in 0..len {
+                    let (child, next) = Self::parse(rest, depth + 1)?;
+                    children.push(child);
+                    rest = next;
+                }
+                Ok((Self::Node(children), rest))
+            }
+            tag => Err(ByteTreeError::InvalidTag(tag).into()),
+        }
+    }
+
+    /// Hexadecimal string of the encoding (see [Self::to_bytes]), as used by Verificatum in the
+    /// descriptions of the groups
+    ///
+    /// Panics if a leaf or a node is longer than `u32::MAX`.
+    pub fn to_hex(&self) -> String {
+        self.to_bytes()
+            .expect("the leaves and the nodes must be shorter than u32::MAX")
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// Decode the byte tree from its hexadecimal string (see [Self::to_hex])
+    pub fn from_hex(hex: &str) -> Result<Self, GmpMEEError> {
+        if !hex.len().is_multiple_of(2) {
+            return Err(ByteTreeError::InvalidHex.into());
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|b| u8::from_str_radix(b, 16).ok())
+                    .ok_or(ByteTreeError::InvalidHex)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+fn read_error(e: io::Error) -> GmpMEEError {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => ByteTreeError::Truncated.into(),
+        _ => ByteTreeError::Io(e.to_string()).into(),
+    }
+}
+
+fn write_error(e: io::Error) -> GmpMEEError {
+    ByteTreeError::Io(e.to_string()).into()
+}
+
+/// Read the tag and the length of a byte tree
+fn read_header(reader: &mut impl Read) -> Result<(u8, usize), GmpMEEError> {
+    let mut header = [0u8; 5];
+    reader.read_exact(&mut header).map_err(read_error)?;
+    let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    Ok((header[0], len))
+}
+
+/// Streaming reader of an array of integers stored as a byte tree (see [ByteTree::from_integers]),
+/// as the files of large integer arrays of Verificatum
+///
+/// The integers are decoded one by one while iterating, so that the whole array never needs to
+/// be in memory.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::bytetree::{ByteTree, IntegerArrayReader};
+/// let values = [3, -5, 1 << 20].map(Integer::from);
+/// let bytes = ByteTree::from_integers(&values).to_bytes().unwrap();
+/// let reader = IntegerArrayReader::new(bytes.as_slice()).unwrap();
+/// assert_eq!(reader.len(), 3);
+/// assert_eq!(reader.collect::<Result<Vec<_>, _>>().unwrap(), values);
+/// ```
+#[derive(Debug)]
+pub struct IntegerArrayReader<R> {
+    reader: R,
+    len: usize,
+    remaining: usize,
+}
+
+impl<R: Read> IntegerArrayReader<R> {
+    /// Read the header of the array
+    ///
+    /// Return an error if the byte tree is not a node.
+    pub fn new(mut reader: R) -> Result<Self, GmpMEEError> {
+        let (tag, len) = read_header(&mut reader)?;
+        match tag {
+            NODE => Ok(Self {
+                reader,
+                len,
+                remaining: len,
+            }),
+            LEAF => Err(ByteTreeError::NotNode.into()),
+            tag => Err(ByteTreeError::InvalidTag(tag).into()),
+        }
+    }
+
+    /// Number of integers in the array
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the array empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the underlying reader, positioned after the integers already read
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_integer(&mut self) -> Result<Integer, GmpMEEError> {
+        let (tag, len) = read_header(&mut self.reader)?;
+        match tag {
+            LEAF => {
+                // no allocation from the length read, which cannot be trusted
+                let mut data = Vec::new();
+                let read = (&mut self.reader)
+                    .take(len as u64)
+                    .read_to_end(&mut data)
+                    .map_err(read_error)?;
+                if read < len {
+                    return Err(ByteTreeError::Truncated.into());
+                }
+                ByteTree::Leaf(data).to_integer()
+            }
+            NODE => Err(ByteTreeError::NotLeaf.into()),
+            tag => Err(ByteTreeError::InvalidTag(tag).into()),
+        }
+    }
+}
+
+impl IntegerArrayReader<BufReader<File>> {
+    /// Open the file of the array
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, GmpMEEError> {
+        Self::new(BufReader::new(File::open(path).map_err(read_error)?))
+    }
+}
+
+impl<R: Read> Iterator for IntegerArrayReader<R> {
+    type Item = Result<Integer, GmpMEEError>;
+
+    /// Next integer of the array
+    ///
+    /// The iteration stops after the first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let res = self.read_integer();
+        self.remaining = match res {
+            Ok(_) => self.remaining - 1,
+            Err(_) => 0,
+        };
+        Some(res)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+/// Streaming writer of an array of integers as a byte tree (see [IntegerArrayReader])
+///
+/// The number of integers is written in the header, so it must be known in advance.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::bytetree::{ByteTree, IntegerArrayWriter};
+/// let values = [3, -5, 1 << 20].map(Integer::from);
+/// let mut writer = IntegerArrayWriter::new(Vec::new(), values.len()).unwrap();
+/// for v in values.iter() {
+///     writer.write(v).unwrap();
+/// }
+/// let bytes = writer.finish().unwrap();
+/// assert_eq!(bytes, ByteTree::from_integers(&values).to_bytes().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct IntegerArrayWriter<W: Write> {
+    writer: W,
+    remaining: usize,
+    element_len: Option<usize>,
+}
+
+impl<W: Write> IntegerArrayWriter<W> {
+    /// Write the header of an array of `len` integers
+    pub fn new(mut writer: W, len: usize) -> Result<Self, GmpMEEError> {
+        writer.write_all(&[NODE]).map_err(write_error)?;
+        writer.write_all(&length(len)?).map_err(write_error)?;
+        Ok(Self {
+            writer,
+            remaining: len,
+            element_len: None,
+        })
+    }
+
+    /// Write the integers with exactly `len` bytes (see [ByteTree::from_integer_with_len]), as
+    /// the elements of a group of Verificatum
+    pub fn with_element_len(mut self, len: usize) -> Self {
+        self.element_len = Some(len);
+        self
+    }
+
+    /// Write the next integer
+    ///
+    /// Return an error if all the integers of the array are already written.
+    pub fn write(&mut self, value: &Integer) -> Result<(), GmpMEEError> {
+        if self.remaining == 0 {
+            return Err(ByteTreeError::TooManyIntegers.into());
+        }
+        let leaf = match self.element_len {
+            Some(len) => ByteTree::from_integer_with_len(value, len)?,
+            None => ByteTree::from_integer(value),
+        };
+        self.writer
+            .write_all(&leaf.to_bytes()?)
+            .map_err(write_error)?;
+        self.remaining -= 1;
+        Ok(())
+    }
+
+    /// Flush the writer and return it
+    ///
+    /// Return an error if some integers of the array are not written.
+    pub fn finish(mut self) -> Result<W, GmpMEEError> {
+        if self.remaining > 0 {
+            return Err(ByteTreeError::MissingIntegers(self.remaining).into());
+        }
+        self.writer.flush().map_err(write_error)?;
+        Ok(self.writer)
+    }
+}
+
+impl IntegerArrayWriter<BufWriter<File>> {
+    /// Create the file of an array of `len` integers
+    pub fn create(path: impl AsRef<Path>, len: usize) -> Result<Self, GmpMEEError> {
+        Self::new(
+            BufWriter::new(File::create(path).map_err(write_error)?),
+            len,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_integers() {
+        for (value, hex) in [
+            (0, "00"),
+            (1, "01"),
+            (127, "7f"),
+            (128, "0080"),
+            (255, "00ff"),
+            (256, "0100"),
+            (-1, "ff"),
+            (-128, "80"),
+            (-129, "ff7f"),
+            (-256, "ff00"),
+        ] {
+            let value = Integer::from(value);
+            let tree = ByteTree::from_integer(&value);
+            assert_eq!(tree.to_hex(), format!("01{:08x}{hex}", hex.len() / 2));
+            assert_eq!(tree.to_integer().unwrap(), value);
+            let fixed = ByteTree::from_integer_with_len(&value, 4).unwrap();
+            assert_eq!(fixed.to_integer().unwrap(), value);
+            assert_eq!(fixed.to_bytes().unwrap().len(), 9);
+        }
+        let big: Integer = (Integer::from(1) << 1000) - 12345;
+        for value in [big.clone(), -big] {
+            let tree = ByteTree::from_integer(&value);
+            assert_eq!(
+                ByteTree::from_bytes(&tree.to_bytes().unwrap()).unwrap(),
+                tree
+            );
+            assert_eq!(tree.to_integer().unwrap(), value);
+        }
+        assert_eq!(
+            ByteTree::from_integer_with_len(&Integer::from(128), 1),
+            Err(ByteTreeError::IntegerTooLong { len: 1 }.into())
+        );
+        assert_eq!(
+            ByteTree::Leaf(vec![]).to_integer(),
+            Err(ByteTreeError::EmptyInteger.into())
+        );
+        assert_eq!(
+            ByteTree::Node(vec![]).to_integer(),
+            Err(ByteTreeError::NotLeaf.into())
+        );
+    }
+
+    #[test]
+    fn test_nested() {
+        let tree = ByteTree::Node(vec![
+            ByteTree::from_integers(&[Integer::from(23), Integer::from(11)]),
+            ByteTree::Node(vec![]),
+            ByteTree::Leaf(b"ModPGroup".to_vec()),
+        ]);
+        let bytes = tree.to_bytes().unwrap();
+        assert_eq!(ByteTree::from_bytes(&bytes).unwrap(), tree);
+        assert_eq!(ByteTree::from_hex(&tree.to_hex()).unwrap(), tree);
+        assert_eq!(
+            tree.children().unwrap()[0].to_integers().unwrap(),
+            [23, 11].map(Integer::from)
+        );
+        assert!(tree.to_integers().is_err());
+        for len in 0..bytes.len() {
+            assert_eq!(
+                ByteTree::from_bytes(&bytes[..len]),
+                Err(ByteTreeError::Truncated.into())
+            );
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            ByteTree::from_bytes(&trailing),
+            Err(ByteTreeError::TrailingBytes(1).into())
+        );
+        assert_eq!(
+            ByteTree::from_bytes(&[2, 0, 0, 0, 0]),
+            Err(ByteTreeError::InvalidTag(2).into())
+        );
+        let deep = [0u8, 0, 0, 0, 1].repeat(MAX_DEPTH + 2);
+        assert_eq!(
+            ByteTree::from_bytes(&deep),
+            Err(ByteTreeError::TooDeep.into())
+        );
+        assert_eq!(
+            ByteTree::from_hex("0"),
+            Err(ByteTreeError::InvalidHex.into())
+        );
+        assert_eq!(
+            ByteTree::from_hex("0g"),
+            Err(ByteTreeError::InvalidHex.into())
+        );
+    }
+
+    #[test]
+    fn test_array_stream() {
+        let values = (0..50)
+            .map(|i| (Integer::from(i) << (i * 7)) - 1000)
+            .collect::<Vec<_>>();
+        let mut writer = IntegerArrayWriter::new(Vec::new(), values.len()).unwrap();
+        for v in values.iter() {
+            writer.write(v).unwrap();
+        }
+        assert_eq!(
+            writer.write(&values[0]),
+            Err(ByteTreeError::TooManyIntegers.into())
+        );
+        let bytes = writer.finish().unwrap();
+        assert_eq!(bytes, ByteTree::from_integers(&values).to_bytes().unwrap());
+        let reader = IntegerArrayReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.len(), values.len());
+        assert_eq!(reader.collect::<Result<Vec<_>, _>>().unwrap(), values);
+
+        let writer = IntegerArrayWriter::new(Vec::new(), 2).unwrap();
+        assert_eq!(
+            writer.finish().err(),
+            Some(ByteTreeError::MissingIntegers(2).into())
+        );
+        let res = IntegerArrayReader::new(&bytes[..bytes.len() - 1])
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(res.len(), values.len());
+        assert_eq!(res.last(), Some(&Err(ByteTreeError::Truncated.into())));
+        assert!(IntegerArrayReader::new(&[1u8, 0, 0, 0, 0][..]).is_err());
+    }
+
+    #[test]
+    fn test_array_file() {
+        let path = std::env::temp_dir().join(format!("rug-gmpmee-array-{}", std::process::id()));
+        let p = Integer::from(1019);
+        let values = (1..20)
+            .map(|i| Integer::from(i * 53) % &p)
+            .collect::<Vec<_>>();
+        let mut writer = IntegerArrayWriter::create(&path, values.len())
+            .unwrap()
+            .with_element_len(2);
+        for v in values.iter() {
+            writer.write(v).unwrap();
+        }
+        writer.finish().unwrap();
+        let reader = IntegerArrayReader::open(&path).unwrap();
+        assert_eq!(reader.collect::<Result<Vec<_>, _>>().unwrap(), values);
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            5 + 7 * values.len() as u64
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,334 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! C API of the multi-exponentiation and of the fixed base exponentiation (only with the feature
+//! `capi`)
+//!
+//! The functions are declared in `include/rug_gmpmee.h`. The library for C is built with
+//! `cargo rustc --release --features capi --crate-type cdylib` (or `staticlib`).
+//!
+//! The integers are passed as their absolute value in unsigned big endian, and the arrays of
+//! integers as the concatenation of integers of the same number of bytes. The results are written
+//! in a buffer of the caller: the number of bytes of the result is always written in `out_len`,
+//! and [CAPI_BUFFER_TOO_SMALL] is returned if it exceeds the capacity of the buffer. A buffer of
+//! the size of the modulus is always large enough.
+//!
+//! The tables are opaque handles created by [rug_gmpmee_table_new] or
+//! [rug_gmpmee_table_from_bytes] and released by [rug_gmpmee_table_free].
+
+use crate::{fpowm::FPowmTable, spown::spowm};
+use rug::{Integer, integer::Order};
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind},
+    slice,
+};
+
+/// The call succeeded
+pub const CAPI_OK: i32 = 0;
+/// A pointer is null
+pub const CAPI_NULL_POINTER: i32 = -1;
+/// The arguments are rejected by the crate (e.g. the modulus is even or zero)
+pub const CAPI_INVALID_ARGUMENT: i32 = -2;
+/// The result is larger than the buffer, whose required size is written in `out_len`
+pub const CAPI_BUFFER_TOO_SMALL: i32 = -3;
+/// An unexpected error happened in the call
+pub const CAPI_INTERNAL_ERROR: i32 = -4;
+
+/// Run the call, converting a panic to [CAPI_INTERNAL_ERROR] since it cannot cross the C ABI
+fn guard(f: impl FnOnce() -> Result<(), i32>) -> i32 {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => CAPI_OK,
+        Ok(Err(code)) => code,
+        Err(_) => CAPI_INTERNAL_ERROR,
+    }
+}
+
+/// Bytes of the pointer, which can be null for an empty slice
+///
+/// # Safety
+/// A non null pointer must be valid for `len` bytes.
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> Result<&'a [u8], i32> {
+    match (ptr.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(CAPI_NULL_POINTER),
+        (false, _) => Ok(unsafe { slice::from_raw_parts(ptr, len) }),
+    }
+}
+
+/// Integer of the bytes of the pointer
+///
+/// # Safety
+/// See [bytes].
+unsafe fn integer(ptr: *const u8, len: usize) -> Result<Integer, i32> {
+    Ok(Integer::from_digits(
+        unsafe { bytes(ptr, len) }?,
+        Order::Msf,
+    ))
+}
+
+/// Integers of the concatenation of `count` integers of `len` bytes
+///
+/// # Safety
+/// See [bytes], with `count * len` bytes.
+unsafe fn integers(ptr: *const u8, len: usize, count: usize) -> Result<Vec<Integer>, i32> {
+    let total = len.checked_mul(count).ok_or(CAPI_INVALID_ARGUMENT)?;
+    let data = unsafe { bytes(ptr, total) }?;
+    Ok(match len {
+        0 => vec![Integer::ZERO; count],
+        _ => data
+            .chunks(len)
+            .map(|c| Integer::from_digits(c, Order::Msf))
+            .collect(),
+    })
+}
+
+/// Write the integer in the buffer of the caller
+///
+/// # Safety
+/// `out` must be valid for `out_cap` bytes and `out_len` must be valid.
+unsafe fn write_integer(
+    value: &Integer,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> Result<(), i32> {
+    if out_len.is_null() || (out.is_null() && out_cap > 0) {
+        return Err(CAPI_NULL_POINTER);
+    }
+    let digits = value.to_digits::<u8>(Order::Msf);
+    unsafe { *out_len = digits.len() };
+    if digits.len() > out_cap {
+        return Err(CAPI_BUFFER_TOO_SMALL);
+    }
+    if !digits.is_empty() {
+        unsafe { slice::from_raw_parts_mut(out, digits.len()) }.copy_from_slice(&digits);
+    }
+    Ok(())
+}
+
+/// Multi-exponentiation `prod bases[i]^exponents[i] mod modulus` of `count` bases of `base_len`
+/// bytes and `count` exponents of `exponent_len` bytes (see [spowm])
+///
+/// # Safety
+/// The pointers must be valid for the given numbers of bytes, and `out_len` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rug_gmpmee_spowm(
+    bases: *const u8,
+    base_len: usize,
+    exponents: *const u8,
+    exponent_len: usize,
+    count: usize,
+    modulus: *const u8,
+    modulus_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> i32 {
+    guard(|| {
+        let bases = unsafe { integers(bases, base_len, count) }?;
+        let exponents = unsafe { integers(exponents, exponent_len, count) }?;
+        let modulus = unsafe { integer(modulus, modulus_len) }?;
+        if count == 0 || modulus == 0 {
+            return Err(CAPI_INVALID_ARGUMENT);
+        }
+        let res = spowm(&bases, &exponents, &modulus).map_err(|_| CAPI_INVALID_ARGUMENT)?;
+        unsafe { write_integer(&res, out, out_cap, out_len) }
+    })
+}
+
+/// Store the table in a new handle
+///
+/// # Safety
+/// `out_table` must be valid.
+unsafe fn write_table(table: FPowmTable, out_table: *mut *mut FPowmTable) -> Result<(), i32> {
+    if out_table.is_null() {
+        return Err(CAPI_NULL_POINTER);
+    }
+    unsafe { *out_table = Box::into_raw(Box::new(table)) };
+    Ok(())
+}
+
+/// Precompute the table of the base modulo the modulus (see [FPowmTable::init_precomp]) and
+/// write its handle in `out_table`
+///
+/// # Safety
+/// The pointers must be valid for the given numbers of bytes, and `out_table` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rug_gmpmee_table_new(
+    base: *const u8,
+    base_len: usize,
+    modulus: *const u8,
+    modulus_len: usize,
+    block_width: usize,
+    exponent_bitlen: usize,
+    out_table: *mut *mut FPowmTable,
+) -> i32 {
+    guard(|| {
+        let base = unsafe { integer(base, base_len) }?;
+        let modulus = unsafe { integer(modulus, modulus_len) }?;
+        let table = FPowmTable::init_precomp(&base, &modulus, block_width, exponent_bitlen)
+            .map_err(|_| CAPI_INVALID_ARGUMENT)?;
+        unsafe { write_table(table, out_table) }
+    })
+}
+
+/// Read a table serialized by [FPowmTable::to_bytes] and write its handle in `out_table`
+///
+/// # Safety
+/// The pointer must be valid for `len` bytes, and `out_table` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rug_gmpmee_table_from_bytes(
+    data: *const u8,
+    len: usize,
+    out_table: *mut *mut FPowmTable,
+) -> i32 {
+    guard(|| {
+        let table = FPowmTable::from_bytes(unsafe { bytes(data, len) }?)
+            .map_err(|_| CAPI_INVALID_ARGUMENT)?;
+        unsafe { write_table(table, out_table) }
+    })
+}
+
+/// Fixed base exponentiation of the exponent with the table (see [FPowmTable::fpowm])
+///
+/// # Safety
+/// The table must be a handle which is not released, and the other pointers must be valid for
+/// the given numbers of bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rug_gmpmee_table_fpowm(
+    table: *const FPowmTable,
+    exponent: *const u8,
+    exponent_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> i32 {
+    guard(|| {
+        let table = unsafe { table.as_ref() }.ok_or(CAPI_NULL_POINTER)?;
+        let exponent = unsafe { integer(exponent, exponent_len) }?;
+        unsafe { write_integer(&table.fpowm(&exponent), out, out_cap, out_len) }
+    })
+}
+
+/// Release the table (nothing is done for a null pointer)
+///
+/// # Safety
+/// The table must be a handle which is not already released.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rug_gmpmee_table_free(table: *mut FPowmTable) {
+    if !table.is_null() {
+        drop(unsafe { Box::from_raw(table) });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn test_spowm() {
+        // 2^10 * 3^5 mod 1019
+        let bases = [0, 2, 0, 3];
+        let exponents = [10, 5];
+        let modulus = [0x03, 0xfb];
+        let mut out = [0u8; 2];
+        let mut out_len = 0;
+        let code = unsafe {
+            rug_gmpmee_spowm(
+                bases.as_ptr(),
+                2,
+                exponents.as_ptr(),
+                1,
+                2,
+                modulus.as_ptr(),
+                2,
+                out.as_mut_ptr(),
+                out.len(),
+                &mut out_len,
+            )
+        };
+        assert_eq!(code, CAPI_OK);
+        let expected = 1024 * 243 % 1019;
+        assert_eq!(
+            &out[..out_len],
+            Integer::from(expected).to_digits::<u8>(Order::Msf)
+        );
+        let code = unsafe {
+            rug_gmpmee_spowm(
+                bases.as_ptr(),
+                2,
+                exponents.as_ptr(),
+                1,
+                2,
+                modulus.as_ptr(),
+                2,
+                out.as_mut_ptr(),
+                0,
+                &mut out_len,
+            )
+        };
+        assert_eq!(code, CAPI_BUFFER_TOO_SMALL);
+        let code = unsafe {
+            rug_gmpmee_spowm(
+                ptr::null(),
+                2,
+                exponents.as_ptr(),
+                1,
+                2,
+                modulus.as_ptr(),
+                2,
+                out.as_mut_ptr(),
+                out.len(),
+                &mut out_len,
+            )
+        };
+        assert_eq!(code, CAPI_NULL_POINTER);
+    }
+
+    #[test]
+    fn test_table() {
+        let modulus = [0x03, 0xfb];
+        let mut table = ptr::null_mut();
+        let code = unsafe {
+            rug_gmpmee_table_new([7].as_ptr(), 1, modulus.as_ptr(), 2, 4, 16, &mut table)
+        };
+        assert_eq!(code, CAPI_OK);
+        let serialized = unsafe { &*table }.to_bytes().unwrap();
+        let mut read = ptr::null_mut();
+        let code = unsafe {
+            rug_gmpmee_table_from_bytes(serialized.as_ptr(), serialized.len(), &mut read)
+        };
+        assert_eq!(code, CAPI_OK);
+        let expected = Integer::from(7)
+            .pow_mod(&Integer::from(300), &Integer::from(1019))
+            .unwrap()
+            .to_digits::<u8>(Order::Msf);
+        for t in [table, read] {
+            let mut out = [0u8; 2];
+            let mut out_len = 0;
+            let code = unsafe {
+                rug_gmpmee_table_fpowm(t, [1, 44].as_ptr(), 2, out.as_mut_ptr(), 2, &mut out_len)
+            };
+            assert_eq!(code, CAPI_OK);
+            assert_eq!(&out[..out_len], expected);
+            unsafe { rug_gmpmee_table_free(t) };
+        }
+        let code = unsafe { rug_gmpmee_table_from_bytes([1, 2].as_ptr(), 2, &mut read) };
+        assert_eq!(code, CAPI_INVALID_ARGUMENT);
+        unsafe { rug_gmpmee_table_free(ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module for the verification of Chaum-Pedersen proofs of equality of discrete logarithms, one
+//! by one or by batch with two multi-exponentiations (see [batch_verify_chaum_pedersen])
+//!
+//! A proof `(a1, a2, c, z)` that `y1 = g1^x` and `y2 = g2^x` have the same exponent `x` is valid if
+//! `g1^z = a1 * y1^c mod p` and `g2^z = a2 * y2^c mod p`, where `(a1, a2) = (g1^k, g2^k)` is the
+//! commitment, `c` the challenge and `z = k + c*x mod q` the response. In a verifiable decryption,
+//! `g1` is the generator, `y1` the public key, `g2` the first component of the ciphertext and `y2`
+//! the decryption share.
+
+use crate::GmpMEEError;
+use crate::spown::{WEIGHT_BITS, reduce, spowm};
+use rug::{Integer, rand::RandState};
+
+/// Chaum-Pedersen proof `(a1, a2, c, z)` with its statement `(g1, g2, y1, y2)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChaumPedersenProof {
+    /// First base
+    pub g1: Integer,
+    /// Second base
+    pub g2: Integer,
+    /// `y1 = g1^x`
+    pub y1: Integer,
+    /// `y2 = g2^x`
+    pub y2: Integer,
+    /// Commitment `a1 = g1^k`
+    pub a1: Integer,
+    /// Commitment `a2 = g2^k`
+    pub a2: Integer,
+    /// Challenge `c`
+    pub c: Integer,
+    /// Response `z = k + c*x mod q`
+    pub z: Integer,
+}
+
+/// `g^z = a * y^c mod p`
+fn check_equation(
+    g: &Integer,
+    y: &Integer,
+    a: &Integer,
+    c: &Integer,
+    z: &Integer,
+    p: &Integer,
+) -> bool {
+    match (g.pow_mod_ref(z, p), y.pow_mod_ref(c, p)) {
+        (Some(lhs), Some(y_c)) => Integer::from(lhs) == Integer::from(y_c) * a % p,
+        _ => false,
+    }
+}
+
+/// Verify the proof, i.e. check both equations `g1^z = a1 * y1^c` and `g2^z = a2 * y2^c mod p`
+pub fn verify_chaum_pedersen(p: &Integer, proof: &ChaumPedersenProof) -> bool {
+    let ChaumPedersenProof {
+        g1,
+        g2,
+        y1,
+        y2,
+        a1,
+        a2,
+        c,
+        z,
+    } = proof;
+    check_equation(g1, y1, a1, c, z, p) && check_equation(g2, y2, a2, c, z, p)
+}
+
+/// `-x mod q` in `[0, q[`
+fn neg_mod(x: Integer, q: &Integer) -> Integer {
+    reduce(&-x, q)
+}
+
+/// Verify the proofs by batch in the subgroup of prime order `q` modulo `p`
+///
+/// With random weights `w_i` of [WEIGHT_BITS] bits drawn from `rand` and shared by both equations,
+/// the function checks that `prod g1_i^(w_i*z_i) * a1_i^(-w_i) * y1_i^(-w_i*c_i) = 1 mod p`, and
+/// the same product for the second equation, with the exponents reduced modulo `q`. Each product is
+/// computed with one multi-exponentiation (see [spowm]).
+///
+/// All the elements of the statements and of the commitments must be in the subgroup of order
+/// `q`, which has to be checked beforehand (e.g. with [crate::groups::are_quadratic_residues] for
+/// a safe prime `p`). Otherwise an invalid batch may be accepted. An empty batch is valid.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::chaum_pedersen::{ChaumPedersenProof, batch_verify_chaum_pedersen};
+/// let (p, q) = (Integer::from(23), Integer::from(11));
+/// let (g1, g2) = (Integer::from(4), Integer::from(9));
+/// let (x, k, c) = (Integer::from(7), Integer::from(3), Integer::from(5));
+/// let pow = |b: &Integer, e: &Integer| b.clone().pow_mod(e, &p).unwrap();
+/// let proof = ChaumPedersenProof {
+///     y1: pow(&g1, &x),
+///     y2: pow(&g2, &x),
+///     a1: pow(&g1, &k),
+///     a2: pow(&g2, &k),
+///     z: (k + &c * x.clone()) % &q,
+///     c,
+///     g1,
+///     g2,
+/// };
+/// let mut rand = RandState::new();
+/// assert!(batch_verify_chaum_pedersen(&p, &q, &[proof], &mut rand).unwrap());
+/// ```
+pub fn batch_verify_chaum_pedersen(
+    p: &Integer,
+    q: &Integer,
+    proofs: &[ChaumPedersenProof],
+    rand: &mut RandState<'_>,
+) -> Result<bool, GmpMEEError> {
+    if proofs.is_empty() {
+        return Ok(true);
+    }
+    let (mut bases1, mut bases2) = (
+        Vec::with_capacity(3 * proofs.len()),
+        Vec::with_capacity(3 * proofs.len()),
+    );
+    let mut exponents = Vec::with_capacity(3 * proofs.len());
+    for proof in proofs {
+        let w = Integer::from(Integer::random_bits(WEIGHT_BITS, rand));
+        exponents.push(reduce(&Integer::from(&w * &proof.z), q));
+        exponents.push(neg_mod(w.clone(), q));
+        exponents.push(neg_mod(w * &proof.c, q));
+        bases1.extend([proof.g1.clone(), proof.a1.clone(), proof.y1.clone()]);
+        bases2.extend([proof.g2.clone(), proof.a2.clone(), proof.y2.clone()]);
+    }
+    Ok(spowm(&bases1, &exponents, p)? == 1 && spowm(&bases2, &exponents, p)? == 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::groups::GroupParams;
+
+    fn prove(
+        group: &GroupParams,
+        g2: &Integer,
+        x: &Integer,
+        rand: &mut RandState<'_>,
+    ) -> ChaumPedersenProof {
+        let pow = |b: &Integer, e: &Integer| b.clone().pow_mod(e, &group.p).unwrap();
+        let k = group.random_exponent(rand);
+        let c = group.random_exponent(rand);
+        ChaumPedersenProof {
+            g1: group.g.clone(),
+            g2: g2.clone(),
+            y1: pow(&group.g, x),
+            y2: pow(g2, x),
+            a1: pow(&group.g, &k),
+            a2: pow(g2, &k),
+            z: (k + Integer::from(&c * x)) % &group.q,
+            c,
+        }
+    }
+
+    #[test]
+    fn test_batch_verify() {
+        let mut rand = RandState::new();
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        let (p, q) = (&group.p, &group.q);
+        // decryption shares of several ciphertexts with the same secret key
+        let x = group.random_exponent(&mut rand);
+        let proofs = group
+            .random_elements(10, &mut rand)
+            .unwrap()
+            .iter()
+            .map(|gamma| prove(&group, gamma, &x, &mut rand))
+            .collect::<Vec<_>>();
+        assert!(proofs.iter().all(|proof| verify_chaum_pedersen(p, proof)));
+        assert!(batch_verify_chaum_pedersen(p, q, &proofs, &mut rand).unwrap());
+        assert!(batch_verify_chaum_pedersen(p, q, &[], &mut rand).unwrap());
+        for i in [0, 5, 9] {
+            let mut forged = proofs.clone();
+            forged[i].z += 1;
+            assert!(!verify_chaum_pedersen(p, &forged[i]));
+            assert!(!batch_verify_chaum_pedersen(p, q, &forged, &mut rand).unwrap());
+            // a wrong decryption share fails only the second equation
+            let mut forged = proofs.clone();
+            forged[i].y2 = forged[i].y2.clone() * &group.g % p;
+            assert!(!verify_chaum_pedersen(p, &forged[i]));
+            assert!(!batch_verify_chaum_pedersen(p, q, &forged, &mut rand).unwrap());
+            let mut forged = proofs.clone();
+            forged[i].a1 = forged[i].a1.clone() * &group.g % p;
+            assert!(!batch_verify_chaum_pedersen(p, q, &forged, &mut rand).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_batch_verify_negative() {
+        let mut rand = RandState::new();
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        let (p, q) = (&group.p, &group.q);
+        let x = group.random_exponent(&mut rand);
+        let mut proofs = group
+            .random_elements(3, &mut rand)
+            .unwrap()
+            .iter()
+            .map(|gamma| prove(&group, gamma, &x, &mut rand))
+            .collect::<Vec<_>>();
+        // Same response and challenge modulo q, hence still valid
+        proofs[0].z -= q;
+        proofs[1].c -= q;
+        assert!(proofs[0].z < 0 && proofs[1].c < 0);
+        assert!(proofs.iter().all(|proof| verify_chaum_pedersen(p, proof)));
+        assert!(batch_verify_chaum_pedersen(p, q, &proofs, &mut rand).unwrap());
+        proofs[0].z -= 1;
+        assert!(!verify_chaum_pedersen(p, &proofs[0]));
+        assert!(!batch_verify_chaum_pedersen(p, q, &proofs, &mut rand).unwrap());
+    }
+}
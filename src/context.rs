@@ -0,0 +1,313 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module bundling the machinery of a modulus and its optional subgroup in one handle (see
+//! [ModulusContext])
+
+use crate::{
+    GmpMEEError,
+    fpowm::{
+        ExponentBitlenPolicy, FPowmCache, FPowmDefaults, FPowmTable, FPownError, check_modulus,
+        defaults,
+    },
+    groups::{GroupParams, cofactor},
+    miller_rabin::miller_rabin,
+    spown::spowm,
+};
+use rug::{Integer, rand::RandState};
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+};
+
+/// Modulus with its optional subgroup order, the fixed-base tables of its registered bases, and the
+/// exponentiations, the sampling and the checks of primality
+///
+/// The tables of the registered bases are built at their first use and kept for the lifetime of
+/// the context. The context is `Send + Sync`, so that one handle can be shared by the threads of an
+/// application.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::context::ModulusContext;
+/// let (p, q, g) = (Integer::from(23), Integer::from(11), Integer::from(4));
+/// let ctx = ModulusContext::new(&p).unwrap().with_order(&q).unwrap();
+/// ctx.register_base(&g);
+/// assert_eq!(ctx.fpowm(&g, &Integer::from(5)).unwrap(), 12);
+/// assert!(ctx.table(&g).unwrap().is_some());
+/// let e = ctx.random_element(&mut RandState::new());
+/// assert!(ctx.is_element(&e));
+/// ```
+pub struct ModulusContext {
+    modulus: Integer,
+    order: Option<Integer>,
+    bases: RwLock<HashSet<Integer>>,
+    tables: FPowmCache,
+}
+
+impl ModulusContext {
+    /// New context for the modulus, without subgroup order nor registered base
+    ///
+    /// Return an error if the modulus is not larger than 1.
+    pub fn new(modulus: &Integer) -> Result<Self, GmpMEEError> {
+        check_modulus(modulus)?;
+        Ok(Self {
+            modulus: modulus.clone(),
+            order: None,
+            bases: RwLock::new(HashSet::new()),
+            tables: FPowmCache::new(),
+        })
+    }
+
+    /// Set the order of the subgroup, which must be a divisor of `modulus - 1`
+    ///
+    /// The tables then cover the exponents of the bit length of the order, and the sampling uses
+    /// the subgroup. Return an error if the order is smaller than 3 or does not divide
+    /// `modulus - 1`.
+    pub fn with_order(mut self, order: &Integer) -> Result<Self, GmpMEEError> {
+        cofactor(&self.modulus, order)?;
+        self.order = Some(order.clone());
+        Ok(self)
+    }
+
+    /// Modulus of the context
+    pub fn modulus(&self) -> &Integer {
+        &self.modulus
+    }
+
+    /// Order of the subgroup, if any
+    pub fn order(&self) -> Option<&Integer> {
+        self.order.as_ref()
+    }
+
+    /// Register the base, so that a table is used for its exponentiations (see [Self::fpowm])
+    pub fn register_base(&self, base: &Integer) {
+        self.bases
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(base.clone());
+    }
+
+    /// Return `true` if the base is registered
+    pub fn is_registered(&self, base: &Integer) -> bool {
+        self.bases
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(base)
+    }
+
+    /// Parameters of the tables: the default block width (see [crate::fpowm::set_defaults]), and
+    /// the bit length of the order or of the modulus
+    fn table_params(&self) -> FPowmDefaults {
+        FPowmDefaults {
+            block_width: defaults().block_width,
+            exponent_bitlen_policy: match &self.order {
+                Some(order) => ExponentBitlenPolicy::Fixed(order.significant_bits() as usize),
+                None => ExponentBitlenPolicy::Modulus,
+            },
+        }
+    }
+
+    /// Table of the base, built at the first call, or `None` if the base is not registered
+    pub fn table(&self, base: &Integer) -> Result<Option<Arc<FPowmTable>>, GmpMEEError> {
+        if !self.is_registered(base) {
+            return Ok(None);
+        }
+        self.tables
+            .get_or_init_with(base, &self.modulus, self.table_params())
+            .map(Some)
+    }
+
+    /// `base^exponent mod modulus`
+    ///
+    /// The table of a registered base is used for the non-negative exponents it covers, and
+    /// `pow_mod` otherwise. Return an error if the exponent is negative and the base is not
+    /// invertible.
+    pub fn fpowm(&self, base: &Integer, exponent: &Integer) -> Result<Integer, GmpMEEError> {
+        if *exponent >= 0
+            && let Some(table) = self.table(base)?
+            && exponent.significant_bits() as usize <= table.exponent_bitlen()
+        {
+            return Ok(table.fpowm(exponent));
+        }
+        base.pow_mod_ref(exponent, &self.modulus)
+            .map(Integer::from)
+            .ok_or_else(|| FPownError::NotInvertible.into())
+    }
+
+    /// `prod bases_i^exponents_i mod modulus` (see [spowm])
+    pub fn spowm(&self, bases: &[Integer], exponents: &[Integer]) -> Result<Integer, GmpMEEError> {
+        spowm(bases, exponents, &self.modulus)
+    }
+
+    /// Uniform exponent in `[0, order[`, or in `[0, modulus[` without order
+    pub fn random_exponent(&self, rand: &mut RandState<'_>) -> Integer {
+        Integer::from(
+            self.order
+                .as_ref()
+                .unwrap_or(&self.modulus)
+                .random_below_ref(rand),
+        )
+    }
+
+    /// Random element of the subgroup, i.e. `h^((modulus-1)/order)` for a uniform `h` in
+    /// `[1, modulus-1]`, or `h` itself without order
+    ///
+    /// For a prime modulus, the element is uniform in the subgroup.
+    pub fn random_element(&self, rand: &mut RandState<'_>) -> Integer {
+        let p_minus_1 = Integer::from(&self.modulus - 1u32);
+        let h = Integer::from(p_minus_1.random_below_ref(rand)) + 1u32;
+        match &self.order {
+            Some(order) => h
+                .pow_mod(&(p_minus_1 / order), &self.modulus)
+                .expect("the exponent is non-negative"),
+            None => h,
+        }
+    }
+
+    /// Check that the element is in `[1, modulus-1]` and, with an order, that `element^order = 1`
+    pub fn is_element(&self, element: &Integer) -> bool {
+        *element > 0
+            && *element < self.modulus
+            && self.order.as_ref().is_none_or(|order| {
+                element
+                    .pow_mod_ref(order, &self.modulus)
+                    .is_some_and(|pow| Integer::from(pow) == 1)
+            })
+    }
+
+    /// Primality test of the modulus with `reps` rounds of Miller-Rabin
+    pub fn is_modulus_prime(&self, reps: i32) -> bool {
+        miller_rabin(&self.modulus, reps)
+    }
+
+    /// Primality test of the order with `reps` rounds of Miller-Rabin, or `None` without order
+    pub fn is_order_prime(&self, reps: i32) -> Option<bool> {
+        self.order.as_ref().map(|order| miller_rabin(order, reps))
+    }
+}
+
+impl TryFrom<&GroupParams> for ModulusContext {
+    type Error = GmpMEEError;
+
+    /// Context of the group, with its generator registered
+    ///
+    /// Return an error if `p` is not larger than 1, or if `q` is smaller than 3 or does not divide
+    /// `p-1`.
+    fn try_from(group: &GroupParams) -> Result<Self, GmpMEEError> {
+        let ctx = Self::new(&group.p)?.with_order(&group.q)?;
+        ctx.register_base(&group.g);
+        Ok(ctx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::groups::GroupError;
+    use std::thread;
+
+    #[test]
+    fn test_context() {
+        let mut rand = RandState::new();
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        let ctx = ModulusContext::try_from(&group).unwrap();
+        assert_eq!((ctx.modulus(), ctx.order()), (&group.p, Some(&group.q)));
+        assert!(ctx.is_registered(&group.g));
+        assert!(ctx.is_modulus_prime(16));
+        assert_eq!(ctx.is_order_prime(16), Some(true));
+        let h = ctx.random_element(&mut rand);
+        assert!(ctx.is_element(&h) && ctx.is_element(&group.g));
+        assert!(!ctx.is_element(&Integer::from(&group.p - 1u32)));
+        assert!(ctx.table(&h).unwrap().is_none());
+        let table = ctx.table(&group.g).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&table, &ctx.table(&group.g).unwrap().unwrap()));
+        assert_eq!(table.exponent_bitlen(), 255);
+        thread::scope(|s| {
+            for _ in 0..4 {
+                let (ctx, group) = (&ctx, &group);
+                let e = ctx.random_exponent(&mut rand);
+                s.spawn(move || {
+                    let expected = group.g.clone().pow_mod(&e, &group.p).unwrap();
+                    assert_eq!(ctx.fpowm(&group.g, &e).unwrap(), expected);
+                });
+            }
+        });
+        for e in [Integer::from(-3), Integer::from(1) << 300, Integer::new()] {
+            let expected = group.g.clone().pow_mod(&e, &group.p).unwrap();
+            assert_eq!(ctx.fpowm(&group.g, &e).unwrap(), expected);
+            assert_eq!(
+                ctx.fpowm(&h, &e).unwrap(),
+                h.clone().pow_mod(&e, &group.p).unwrap()
+            );
+        }
+        let exponents = [
+            ctx.random_exponent(&mut rand),
+            ctx.random_exponent(&mut rand),
+        ];
+        assert_eq!(
+            ctx.spowm(&[group.g.clone(), h.clone()], &exponents)
+                .unwrap(),
+            ctx.fpowm(&group.g, &exponents[0]).unwrap() * ctx.fpowm(&h, &exponents[1]).unwrap()
+                % &group.p
+        );
+        let ctx = ModulusContext::new(&Integer::from(15)).unwrap();
+        assert_eq!(ctx.order(), None);
+        assert_eq!(ctx.is_order_prime(16), None);
+        assert!(!ctx.is_modulus_prime(16));
+        assert!(ctx.is_element(&Integer::from(14)));
+        assert!(ctx.random_exponent(&mut rand) < 15);
+        assert_eq!(
+            ctx.fpowm(&Integer::from(3), &Integer::from(-1)),
+            Err(FPownError::NotInvertible.into())
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        for modulus in [-5, 0, 1] {
+            assert_eq!(
+                ModulusContext::new(&Integer::from(modulus)).err(),
+                Some(FPownError::ModulusTooSmall.into())
+            );
+        }
+        let ctx = || ModulusContext::new(&Integer::from(23)).unwrap();
+        for order in [0, 2, 5] {
+            assert_eq!(
+                ctx().with_order(&Integer::from(order)).err(),
+                Some(
+                    GroupError::InvalidOrder {
+                        p: Integer::from(23),
+                        q: Integer::from(order)
+                    }
+                    .into()
+                )
+            );
+        }
+        assert!(ctx().with_order(&Integer::from(11)).is_ok());
+        let group = GroupParams {
+            p: Integer::from(23),
+            q: Integer::from(7),
+            g: Integer::from(4),
+        };
+        assert!(ModulusContext::try_from(&group).is_err());
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ModulusContext>();
+    }
+}
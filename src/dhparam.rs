@@ -0,0 +1,294 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module to read the Diffie-Hellman parameters of OpenSSL (`openssl dhparam` or
+//! `openssl genpkey -genparam`)
+//!
+//! The parameters are encoded in DER, as PKCS#3 (`SEQUENCE { p, g, privateValueLength OPTIONAL }`,
+//! PEM label `DH PARAMETERS`) or as X9.42 (`SEQUENCE { p, g, q, j OPTIONAL, validationParms
+//! OPTIONAL }`, PEM label `X9.42 DH PARAMETERS`). Without the order `q` in PKCS#3, `p` must be a
+//! safe prime and the order is `(p-1)/2`.
+//! ```
+//! use rug_gmpmee::{dhparam::DhParams, groups::GroupParams, standard_groups::StandardGroup};
+//! let pem = "-----BEGIN DH PARAMETERS-----
+//! MIIBCAKCAQEA///////////JD9qiIWjCNMTGYouA3BzRKQJOCIpnzHQCC76mOxOb
+//! IlFKCHmONATd75UZs806QxswKwpt8l8UN0/hNW1tUcJF5IW1dmJefsb0TELppjft
+//! awv/XLb0Brft7jhr+1qJn6WunyQRfEsf5kkoZlHs5Fs9wgB8uKFjvwWY2kg2HFXT
+//! mmkWP6j9JM9fg2VdI9yjrZYcYvNWIIVSu57VKQdwlpZtZww1Tkq8mATxdGwIyhgh
+//! fDKQXkYuNs474553LBgOhgObJ4Oi7Aeij7XFXfBvTFLJ3ivL9pVYFxg5lUl86pVq
+//! 5RXSJhiY+gUQFXKOWoqsqmj//////////wIBAg==
+//! -----END DH PARAMETERS-----";
+//! let params = DhParams::from_pem(pem).unwrap();
+//! assert_eq!(params.group(16).unwrap(), GroupParams::from(StandardGroup::Modp2048));
+//! ```
+
+use crate::{GmpMEEError, fpowm::FPowmTable, groups::GroupParams};
+use rug::{Integer, integer::Order};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DhParamError {
+    #[error("No PEM block of Diffie-Hellman parameters found")]
+    NoPemBlock,
+    #[error("Invalid base64 in the PEM block")]
+    InvalidBase64,
+    #[error("Invalid DER encoding: {0}")]
+    InvalidDer(String),
+    #[error("The parameters are not a group of prime order")]
+    InvalidGroup,
+}
+
+/// Encoding of the Diffie-Hellman parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DhParamsFormat {
+    /// PKCS#3, without the order of the subgroup
+    Pkcs3,
+    /// X9.42 (RFC 3279), with the order of the subgroup
+    X942,
+}
+
+impl DhParamsFormat {
+    /// Label of the PEM block
+    pub fn pem_label(&self) -> &'static str {
+        match self {
+            Self::Pkcs3 => "DH PARAMETERS",
+            Self::X942 => "X9.42 DH PARAMETERS",
+        }
+    }
+}
+
+/// Diffie-Hellman parameters read from DER or PEM
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DhParams {
+    pub p: Integer,
+    pub g: Integer,
+    /// Order of the subgroup, only given in X9.42
+    pub q: Option<Integer>,
+}
+
+fn der_error(msg: &str) -> GmpMEEError {
+    DhParamError::InvalidDer(msg.to_string()).into()
+}
+
+/// Read a DER element with the expected tag, returning its content and the remaining bytes
+fn der_element(bytes: &[u8], tag: u8) -> Result<(&[u8], &[u8]), GmpMEEError> {
+    let (&t, rest) = bytes.split_first().ok_or_else(|| der_error("truncated"))?;
+    if t != tag {
+        return Err(der_error(&format!("tag {t:#04x} instead of {tag:#04x}")));
+    }
+    let (&first, mut rest) = rest.split_first().ok_or_else(|| der_error("truncated"))?;
+    let len = match first {
+        0..=0x7f => first as usize,
+        0x81..=0x84 => {
+            let (len_bytes, r) = rest
+                .split_at_checked((first & 0x7f) as usize)
+                .ok_or_else(|| der_error("truncated"))?;
+            rest = r;
+            len_bytes
+                .iter()
+                .fold(0usize, |acc, &b| (acc << 8) | b as usize)
+        }
+        _ => return Err(der_error("unsupported length")),
+    };
+    rest.split_at_checked(len)
+        .ok_or_else(|| der_error("truncated"))
+}
+
+/// Read a positive DER integer, returning the remaining bytes
+fn der_positive_integer(bytes: &[u8]) -> Result<(Integer, &[u8]), GmpMEEError> {
+    let (content, rest) = der_element(bytes, 0x02)?;
+    match content.first() {
+        None => Err(der_error("empty integer")),
+        Some(b) if b & 0x80 != 0 => Err(der_error("negative integer")),
+        Some(_) => Ok((Integer::from_digits(content, Order::Msf), rest)),
+    }
+}
+
+/// Decode base64 (standard alphabet with padding), ignoring the whitespaces
+fn base64_decode(s: &str) -> Result<Vec<u8>, GmpMEEError> {
+    let symbols = s
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    if !symbols.len().is_multiple_of(4) {
+        return Err(DhParamError::InvalidBase64.into());
+    }
+    let padding = symbols.iter().rev().take_while(|&&b| b == b'=').count();
+    if padding > 2 {
+        return Err(DhParamError::InvalidBase64.into());
+    }
+    let mut res = Vec::with_capacity(symbols.len() / 4 * 3);
+    let mut acc = 0u32;
+    for (i, &b) in symbols[..symbols.len() - padding].iter().enumerate() {
+        let v = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(DhParamError::InvalidBase64.into()),
+        };
+        acc = (acc << 6) | v as u32;
+        if i % 4 == 3 {
+            res.extend_from_slice(&acc.to_be_bytes()[1..]);
+            acc = 0;
+        }
+    }
+    match padding {
+        1 => res.extend_from_slice(&(acc << 6).to_be_bytes()[1..3]),
+        2 => res.push((acc >> 4) as u8),
+        _ => {}
+    }
+    Ok(res)
+}
+
+impl DhParams {
+    /// Read the parameters from DER in the given format
+    ///
+    /// The optional fields after `g` (PKCS#3) or after `q` (X9.42) are ignored.
+    pub fn from_der(der: &[u8], format: DhParamsFormat) -> Result<Self, GmpMEEError> {
+        let (content, rest) = der_element(der, 0x30)?;
+        if !rest.is_empty() {
+            return Err(der_error("bytes after the parameters"));
+        }
+        let (p, content) = der_positive_integer(content)?;
+        let (g, content) = der_positive_integer(content)?;
+        let q = match format {
+            DhParamsFormat::Pkcs3 => None,
+            DhParamsFormat::X942 => Some(der_positive_integer(content)?.0),
+        };
+        Ok(Self { p, g, q })
+    }
+
+    /// Read the parameters from the first PEM block labeled `DH PARAMETERS` or
+    /// `X9.42 DH PARAMETERS`
+    pub fn from_pem(pem: &str) -> Result<Self, GmpMEEError> {
+        for format in [DhParamsFormat::Pkcs3, DhParamsFormat::X942] {
+            let begin = format!("-----BEGIN {}-----", format.pem_label());
+            let end = format!("-----END {}-----", format.pem_label());
+            if let Some(start) = pem.find(&begin)
+                && let Some(len) = pem[start + begin.len()..].find(&end)
+            {
+                let body = &pem[start + begin.len()..start + begin.len() + len];
+                return Self::from_der(&base64_decode(body)?, format);
+            }
+        }
+        Err(DhParamError::NoPemBlock.into())
+    }
+
+    /// Order of the subgroup, given in X9.42 or `(p-1)/2` otherwise
+    pub fn order(&self) -> Integer {
+        match &self.q {
+            Some(q) => q.clone(),
+            None => Integer::from(&self.p - 1u32) >> 1,
+        }
+    }
+
+    /// Group of the parameters, verified with `reps` rounds of Miller-Rabin
+    ///
+    /// Return an error if the parameters are not a group of prime order (see
+    /// [GroupParams::is_prime_order_group]).
+    pub fn group(&self, reps: i32) -> Result<GroupParams, GmpMEEError> {
+        let group = GroupParams {
+            p: self.p.clone(),
+            q: self.order(),
+            g: self.g.clone(),
+        };
+        match group.is_prime_order_group(reps) {
+            true => Ok(group),
+            false => Err(DhParamError::InvalidGroup.into()),
+        }
+    }
+
+    /// Precomputed table for the generator of the verified group (see [Self::group])
+    pub fn table(&self, reps: i32) -> Result<FPowmTable, GmpMEEError> {
+        self.group(reps)?.table()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::standard_groups::StandardGroup;
+    use rug::rand::RandState;
+
+    const X942_PEM: &str = "-----BEGIN X9.42 DH PARAMETERS-----\nMIICLAKCAQEAh6jmHbS2Zjz/u9GcZRlZmYzu9ghmDdDyXSzu1ENeOwDgDfjx1hlX\n1Pr330VhsqowFsPZETQJb6o79Cltgw6afCCeDGSXUXq9WoqdMGvPZ+2R+eZyW0dY\nwCLgse9Cdb97bFv8EdRfkIi5QfVOseWbuLw5oL8SMH9cT9twxYGyP3a2Osrhyqa3\nkC1SUmc1SIoO8TxtmlG/pKs62DR3llJNjvahZ7WkGCXZZ+FE5RQFZCUcysuD5rSG\n9rPKP3lxUGAmwLhX9omWKFbe1AEKvQvmIcOjlgpU5xDDdfJjddcBQQOktUMwwZiv\nEmEW0iduEXFfaTh3+tfvCcrbCUrpHhoVlwKCAQA/syybcxNNCy53UGZg7b1ITKex\njyHvIFQH9Hk6GguhJRDbwVB3vkY//0/tSqwLtVW+OmwbDGtHsbw3c79+jG9ikBIo\n+MKMuxilWuMTQQAKZQGW+THHelfy3fRj5ensFEt3feYqqrioYorDdtKC1u04ZOZ5\ngkKOvIMdFDSPby+Rk7UEWvJ2cWTh38lnwfs/LlWkvRv/6DucgNBSuYXRguoK2yo7\ncxPT/hTISEseBSWIubfSu9LfAWGZ7NBuFVfNCRWzNTu7ZODsN3/QKDcN+StSx4kU\nKM3GfrYYS1I9HbJGwy9jB4SQ8A741kfRSNR5VFFeIyfP75jFgmZLTA9sxBZZAiEA\njPg2QqcJoJe0R5l2QBKdopmxpH0es3ULowiw/mT1+9M=\n-----END X9.42 DH PARAMETERS-----";
+
+    #[test]
+    fn test_pem() {
+        let params = DhParams::from_pem(X942_PEM).unwrap();
+        let expected = GroupParams::from(StandardGroup::Modp2048S256);
+        assert_eq!(params.q.as_ref(), Some(&expected.q));
+        assert_eq!(params.group(16).unwrap(), expected);
+        let group = params.group(16).unwrap();
+        let mut rand = RandState::new();
+        for e in group.random_elements(5, &mut rand).unwrap() {
+            assert_ne!(e, 1);
+            assert_eq!(e.pow_mod(&group.q, &group.p).unwrap(), 1);
+        }
+        assert_eq!(
+            params.table(16).unwrap().fpowm(&Integer::from(12345)),
+            expected
+                .g
+                .clone()
+                .pow_mod(&Integer::from(12345), &expected.p)
+                .unwrap()
+        );
+        assert_eq!(
+            DhParams::from_pem("-----BEGIN PUBLIC KEY-----\n-----END PUBLIC KEY-----"),
+            Err(DhParamError::NoPemBlock.into())
+        );
+        assert_eq!(
+            DhParams::from_pem("-----BEGIN DH PARAMETERS-----\nMII*\n-----END DH PARAMETERS-----"),
+            Err(DhParamError::InvalidBase64.into())
+        );
+    }
+
+    #[test]
+    fn test_der() {
+        let der = [0x30, 0x06, 0x02, 0x01, 0x17, 0x02, 0x01, 0x04];
+        let params = DhParams::from_der(&der, DhParamsFormat::Pkcs3).unwrap();
+        assert_eq!(params.order(), 11);
+        assert_eq!(
+            params.group(16).unwrap(),
+            GroupParams {
+                p: Integer::from(23),
+                q: Integer::from(11),
+                g: Integer::from(4)
+            }
+        );
+        // 5 generates the whole group of order 22
+        let der = [0x30, 0x06, 0x02, 0x01, 0x17, 0x02, 0x01, 0x05];
+        let params = DhParams::from_der(&der, DhParamsFormat::Pkcs3).unwrap();
+        assert_eq!(params.group(16), Err(DhParamError::InvalidGroup.into()));
+        assert!(DhParams::from_der(&der, DhParamsFormat::X942).is_err());
+        for len in 0..der.len() {
+            assert!(DhParams::from_der(&der[..len], DhParamsFormat::Pkcs3).is_err());
+        }
+        let negative = [0x30, 0x06, 0x02, 0x01, 0x97, 0x02, 0x01, 0x05];
+        assert!(DhParams::from_der(&negative, DhParamsFormat::Pkcs3).is_err());
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64_decode("TWFu").unwrap(), b"Man");
+        assert_eq!(base64_decode("TW\nE=").unwrap(), b"Ma");
+        assert_eq!(base64_decode("TQ==").unwrap(), b"M");
+        assert_eq!(base64_decode("").unwrap(), b"");
+        assert!(base64_decode("TQ=").is_err());
+        assert!(base64_decode("T===").is_err());
+    }
+}
@@ -0,0 +1,400 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module for the ElGamal encryption using precomputed tables for the generator and the public key
+//!
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::fpowm::FPowmTable;
+//! use rug_gmpmee::elgamal::encrypt;
+//! let p = Integer::from(23);
+//! let g = Integer::from(4);
+//! let pk = Integer::from(9);
+//! let g_table = FPowmTable::init_precomp(&g, &p, 4, 8).unwrap();
+//! let pk_table = FPowmTable::init_precomp(&pk, &p, 4, 8).unwrap();
+//! let c = encrypt(&g_table, &pk_table, &Integer::from(3), &Integer::from(5)).unwrap();
+//! assert_eq!(c.gamma, g.pow_mod(&Integer::from(5), &p).unwrap());
+//! ```
+//!
+//! The key pairs are generated in a group of [GroupParams] (see [KeyPair]), and the ciphertexts can
+//! be decrypted and re-encrypted, one by one or by batch
+//! ```
+//! use rug::{Integer, rand::RandState};
+//! use rug_gmpmee::elgamal::{KeyPair, decrypt, encrypt, reencrypt};
+//! use rug_gmpmee::groups::GroupParams;
+//! let mut rand = RandState::new();
+//! let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+//! let keys = KeyPair::generate(&group, &mut rand);
+//! let (g_table, pk_table) = (group.table().unwrap(), keys.public_key_table(&group).unwrap());
+//! let m = group.random_element(&mut rand).unwrap();
+//! let c = encrypt(&g_table, &pk_table, &m, &group.random_exponent(&mut rand)).unwrap();
+//! let c = reencrypt(&g_table, &pk_table, &c, &group.random_exponent(&mut rand)).unwrap();
+//! assert_eq!(decrypt(&group.p, &keys.secret_key, &c).unwrap(), m);
+//! ```
+
+use crate::GmpMEEError;
+use crate::fpowm::{FPowmTable, check_same_len, check_same_modulus};
+use crate::groups::GroupParams;
+use crate::parallel::map_chunks;
+use crate::telemetry::{self, Batch};
+use crate::trace::timed_span;
+use rug::{Integer, rand::RandState};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ElGamalError {
+    #[error("The first component of the ciphertext is not invertible modulo the modulus")]
+    NotInvertible,
+    #[error("The permutation is not a permutation of the {len} ciphertexts")]
+    InvalidPermutation { len: usize },
+}
+
+/// Key pair `(x, g^x)` of a group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPair {
+    /// Secret key `x` in `[1, q[`
+    pub secret_key: Integer,
+    /// Public key `g^x mod p`
+    pub public_key: Integer,
+}
+
+impl KeyPair {
+    /// Generate a key pair in the group, with the secret key drawn uniformly in `[1, q[`
+    pub fn generate(group: &GroupParams, rand: &mut RandState<'_>) -> Self {
+        let secret_key =
+            Integer::from(Integer::from(&group.q - 1u32).random_below_ref(rand)) + 1u32;
+        let public_key = group
+            .g
+            .pow_mod_ref(&secret_key, &group.p)
+            .map(Integer::from)
+            .expect("the secret key is positive");
+        Self {
+            secret_key,
+            public_key,
+        }
+    }
+
+    /// Precomputed table for the public key in the group (see [FPowmTable::for_subgroup])
+    pub fn public_key_table(&self, group: &GroupParams) -> Result<FPowmTable, GmpMEEError> {
+        FPowmTable::for_subgroup(&self.public_key, &group.p, &group.q)
+    }
+}
+
+/// ElGamal ciphertext `(gamma, phi) = (g^r, pk^r * m)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ciphertext {
+    pub gamma: Integer,
+    pub phi: Integer,
+}
+
+fn encrypt_unchecked(
+    g_table: &FPowmTable,
+    pk_table: &FPowmTable,
+    message: &Integer,
+    randomness: &Integer,
+) -> Ciphertext {
+    let gamma = g_table.fpowm(randomness);
+    let phi = (pk_table.fpowm(randomness) * message) % pk_table.modulus();
+    Ciphertext { gamma, phi }
+}
+
+/// Encrypt the message with the randomness, using the tables of the generator `g` and of the public key `pk`
+///
+/// The tables must have the same modulus
+pub fn encrypt(
+    g_table: &FPowmTable,
+    pk_table: &FPowmTable,
+    message: &Integer,
+    randomness: &Integer,
+) -> Result<Ciphertext, GmpMEEError> {
+    check_same_modulus(g_table, pk_table)?;
+    Ok(encrypt_unchecked(g_table, pk_table, message, randomness))
+}
+
+/// Encrypt each message with the randomness at the same position
+///
+/// The number of messages and of randomness must be the same
+pub fn encrypt_batch(
+    g_table: &FPowmTable,
+    pk_table: &FPowmTable,
+    messages: &[Integer],
+    randomness: &[Integer],
+) -> Result<Vec<Ciphertext>, GmpMEEError> {
+    timed_span!(
+        "elgamal_encrypt_batch",
+        count = messages.len(),
+        modulus_bits = g_table.modulus().significant_bits()
+    );
+    telemetry::record_batch(Batch::ElGamalEncrypt, messages.len());
+    check_same_modulus(g_table, pk_table)?;
+    check_same_len(messages.len(), randomness.len())?;
+    Ok(messages
+        .iter()
+        .zip(randomness.iter())
+        .map(|(m, r)| encrypt_unchecked(g_table, pk_table, m, r))
+        .collect())
+}
+
+/// Decrypt the ciphertext with the secret key, i.e. compute `phi * gamma^(-sk) mod p`
+///
+/// Return an error if `gamma` is not invertible modulo `p`.
+pub fn decrypt(
+    modulus: &Integer,
+    secret_key: &Integer,
+    ciphertext: &Ciphertext,
+) -> Result<Integer, GmpMEEError> {
+    let neg_sk = Integer::from(-secret_key);
+    let mask = ciphertext
+        .gamma
+        .pow_mod_ref(&neg_sk, modulus)
+        .map(Integer::from)
+        .ok_or(ElGamalError::NotInvertible)?;
+    Ok(mask * &ciphertext.phi % modulus)
+}
+
+/// Decrypt each ciphertext with the secret key (see [decrypt])
+pub fn decrypt_batch(
+    modulus: &Integer,
+    secret_key: &Integer,
+    ciphertexts: &[Ciphertext],
+) -> Result<Vec<Integer>, GmpMEEError> {
+    timed_span!(
+        "elgamal_decrypt_batch",
+        count = ciphertexts.len(),
+        modulus_bits = modulus.significant_bits()
+    );
+    telemetry::record_batch(Batch::ElGamalDecrypt, ciphertexts.len());
+    ciphertexts
+        .iter()
+        .map(|c| decrypt(modulus, secret_key, c))
+        .collect()
+}
+
+fn reencrypt_unchecked(
+    g_table: &FPowmTable,
+    pk_table: &FPowmTable,
+    ciphertext: &Ciphertext,
+    randomness: &Integer,
+) -> Ciphertext {
+    let modulus = g_table.modulus();
+    let gamma = g_table.fpowm(randomness) * &ciphertext.gamma % modulus;
+    let phi = pk_table.fpowm(randomness) * &ciphertext.phi % modulus;
+    Ciphertext { gamma, phi }
+}
+
+/// Re-encrypt the ciphertext with the randomness, i.e. compute `(gamma * g^r, phi * pk^r)`, using
+/// the tables of the generator `g` and of the public key `pk`
+///
+/// The tables must have the same modulus
+pub fn reencrypt(
+    g_table: &FPowmTable,
+    pk_table: &FPowmTable,
+    ciphertext: &Ciphertext,
+    randomness: &Integer,
+) -> Result<Ciphertext, GmpMEEError> {
+    check_same_modulus(g_table, pk_table)?;
+    Ok(reencrypt_unchecked(
+        g_table, pk_table, ciphertext, randomness,
+    ))
+}
+
+/// Re-encrypt each ciphertext with the randomness at the same position
+///
+/// The number of ciphertexts and of randomness must be the same
+pub fn reencrypt_batch(
+    g_table: &FPowmTable,
+    pk_table: &FPowmTable,
+    ciphertexts: &[Ciphertext],
+    randomness: &[Integer],
+) -> Result<Vec<Ciphertext>, GmpMEEError> {
+    timed_span!(
+        "elgamal_reencrypt_batch",
+        count = ciphertexts.len(),
+        modulus_bits = g_table.modulus().significant_bits()
+    );
+    telemetry::record_batch(Batch::ElGamalReencrypt, ciphertexts.len());
+    check_same_modulus(g_table, pk_table)?;
+    check_same_len(ciphertexts.len(), randomness.len())?;
+    Ok(ciphertexts
+        .iter()
+        .zip(randomness.iter())
+        .map(|(c, r)| reencrypt_unchecked(g_table, pk_table, c, r))
+        .collect())
+}
+
+/// Check that `permutation` is a permutation of `[0, len[`
+fn check_permutation(permutation: &[usize], len: usize) -> Result<(), GmpMEEError> {
+    let mut seen = vec![false; len];
+    let valid = permutation.len() == len
+        && permutation
+            .iter()
+            .all(|&i| i < len && !std::mem::replace(&mut seen[i], true));
+    match valid {
+        true => Ok(()),
+        false => Err(ElGamalError::InvalidPermutation { len }.into()),
+    }
+}
+
+/// Re-encrypt and permute the ciphertexts, the inner loop of a re-encryption mix-net
+///
+/// The output `i` is the ciphertext `permutation[i]` re-encrypted with the randomness `i`. The
+/// outputs are split in one chunk per available core, computed in parallel with the shared tables
+/// of the generator `g` and of the public key `pk`. The tables must have the same modulus, the
+/// number of ciphertexts and of randomness must be the same, and the permutation must be a
+/// permutation of the indices of the ciphertexts.
+pub fn reencrypt_mix(
+    g_table: &FPowmTable,
+    pk_table: &FPowmTable,
+    ciphertexts: &[Ciphertext],
+    randomness: &[Integer],
+    permutation: &[usize],
+) -> Result<Vec<Ciphertext>, GmpMEEError> {
+    timed_span!(
+        "elgamal_reencrypt_mix",
+        count = ciphertexts.len(),
+        modulus_bits = g_table.modulus().significant_bits()
+    );
+    telemetry::record_batch(Batch::ElGamalReencryptMix, ciphertexts.len());
+    check_same_modulus(g_table, pk_table)?;
+    check_same_len(ciphertexts.len(), randomness.len())?;
+    check_permutation(permutation, ciphertexts.len())?;
+    let jobs = randomness.iter().zip(permutation).collect::<Vec<_>>();
+    Ok(map_chunks(&jobs, |&(r, &i)| {
+        reencrypt_unchecked(g_table, pk_table, &ciphertexts[i], r)
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fpowm::{FPownError, test_tables};
+
+    #[test]
+    fn test_encrypt() {
+        let (p, g, pk, g_table, pk_table) = test_tables();
+        let m = Integer::from(3);
+        let r = Integer::from(5);
+        let c = encrypt(&g_table, &pk_table, &m, &r).unwrap();
+        assert_eq!(c.gamma, g.pow_mod(&r, &p).unwrap());
+        assert_eq!(c.phi, (pk.pow_mod(&r, &p).unwrap() * m) % &p);
+    }
+
+    #[test]
+    fn test_encrypt_batch() {
+        let (_, _, _, g_table, pk_table) = test_tables();
+        let ms = [Integer::from(3), Integer::from(8), Integer::from(13)];
+        let rs = [Integer::from(5), Integer::from(1), Integer::from(10)];
+        let cs = encrypt_batch(&g_table, &pk_table, &ms, &rs).unwrap();
+        for ((m, r), c) in ms.iter().zip(rs.iter()).zip(cs.iter()) {
+            assert_eq!(c, &encrypt(&g_table, &pk_table, m, r).unwrap());
+        }
+        assert!(encrypt_batch(&g_table, &pk_table, &ms, &rs[..2]).is_err());
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let mut rand = RandState::new();
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        let keys = KeyPair::generate(&group, &mut rand);
+        assert!(keys.secret_key > 0 && keys.secret_key < group.q);
+        assert_eq!(
+            keys.public_key,
+            group.g.clone().pow_mod(&keys.secret_key, &group.p).unwrap()
+        );
+        let g_table = group.table().unwrap();
+        let pk_table = keys.public_key_table(&group).unwrap();
+        let messages = group.random_elements(10, &mut rand).unwrap();
+        let randomness = (0..10)
+            .map(|_| group.random_exponent(&mut rand))
+            .collect::<Vec<_>>();
+        let cs = encrypt_batch(&g_table, &pk_table, &messages, &randomness).unwrap();
+        assert_eq!(
+            decrypt_batch(&group.p, &keys.secret_key, &cs).unwrap(),
+            messages
+        );
+        let reencrypted = reencrypt_batch(&g_table, &pk_table, &cs, &randomness).unwrap();
+        for ((c, r), rc) in cs.iter().zip(randomness.iter()).zip(reencrypted.iter()) {
+            assert_ne!(c, rc);
+            assert_eq!(rc, &reencrypt(&g_table, &pk_table, c, r).unwrap());
+            assert_eq!(
+                rc.gamma,
+                group.g.clone().pow_mod(r, &group.p).unwrap() * &c.gamma % &group.p
+            );
+        }
+        assert_eq!(
+            decrypt_batch(&group.p, &keys.secret_key, &reencrypted).unwrap(),
+            messages
+        );
+        assert!(reencrypt_batch(&g_table, &pk_table, &cs, &randomness[..9]).is_err());
+        let zero = Ciphertext {
+            gamma: Integer::new(),
+            phi: Integer::from(1),
+        };
+        assert_eq!(
+            decrypt(&group.p, &keys.secret_key, &zero),
+            Err(ElGamalError::NotInvertible.into())
+        );
+    }
+
+    #[test]
+    fn test_reencrypt_mix() {
+        let mut rand = RandState::new();
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        let keys = KeyPair::generate(&group, &mut rand);
+        let g_table = group.table().unwrap();
+        let pk_table = keys.public_key_table(&group).unwrap();
+        let n = 25;
+        let messages = group.random_elements(n, &mut rand).unwrap();
+        let randomness = (0..n)
+            .map(|_| group.random_exponent(&mut rand))
+            .collect::<Vec<_>>();
+        let cs = encrypt_batch(&g_table, &pk_table, &messages, &randomness).unwrap();
+        let permutation = (0..n).map(|i| (7 * i + 3) % n).collect::<Vec<_>>();
+        let mixed = reencrypt_mix(&g_table, &pk_table, &cs, &randomness, &permutation).unwrap();
+        let decrypted = decrypt_batch(&group.p, &keys.secret_key, &mixed).unwrap();
+        for (i, (c, &j)) in mixed.iter().zip(permutation.iter()).enumerate() {
+            assert_eq!(
+                c,
+                &reencrypt(&g_table, &pk_table, &cs[j], &randomness[i]).unwrap()
+            );
+            assert_eq!(decrypted[i], messages[j]);
+        }
+        assert!(
+            reencrypt_mix(&g_table, &pk_table, &[], &[], &[])
+                .unwrap()
+                .is_empty()
+        );
+        let mut invalid = permutation.clone();
+        invalid[0] = invalid[1];
+        for permutation in [&invalid[..], &permutation[..n - 1], &[n; 25][..]] {
+            assert_eq!(
+                reencrypt_mix(&g_table, &pk_table, &cs, &randomness, permutation),
+                Err(ElGamalError::InvalidPermutation { len: n }.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_different_modulus() {
+        let (_, _, _, g_table, _) = test_tables();
+        let pk_table =
+            FPowmTable::init_precomp(&Integer::from(9), &Integer::from(29), 4, 8).unwrap();
+        assert_eq!(
+            encrypt(&g_table, &pk_table, &Integer::from(3), &Integer::from(5)),
+            Err(FPownError::DifferentModulus.into())
+        );
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Pure Rust implementations of the exponentiations of GMPMEE (only with the feature `fallback`)
+//!
+//! The functions and the tables of this module have the same signatures as [crate::spown::spowm]
+//! and as the constructor and the exponentiation of [crate::fpowm::FPowmTable], and use only the
+//! arithmetic of rug. They are slower than GMPMEE, but permit to run the same code where the
+//! native library behaves poorly or for the tests of the native code, by switching the import:
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::fallback::{FPowmTable, spowm};
+//! let p = Integer::from(1019);
+//! let bases = [Integer::from(2), Integer::from(3)];
+//! let exponents = [Integer::from(10), Integer::from(5)];
+//! assert_eq!(spowm(&bases, &exponents, &p).unwrap(), 1024 * 243 % 1019);
+//! let table = FPowmTable::init_precomp(&bases[0], &p, 4, 16).unwrap();
+//! assert_eq!(table.fpowm(&exponents[0]), 1024 % 1019);
+//! ```
+//!
+//! Without the feature `gmpmee` or on wasm32, the crate does not link GMPMEE: the same pure Rust code
+//! replaces it in [crate::spown], [crate::fpowm::FPowmTable] and the prime tests of [crate::miller_rabin].
+//! ```toml
+//! rug-gmpmee = { version = "0.2", default-features = false, features = ["cache", "fallback"] }
+//! ```
+
+#[cfg(any(not(feature = "gmpmee"), target_arch = "wasm32"))]
+pub(crate) mod sys;
+
+use crate::{
+    GmpMEEError,
+    fpowm::{FPownError, pow_mod_window_size},
+    spown::SPownError,
+};
+use rug::Integer;
+
+/// Fixed base exponentiation in pure Rust, with the comb method which uses the same parameters
+/// as the tables of GMPMEE (see [crate::fpowm::CombTable])
+pub type FPowmTable = crate::fpowm::CombTable;
+
+/// Largest window of [spowm], which keeps `2^(w-1)` odd powers for each base
+const MAX_WINDOW: usize = 6;
+
+/// Odd powers `base^1, base^3, ..., base^(2^window-1) mod modulus`
+fn odd_powers(base: &Integer, window: usize, modulus: &Integer) -> Vec<Integer> {
+    let square = Integer::from(base.square_ref()) % modulus;
+    let mut powers = Vec::with_capacity(1 << (window - 1));
+    powers.push(base.clone());
+    for j in 1..1 << (window - 1) {
+        let power = Integer::from(&powers[j - 1] * &square) % modulus;
+        powers.push(power);
+    }
+    powers
+}
+
+/// Multi exponentiation `prod_{i=0}^{n} b_i^{e_i} mod m` with interleaved sliding windows
+///
+/// The exponents are scanned together from the most significant bit, so that the squarings are
+/// shared by all the bases. The negative exponents use the inverse of the base.
+///
+/// Return an error if the numbers of bases and exponents are not the same, or if the base of a
+/// negative exponent is not invertible. Panics if the modulus is zero.
+pub fn spowm(
+    bases: &[Integer],
+    exponents: &[Integer],
+    modulus: &Integer,
+) -> Result<Integer, GmpMEEError> {
+    if bases.len() != exponents.len() {
+        return Err(SPownError::NotSameLen {
+            base: bases.len(),
+            exponent: exponents.len(),
+        }
+        .into());
+    }
+    let bits = exponents
+        .iter()
+        .map(|e| e.significant_bits() as usize)
+        .max()
+        .unwrap_or(0);
+    let window = pow_mod_window_size(bits).min(MAX_WINDOW);
+    // windows[i] contains the (base, odd power) multiplied after the squaring for the bit i
+    let mut windows = vec![Vec::new(); bits];
+    let mut powers = Vec::with_capacity(bases.len());
+    for (k, (base, exponent)) in bases.iter().zip(exponents).enumerate() {
+        let mut base = match *exponent < 0 {
+            true => Integer::from(base.invert_ref(modulus).ok_or(FPownError::NotInvertible)?),
+            false => Integer::from(base % modulus),
+        };
+        if base < 0 {
+            base += modulus;
+        }
+        let exponent = Integer::from(exponent.abs_ref());
+        let mut i = exponent.significant_bits() as usize;
+        while i > 0 {
+            i -= 1;
+            if !exponent.get_bit(i as u32) {
+                continue;
+            }
+            // the window ends at its lowest set bit, so that its value is odd
+            let low = (i.saturating_sub(window - 1)..=i)
+                .find(|&j| exponent.get_bit(j as u32))
+                .unwrap_or(i);
+            let value = (low..=i).rev().fold(0usize, |v, j| {
+                (v << 1) | exponent.get_bit(j as u32) as usize
+            });
+            windows[low].push((k, value >> 1));
+            i = low;
+        }
+        powers.push(odd_powers(&base, window, modulus));
+    }
+    let mut res = Integer::from(1) % modulus;
+    for i in (0..bits).rev() {
+        res.square_mut();
+        res %= modulus;
+        for &(k, index) in windows[i].iter() {
+            res *= &powers[k][index];
+            res %= modulus;
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spown;
+    use rug::rand::RandState;
+
+    #[test]
+    fn test_spowm() {
+        let mut rand = RandState::new();
+        let p = Integer::from(Integer::random_bits(256, &mut rand)) | 1u32;
+        for (len, bits) in [(1, 0), (1, 1), (3, 7), (5, 100), (10, 256), (4, 2000)] {
+            let bases = (0..len)
+                .map(|_| Integer::from(Integer::random_bits(256, &mut rand)))
+                .collect::<Vec<_>>();
+            let exponents = (0..len)
+                .map(|_| Integer::from(Integer::random_bits(bits, &mut rand)))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                spowm(&bases, &exponents, &p).unwrap(),
+                spown::spowm(&bases, &exponents, &p).unwrap(),
+                "{len} {bits}"
+            );
+        }
+        let p = Integer::from(1019);
+        let bases = [Integer::from(-2), Integer::from(3)];
+        let exponents = [Integer::from(-1), Integer::from(2)];
+        let expected = Integer::from(-2).pow_mod(&Integer::from(-1), &p).unwrap() * 9 % &p;
+        assert_eq!(spowm(&bases, &exponents, &p).unwrap(), expected);
+        assert_eq!(spowm(&[], &[], &p).unwrap(), 1);
+        assert_eq!(spowm(&bases, &exponents, &Integer::from(1)).unwrap(), 0);
+        assert_eq!(
+            spowm(&bases, &exponents[..1], &p),
+            Err(SPownError::NotSameLen {
+                base: 2,
+                exponent: 1
+            }
+            .into())
+        );
+        assert_eq!(
+            spowm(&[Integer::from(1019)], &[Integer::from(-1)], &p),
+            Err(FPownError::NotInvertible.into())
+        );
+    }
+}
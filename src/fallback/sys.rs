@@ -0,0 +1,462 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Pure Rust replacement of the functions of gmpmee-sys used by the crate, when the feature
+//! `gmpmee` is disabled or on wasm32
+//!
+//! The functions have the same signatures as the ones of GMPMEE, and the tables and the states have
+//! the same fields, so that [crate::spown], [crate::fpowm::FPowmTable] and [crate::miller_rabin] are
+//! the same code for both backends. The table of the fixed base exponentiation is the comb table of
+//! GMPMEE, with a single sub-table of `2^block_width` entries.
+
+#![allow(non_camel_case_types)]
+
+use gmp_mpfr_sys::gmp::{mpz_t, randstate_t};
+use rug::{Assign, Integer, rand::RandState};
+use std::{
+    ffi::{c_int, c_long, c_ulong},
+    mem::ManuallyDrop,
+    ptr, slice,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct gmpmee_spowm_tab {
+    pub len: c_long,
+    pub block_width: c_long,
+    pub tabs_len: c_long,
+    pub tabs: *mut *mut mpz_t,
+    pub modulus: mpz_t,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct gmpmee_fpowm_tab {
+    pub spowm_table: gmpmee_spowm_tab,
+    pub stretch: c_long,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct gmpmee_millerrabin_state {
+    pub n: mpz_t,
+    pub n_minus_1: mpz_t,
+    pub q: mpz_t,
+    pub k: c_ulong,
+    pub y: mpz_t,
+    pub r: mpz_t,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct gmpmee_millerrabin_safe_state {
+    pub nstate: gmpmee_millerrabin_state,
+    pub mstate: gmpmee_millerrabin_state,
+}
+
+/// Integer behind a pointer given to the functions
+unsafe fn int<'a>(z: *const mpz_t) -> &'a Integer {
+    unsafe { &*z.cast::<Integer>() }
+}
+
+/// Mutable integer behind a pointer given to the functions
+unsafe fn int_mut<'a>(z: *mut mpz_t) -> &'a mut Integer {
+    unsafe { &mut *z.cast::<Integer>() }
+}
+
+/// Call `f` with the random state behind the pointer, which stays owned by the caller
+unsafe fn with_rand<T>(rstate: *mut randstate_t, f: impl FnOnce(&mut RandState<'_>) -> T) -> T {
+    let mut rand = ManuallyDrop::new(unsafe { RandState::from_raw(rstate.read()) });
+    let res = f(&mut rand);
+    unsafe { rstate.write(ManuallyDrop::into_inner(rand).into_raw()) };
+    res
+}
+
+/// Replace `gmpmee_spowm` by [super::spowm]
+///
+/// The result is zero if the base of a negative exponent is not invertible.
+pub unsafe fn gmpmee_spowm(
+    rop: *mut mpz_t,
+    bases: *const mpz_t,
+    exponents: *const mpz_t,
+    len: c_long,
+    modulus: *const mpz_t,
+) {
+    let len = len as usize;
+    let (bases, exponents) = unsafe {
+        (
+            slice::from_raw_parts(bases.cast::<Integer>(), len),
+            slice::from_raw_parts(exponents.cast::<Integer>(), len),
+        )
+    };
+    let res = super::spowm(bases, exponents, unsafe { int(modulus) }).unwrap_or_default();
+    unsafe { int_mut(rop).assign(res) }
+}
+
+/// Entries of the single sub-table of a table initialized by [gmpmee_fpowm_init]
+unsafe fn fpowm_entries<'a>(table: *const gmpmee_fpowm_tab) -> &'a mut [Integer] {
+    unsafe {
+        let size = 1usize << (*table).spowm_table.block_width;
+        slice::from_raw_parts_mut((*(*table).spowm_table.tabs).cast::<Integer>(), size)
+    }
+}
+
+/// Replace `gmpmee_fpowm_init`, the blocks of the exponents having `stretch` bits
+pub unsafe fn gmpmee_fpowm_init(
+    table: *mut gmpmee_fpowm_tab,
+    modulus: *const mpz_t,
+    block_width: c_long,
+    expbitlen: c_long,
+) {
+    let entries = (0..1usize << block_width)
+        .map(|_| Integer::new())
+        .collect::<Box<[Integer]>>();
+    let tabs = Box::new([Box::into_raw(entries).cast::<mpz_t>()]);
+    let modulus = Integer::from(unsafe { int(modulus) });
+    unsafe {
+        table.write(gmpmee_fpowm_tab {
+            spowm_table: gmpmee_spowm_tab {
+                len: block_width,
+                block_width,
+                tabs_len: 1,
+                tabs: Box::into_raw(tabs).cast::<*mut mpz_t>(),
+                modulus: modulus.into_raw(),
+            },
+            stretch: (expbitlen + block_width - 1) / block_width,
+        })
+    }
+}
+
+/// Replace `gmpmee_fpowm_init_precomp`
+pub unsafe fn gmpmee_fpowm_init_precomp(
+    table: *mut gmpmee_fpowm_tab,
+    basis: *const mpz_t,
+    modulus: *const mpz_t,
+    block_width: c_long,
+    expbitlen: c_long,
+) {
+    unsafe {
+        gmpmee_fpowm_init(table, modulus, block_width, expbitlen);
+        gmpmee_fpowm_precomp(table, basis);
+    }
+}
+
+/// Replace `gmpmee_fpowm_precomp`: the entry `J` is `prod_{i in J} basis^(2^(i*stretch))`
+pub unsafe fn gmpmee_fpowm_precomp(table: *mut gmpmee_fpowm_tab, basis: *const mpz_t) {
+    let (modulus, stretch, entries) = unsafe {
+        (
+            int(&raw const (*table).spowm_table.modulus),
+            (*table).stretch,
+            fpowm_entries(table),
+        )
+    };
+    entries[0].assign(1);
+    entries[0] %= modulus;
+    let mut block_base = Integer::from(unsafe { int(basis) } % modulus);
+    if block_base < 0 {
+        block_base += modulus;
+    }
+    let mut half = 1;
+    while half < entries.len() {
+        let (low, high) = entries.split_at_mut(half);
+        for (entry, low) in high[..half].iter_mut().zip(low.iter()) {
+            entry.assign(low * &block_base);
+            *entry %= modulus;
+        }
+        for _ in 0..stretch {
+            block_base.square_mut();
+            block_base %= modulus;
+        }
+        half <<= 1;
+    }
+}
+
+/// Replace `gmpmee_fpowm`
+///
+/// The negative exponents and the exponents longer than the table are computed with `pow_mod`,
+/// the result being zero if the base is not invertible.
+pub unsafe fn gmpmee_fpowm(
+    rop: *mut mpz_t,
+    table: *const gmpmee_fpowm_tab,
+    exponent: *const mpz_t,
+) {
+    let (rop, modulus, block_width, stretch, entries, exponent) = unsafe {
+        (
+            int_mut(rop),
+            int(&raw const (*table).spowm_table.modulus),
+            (*table).spowm_table.block_width as usize,
+            (*table).stretch as usize,
+            fpowm_entries(table),
+            int(exponent),
+        )
+    };
+    if *exponent < 0 || exponent.significant_bits() as usize > block_width * stretch {
+        match entries[1].pow_mod_ref(exponent, modulus) {
+            Some(res) => rop.assign(res),
+            None => rop.assign(0),
+        }
+        return;
+    }
+    rop.assign(&entries[0]);
+    for k in (0..stretch).rev() {
+        rop.square_mut();
+        let index = (0..block_width)
+            .filter(|i| exponent.get_bit((i * stretch + k) as u32))
+            .fold(0usize, |index, i| index | (1 << i));
+        *rop *= &entries[index];
+        *rop %= modulus;
+    }
+}
+
+/// Replace `gmpmee_fpowm_clear`
+pub unsafe fn gmpmee_fpowm_clear(table: *mut gmpmee_fpowm_tab) {
+    unsafe {
+        let spowm_table = &mut (*table).spowm_table;
+        if !spowm_table.tabs.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                (*spowm_table.tabs).cast::<Integer>(),
+                1usize << spowm_table.block_width,
+            )));
+            drop(Box::from_raw(spowm_table.tabs.cast::<[*mut mpz_t; 1]>()));
+            spowm_table.tabs = ptr::null_mut();
+        }
+        drop(Integer::from_raw(spowm_table.modulus));
+    }
+}
+
+/// Answer for the candidates smaller than 5 and the even candidates, which have no random base
+fn trivial_answer(n: &Integer) -> Option<bool> {
+    if *n < 5 {
+        return Some(*n == 2 || *n == 3);
+    }
+    n.is_even().then_some(false)
+}
+
+/// Write `n-1 = q*2^k` in the state, for its candidate `n`
+unsafe fn decompose(state: *mut gmpmee_millerrabin_state) {
+    unsafe {
+        let n_minus_1 = int_mut(&raw mut (*state).n_minus_1);
+        n_minus_1.assign(int(&raw const (*state).n) - 1u32);
+        let k = n_minus_1.find_one(0).unwrap_or(0);
+        int_mut(&raw mut (*state).q).assign(&*n_minus_1 >> k);
+        (*state).k = k.into();
+    }
+}
+
+/// Replace `gmpmee_millerrabin_init`
+pub unsafe fn gmpmee_millerrabin_init(state: *mut gmpmee_millerrabin_state, n: *const mpz_t) {
+    unsafe {
+        state.write(gmpmee_millerrabin_state {
+            n: Integer::from(int(n)).into_raw(),
+            n_minus_1: Integer::new().into_raw(),
+            q: Integer::new().into_raw(),
+            k: 0,
+            y: Integer::new().into_raw(),
+            r: Integer::new().into_raw(),
+        });
+        decompose(state);
+    }
+}
+
+/// Replace `gmpmee_millerrabin_next_cand`, i.e. move to `n+2`
+pub unsafe fn gmpmee_millerrabin_next_cand(state: *mut gmpmee_millerrabin_state) {
+    unsafe {
+        *int_mut(&raw mut (*state).n) += 2u32;
+        decompose(state);
+    }
+}
+
+/// Replace `gmpmee_millerrabin_once`, i.e. one round with the base `base`
+pub unsafe fn gmpmee_millerrabin_once(
+    state: *mut gmpmee_millerrabin_state,
+    base: *const mpz_t,
+) -> c_int {
+    let (n, n_minus_1, q, k, y) = unsafe {
+        (
+            int(&raw const (*state).n),
+            int(&raw const (*state).n_minus_1),
+            int(&raw const (*state).q),
+            (*state).k,
+            int_mut(&raw mut (*state).y),
+        )
+    };
+    match unsafe { int(base) }.pow_mod_ref(q, n) {
+        Some(power) => y.assign(power),
+        None => return 0,
+    }
+    if *y == 1 || y == n_minus_1 {
+        return 1;
+    }
+    for _ in 1..k {
+        y.square_mut();
+        *y %= n;
+        if y == n_minus_1 {
+            return 1;
+        }
+        if *y == 1 {
+            return 0;
+        }
+    }
+    0
+}
+
+/// Replace `gmpmee_millerrabin_reps_rs`, the bases being drawn uniformly in `[2, n-2]`
+pub unsafe fn gmpmee_millerrabin_reps_rs(
+    rstate: *mut randstate_t,
+    state: *mut gmpmee_millerrabin_state,
+    reps: c_int,
+) -> c_int {
+    let n = unsafe { int(&raw const (*state).n) };
+    if let Some(answer) = trivial_answer(n) {
+        return answer.into();
+    }
+    let bound = Integer::from(n - 3u32);
+    let mut base = Integer::new();
+    let passed = unsafe {
+        with_rand(rstate, |rand| {
+            (0..reps).all(|_| {
+                base.assign(bound.random_below_ref(rand));
+                base += 2u32;
+                gmpmee_millerrabin_once(state, base.as_raw()) != 0
+            })
+        })
+    };
+    passed.into()
+}
+
+/// Replace `gmpmee_millerrabin_clear`
+pub unsafe fn gmpmee_millerrabin_clear(state: *mut gmpmee_millerrabin_state) {
+    unsafe {
+        let state = state.read();
+        for z in [state.n, state.n_minus_1, state.q, state.y, state.r] {
+            drop(Integer::from_raw(z));
+        }
+    }
+}
+
+/// Replace `gmpmee_millerrabin_rs`, i.e. `reps` rounds for `n`
+pub unsafe fn gmpmee_millerrabin_rs(
+    rstate: *mut randstate_t,
+    n: *const mpz_t,
+    reps: c_int,
+) -> c_int {
+    unsafe {
+        let mut state = std::mem::MaybeUninit::uninit();
+        gmpmee_millerrabin_init(state.as_mut_ptr(), n);
+        let res = gmpmee_millerrabin_reps_rs(rstate, state.as_mut_ptr(), reps);
+        gmpmee_millerrabin_clear(state.as_mut_ptr());
+        res
+    }
+}
+
+/// Replace `gmpmee_millerrabin_safe_init`, the second state testing `(n-1)/2`
+pub unsafe fn gmpmee_millerrabin_safe_init(
+    state: *mut gmpmee_millerrabin_safe_state,
+    n: *const mpz_t,
+) {
+    let m = Integer::from(unsafe { int(n) } >> 1);
+    unsafe {
+        gmpmee_millerrabin_init(&raw mut (*state).nstate, n);
+        gmpmee_millerrabin_init(&raw mut (*state).mstate, m.as_raw());
+    }
+}
+
+/// Replace `gmpmee_millerrabin_safe_next_cand`, i.e. move to `n+4`, keeping `n = 3 mod 4`
+pub unsafe fn gmpmee_millerrabin_safe_next_cand(state: *mut gmpmee_millerrabin_safe_state) {
+    unsafe {
+        *int_mut(&raw mut (*state).nstate.n) += 4u32;
+        decompose(&raw mut (*state).nstate);
+        gmpmee_millerrabin_next_cand(&raw mut (*state).mstate);
+    }
+}
+
+/// Replace `gmpmee_millerrabin_safe_reps_rs`, i.e. `reps` rounds for `n` and then for `(n-1)/2`
+pub unsafe fn gmpmee_millerrabin_safe_reps_rs(
+    rstate: *mut randstate_t,
+    state: *mut gmpmee_millerrabin_safe_state,
+    reps: c_int,
+) -> c_int {
+    unsafe {
+        let passed = gmpmee_millerrabin_reps_rs(rstate, &raw mut (*state).nstate, reps) != 0
+            && gmpmee_millerrabin_reps_rs(rstate, &raw mut (*state).mstate, reps) != 0;
+        passed.into()
+    }
+}
+
+/// Replace `gmpmee_millerrabin_safe_clear`
+pub unsafe fn gmpmee_millerrabin_safe_clear(state: *mut gmpmee_millerrabin_safe_state) {
+    unsafe {
+        gmpmee_millerrabin_clear(&raw mut (*state).nstate);
+        gmpmee_millerrabin_clear(&raw mut (*state).mstate);
+    }
+}
+
+/// Replace `gmpmee_millerrabin_safe_rs`, i.e. `reps` rounds for `n` and for `(n-1)/2`
+pub unsafe fn gmpmee_millerrabin_safe_rs(
+    rstate: *mut randstate_t,
+    n: *const mpz_t,
+    reps: c_int,
+) -> c_int {
+    unsafe {
+        let mut state = std::mem::MaybeUninit::uninit();
+        gmpmee_millerrabin_safe_init(state.as_mut_ptr(), n);
+        let res = gmpmee_millerrabin_safe_reps_rs(rstate, state.as_mut_ptr(), reps);
+        gmpmee_millerrabin_safe_clear(state.as_mut_ptr());
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{fpowm::FPowmTable, miller_rabin::miller_rabin_with_rand};
+
+    #[test]
+    fn test_fpowm() {
+        let mut rand = RandState::new();
+        let p = Integer::from(Integer::random_bits(256, &mut rand)) | 1u32;
+        let b = Integer::from(Integer::random_bits(256, &mut rand)) - &p;
+        for (block_width, exponent_bitlen) in [(1, 0), (1, 64), (4, 256), (5, 100)] {
+            let table = FPowmTable::init_precomp(&b, &p, block_width, exponent_bitlen).unwrap();
+            for bits in [0, 1, exponent_bitlen as u32, 300] {
+                let e = Integer::from(Integer::random_bits(bits, &mut rand));
+                assert_eq!(
+                    table.fpowm(&e),
+                    b.pow_mod_ref(&e, &p).map(Integer::from).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_miller_rabin() {
+        let mut rand = RandState::new();
+        for n in 0..2000u32 {
+            let n = Integer::from(n);
+            let expected = n.is_probably_prime(30) != rug::integer::IsPrime::No;
+            assert_eq!(miller_rabin_with_rand(&n, 16, &mut rand), expected, "{n}");
+            let passed = unsafe { gmpmee_millerrabin_rs(rand.as_raw_mut(), n.as_raw(), 16) };
+            assert_eq!(passed != 0, expected, "{n}");
+        }
+        // 1019 is a safe prime, 1021 is prime but 510 is not
+        let mut safe = |n: u32| unsafe {
+            gmpmee_millerrabin_safe_rs(rand.as_raw_mut(), Integer::from(n).as_raw(), 16) != 0
+        };
+        assert!(safe(1019));
+        assert!(!safe(1021));
+        assert!(!safe(1023));
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module for the conversions between [Integer] and the elements of the prime fields of
+//! [arkworks](https://docs.rs/ark-ff) (only with the feature `ark-ff`) and of
+//! [ff](https://docs.rs/ff) (only with the feature `ff`)
+//!
+//! The integers converted to the field must be in `[0, modulus)`, so that a value is never reduced
+//! silently. The modulus of the field is given as [Integer] by [ark_modulus] and [ff_modulus], to
+//! check e.g. the public parameters with the exponentiations of the crate.
+
+use crate::GmpMEEError;
+use rug::{Integer, integer::Order};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FieldError {
+    #[error("The integer {value} is not in [0, {modulus})")]
+    OutOfRange { value: Integer, modulus: Integer },
+}
+
+/// Check that the value is in `[0, modulus)`
+fn check_range(value: &Integer, modulus: Integer) -> Result<(), GmpMEEError> {
+    if *value < 0 || *value >= modulus {
+        return Err(FieldError::OutOfRange {
+            value: value.clone(),
+            modulus,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Modulus of the prime field of arkworks
+#[cfg(feature = "ark-ff")]
+pub fn ark_modulus<F: ark_ff::PrimeField>() -> Integer {
+    use ark_ff::BigInteger;
+    Integer::from_digits(&F::MODULUS.to_bytes_le(), Order::Lsf)
+}
+
+/// Integer in `[0, modulus)` of the element of the prime field of arkworks
+#[cfg(feature = "ark-ff")]
+pub fn integer_from_ark<F: ark_ff::PrimeField>(element: &F) -> Integer {
+    use ark_ff::BigInteger;
+    Integer::from_digits(&element.into_bigint().to_bytes_le(), Order::Lsf)
+}
+
+/// Element of the prime field of arkworks of the integer
+///
+/// Return an error if the integer is not in `[0, modulus)`.
+#[cfg(feature = "ark-ff")]
+pub fn ark_from_integer<F: ark_ff::PrimeField>(value: &Integer) -> Result<F, GmpMEEError> {
+    check_range(value, ark_modulus::<F>())?;
+    Ok(F::from_le_bytes_mod_order(
+        &value.to_digits::<u8>(Order::Lsf),
+    ))
+}
+
+/// Byte order of the representations of the field of ff, which is not fixed by the trait
+///
+/// The representation of one has its only non-zero byte at the start in little-endian.
+#[cfg(feature = "ff")]
+fn ff_order<F: ff::PrimeField>() -> Order {
+    match F::ONE.to_repr().as_ref().first() {
+        Some(1) => Order::Lsf,
+        _ => Order::Msf,
+    }
+}
+
+/// Modulus of the prime field of ff
+#[cfg(feature = "ff")]
+pub fn ff_modulus<F: ff::PrimeField>() -> Integer {
+    let hex = F::MODULUS.trim_start_matches("0x");
+    Integer::from_str_radix(hex, 16).expect("the modulus of ff must be hexadecimal")
+}
+
+/// Integer in `[0, modulus)` of the element of the prime field of ff
+#[cfg(feature = "ff")]
+pub fn integer_from_ff<F: ff::PrimeField>(element: &F) -> Integer {
+    Integer::from_digits(element.to_repr().as_ref(), ff_order::<F>())
+}
+
+/// Element of the prime field of ff of the integer
+///
+/// Return an error if the integer is not in `[0, modulus)`.
+#[cfg(feature = "ff")]
+pub fn ff_from_integer<F: ff::PrimeField>(value: &Integer) -> Result<F, GmpMEEError> {
+    check_range(value, ff_modulus::<F>())?;
+    let order = ff_order::<F>();
+    let digits = value.to_digits::<u8>(order);
+    // the default representation is zero, so that only the digits are written
+    let mut repr = F::Repr::default();
+    let bytes = repr.as_mut();
+    let start = match order {
+        Order::Lsf => 0,
+        _ => bytes.len() - digits.len(),
+    };
+    bytes[start..start + digits.len()].copy_from_slice(&digits);
+    Ok(Option::from(F::from_repr(repr)).expect("the integer is smaller than the modulus"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "ark-ff")]
+    mod ark {
+        use ark_ff::{Fp64, MontBackend, MontConfig};
+
+        #[derive(MontConfig)]
+        #[modulus = "1019"]
+        #[generator = "2"]
+        pub struct FConfig;
+        pub type F = Fp64<MontBackend<FConfig, 1>>;
+    }
+
+    #[cfg(feature = "ark-ff")]
+    #[test]
+    fn test_ark() {
+        use ark::F;
+        assert_eq!(ark_modulus::<F>(), 1019);
+        for v in [0, 1, 2, 500, 1018] {
+            let value = Integer::from(v);
+            let element = ark_from_integer::<F>(&value).unwrap();
+            assert_eq!(element, F::from(v as u64));
+            assert_eq!(integer_from_ark(&element), value);
+        }
+        assert_eq!(integer_from_ark(&-F::from(1u64)), 1018);
+        for v in [-1, 1019] {
+            assert_eq!(
+                ark_from_integer::<F>(&Integer::from(v)),
+                Err(FieldError::OutOfRange {
+                    value: Integer::from(v),
+                    modulus: Integer::from(1019)
+                }
+                .into())
+            );
+        }
+    }
+    // the derive macro defines constants in the module, hence one module per field
+    #[cfg(feature = "ff")]
+    mod ff_little {
+        use ff::PrimeField;
+
+        #[derive(PrimeField)]
+        #[PrimeFieldModulus = "1019"]
+        #[PrimeFieldGenerator = "2"]
+        #[PrimeFieldReprEndianness = "little"]
+        pub struct F([u64; 1]);
+    }
+
+    #[cfg(feature = "ff")]
+    mod ff_big {
+        use ff::PrimeField;
+
+        #[derive(PrimeField)]
+        #[PrimeFieldModulus = "1019"]
+        #[PrimeFieldGenerator = "2"]
+        #[PrimeFieldReprEndianness = "big"]
+        pub struct F([u64; 1]);
+    }
+
+    #[cfg(feature = "ff")]
+    fn check_ff<F: ff::PrimeField>() {
+        assert_eq!(ff_modulus::<F>(), 1019);
+        for v in [0, 1, 2, 500, 1018] {
+            let value = Integer::from(v);
+            let element = ff_from_integer::<F>(&value).unwrap();
+            assert_eq!(element, F::from(v as u64));
+            assert_eq!(integer_from_ff(&element), value);
+        }
+        assert_eq!(integer_from_ff(&-F::ONE), 1018);
+        assert!(ff_from_integer::<F>(&Integer::from(1019)).is_err());
+        assert!(ff_from_integer::<F>(&Integer::from(-1)).is_err());
+    }
+
+    #[cfg(feature = "ff")]
+    #[test]
+    fn test_ff() {
+        check_ff::<ff_little::F>();
+        check_ff::<ff_big::F>();
+    }
+}
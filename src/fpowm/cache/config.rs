@@ -0,0 +1,221 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Initialization of the static cache from a configuration source (only with the feature `config`)
+//!
+//! The configuration contains the base and the modulus in hexadecimal, and optionally the block
+//! width and the exponent bit length. The missing values are taken from the defaults (see
+//! [crate::fpowm::set_defaults]). In a TOML file:
+//! ```toml
+//! base = "7"
+//! modulus = "3FB"
+//! block_width = 4
+//! exponent_bitlen = 16
+//! ```
+//! The same keys are used in a JSON file. In the environment, the variables are
+//! `RUG_GMPMEE_CACHE_BASE`, `RUG_GMPMEE_CACHE_MODULUS`, `RUG_GMPMEE_CACHE_BLOCK_WIDTH` and
+//! `RUG_GMPMEE_CACHE_EXPONENT_BITLEN`.
+
+use super::{CacheError, cache_init_precomp};
+use crate::{GmpMEEError, fpowm::defaults};
+use rug::Integer;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the environment variables read by [CacheConfig::from_env]
+pub const CACHE_ENV_PREFIX: &str = "RUG_GMPMEE_CACHE_";
+
+/// Source of the configuration of the cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheConfigSource {
+    /// TOML or JSON file, according to the extension of the file (`.json` for JSON, TOML otherwise)
+    File(PathBuf),
+    /// Environment variables with the prefix [CACHE_ENV_PREFIX]
+    Env,
+}
+
+/// Parameters of the static cache read from a configuration source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheConfig {
+    pub base: Integer,
+    pub modulus: Integer,
+    pub block_width: usize,
+    pub exponent_bitlen: usize,
+}
+
+/// Configuration as written in the source, before the parsing of the integers
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCacheConfig {
+    base: String,
+    modulus: String,
+    block_width: Option<usize>,
+    exponent_bitlen: Option<usize>,
+}
+
+fn config_error(msg: impl Into<String>) -> GmpMEEError {
+    CacheError::Config(msg.into()).into()
+}
+
+fn parse_hex(key: &str, value: &str) -> Result<Integer, GmpMEEError> {
+    crate::parse_hex(value)
+        .map_err(|e| config_error(format!("{key} is not a hexadecimal number: {e}")))
+}
+
+impl TryFrom<RawCacheConfig> for CacheConfig {
+    type Error = GmpMEEError;
+
+    fn try_from(raw: RawCacheConfig) -> Result<Self, Self::Error> {
+        let base = parse_hex("base", &raw.base)?;
+        let modulus = parse_hex("modulus", &raw.modulus)?;
+        let defaults = defaults();
+        Ok(Self {
+            block_width: raw.block_width.unwrap_or(defaults.block_width),
+            exponent_bitlen: raw
+                .exponent_bitlen
+                .unwrap_or_else(|| defaults.exponent_bitlen_policy.exponent_bitlen(&modulus)),
+            base,
+            modulus,
+        })
+    }
+}
+
+impl CacheConfig {
+    /// Read the configuration from the source
+    pub fn from_source(source: &CacheConfigSource) -> Result<Self, GmpMEEError> {
+        match source {
+            CacheConfigSource::File(path) => Self::from_file(path),
+            CacheConfigSource::Env => Self::from_env(),
+        }
+    }
+
+    /// Read the configuration from a TOML or JSON file (`.json` for JSON, TOML otherwise)
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, GmpMEEError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| config_error(format!("cannot read {}: {e}", path.display())))?;
+        match path.extension().is_some_and(|ext| ext == "json") {
+            true => Self::from_json_str(&content),
+            false => Self::from_toml_str(&content),
+        }
+    }
+
+    /// Read the configuration from a TOML string
+    pub fn from_toml_str(s: &str) -> Result<Self, GmpMEEError> {
+        toml::from_str::<RawCacheConfig>(s)
+            .map_err(|e| config_error(e.to_string()))?
+            .try_into()
+    }
+
+    /// Read the configuration from a JSON string
+    pub fn from_json_str(s: &str) -> Result<Self, GmpMEEError> {
+        serde_json::from_str::<RawCacheConfig>(s)
+            .map_err(|e| config_error(e.to_string()))?
+            .try_into()
+    }
+
+    /// Read the configuration from the environment variables with the prefix [CACHE_ENV_PREFIX]
+    pub fn from_env() -> Result<Self, GmpMEEError> {
+        Self::from_vars(|key| std::env::var(format!("{CACHE_ENV_PREFIX}{key}")).ok())
+    }
+
+    /// Read the configuration with the function returning the value of a key (e.g. `BASE`)
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, GmpMEEError> {
+        let required = |key: &str| {
+            var(key).ok_or_else(|| config_error(format!("{CACHE_ENV_PREFIX}{key} is not set")))
+        };
+        let optional = |key: &str| {
+            var(key)
+                .map(|v| {
+                    v.trim().parse::<usize>().map_err(|e| {
+                        config_error(format!("{CACHE_ENV_PREFIX}{key} is not a number: {e}"))
+                    })
+                })
+                .transpose()
+        };
+        RawCacheConfig {
+            base: required("BASE")?,
+            modulus: required("MODULUS")?,
+            block_width: optional("BLOCK_WIDTH")?,
+            exponent_bitlen: optional("EXPONENT_BITLEN")?,
+        }
+        .try_into()
+    }
+}
+
+/// Initialize the static cache with the parameters read from the configuration source
+///
+/// Return the same result as [cache_init_precomp].
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::fpowm::{CacheConfigSource, cache_fpown, cache_init_from_config};
+/// let path = std::env::temp_dir().join("rug-gmpmee-doc-cache.toml");
+/// std::fs::write(&path, "base = \"7\"\nmodulus = \"3FB\"\nblock_width = 4\n").unwrap();
+/// assert!(cache_init_from_config(&CacheConfigSource::File(path.clone())).unwrap());
+/// let (b, p, e) = (Integer::from(7), Integer::from(1019), Integer::from(1000));
+/// assert_eq!(cache_fpown(&e).unwrap(), b.pow_mod(&e, &p).unwrap());
+/// std::fs::remove_file(path).unwrap();
+/// ```
+pub fn cache_init_from_config(source: &CacheConfigSource) -> Result<bool, GmpMEEError> {
+    let config = CacheConfig::from_source(source)?;
+    cache_init_precomp(
+        &config.base,
+        &config.modulus,
+        config.block_width,
+        config.exponent_bitlen,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_config() {
+        let expected = CacheConfig {
+            base: Integer::from(7),
+            modulus: Integer::from(1019),
+            block_width: 4,
+            exponent_bitlen: 16,
+        };
+        let toml = "base = \"7\"\nmodulus = \"0x3FB\"\nblock_width = 4\nexponent_bitlen = 16\n";
+        assert_eq!(CacheConfig::from_toml_str(toml).unwrap(), expected);
+        let json = r#"{"base": "7", "modulus": "3fb", "block_width": 4, "exponent_bitlen": 16}"#;
+        assert_eq!(CacheConfig::from_json_str(json).unwrap(), expected);
+        let vars = HashMap::from([
+            ("BASE", "7"),
+            ("MODULUS", "3FB"),
+            ("BLOCK_WIDTH", "4"),
+            ("EXPONENT_BITLEN", " 16"),
+        ]);
+        assert_eq!(
+            CacheConfig::from_vars(|k| vars.get(k).map(|v| v.to_string())).unwrap(),
+            expected
+        );
+        let config = CacheConfig::from_json_str(r#"{"base": "7", "modulus": "3FB"}"#).unwrap();
+        assert_eq!(config.block_width, defaults().block_width);
+        assert!(matches!(
+            CacheConfig::from_toml_str("base = \"7\"\nmodulus = \"xyz\"\n"),
+            Err(GmpMEEError::Cache(CacheError::Config(_)))
+        ));
+        assert!(CacheConfig::from_toml_str("base = \"7\"\n").is_err());
+        assert!(CacheConfig::from_json_str(r#"{"base": "7", "modulus": "3FB", "x": 1}"#).is_err());
+        assert!(CacheConfig::from_json_str(r#"{"base": "-7", "modulus": "3FB"}"#).is_err());
+        assert!(CacheConfig::from_vars(|k| (k == "BASE").then(|| "7".to_string())).is_err());
+        assert!(CacheConfig::from_file("/non/existing/file.toml").is_err());
+    }
+}
@@ -0,0 +1,1327 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Caches with a process-global state (only with the feature `cache`)
+//!
+//! See the documentation of the parent module for the description of the caches.
+
+use super::{CacheEntry, FPowmCache};
+use crate::{
+    GmpMEEError,
+    fpowm::{FPowmDefaults, FPowmTable, FPownError, defaults, serialize::io_error},
+    parallel::THREADS,
+};
+use rug::Integer;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+use std::sync::{Arc, Condvar, LazyLock, Mutex, OnceLock, RwLock};
+use thiserror::Error;
+
+/// Errors of the caches with a process-global state
+///
+/// The variants replace `FPownError::CacheNotInitialized` and `FPownError::CacheAlreadyInitialized`,
+/// which are deprecated. The old variants can be obtained with `FPownError::try_from`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CacheError {
+    #[error("The cache is not initialized")]
+    NotInitialized,
+    #[error("The cache is already initialized with another base or modulus")]
+    ParameterMismatch {
+        existing_base: Integer,
+        existing_modulus: Integer,
+    },
+    #[error("Error in the configuration of the cache: {0}")]
+    Config(String),
+    #[error("The initialization of the cache panicked: {0}")]
+    InitPanicked(String),
+}
+
+#[allow(deprecated)]
+impl TryFrom<CacheError> for FPownError {
+    type Error = CacheError;
+
+    /// Convert the errors that have a deprecated counterpart in [FPownError], and return the
+    /// other errors unchanged
+    fn try_from(value: CacheError) -> Result<Self, CacheError> {
+        match value {
+            CacheError::NotInitialized => Ok(FPownError::CacheNotInitialized),
+            CacheError::ParameterMismatch {
+                existing_base,
+                existing_modulus,
+            } => Ok(FPownError::CacheAlreadyInitialized {
+                existing_base,
+                existing_modulus,
+            }),
+            other => Err(other),
+        }
+    }
+}
+
+/// Snapshot of the counters of the caches (see [cache_metrics])
+///
+/// The counters are common to all the caches of the module (static, named, thread and [FPowmCache]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheMetrics {
+    /// Number of tables calculated for the caches
+    pub initializations: u64,
+    /// Number of lookups of a table in the caches
+    pub lookups: u64,
+    /// Number of lookups finding the table
+    pub hits: u64,
+    /// Number of lookups not finding the table
+    pub misses: u64,
+    /// Number of tables removed from a [FPowmCache] to respect its memory budget
+    pub evictions: u64,
+    /// Estimation of the memory currently used by the tables in the caches, in bytes
+    pub bytes: u64,
+}
+
+impl CacheMetrics {
+    /// Proportion of the lookups finding the table (`None` if there was no lookup)
+    pub fn hit_ratio(&self) -> Option<f64> {
+        (self.lookups > 0).then(|| self.hits as f64 / self.lookups as f64)
+    }
+}
+
+pub(super) struct AtomicCacheMetrics {
+    pub(super) initializations: AtomicU64,
+    pub(super) lookups: AtomicU64,
+    pub(super) hits: AtomicU64,
+    pub(super) misses: AtomicU64,
+    pub(super) evictions: AtomicU64,
+    pub(super) bytes: AtomicU64,
+}
+
+impl AtomicCacheMetrics {
+    pub(super) fn record_lookup(&self, hit: bool) {
+        crate::telemetry::record_cache_lookup(hit);
+        self.lookups.fetch_add(1, Relaxed);
+        match hit {
+            true => self.hits.fetch_add(1, Relaxed),
+            false => self.misses.fetch_add(1, Relaxed),
+        };
+    }
+}
+
+pub(super) static METRICS: AtomicCacheMetrics = AtomicCacheMetrics {
+    initializations: AtomicU64::new(0),
+    lookups: AtomicU64::new(0),
+    hits: AtomicU64::new(0),
+    misses: AtomicU64::new(0),
+    evictions: AtomicU64::new(0),
+    bytes: AtomicU64::new(0),
+};
+
+/// Return the current counters of the caches
+pub fn cache_metrics() -> CacheMetrics {
+    CacheMetrics {
+        initializations: METRICS.initializations.load(Relaxed),
+        lookups: METRICS.lookups.load(Relaxed),
+        hits: METRICS.hits.load(Relaxed),
+        misses: METRICS.misses.load(Relaxed),
+        evictions: METRICS.evictions.load(Relaxed),
+        bytes: METRICS.bytes.load(Relaxed),
+    }
+}
+
+/// Reset the counters of the caches to zero, except the memory used by the tables
+pub fn reset_cache_metrics() {
+    METRICS.initializations.store(0, Relaxed);
+    METRICS.lookups.store(0, Relaxed);
+    METRICS.hits.store(0, Relaxed);
+    METRICS.misses.store(0, Relaxed);
+    METRICS.evictions.store(0, Relaxed);
+}
+
+static GLOBAL_CACHE: LazyLock<FPowmCache> = LazyLock::new(FPowmCache::new);
+
+/// Global [FPowmCache] of the process, without memory budget at the beginning
+pub fn global_cache() -> &'static FPowmCache {
+    &GLOBAL_CACHE
+}
+
+/// Precompute the tables of the global cache ([global_cache]) for all the pairs `(base, modulus)`
+/// in parallel (see [FPowmCache::warm])
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::fpowm::{cache_warm, defaults, global_cache};
+/// let p = Integer::from(1019);
+/// let pairs: Vec<_> = (2..6).map(|b| (Integer::from(b), p.clone())).collect();
+/// cache_warm(&pairs, defaults()).unwrap();
+/// assert!(global_cache().get(&Integer::from(3), &p).is_some());
+/// ```
+pub fn cache_warm(pairs: &[(Integer, Integer)], params: FPowmDefaults) -> Result<(), GmpMEEError> {
+    global_cache().warm(pairs, params)
+}
+
+static CACHE_FPOWM_TABLE: OnceLock<FPownMTableStatic> = OnceLock::new();
+
+struct FPownMTableStatic {
+    table: Arc<FPowmTable>,
+    modulus: Arc<Integer>,
+    base: Arc<Integer>,
+    hits: AtomicU64,
+}
+
+impl FPownMTableStatic {
+    fn new(
+        base: &Integer,
+        modulus: &Integer,
+        block_width: usize,
+        exponent_bitlen: usize,
+    ) -> Result<Self, GmpMEEError> {
+        let table = FPowmTable::init_precomp(base, modulus, block_width, exponent_bitlen)?;
+        METRICS.initializations.fetch_add(1, Relaxed);
+        Self::from_table(table)
+    }
+
+    /// Wrap a table, whose precomputation must have been done
+    fn from_table(table: FPowmTable) -> Result<Self, GmpMEEError> {
+        let base = Arc::new(table.base().ok_or(FPownError::NotPrecomputed)?.clone());
+        let modulus = Arc::new(table.modulus().clone());
+        METRICS.bytes.fetch_add(table.memory_size() as u64, Relaxed);
+        Ok(Self {
+            table: Arc::new(table),
+            modulus,
+            base,
+            hits: AtomicU64::new(0),
+        })
+    }
+
+    /// Return `Ok(false)` if the cache has the same base and modulus, and an error otherwise
+    fn check_same(&self, base: &Integer, modulus: &Integer) -> Result<bool, GmpMEEError> {
+        if *self.base == *base && *self.modulus == *modulus {
+            return Ok(false);
+        }
+        Err(CacheError::ParameterMismatch {
+            existing_base: Integer::clone(&self.base),
+            existing_modulus: Integer::clone(&self.modulus),
+        }
+        .into())
+    }
+
+    /// Exponentiation for a lookup of the cache
+    fn fpowm(&self, exponent: &Integer) -> Integer {
+        METRICS.record_lookup(true);
+        self.hits.fetch_add(1, Relaxed);
+        self.table.fpowm(exponent)
+    }
+
+    fn base_modulus(&self) -> (Arc<Integer>, Arc<Integer>) {
+        (self.base.clone(), self.modulus.clone())
+    }
+
+    fn entry(&self) -> CacheEntry {
+        CacheEntry::new(&self.table, self.hits.load(Relaxed))
+    }
+}
+
+impl Drop for FPownMTableStatic {
+    fn drop(&mut self) {
+        METRICS
+            .bytes
+            .fetch_sub(self.table.memory_size() as u64, Relaxed);
+    }
+}
+
+fn is_cache_initialized() -> bool {
+    CACHE_FPOWM_TABLE.get().is_some()
+}
+
+/// Initialize the cache with the given parameters.
+///
+/// The cache cannot be changed anymore. If the cache is already initialized, return `Ok(false)` if
+/// the base and the modulus are the same, and the error [CacheError::ParameterMismatch]
+/// otherwise
+pub fn cache_init_precomp(
+    base: &Integer,
+    modulus: &Integer,
+    block_width: usize,
+    exponent_bitlen: usize,
+) -> Result<bool, GmpMEEError> {
+    if let Some(cache) = CACHE_FPOWM_TABLE.get() {
+        return cache.check_same(base, modulus);
+    }
+    match CACHE_FPOWM_TABLE.set(FPownMTableStatic::new(
+        base,
+        modulus,
+        block_width,
+        exponent_bitlen,
+    )?) {
+        Ok(()) => Ok(true),
+        Err(_) => CACHE_FPOWM_TABLE.get().unwrap().check_same(base, modulus),
+    }
+}
+
+/// Install a table built elsewhere (e.g. read from a file or calculated with custom parameters) in
+/// the static cache
+///
+/// The precomputation of the table must have been done. As for [cache_init_precomp], the cache cannot
+/// be changed anymore: if the cache is already initialized, return `Ok(false)` if the base and the
+/// modulus are the same, and the error [CacheError::ParameterMismatch] otherwise
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::fpowm::{FPowmTable, cache_fpown, cache_set_table};
+/// let (b, p, e) = (Integer::from(7), Integer::from(1019), Integer::from(1000));
+/// let bytes = FPowmTable::init_precomp(&b, &p, 4, 16).unwrap().to_bytes().unwrap();
+/// assert!(cache_set_table(FPowmTable::from_bytes(&bytes).unwrap()).unwrap());
+/// assert_eq!(cache_fpown(&e).unwrap(), b.pow_mod(&e, &p).unwrap());
+/// ```
+pub fn cache_set_table(table: FPowmTable) -> Result<bool, GmpMEEError> {
+    let cache = FPownMTableStatic::from_table(table)?;
+    if let Some(existing) = CACHE_FPOWM_TABLE.get() {
+        return existing.check_same(&cache.base, &cache.modulus);
+    }
+    let (base, modulus) = (cache.base.clone(), cache.modulus.clone());
+    match CACHE_FPOWM_TABLE.set(cache) {
+        Ok(()) => Ok(true),
+        Err(_) => CACHE_FPOWM_TABLE.get().unwrap().check_same(&base, &modulus),
+    }
+}
+
+/// Initialize the cache with the parameters set with [super::set_defaults]
+///
+/// The cache cannot be changed anymore (see [cache_init_precomp])
+pub fn cache_init_precomp_default(base: &Integer, modulus: &Integer) -> Result<bool, GmpMEEError> {
+    let defaults = defaults();
+    cache_init_precomp(
+        base,
+        modulus,
+        defaults.block_width,
+        defaults.exponent_bitlen_policy.exponent_bitlen(modulus),
+    )
+}
+
+/// Initialize the cache with the given parameters on a worker thread
+///
+/// The function returns immediately. The returned handle permits to poll if the cache is ready, or
+/// to wait for it. Until the precomputation is finished, [cache_fpown] returns `None`.
+///
+/// On the targets without threads (`wasm32-unknown-unknown`), the precomputation is done before the
+/// function returns.
+pub fn cache_init_precomp_background(
+    base: &Integer,
+    modulus: &Integer,
+    block_width: usize,
+    exponent_bitlen: usize,
+) -> CacheHandle {
+    let (base, modulus) = (base.clone(), modulus.clone());
+    CacheHandle::spawn(CacheTarget::Static, move || {
+        cache_init_precomp(&base, &modulus, block_width, exponent_bitlen)
+    })
+}
+
+/// Result of the initialization of a cache
+type InitResult = Result<bool, GmpMEEError>;
+
+/// Cache initialized by a [CacheHandle]
+#[derive(Debug, Clone)]
+enum CacheTarget {
+    Static,
+    Named(String),
+}
+
+/// Handle on a cache initialized on a worker thread
+///
+/// The handle can be cloned, and it can be used to wait for the result of the initialization or to
+/// calculate exponentiations, blocking until the cache is ready.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::fpowm::cache_init_named_background;
+/// let p = Integer::from(1019);
+/// let b = Integer::from(7);
+/// let e = Integer::from(1000);
+/// let handle = cache_init_named_background("background-doc", &b, &p, 4, 16);
+/// // The service can do something else in between
+/// assert_eq!(handle.fpowm(&e).unwrap(), b.pow_mod(&e, &p).unwrap());
+/// assert!(handle.is_ready());
+/// ```
+#[derive(Clone)]
+pub struct CacheHandle {
+    target: CacheTarget,
+    state: Arc<(Mutex<Option<InitResult>>, Condvar)>,
+}
+
+impl CacheHandle {
+    fn spawn<F>(target: CacheTarget, init: F) -> Self
+    where
+        F: FnOnce() -> InitResult + Send + 'static,
+    {
+        let state = Arc::new((Mutex::new(None), Condvar::new()));
+        let worker_state = state.clone();
+        let run = move || {
+            // The waiters must be woken up even if the initialization panics
+            let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(init)).unwrap_or_else(
+                |payload| Err(CacheError::InitPanicked(panic_message(&*payload)).into()),
+            );
+            let (lock, condvar) = &*worker_state;
+            *lock.lock().unwrap_or_else(|e| e.into_inner()) = Some(res);
+            condvar.notify_all();
+        };
+        // Without threads, the initialization is done before returning the handle
+        match THREADS {
+            true => {
+                std::thread::spawn(run);
+            }
+            false => run(),
+        }
+        Self { target, state }
+    }
+
+    /// Return `true` if the initialization is finished (successfully or not)
+    pub fn is_ready(&self) -> bool {
+        self.state
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// Block until the initialization is finished and return its result (see [cache_init_precomp])
+    pub fn wait(&self) -> Result<bool, GmpMEEError> {
+        let (lock, condvar) = &*self.state;
+        let guard = condvar
+            .wait_while(lock.lock().unwrap_or_else(|e| e.into_inner()), |res| {
+                res.is_none()
+            })
+            .unwrap_or_else(|e| e.into_inner());
+        guard.clone().unwrap()
+    }
+
+    /// Calculate `gmpmee_fpowm` with the cache, blocking until the cache is ready
+    ///
+    /// Return the error of the initialization, if any
+    pub fn fpowm(&self, exponent: &Integer) -> Result<Integer, GmpMEEError> {
+        self.wait()?;
+        // The named cache can have been removed in the meantime
+        match &self.target {
+            CacheTarget::Static => try_cache_fpown(exponent),
+            CacheTarget::Named(name) => try_cache_fpowm_named(name, exponent),
+        }
+    }
+}
+
+/// Message of the payload of a panic
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|m| m.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Calculate `gmpmee_fpowm` using the cache
+///
+/// If the cache is not initialized, then return `None` (see [try_cache_fpown] for the error)
+pub fn cache_fpown(exponent: &Integer) -> Option<Integer> {
+    try_cache_fpown(exponent).ok()
+}
+
+/// Calculate `gmpmee_fpowm` using the cache
+///
+/// If the cache is not initialized, then return the error [CacheError::NotInitialized]
+pub fn try_cache_fpown(exponent: &Integer) -> Result<Integer, GmpMEEError> {
+    if !is_cache_initialized() {
+        METRICS.record_lookup(false);
+        return Err(CacheError::NotInitialized.into());
+    }
+    Ok(CACHE_FPOWM_TABLE.get().unwrap().fpowm(exponent))
+}
+
+/// Return the base and the modulus as tuple used for the initialization of the cache
+///
+/// The integers are shared with the cache, without copy. If the cache is not initialized, then
+/// return `None`
+pub fn cache_base_modulus() -> Option<(Arc<Integer>, Arc<Integer>)> {
+    CACHE_FPOWM_TABLE.get().map(FPownMTableStatic::base_modulus)
+}
+
+/// Return the base and the modulus as tuple used for the initialization of the cache
+///
+/// If the cache is not initialized, then return the error [CacheError::NotInitialized]
+pub fn try_cache_base_modulus() -> Result<(Arc<Integer>, Arc<Integer>), GmpMEEError> {
+    cache_base_modulus().ok_or(CacheError::NotInitialized.into())
+}
+
+/// Return the table of the cache, shared with the cache
+///
+/// The table can be used directly, e.g. with the parallel iterators (feature `rayon`), without
+/// looking up the cache for each exponentiation. If the cache is not initialized, then return `None`
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::fpowm::{cache_init_precomp, cache_table};
+/// let (b, p) = (Integer::from(7), Integer::from(1019));
+/// assert!(cache_table().is_none());
+/// cache_init_precomp(&b, &p, 4, 16).unwrap();
+/// let table = cache_table().unwrap();
+/// let exponents = [Integer::from(10), Integer::from(1000)];
+/// for e in exponents.iter() {
+///     assert_eq!(table.fpowm(e), b.clone().pow_mod(e, &p).unwrap());
+/// }
+/// ```
+pub fn cache_table() -> Option<Arc<FPowmTable>> {
+    CACHE_FPOWM_TABLE.get().map(|cache| cache.table.clone())
+}
+
+static NAMED_CACHES: LazyLock<RwLock<HashMap<String, Arc<FPownMTableStatic>>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Initialize the cache registered under `name` with the given parameters
+///
+/// The named caches permit to keep the tables of several deployments separated in the same process.
+/// As for the static cache, a named cache cannot be changed anymore: if the cache is already
+/// initialized, return `Ok(false)` if the base and the modulus are the same, and the error
+/// [CacheError::ParameterMismatch] otherwise.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::fpowm::{cache_fpowm_named, cache_init_named};
+/// let p = Integer::from(1019);
+/// let b = Integer::from(7);
+/// let e = Integer::from(1000);
+/// assert!(cache_init_named("ballot-group", &b, &p, 4, 16).unwrap());
+/// assert_eq!(cache_fpowm_named("ballot-group", &e).unwrap(), b.pow_mod(&e, &p).unwrap());
+/// assert!(cache_fpowm_named("other-group", &e).is_none());
+/// ```
+pub fn cache_init_named(
+    name: &str,
+    base: &Integer,
+    modulus: &Integer,
+    block_width: usize,
+    exponent_bitlen: usize,
+) -> Result<bool, GmpMEEError> {
+    if let Some(cache) = named_cache(name) {
+        return cache.check_same(base, modulus);
+    }
+    let cache = Arc::new(FPownMTableStatic::new(
+        base,
+        modulus,
+        block_width,
+        exponent_bitlen,
+    )?);
+    let mut caches = NAMED_CACHES.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(existing) = caches.get(name) {
+        return existing.check_same(base, modulus);
+    }
+    caches.insert(name.to_string(), cache);
+    Ok(true)
+}
+
+/// Initialize the cache registered under `name` with the parameters set with [super::set_defaults]
+pub fn cache_init_named_default(
+    name: &str,
+    base: &Integer,
+    modulus: &Integer,
+) -> Result<bool, GmpMEEError> {
+    let defaults = defaults();
+    cache_init_named(
+        name,
+        base,
+        modulus,
+        defaults.block_width,
+        defaults.exponent_bitlen_policy.exponent_bitlen(modulus),
+    )
+}
+
+/// Install a table built elsewhere in the cache registered under `name` (see [cache_set_table])
+///
+/// The same rules as [cache_init_named] apply
+pub fn cache_set_table_named(name: &str, table: FPowmTable) -> Result<bool, GmpMEEError> {
+    let cache = Arc::new(FPownMTableStatic::from_table(table)?);
+    let mut caches = NAMED_CACHES.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(existing) = caches.get(name) {
+        return existing.check_same(&cache.base, &cache.modulus);
+    }
+    caches.insert(name.to_string(), cache);
+    Ok(true)
+}
+
+/// Initialize the cache registered under `name` with the given parameters on a worker thread
+///
+/// See [cache_init_precomp_background]
+pub fn cache_init_named_background(
+    name: &str,
+    base: &Integer,
+    modulus: &Integer,
+    block_width: usize,
+    exponent_bitlen: usize,
+) -> CacheHandle {
+    let (name, base, modulus) = (name.to_string(), base.clone(), modulus.clone());
+    CacheHandle::spawn(CacheTarget::Named(name.clone()), move || {
+        cache_init_named(&name, &base, &modulus, block_width, exponent_bitlen)
+    })
+}
+
+fn named_cache(name: &str) -> Option<Arc<FPownMTableStatic>> {
+    NAMED_CACHES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned()
+}
+
+/// Calculate `gmpmee_fpowm` using the cache registered under `name`
+///
+/// If the cache is not initialized, then return `None`
+pub fn cache_fpowm_named(name: &str, exponent: &Integer) -> Option<Integer> {
+    try_cache_fpowm_named(name, exponent).ok()
+}
+
+/// Calculate `gmpmee_fpowm` using the cache registered under `name`
+///
+/// If the cache is not initialized, then return the error [CacheError::NotInitialized]
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::{GmpMEEError, fpowm::{CacheError, try_cache_fpowm_named}};
+/// assert_eq!(
+///     try_cache_fpowm_named("not-initialized", &Integer::from(4)),
+///     Err(GmpMEEError::Cache(CacheError::NotInitialized))
+/// );
+/// ```
+pub fn try_cache_fpowm_named(name: &str, exponent: &Integer) -> Result<Integer, GmpMEEError> {
+    match named_cache(name) {
+        Some(cache) => Ok(cache.fpowm(exponent)),
+        None => {
+            METRICS.record_lookup(false);
+            Err(CacheError::NotInitialized.into())
+        }
+    }
+}
+
+/// Return the base and the modulus used for the initialization of the cache registered under `name`
+///
+/// If the cache is not initialized, then return `None`
+pub fn cache_base_modulus_named(name: &str) -> Option<(Arc<Integer>, Arc<Integer>)> {
+    named_cache(name).map(|cache| cache.base_modulus())
+}
+
+/// Return the table of the cache registered under `name`, shared with the cache (see [cache_table])
+///
+/// The table remains valid if the cache is removed in the meantime
+pub fn cache_table_named(name: &str) -> Option<Arc<FPowmTable>> {
+    named_cache(name).map(|cache| cache.table.clone())
+}
+
+/// Remove the cache registered under `name`
+///
+/// Return `true` if the cache existed. The table is freed when it is not used anymore.
+pub fn cache_remove_named(name: &str) -> bool {
+    NAMED_CACHES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(name)
+        .is_some()
+}
+
+/// Names of the initialized named caches
+pub fn cache_names() -> Vec<String> {
+    NAMED_CACHES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .keys()
+        .cloned()
+        .collect()
+}
+
+const STATIC_FILE: &str = "static.fpowm";
+const NAMED_PREFIX: &str = "named-";
+const HASHED_PREFIX: &str = "sha256-";
+const GLOBAL_PREFIX: &str = "global-";
+const EXTENSION: &str = ".fpowm";
+/// Extension of the file containing the name of a cache whose file name is hashed
+const NAME_EXTENSION: &str = "name";
+/// Maximal length of a name in hexadecimal in a file name, which must not exceed 255 bytes
+const MAX_HEX_NAME_LEN: usize = 200;
+
+/// Write the table in a temporary file, which replaces the file at `path` only once the whole
+/// table has been written
+fn write_table_file(path: &Path, table: &FPowmTable) -> Result<(), GmpMEEError> {
+    let tmp_path = path.with_extension("tmp");
+    let res = File::create(&tmp_path)
+        .map_err(io_error)
+        .and_then(|file| table.write_to(BufWriter::new(file)))
+        .and_then(|()| fs::rename(&tmp_path, path).map_err(io_error));
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    res
+}
+
+fn read_table_file(path: &Path, saved: SavedTable) -> Result<(), GmpMEEError> {
+    let file = File::open(path).map_err(io_error)?;
+    let table = FPowmTable::read_from(BufReader::new(file))?;
+    match saved {
+        SavedTable::Static => cache_set_table(table).map(|_| ()),
+        SavedTable::Named(name) => cache_set_table_named(&name, table).map(|_| ()),
+        SavedTable::HashedNamed(digest) => {
+            let name = fs::read_to_string(path.with_extension(NAME_EXTENSION)).map_err(io_error)?;
+            if name_to_hex_digest(&name) != digest {
+                return Err(FPownError::Serialization(format!(
+                    "the name of the cache does not match the file {}",
+                    path.display()
+                ))
+                .into());
+            }
+            cache_set_table_named(&name, table).map(|_| ())
+        }
+        SavedTable::Global => global_cache().insert(table).map(|_| ()),
+    }
+}
+
+/// Outcome of [cache_save_all] and [cache_load_all]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheFilesReport {
+    /// Number of tables saved or loaded
+    pub count: usize,
+    /// Files of the tables that have been skipped, with the error that occurred
+    pub skipped: Vec<(PathBuf, GmpMEEError)>,
+}
+
+impl CacheFilesReport {
+    fn record(&mut self, path: PathBuf, res: Result<(), GmpMEEError>) {
+        match res {
+            Ok(()) => self.count += 1,
+            Err(e) => self.skipped.push((path, e)),
+        }
+    }
+}
+
+/// File of a saved table, identified by its name
+enum SavedTable {
+    Static,
+    Named(String),
+    /// Named cache whose name is too long for a file name, identified by the SHA-256 digest of the
+    /// name in hexadecimal
+    HashedNamed(String),
+    Global,
+}
+
+impl SavedTable {
+    fn parse(file_name: &str) -> Option<Self> {
+        let stem = file_name.strip_suffix(EXTENSION)?;
+        if file_name == STATIC_FILE {
+            return Some(Self::Static);
+        }
+        if let Some(hex) = stem.strip_prefix(NAMED_PREFIX) {
+            if let Some(digest) = hex.strip_prefix(HASHED_PREFIX) {
+                return (digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .then(|| Self::HashedNamed(digest.to_string()));
+            }
+            return hex_to_name(hex).map(Self::Named);
+        }
+        stem.strip_prefix(GLOBAL_PREFIX)
+            .filter(|index| index.parse::<usize>().is_ok())
+            .map(|_| Self::Global)
+    }
+}
+
+fn name_to_hex(name: &str) -> String {
+    name.bytes().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Stem of the file of the table of a named cache
+///
+/// The name is encoded in hexadecimal. If the file name would be too long, the name is replaced by
+/// its SHA-256 digest, and the name is saved in a file with the extension `.name` next to the table
+fn named_file_stem(name: &str) -> (String, bool) {
+    let hex = name_to_hex(name);
+    match hex.len() <= MAX_HEX_NAME_LEN {
+        true => (format!("{NAMED_PREFIX}{hex}"), false),
+        false => (
+            format!("{NAMED_PREFIX}{HASHED_PREFIX}{}", name_to_hex_digest(name)),
+            true,
+        ),
+    }
+}
+
+fn name_to_hex_digest(name: &str) -> String {
+    Sha256::digest(name.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn hex_to_name(hex: &str) -> Option<String> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Save the tables of the static cache, of the named caches and of the [global_cache] in the directory
+///
+/// Each table is saved in its own file with the format of [FPowmTable::write_to]. The thread caches
+/// are not saved.
+///
+/// Each table is written in a temporary file, which replaces the previous file only once the whole
+/// table has been written. The tables that cannot be saved (e.g. with an even modulus) are skipped
+/// and returned in the report with the error, and their previous file, if any, is kept. The other
+/// tables saved previously in the directory are removed.
+///
+/// The file of a named cache contains the name in hexadecimal. For the long names, the file name
+/// contains the SHA-256 digest of the name instead, and the name is saved in a file with the
+/// extension `.name`.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::fpowm::{cache_fpowm_named, cache_init_named, cache_load_all, cache_remove_named, cache_save_all};
+/// let dir = std::env::temp_dir().join("rug-gmpmee-doc-save-all");
+/// let (b, p, e) = (Integer::from(7), Integer::from(1019), Integer::from(1000));
+/// cache_init_named("doc", &b, &p, 4, 16).unwrap();
+/// assert!(cache_save_all(&dir).unwrap().count >= 1);
+/// cache_remove_named("doc");
+/// assert!(cache_load_all(&dir).unwrap().count >= 1);
+/// assert_eq!(cache_fpowm_named("doc", &e).unwrap(), b.pow_mod(&e, &p).unwrap());
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn cache_save_all(dir: impl AsRef<Path>) -> Result<CacheFilesReport, GmpMEEError> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).map_err(io_error)?;
+    let mut report = CacheFilesReport::default();
+    let mut written = HashSet::new();
+    if let Some(cache) = CACHE_FPOWM_TABLE.get() {
+        let path = dir.join(STATIC_FILE);
+        report.record(path.clone(), write_table_file(&path, &cache.table));
+        written.insert(path);
+    }
+    let named: Vec<_> = NAMED_CACHES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(name, cache)| (name.clone(), cache.clone()))
+        .collect();
+    for (name, cache) in named {
+        let (stem, hashed) = named_file_stem(&name);
+        let path = dir.join(format!("{stem}{EXTENSION}"));
+        let res = match hashed {
+            true => fs::write(path.with_extension(NAME_EXTENSION), &name).map_err(io_error),
+            false => Ok(()),
+        };
+        report.record(
+            path.clone(),
+            res.and_then(|()| write_table_file(&path, &cache.table)),
+        );
+        written.insert(path);
+    }
+    for (i, table) in global_cache().tables().iter().enumerate() {
+        let path = dir.join(format!("{GLOBAL_PREFIX}{i}{EXTENSION}"));
+        report.record(path.clone(), write_table_file(&path, table));
+        written.insert(path);
+    }
+    // Remove the tables of the previous saves that are not in the caches anymore
+    for entry in fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if !written.contains(&path)
+            && path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(SavedTable::parse)
+                .is_some()
+        {
+            fs::remove_file(&path).map_err(io_error)?;
+            let name_path = path.with_extension(NAME_EXTENSION);
+            if name_path.exists() {
+                fs::remove_file(&name_path).map_err(io_error)?;
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Load the tables saved with [cache_save_all] into the caches
+///
+/// The static and the named caches follow the rules of [cache_init_precomp]: a table is not loaded if
+/// the cache is already initialized with another base or modulus. The files that cannot be read or
+/// loaded are skipped and returned in the report with the error, and the other tables are loaded.
+pub fn cache_load_all(dir: impl AsRef<Path>) -> Result<CacheFilesReport, GmpMEEError> {
+    let mut report = CacheFilesReport::default();
+    for entry in fs::read_dir(dir.as_ref()).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        let Some(saved) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(SavedTable::parse)
+        else {
+            continue;
+        };
+        let res = read_table_file(&path, saved);
+        report.record(path, res);
+    }
+    Ok(report)
+}
+
+/// Cache containing a table (see [cache_entries])
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CacheLocation {
+    /// Static cache
+    Static,
+    /// Cache registered under the name
+    Named(String),
+    /// Global cache ([global_cache])
+    Global,
+    /// Cache of the current thread
+    Thread,
+}
+
+/// Describe the tables of the caches: the static cache, the named caches, the [global_cache] and the
+/// cache of the current thread
+///
+/// For each table, return the cache containing it, the fingerprints of the base and of the modulus,
+/// the parameters, the memory size and the number of lookups finding the table. The caches of the
+/// other threads are not accessible.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::fpowm::{CacheLocation, cache_entries, cache_fpowm_named, cache_init_named};
+/// let (b, p, e) = (Integer::from(7), Integer::from(1019), Integer::from(1000));
+/// cache_init_named("inspected", &b, &p, 4, 16).unwrap();
+/// cache_fpowm_named("inspected", &e).unwrap();
+/// let (_, entry) = cache_entries()
+///     .into_iter()
+///     .find(|(location, _)| location == &CacheLocation::Named("inspected".to_string()))
+///     .unwrap();
+/// assert_eq!((entry.block_width, entry.exponent_bitlen, entry.hits), (4, 16, 1));
+/// ```
+pub fn cache_entries() -> Vec<(CacheLocation, CacheEntry)> {
+    let mut entries = vec![];
+    if let Some(cache) = CACHE_FPOWM_TABLE.get() {
+        entries.push((CacheLocation::Static, cache.entry()));
+    }
+    let mut named: Vec<_> = NAMED_CACHES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(name, cache)| (name.clone(), cache.entry()))
+        .collect();
+    named.sort_by(|(a, _), (b, _)| a.cmp(b));
+    entries.extend(
+        named
+            .into_iter()
+            .map(|(name, entry)| (CacheLocation::Named(name), entry)),
+    );
+    entries.extend(
+        global_cache()
+            .entries()
+            .into_iter()
+            .map(|entry| (CacheLocation::Global, entry)),
+    );
+    if let Some(entry) =
+        THREAD_CACHE_FPOWM_TABLE.with_borrow(|cache| cache.as_ref().map(FPownMTableStatic::entry))
+    {
+        entries.push((CacheLocation::Thread, entry));
+    }
+    entries
+}
+
+thread_local! {
+    static THREAD_CACHE_FPOWM_TABLE: RefCell<Option<FPownMTableStatic>> = const { RefCell::new(None) };
+}
+
+/// Initialize the cache of the current thread with the given parameters
+///
+/// Each thread has its own cache, which is used without synchronization. Different threads can use
+/// different parameters. If the cache of the thread is already initialized, return `Ok(false)` if the
+/// base and the modulus are the same, and the error [CacheError::ParameterMismatch] otherwise
+/// (see [thread_cache_clear] to change the parameters)
+pub fn thread_cache_init_precomp(
+    base: &Integer,
+    modulus: &Integer,
+    block_width: usize,
+    exponent_bitlen: usize,
+) -> Result<bool, GmpMEEError> {
+    THREAD_CACHE_FPOWM_TABLE.with_borrow_mut(|cache| {
+        if let Some(cache) = cache {
+            return cache.check_same(base, modulus);
+        }
+        *cache = Some(FPownMTableStatic::new(
+            base,
+            modulus,
+            block_width,
+            exponent_bitlen,
+        )?);
+        Ok(true)
+    })
+}
+
+/// Initialize the cache of the current thread with the parameters set with [super::set_defaults]
+///
+/// See [thread_cache_init_precomp]
+pub fn thread_cache_init_precomp_default(
+    base: &Integer,
+    modulus: &Integer,
+) -> Result<bool, GmpMEEError> {
+    let defaults = defaults();
+    thread_cache_init_precomp(
+        base,
+        modulus,
+        defaults.block_width,
+        defaults.exponent_bitlen_policy.exponent_bitlen(modulus),
+    )
+}
+
+/// Calculate `gmpmee_fpowm` using the cache of the current thread
+///
+/// If the cache of the thread is not initialized, then return `None`
+pub fn thread_cache_fpowm(exponent: &Integer) -> Option<Integer> {
+    try_thread_cache_fpowm(exponent).ok()
+}
+
+/// Calculate `gmpmee_fpowm` using the cache of the current thread
+///
+/// If the cache of the thread is not initialized, then return the error [CacheError::NotInitialized]
+pub fn try_thread_cache_fpowm(exponent: &Integer) -> Result<Integer, GmpMEEError> {
+    let res =
+        THREAD_CACHE_FPOWM_TABLE.with_borrow(|cache| cache.as_ref().map(|c| c.fpowm(exponent)));
+    if res.is_none() {
+        METRICS.record_lookup(false);
+    }
+    res.ok_or(CacheError::NotInitialized.into())
+}
+
+/// Return the base and the modulus used for the initialization of the cache of the current thread
+///
+/// If the cache of the thread is not initialized, then return `None`
+pub fn thread_cache_base_modulus() -> Option<(Arc<Integer>, Arc<Integer>)> {
+    THREAD_CACHE_FPOWM_TABLE
+        .with_borrow(|cache| cache.as_ref().map(FPownMTableStatic::base_modulus))
+}
+
+/// Return the table of the cache of the current thread, shared with the cache (see [cache_table])
+pub fn thread_cache_table() -> Option<Arc<FPowmTable>> {
+    THREAD_CACHE_FPOWM_TABLE.with_borrow(|cache| cache.as_ref().map(|c| c.table.clone()))
+}
+
+/// Remove the table from the cache of the current thread
+pub fn thread_cache_clear() {
+    THREAD_CACHE_FPOWM_TABLE.with_borrow_mut(|cache| *cache = None);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rayon::iter::IntoParallelRefIterator;
+    use rayon::prelude::*;
+    use rug::rand::RandState;
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_save_load() {
+        let dir = std::env::temp_dir().join(format!("rug-gmpmee-test-{}", std::process::id()));
+        let (b, p, e) = (Integer::from(7), Integer::from(1019), Integer::from(1000));
+        cache_init_named("test-persist/ü", &b, &p, 4, 16).unwrap();
+        let long_name = "test-persist-".repeat(30);
+        cache_init_named(&long_name, &b, &p, 4, 16).unwrap();
+        let b2 = Integer::from(123);
+        global_cache().get_or_init(&b2, &p).unwrap();
+        assert!(cache_save_all(&dir).unwrap().count >= 3);
+        cache_remove_named("test-persist/ü");
+        cache_remove_named(&long_name);
+        global_cache().clear();
+        std::fs::write(dir.join("other.txt"), "not a table").unwrap();
+        assert!(cache_load_all(&dir).unwrap().count >= 3);
+        assert_eq!(
+            cache_fpowm_named("test-persist/ü", &e).unwrap(),
+            b.clone().pow_mod(&e, &p).unwrap()
+        );
+        assert!(global_cache().get(&b2, &p).is_some());
+        assert_eq!(
+            cache_fpowm_named(&long_name, &e).unwrap(),
+            b.pow_mod(&e, &p).unwrap()
+        );
+        cache_remove_named(&long_name);
+        assert!(dir.join("other.txt").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            hex_to_name(&name_to_hex("a-b ü")),
+            Some("a-b ü".to_string())
+        );
+        assert!(hex_to_name("abc").is_none());
+    }
+
+    #[test]
+    fn test_save_load_skipped() {
+        let dir = std::env::temp_dir().join(format!("rug-gmpmee-test-skip-{}", std::process::id()));
+        let (b, p, e) = (Integer::from(7), Integer::from(1019), Integer::from(1000));
+        cache_init_named("test-skip-odd", &b, &p, 4, 16).unwrap();
+        cache_init_named("test-skip-even", &b, &Integer::from(1018), 4, 16).unwrap();
+        let report = cache_save_all(&dir).unwrap();
+        let even_path = dir.join(format!(
+            "{NAMED_PREFIX}{}{EXTENSION}",
+            name_to_hex("test-skip-even")
+        ));
+        assert!(matches!(
+            report.skipped.iter().find(|(path, _)| *path == even_path),
+            Some((
+                _,
+                GmpMEEError::FPowmParameters(FPownError::Serialization(_))
+            ))
+        ));
+        assert!(!even_path.exists());
+        let odd_path = dir.join(format!(
+            "{NAMED_PREFIX}{}{EXTENSION}",
+            name_to_hex("test-skip-odd")
+        ));
+        assert!(odd_path.exists());
+        // A table that cannot be saved anymore keeps its previous file
+        std::fs::copy(&odd_path, &even_path).unwrap();
+        cache_save_all(&dir).unwrap();
+        assert!(even_path.exists());
+        cache_remove_named("test-skip-odd");
+        cache_remove_named("test-skip-even");
+        let corrupted = dir.join(format!(
+            "{NAMED_PREFIX}{}{EXTENSION}",
+            name_to_hex("test-skip-bad")
+        ));
+        std::fs::write(&corrupted, "not a table").unwrap();
+        let report = cache_load_all(&dir).unwrap();
+        assert!(report.skipped.iter().any(|(path, _)| *path == corrupted));
+        assert_eq!(
+            cache_fpowm_named("test-skip-odd", &e).unwrap(),
+            b.pow_mod(&e, &p).unwrap()
+        );
+        cache_remove_named("test-skip-odd");
+        cache_remove_named("test-skip-even");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_metrics() {
+        let before = cache_metrics();
+        let e = Integer::from(1000);
+        let cache = FPowmCache::new();
+        cache
+            .fpowm(&Integer::from(7), &Integer::from(1019), &e)
+            .unwrap();
+        cache
+            .fpowm(&Integer::from(7), &Integer::from(1019), &e)
+            .unwrap();
+        let after = cache_metrics();
+        assert!(after.initializations > before.initializations);
+        assert!(after.lookups >= before.lookups + 3);
+        assert!(after.hits > before.hits);
+        assert!(after.misses > before.misses);
+        assert!(after.hit_ratio().is_some());
+        assert!(CacheMetrics::default().hit_ratio().is_none());
+    }
+
+    #[test]
+    fn test_set_table() {
+        let (b, p, e) = (Integer::from(7), Integer::from(1019), Integer::from(1000));
+        let table = FPowmTable::init_precomp(&b, &p, 3, 12).unwrap();
+        assert!(cache_set_table_named("test-set", table).unwrap());
+        let table = FPowmTable::init_precomp(&b, &p, 5, 16).unwrap();
+        assert!(!cache_set_table_named("test-set", table).unwrap());
+        assert_eq!(cache_table_named("test-set").unwrap().block_width(), 3);
+        assert_eq!(
+            cache_fpowm_named("test-set", &e).unwrap(),
+            b.pow_mod(&e, &p).unwrap()
+        );
+        let table = FPowmTable::init(&p, 4, 16).unwrap();
+        assert_eq!(
+            cache_set_table_named("test-set-2", table),
+            Err(FPownError::NotPrecomputed.into())
+        );
+        cache_remove_named("test-set");
+    }
+
+    #[test]
+    fn test_named_cache() {
+        let e = Integer::from(1000);
+        let b = Integer::from(7);
+        let p1 = Integer::from(1019);
+        let p2 = Integer::from(1031);
+        assert!(cache_init_named("test-1", &b, &p1, 4, 16).unwrap());
+        assert!(cache_init_named("test-2", &b, &p2, 4, 16).unwrap());
+        assert!(!cache_init_named("test-1", &b, &p1, 4, 16).unwrap());
+        assert_eq!(
+            cache_init_named("test-1", &b, &p2, 4, 16),
+            Err(GmpMEEError::Cache(CacheError::ParameterMismatch {
+                existing_base: b.clone(),
+                existing_modulus: p1.clone()
+            }))
+        );
+        assert_eq!(
+            cache_fpowm_named("test-1", &e).unwrap(),
+            b.clone().pow_mod(&e, &p1).unwrap()
+        );
+        assert_eq!(
+            cache_fpowm_named("test-2", &e).unwrap(),
+            b.clone().pow_mod(&e, &p2).unwrap()
+        );
+        assert_eq!(
+            cache_base_modulus_named("test-2"),
+            Some((Arc::new(b.clone()), Arc::new(p2.clone())))
+        );
+        assert_eq!(
+            cache_table_named("test-2").unwrap().fpowm(&e),
+            b.pow_mod(&e, &p2).unwrap()
+        );
+        assert!(cache_names().contains(&"test-1".to_string()));
+        assert!(cache_remove_named("test-1"));
+        assert!(!cache_remove_named("test-1"));
+        assert!(cache_fpowm_named("test-1", &e).is_none());
+        assert_eq!(
+            try_cache_fpowm_named("test-1", &e),
+            Err(GmpMEEError::Cache(CacheError::NotInitialized))
+        );
+        assert!(try_cache_fpowm_named("test-2", &e).is_ok());
+    }
+
+    #[test]
+    fn test_background() {
+        let e = Integer::from(1000);
+        let b = Integer::from(7);
+        let p = Integer::from(1019);
+        let handle = cache_init_named_background("test-background", &b, &p, 4, 16);
+        assert_eq!(handle.wait(), Ok(true));
+        assert!(handle.is_ready());
+        assert_eq!(
+            handle.fpowm(&e).unwrap(),
+            b.clone().pow_mod(&e, &p).unwrap()
+        );
+        let handle = cache_init_named_background("test-background", &Integer::from(3), &p, 4, 16);
+        assert!(handle.clone().fpowm(&e).is_err());
+        cache_remove_named("test-background");
+        let handle = cache_init_named_background("test-background", &b, &p, 0, 16);
+        assert!(handle.wait().is_err());
+        let handle = CacheHandle::spawn(CacheTarget::Named("test-panic".to_string()), || {
+            panic!("precomputation failed")
+        });
+        assert_eq!(
+            handle.wait(),
+            Err(CacheError::InitPanicked("precomputation failed".to_string()).into())
+        );
+        assert!(handle.is_ready());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_errors() {
+        assert_eq!(
+            FPownError::try_from(CacheError::NotInitialized),
+            Ok(FPownError::CacheNotInitialized)
+        );
+        assert_eq!(
+            FPownError::try_from(CacheError::ParameterMismatch {
+                existing_base: Integer::from(7),
+                existing_modulus: Integer::from(1019),
+            }),
+            Ok(FPownError::CacheAlreadyInitialized {
+                existing_base: Integer::from(7),
+                existing_modulus: Integer::from(1019),
+            })
+        );
+        let err = CacheError::Config("wrong".to_string());
+        assert_eq!(FPownError::try_from(err.clone()), Err(err));
+    }
+
+    #[test]
+    fn test_thread_cache() {
+        let e = Integer::from(1000);
+        let handles: Vec<_> = [1019u32, 1031, 1033]
+            .into_iter()
+            .map(|p| {
+                let e = e.clone();
+                std::thread::spawn(move || {
+                    let p = Integer::from(p);
+                    let b = Integer::from(7);
+                    assert!(thread_cache_fpowm(&e).is_none());
+                    assert!(try_thread_cache_fpowm(&e).is_err());
+                    assert!(thread_cache_init_precomp(&b, &p, 4, 16).unwrap());
+                    assert!(!thread_cache_init_precomp(&b, &p, 4, 16).unwrap());
+                    assert!(thread_cache_init_precomp(&Integer::from(3), &p, 4, 16).is_err());
+                    assert_eq!(
+                        thread_cache_base_modulus(),
+                        Some((Arc::new(b.clone()), Arc::new(p.clone())))
+                    );
+                    assert_eq!(thread_cache_table().unwrap().modulus(), &p);
+                    assert!(
+                        cache_entries()
+                            .iter()
+                            .any(|(location, entry)| location == &CacheLocation::Thread
+                                && entry.hits == 0)
+                    );
+                    assert_eq!(thread_cache_fpowm(&e).unwrap(), b.pow_mod(&e, &p).unwrap());
+                    thread_cache_clear();
+                    assert!(thread_cache_base_modulus().is_none());
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_cache() {
+        let p =  Integer::from(Integer::parse_radix(
+            "CE9E0307D2AE75BDBEEC3E0A6E71A279417B56C955C602FFFD067586BACFDAC3BCC49A49EB4D126F5E9255E57C14F3E09492B6496EC8AC1366FC4BB7F678573FA2767E6547FA727FC0E631AA6F155195C035AF7273F31DFAE1166D1805C8522E95F9AF9CE33239BF3B68111141C20026673A6C8B9AD5FA8372ED716799FE05C0BB6EAF9FCA1590BD9644DBEFAA77BA01FD1C0D4F2D53BAAE965B1786EC55961A8E2D3E4FE8505914A408D50E6B99B71CDA78D8F9AF1A662512F8C4C3A9E72AC72D40AE5D4A0E6571135CBBAAE08C7A2AA0892F664549FA7EEC81BA912743F3E584AC2B2092243C4A17EC98DF079D8EECB8B885E6BBAFA452AAFA8CB8C08024EFF28DE4AF4AC710DCD3D66FD88212101BCB412BCA775F94A2DCE18B1A6452D4CF818B6D099D4505E0040C57AE1F3E84F2F8E07A69C0024C05ACE05666A6B63B0695904478487E78CD0704C14461F24636D7A3F267A654EEDCF8789C7F627C72B4CBD54EED6531C0E54E325D6F09CB648AE9185A7BDA6553E40B125C78E5EAA867", 16
+        ).unwrap());
+        let mut rand = RandState::new();
+        let base = Integer::from(Integer::random_bits(2048, &mut rand));
+        assert!(cache_base_modulus().is_none());
+        assert_eq!(
+            try_cache_fpown(&Integer::from(2)),
+            Err(GmpMEEError::Cache(CacheError::NotInitialized))
+        );
+        let res_init = cache_init_precomp(&base, &p, 16, 1024);
+        assert!(res_init.is_ok());
+        assert!(res_init.unwrap());
+        let expected = (Arc::new(base.clone()), Arc::new(p.clone()));
+        assert_eq!(cache_base_modulus().unwrap(), expected);
+        assert_eq!(try_cache_base_modulus().unwrap(), expected);
+        assert_eq!(cache_table().unwrap().base(), Some(&base));
+        assert_eq!(cache_init_precomp(&base, &p, 16, 1024), Ok(false));
+        assert!(matches!(
+            cache_init_precomp(&Integer::from(2), &p, 16, 1024),
+            Err(GmpMEEError::Cache(CacheError::ParameterMismatch { .. }))
+        ));
+        let nb_exps = 100;
+        let mut exponents = vec![];
+        (0..nb_exps)
+            .for_each(|_| exponents.push(Integer::from(Integer::random_bits(1024, &mut rand))));
+        let begin_rug = SystemTime::now();
+        let res_rug = exponents
+            .par_iter()
+            .map(|e| Integer::from(base.pow_mod_ref(e, &p).unwrap()))
+            .collect::<Vec<_>>();
+        let duration_rug = begin_rug.elapsed().unwrap();
+        let begin_fpowm = SystemTime::now();
+        let res_fpowm = exponents
+            .par_iter()
+            .map(|e| cache_fpown(e).unwrap())
+            .collect::<Vec<_>>();
+        let duration_fpowm = begin_fpowm.elapsed().unwrap();
+        assert_eq!(res_fpowm.len(), res_rug.len());
+        for res in res_fpowm.iter() {
+            assert!(res_rug.contains(res));
+        }
+        assert!(
+            duration_rug > duration_fpowm,
+            "The duration of fpown (={} ms) is bigger than duration with rug (={} ms)",
+            duration_fpowm.as_millis(),
+            duration_rug.as_millis()
+        );
+        //println!("Duration rug: {} micro s", duration_rug.as_micros());
+        //println!("Duration fpowm: {} micro s", duration_fpowm.as_micros());
+    }
+}
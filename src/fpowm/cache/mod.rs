@@ -0,0 +1,451 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Caches of precomputed tables
+//!
+//! The [FPowmCache] contains tables for several bases and moduli, which are calculated when they are
+//! used for the first time. With a memory budget, the least recently used tables are removed when the
+//! budget is exceeded.
+//!
+//! The following caches have a process-global state and are only available with the feature `cache`,
+//! which is enabled by default:
+//! - The static cache contains a single table, which is initialized once.
+//! - The named caches (functions `cache_*_named`) contain a single table per name, and permit to keep
+//!   the tables of several deployments separated.
+//! - The thread cache (functions `thread_cache_*`) contains a single table per thread, which is used
+//!   without any synchronization.
+//! - The global cache (`global_cache`) is a [FPowmCache] shared by the whole process, which can be
+//!   filled at startup with `cache_warm`.
+//!
+//! The tables of these caches can be saved in a directory with `cache_save_all`, and loaded again
+//! after a restart with `cache_load_all`. The counters of the caches (lookups, hits, etc.) are
+//! returned by `cache_metrics`, and the tables in the caches are described by `cache_entries`.
+//!
+//! With the feature `config`, the static cache can be initialized from a configuration file or from
+//! environment variables with `cache_init_from_config`.
+
+use super::{FPowmDefaults, FPowmTable, FPownError, defaults};
+use crate::{GmpMEEError, lru::LruCache, parallel::available_workers, trace::timed_span};
+#[cfg(feature = "cache")]
+use global::METRICS;
+use rug::{Integer, integer::Order};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "cache")]
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "config")]
+mod config;
+#[cfg(feature = "cache")]
+mod global;
+
+#[cfg(feature = "config")]
+pub use config::*;
+#[cfg(feature = "cache")]
+pub use global::*;
+
+/// Cache of tables for several bases and moduli, with an optional memory budget
+///
+/// The tables are calculated with the parameters set with [super::set_defaults] the first time a pair
+/// base/modulus is used. If the memory used by the tables exceeds the budget, the least recently used
+/// tables are removed from the cache. A table larger than the budget is calculated but not kept.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::fpowm::FPowmCache;
+/// let cache = FPowmCache::new().with_memory_budget(1 << 20);
+/// let p = Integer::from(1019);
+/// let e = Integer::from(1000);
+/// for b in [3, 5, 7] {
+///     let b = Integer::from(b);
+///     assert_eq!(cache.fpowm(&b, &p, &e).unwrap(), b.pow_mod(&e, &p).unwrap());
+/// }
+/// assert!(cache.memory_used() <= 1 << 20);
+/// ```
+pub struct FPowmCache {
+    inner: Mutex<FPowmCacheInner>,
+}
+
+/// Description of a table in a cache (see [FPowmCache::entries] and `cache_entries`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// Fingerprint of the base: the first 8 bytes of the SHA-256 digest of the base, in hexadecimal
+    pub base_fingerprint: String,
+    /// Fingerprint of the modulus, calculated as for the base
+    pub modulus_fingerprint: String,
+    /// Block width of the table
+    pub block_width: usize,
+    /// Exponent bit length of the table
+    pub exponent_bitlen: usize,
+    /// Estimation of the memory used by the table, in bytes
+    pub memory_size: usize,
+    /// Number of lookups of the cache finding the table
+    pub hits: u64,
+}
+
+impl CacheEntry {
+    pub(super) fn new(table: &FPowmTable, hits: u64) -> Self {
+        Self {
+            base_fingerprint: table.base().map(fingerprint).unwrap_or_default(),
+            modulus_fingerprint: fingerprint(table.modulus()),
+            block_width: table.block_width(),
+            exponent_bitlen: table.exponent_bitlen(),
+            memory_size: table.memory_size(),
+            hits,
+        }
+    }
+}
+
+/// First 8 bytes of the SHA-256 digest of the absolute value in big endian, in hexadecimal
+fn fingerprint(value: &Integer) -> String {
+    Sha256::digest(value.to_digits::<u8>(Order::Msf))[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Table in a [FPowmCache] with the number of lookups finding it
+struct CachedTable {
+    table: Arc<FPowmTable>,
+    hits: u64,
+}
+
+struct FPowmCacheInner {
+    tables: LruCache<(Integer, Integer), CachedTable>,
+    memory_budget: Option<usize>,
+    memory_used: usize,
+}
+
+impl FPowmCacheInner {
+    fn remove_memory(&mut self, size: usize) {
+        self.memory_used -= size;
+        #[cfg(feature = "cache")]
+        METRICS.bytes.fetch_sub(size as u64, Relaxed);
+    }
+
+    fn evict_over_budget(&mut self) {
+        if let Some(budget) = self.memory_budget {
+            while self.memory_used > budget {
+                match self.tables.pop_lru() {
+                    Some(cached) => {
+                        self.remove_memory(cached.table.memory_size());
+                        #[cfg(feature = "cache")]
+                        METRICS.evictions.fetch_add(1, Relaxed);
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+}
+
+impl Drop for FPowmCache {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl Default for FPowmCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FPowmCache {
+    /// New empty cache without memory budget
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(FPowmCacheInner {
+                tables: LruCache::new(usize::MAX),
+                memory_budget: None,
+                memory_used: 0,
+            }),
+        }
+    }
+
+    /// Set the memory budget in bytes
+    pub fn with_memory_budget(self, memory_budget: usize) -> Self {
+        self.set_memory_budget(Some(memory_budget));
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FPowmCacheInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Change the memory budget in bytes (`None` for no budget), removing the least recently used
+    /// tables if necessary
+    pub fn set_memory_budget(&self, memory_budget: Option<usize>) {
+        let mut inner = self.lock();
+        inner.memory_budget = memory_budget;
+        inner.evict_over_budget();
+    }
+
+    /// Memory budget in bytes
+    pub fn memory_budget(&self) -> Option<usize> {
+        self.lock().memory_budget
+    }
+
+    /// Estimation of the memory used by the tables in the cache, in bytes
+    pub fn memory_used(&self) -> usize {
+        self.lock().memory_used
+    }
+
+    /// Number of tables in the cache
+    pub fn len(&self) -> usize {
+        self.lock().tables.len()
+    }
+
+    /// Return `true` if the cache contains no table
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the tables from the cache
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.tables.clear();
+        let size = inner.memory_used;
+        inner.remove_memory(size);
+    }
+
+    /// Return the table for the base and the modulus, if it is in the cache
+    pub fn get(&self, base: &Integer, modulus: &Integer) -> Option<Arc<FPowmTable>> {
+        let res = self
+            .lock()
+            .tables
+            .get_mut(&(base.clone(), modulus.clone()))
+            .map(|cached| {
+                cached.hits += 1;
+                cached.table.clone()
+            });
+        #[cfg(feature = "cache")]
+        METRICS.record_lookup(res.is_some());
+        res
+    }
+
+    /// Return the table for the base and the modulus, calculating it with the parameters set with
+    /// [super::set_defaults] if it is not in the cache
+    ///
+    /// The table is calculated without locking the cache.
+    pub fn get_or_init(
+        &self,
+        base: &Integer,
+        modulus: &Integer,
+    ) -> Result<Arc<FPowmTable>, GmpMEEError> {
+        self.get_or_init_with(base, modulus, defaults())
+    }
+
+    /// Return the table for the base and the modulus, calculating it with the given parameters if it
+    /// is not in the cache
+    pub fn get_or_init_with(
+        &self,
+        base: &Integer,
+        modulus: &Integer,
+        params: FPowmDefaults,
+    ) -> Result<Arc<FPowmTable>, GmpMEEError> {
+        if let Some(table) = self.get(base, modulus) {
+            return Ok(table);
+        }
+        let table = Arc::new(FPowmTable::init_precomp(
+            base,
+            modulus,
+            params.block_width,
+            params.exponent_bitlen_policy.exponent_bitlen(modulus),
+        )?);
+        #[cfg(feature = "cache")]
+        METRICS.initializations.fetch_add(1, Relaxed);
+        Ok(self.store((base.clone(), modulus.clone()), table, false))
+    }
+
+    /// Insert a table built elsewhere (e.g. read from a file) into the cache
+    ///
+    /// The table replaces the table with the same base and modulus, if any. The precomputation of the
+    /// table must have been done.
+    pub fn insert(&self, table: FPowmTable) -> Result<Arc<FPowmTable>, GmpMEEError> {
+        let base = table.base().ok_or(FPownError::NotPrecomputed)?.clone();
+        let key = (base, table.modulus().clone());
+        Ok(self.store(key, Arc::new(table), true))
+    }
+
+    /// Tables currently in the cache
+    pub fn tables(&self) -> Vec<Arc<FPowmTable>> {
+        self.lock()
+            .tables
+            .values()
+            .map(|cached| cached.table.clone())
+            .collect()
+    }
+
+    /// Description of the tables currently in the cache
+    pub fn entries(&self) -> Vec<CacheEntry> {
+        self.lock()
+            .tables
+            .values()
+            .map(|cached| CacheEntry::new(&cached.table, cached.hits))
+            .collect()
+    }
+
+    /// Store the table in the cache, respecting the memory budget
+    ///
+    /// If `replace` is `false` and the cache already contains a table for the key, the existing table
+    /// is kept and returned.
+    fn store(
+        &self,
+        key: (Integer, Integer),
+        table: Arc<FPowmTable>,
+        replace: bool,
+    ) -> Arc<FPowmTable> {
+        let size = table.memory_size();
+        let mut inner = self.lock();
+        if !replace && let Some(existing) = inner.tables.get(&key) {
+            return existing.table.clone();
+        }
+        if inner.memory_budget.is_some_and(|budget| size > budget) {
+            return table;
+        }
+        let cached = CachedTable {
+            table: table.clone(),
+            hits: 0,
+        };
+        for replaced in inner.tables.insert(key, cached) {
+            inner.remove_memory(replaced.table.memory_size());
+        }
+        inner.memory_used += size;
+        #[cfg(feature = "cache")]
+        METRICS.bytes.fetch_add(size as u64, Relaxed);
+        inner.evict_over_budget();
+        table
+    }
+
+    /// Precompute the tables for all the pairs `(base, modulus)` in parallel
+    ///
+    /// The work is distributed over the available cores, or done on the calling thread with a single
+    /// core. The tables already in the cache are not calculated again. Return the first error, if
+    /// any.
+    pub fn warm(
+        &self,
+        pairs: &[(Integer, Integer)],
+        params: FPowmDefaults,
+    ) -> Result<(), GmpMEEError> {
+        timed_span!(
+            "fpowm_cache_warm",
+            count = pairs.len(),
+            block_width = params.block_width
+        );
+        if pairs.is_empty() {
+            return Ok(());
+        }
+        let nb_threads = available_workers();
+        if nb_threads == 1 {
+            return pairs
+                .iter()
+                .try_for_each(|(b, m)| self.get_or_init_with(b, m, params).map(|_| ()));
+        }
+        let chunk_size = pairs.len().div_ceil(nb_threads);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = pairs
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .try_for_each(|(b, m)| self.get_or_init_with(b, m, params).map(|_| ()))
+                    })
+                })
+                .collect();
+            handles.into_iter().try_for_each(|h| h.join().unwrap())
+        })
+    }
+
+    /// Calculate `base^exponent mod modulus` with the table of the cache
+    pub fn fpowm(
+        &self,
+        base: &Integer,
+        modulus: &Integer,
+        exponent: &Integer,
+    ) -> Result<Integer, GmpMEEError> {
+        Ok(self.get_or_init(base, modulus)?.fpowm(exponent))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ExponentBitlenPolicy;
+    use super::*;
+
+    #[test]
+    fn test_warm() {
+        let cache = FPowmCache::new();
+        let p = Integer::from(1019);
+        let pairs: Vec<_> = (2..20u32).map(|b| (Integer::from(b), p.clone())).collect();
+        let params = FPowmDefaults {
+            block_width: 4,
+            exponent_bitlen_policy: ExponentBitlenPolicy::Fixed(16),
+        };
+        cache.warm(&pairs, params).unwrap();
+        assert_eq!(cache.len(), pairs.len());
+        let tab = cache.get(&Integer::from(5), &p).unwrap();
+        assert_eq!(tab.block_width(), 4);
+        assert_eq!(tab.exponent_bitlen(), 16);
+        let params = FPowmDefaults {
+            block_width: usize::MAX,
+            exponent_bitlen_policy: ExponentBitlenPolicy::Modulus,
+        };
+        assert!(
+            cache
+                .warm(&[(Integer::from(3), Integer::from(1031))], params)
+                .is_err()
+        );
+        assert!(cache.warm(&[], params).is_ok());
+    }
+
+    #[test]
+    fn test_fpowm_cache() {
+        let p = Integer::from(1019);
+        let e = Integer::from(1000);
+        let cache = FPowmCache::new();
+        assert!(cache.is_empty());
+        for b in [3u32, 5, 7, 3] {
+            let b = Integer::from(b);
+            assert_eq!(cache.fpowm(&b, &p, &e).unwrap(), b.pow_mod(&e, &p).unwrap());
+        }
+        assert_eq!(cache.len(), 3);
+        let entries = cache.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries.iter().map(|entry| entry.hits).sum::<u64>(), 1);
+        assert!(
+            entries
+                .iter()
+                .all(|entry| entry.modulus_fingerprint.len() == 16)
+        );
+        assert_ne!(entries[0].base_fingerprint, entries[1].base_fingerprint);
+        let size = cache.memory_used() / 3;
+        assert!(size > 0);
+        cache.set_memory_budget(Some(2 * size));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&Integer::from(5), &p).is_none());
+        assert!(cache.get(&Integer::from(3), &p).is_some());
+        cache.fpowm(&Integer::from(11), &p, &e).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&Integer::from(7), &p).is_none());
+        cache.set_memory_budget(Some(size / 2));
+        assert!(cache.is_empty());
+        let b = Integer::from(13);
+        assert_eq!(cache.fpowm(&b, &p, &e).unwrap(), b.pow_mod(&e, &p).unwrap());
+        assert!(cache.is_empty());
+        assert_eq!(cache.memory_used(), 0);
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Fixed base exponentiation with the comb method of Lim and Lee, in pure Rust (see [CombTable])
+
+use super::{FixedBaseExp, check_block_width, check_modulus};
+use crate::GmpMEEError;
+use rug::Integer;
+
+/// Table of the comb method of Lim and Lee for a fixed base, as an alternative to [super::FPowmTable]
+///
+/// The exponents of `exponent_bitlen` bits are split in `h = block_width` blocks of
+/// `a = ceil(exponent_bitlen/h)` bits. The table contains the `2^h` products
+/// `prod_{i in J} base^(2^(i*a))` for the subsets `J` of `[0, h[`, and an exponentiation needs `a`
+/// squarings and `a` multiplications. The table is computed with the arithmetic of rug only, which
+/// permits to compare the approaches and to avoid the C table code of GMPMEE where it behaves poorly.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::fpowm::{CombTable, FixedBaseExp};
+/// let p = Integer::from(13);
+/// let b = Integer::from(7);
+/// let tab = CombTable::init_precomp(&b, &p, 4, 16).unwrap();
+/// assert_eq!(tab.fpowm(&Integer::from(4)), b.pow_mod(&Integer::from(4), &p).unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CombTable {
+    base: Integer,
+    modulus: Integer,
+    block_width: usize,
+    exponent_bitlen: usize,
+    /// Number `a` of bits of each block
+    rows: usize,
+    entries: Vec<Integer>,
+}
+
+impl CombTable {
+    /// Precompute the table for the base, with `block_width` blocks for the exponents of
+    /// `exponent_bitlen` bits
+    ///
+    /// Return the same errors as [super::FPowmTable::init_precomp]: the modulus must be larger
+    /// than 1, and the block width must be positive and smaller than the number of bits of `usize`.
+    pub fn init_precomp(
+        base: &Integer,
+        modulus: &Integer,
+        block_width: usize,
+        exponent_bitlen: usize,
+    ) -> Result<Self, GmpMEEError> {
+        check_modulus(modulus)?;
+        check_block_width(block_width)?;
+        let rows = exponent_bitlen.div_ceil(block_width).max(1);
+        let mut entries = Vec::with_capacity(1 << block_width);
+        entries.push(Integer::from(1) % modulus);
+        // base^(2^(i*a)) for the block i
+        let mut block_base = Integer::from(base % modulus);
+        for _ in 0..block_width {
+            for j in 0..entries.len() {
+                let entry = Integer::from(&entries[j] * &block_base) % modulus;
+                entries.push(entry);
+            }
+            for _ in 0..rows {
+                block_base.square_mut();
+                block_base %= modulus;
+            }
+        }
+        Ok(Self {
+            base: base.clone(),
+            modulus: modulus.clone(),
+            block_width,
+            exponent_bitlen,
+            rows,
+            entries,
+        })
+    }
+
+    /// Base of the table
+    pub fn base(&self) -> &Integer {
+        &self.base
+    }
+
+    /// Modulus of the table
+    pub fn modulus(&self) -> &Integer {
+        &self.modulus
+    }
+
+    /// Number of blocks of the exponents
+    pub fn block_width(&self) -> usize {
+        self.block_width
+    }
+
+    /// Bit length of the exponents covered by the table
+    pub fn exponent_bitlen(&self) -> usize {
+        self.exponent_bitlen
+    }
+
+    /// `base^exponent mod modulus`
+    ///
+    /// The negative exponents and the exponents longer than the blocks of the table are computed
+    /// with `pow_mod` directly. Panics if the exponent is negative and the base is not invertible.
+    pub fn fpowm(&self, exponent: &Integer) -> Integer {
+        if *exponent < 0 || exponent.significant_bits() as usize > self.rows * self.block_width {
+            return Integer::from(
+                self.base
+                    .pow_mod_ref(exponent, &self.modulus)
+                    .expect("the base must be invertible for a negative exponent"),
+            );
+        }
+        let mut res = self.entries[0].clone();
+        for k in (0..self.rows).rev() {
+            res.square_mut();
+            let index = (0..self.block_width)
+                .filter(|i| exponent.get_bit((i * self.rows + k) as u32))
+                .fold(0usize, |index, i| index | (1 << i));
+            res *= &self.entries[index];
+            res %= &self.modulus;
+        }
+        res
+    }
+}
+
+impl FixedBaseExp for CombTable {
+    fn modulus(&self) -> &Integer {
+        CombTable::modulus(self)
+    }
+
+    fn fpowm(&self, exponent: &Integer) -> Integer {
+        CombTable::fpowm(self, exponent)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fpowm::{FPowmTable, FPownError};
+    use rug::rand::RandState;
+
+    #[test]
+    fn test_comb() {
+        let mut rand = RandState::new();
+        let p = Integer::from(Integer::random_bits(512, &mut rand)) | 1u32;
+        let b = Integer::from(Integer::random_bits(512, &mut rand)) % &p;
+        for (block_width, exponent_bitlen) in [(1, 1), (1, 64), (4, 256), (5, 256), (8, 100)] {
+            let comb = CombTable::init_precomp(&b, &p, block_width, exponent_bitlen).unwrap();
+            let gmpmee = FPowmTable::init_precomp(&b, &p, block_width, exponent_bitlen).unwrap();
+            let tables: [&dyn FixedBaseExp; 2] = [&comb, &gmpmee];
+            for bits in [0, 1, exponent_bitlen as u32, exponent_bitlen as u32 + 50] {
+                let e = Integer::from(Integer::random_bits(bits, &mut rand));
+                let expected = b.clone().pow_mod(&e, &p).unwrap();
+                assert_eq!(comb.fpowm(&e), expected, "{block_width} {exponent_bitlen}");
+                if bits as usize <= exponent_bitlen {
+                    for table in tables {
+                        assert_eq!(table.fpowm(&e), expected);
+                        assert_eq!(table.modulus(), &p);
+                    }
+                }
+            }
+        }
+        let comb = CombTable::init_precomp(&Integer::from(7), &Integer::from(13), 2, 8).unwrap();
+        assert_eq!(comb.fpowm(&Integer::from(-1)), 2);
+        assert_eq!(comb.base(), &Integer::from(7));
+        assert_eq!((comb.block_width(), comb.exponent_bitlen()), (2, 8));
+        assert_eq!(
+            CombTable::init_precomp(&Integer::from(7), &Integer::from(13), 0, 8),
+            Err(FPownError::BlockWidth(0).into())
+        );
+        for modulus in [-13, 0, 1] {
+            let modulus = Integer::from(modulus);
+            assert_eq!(
+                CombTable::init_precomp(&Integer::from(7), &modulus, 2, 8),
+                Err(FPownError::ModulusTooSmall.into())
+            );
+            assert!(FPowmTable::init_precomp(&Integer::from(7), &modulus, 2, 8).is_err());
+        }
+    }
+}
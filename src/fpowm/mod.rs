@@ -0,0 +1,1446 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module to wrap the functions related to the fixed-sized modulo exponential in gmpmee
+//!
+//! The structure [FPowmTable] permits to initialized the precomputation and to perform the exponentiate
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::fpowm::FPowmTable;
+//! let p = Integer::from(13);
+//! let b = Integer::from(7);
+//! let e = Integer::from(4);
+//! let tab = FPowmTable::init_precomp(&b, &p, 16, 16).unwrap();
+//! let res = tab.fpowm(&e);
+//! assert_eq!(res, b.pow_mod(&e, &p).unwrap());
+//! ```
+//!
+//! The parameters can be derived from the group with [FPowmTable::for_group] or
+//! [FPowmTable::for_subgroup], which avoid to choose a too small exponent bit length
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::fpowm::FPowmTable;
+//! let p = Integer::from(23);
+//! let q = Integer::from(11);
+//! let g = Integer::from(4);
+//! let e = Integer::from(7);
+//! let tab = FPowmTable::for_subgroup(&g, &p, &q).unwrap();
+//! assert_eq!(tab.fpowm(&e), g.pow_mod(&e, &p).unwrap());
+//! ```
+//!
+//! The tables can be stored with [FPowmTable::write_to] and loaded again with
+//! [FPowmTable::read_from]. The serialized tables contain a checksum that is verified when loading
+//! them, and [FPowmTable::verify_integrity] checks a table already in memory. For large parameters,
+//! [FPowmTable::write_precomp] writes the table while computing it, without keeping it in memory.
+//! With the feature `shm`, a table can be written in a segment mapped read-only by several processes
+//! (see `FPowmTable::write_shared` and `FPowmTable::open_shared`).
+//!
+//! It is possible to used a cache table, as static variable (only with the feature `cache`). The cache
+//! must be initiliazed once and cannot be changed anymore
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::fpowm::{cache_init_precomp, cache_fpown, cache_base_modulus};
+//! let p = Integer::from(13);
+//! let b = Integer::from(7);
+//! let e = Integer::from(4);
+//! assert!(cache_base_modulus().is_none());
+//! let res_init = cache_init_precomp(&b, &p, 16, 1024);
+//! assert!(res_init.is_ok());
+//! assert!(res_init.unwrap());
+//! let (base, modulus) = cache_base_modulus().unwrap();
+//! assert_eq!((base.as_ref(), modulus.as_ref()), (&b, &p));
+//! assert_eq!(cache_fpown(&e).unwrap(),b.pow_mod(&e, &p).unwrap());
+//! ```
+//!
+//! For several bases or moduli, [FPowmCache] keeps the tables of the pairs base/modulus that are used,
+//! within an optional memory budget.
+//!
+//! [CombTable] implements the comb method of Lim and Lee in pure Rust, as an alternative to the
+//! tables of GMPMEE. Both implement the trait [FixedBaseExp], so that the code using a fixed base
+//! exponentiation can select the backend.
+
+mod cache;
+mod comb;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod serialize;
+#[cfg(feature = "shm")]
+mod shared;
+
+use crate::{
+    GmpMEEError,
+    lru::LruCache,
+    sys::{
+        gmpmee_fpowm, gmpmee_fpowm_clear, gmpmee_fpowm_init, gmpmee_fpowm_init_precomp,
+        gmpmee_fpowm_precomp, gmpmee_fpowm_tab, gmpmee_spowm_tab,
+    },
+    telemetry::{self, Exponentiation},
+    trace::timed_span,
+    usize_to_size_t_type,
+};
+#[cfg(feature = "config")]
+pub use cache::{CACHE_ENV_PREFIX, CacheConfig, CacheConfigSource, cache_init_from_config};
+pub use cache::{CacheEntry, FPowmCache};
+#[cfg(feature = "cache")]
+pub use cache::{
+    CacheError, CacheHandle, CacheLocation, CacheMetrics, cache_base_modulus,
+    cache_base_modulus_named, cache_entries, cache_fpowm_named, cache_fpown, cache_init_named,
+    cache_init_named_background, cache_init_named_default, cache_init_precomp,
+    cache_init_precomp_background, cache_init_precomp_default, cache_load_all, cache_metrics,
+    cache_names, cache_remove_named, cache_save_all, cache_set_table, cache_set_table_named,
+    cache_table, cache_table_named, cache_warm, global_cache, reset_cache_metrics,
+    thread_cache_base_modulus, thread_cache_clear, thread_cache_fpowm, thread_cache_init_precomp,
+    thread_cache_init_precomp_default, thread_cache_table, try_cache_base_modulus,
+    try_cache_fpowm_named, try_cache_fpown, try_thread_cache_fpowm,
+};
+pub use comb::CombTable;
+#[cfg(feature = "rayon")]
+pub use parallel::{FPowmParallelIterator, FPowmWith};
+use rug::{Assign, Complete, Integer, ops::Pow};
+use std::cmp::Ordering;
+use std::sync::{Mutex, RwLock};
+use thiserror::Error;
+
+/// Block width used if no other value is set with [set_defaults]
+pub const DEFAULT_BLOCK_WIDTH: usize = 12;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FPownError {
+    #[error("{variable} cannot be casted to i64 (in {method}): {source}")]
+    ExponentCast {
+        method: &'static str,
+        variable: &'static str,
+        source: std::num::TryFromIntError,
+    },
+    #[deprecated(
+        since = "0.2.2",
+        note = "the caches return `CacheError::ParameterMismatch`"
+    )]
+    #[error("The cache is already initialized with another base or modulus")]
+    CacheAlreadyInitialized {
+        existing_base: Integer,
+        existing_modulus: Integer,
+    },
+    #[deprecated(
+        since = "0.2.2",
+        note = "the caches return `CacheError::NotInitialized`"
+    )]
+    #[error("The cache is not initialized")]
+    CacheNotInitialized,
+    #[error("The table has no base: the precomputation has not been done")]
+    NotPrecomputed,
+    #[error("Error in the serialization of the table: {0}")]
+    Serialization(String),
+    #[error("The integrity of the table cannot be verified: {0}")]
+    Integrity(String),
+    #[error("The modulus must be odd for secret exponents")]
+    EvenModulus,
+    #[error("At least one tier must be given")]
+    NoTier,
+    #[error("The base is not invertible modulo the modulus")]
+    NotInvertible,
+    #[error("Index {index} of the base is out of range (number of bases: {len})")]
+    BaseIndex { index: usize, len: usize },
+    #[error("Block width {0} must be positive and smaller than the number of bits of usize")]
+    BlockWidth(usize),
+    #[error("The modulus must be larger than 1")]
+    ModulusTooSmall,
+    #[error("The tables have not the same modulus")]
+    DifferentModulus,
+    #[error("Len of inputs {inputs} is not the same than len of randomness {randomness}")]
+    NotSameLen { inputs: usize, randomness: usize },
+}
+
+/// Policy to calculate the bit length of the exponents, when it is not given explicitly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExponentBitlenPolicy {
+    /// Use the bit length of the modulus
+    Modulus,
+    /// Use always the given bit length
+    Fixed(usize),
+}
+
+impl ExponentBitlenPolicy {
+    /// Exponent bit length to use for the given modulus
+    pub fn exponent_bitlen(&self, modulus: &Integer) -> usize {
+        match self {
+            ExponentBitlenPolicy::Modulus => modulus.significant_bits() as usize,
+            ExponentBitlenPolicy::Fixed(bitlen) => *bitlen,
+        }
+    }
+}
+
+/// Default tuning parameters used by the convenience constructors and the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FPowmDefaults {
+    pub block_width: usize,
+    pub exponent_bitlen_policy: ExponentBitlenPolicy,
+}
+
+static DEFAULTS: RwLock<FPowmDefaults> = RwLock::new(FPowmDefaults {
+    block_width: DEFAULT_BLOCK_WIDTH,
+    exponent_bitlen_policy: ExponentBitlenPolicy::Modulus,
+});
+
+/// Set the default tuning parameters for the whole process
+///
+/// The defaults are used by [FPowmTable::init_precomp_default] and `cache_init_precomp_default`
+pub fn set_defaults(
+    block_width: usize,
+    exponent_bitlen_policy: ExponentBitlenPolicy,
+) -> Result<(), GmpMEEError> {
+    check_block_width(block_width)?;
+    *DEFAULTS.write().unwrap_or_else(|e| e.into_inner()) = FPowmDefaults {
+        block_width,
+        exponent_bitlen_policy,
+    };
+    Ok(())
+}
+
+/// Return an error if the block width is zero or not smaller than the number of bits of `usize`
+pub(crate) fn check_block_width(block_width: usize) -> Result<(), FPownError> {
+    if block_width == 0 || block_width >= usize::BITS as usize {
+        return Err(FPownError::BlockWidth(block_width));
+    }
+    Ok(())
+}
+
+/// Return an error if the modulus is not larger than 1
+pub(crate) fn check_modulus(modulus: &Integer) -> Result<(), FPownError> {
+    if *modulus <= 1 {
+        return Err(FPownError::ModulusTooSmall);
+    }
+    Ok(())
+}
+
+/// Return an error if the tables, e.g. of the two generators of a scheme, have not the same
+/// modulus
+pub(crate) fn check_same_modulus(
+    first: &FPowmTable,
+    second: &FPowmTable,
+) -> Result<(), FPownError> {
+    if first.modulus() != second.modulus() {
+        return Err(FPownError::DifferentModulus);
+    }
+    Ok(())
+}
+
+/// Return an error if the inputs of a batch have not one randomness each
+pub(crate) fn check_same_len(inputs: usize, randomness: usize) -> Result<(), FPownError> {
+    if inputs != randomness {
+        return Err(FPownError::NotSameLen { inputs, randomness });
+    }
+    Ok(())
+}
+
+/// Return the current default tuning parameters
+pub fn defaults() -> FPowmDefaults {
+    *DEFAULTS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Mode of the exponents used with a table (only with the feature `constant-time`)
+///
+/// GMPMEE's `fpowm` is not constant-time. For secret exponents, the mode [ExponentMode::Secret]
+/// computes the exponentiation with `mpz_powm_sec`, whose timing and memory accesses do not depend
+/// on the exponent. It is much slower than the precomputed table. Zero and negative exponents
+/// always use the table.
+#[cfg(feature = "constant-time")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExponentMode {
+    /// The exponents are public
+    #[default]
+    Public,
+    /// The exponents are secret
+    Secret,
+}
+
+/// Fixed base exponentiation with a precomputed table, implemented by [FPowmTable] (GMPMEE) and
+/// [CombTable] (pure Rust)
+pub trait FixedBaseExp {
+    /// Modulus of the exponentiations
+    fn modulus(&self) -> &Integer;
+
+    /// `base^exponent mod modulus` for the fixed base of the table
+    fn fpowm(&self, exponent: &Integer) -> Integer;
+}
+
+/// Structure containing the structure of the table to precompute of fixed-sized modulo exponential
+///
+/// The structure implementes `Sync` and `Send` for the caching function
+pub struct FPowmTable {
+    inner: gmpmee_fpowm_tab,
+    modulus: Integer,
+    base: Option<Integer>,
+    block_width: usize,
+    exponent_bitlen: usize,
+    inverse: Option<Box<FPowmTable>>,
+    extension: Option<Box<FPowmTable>>,
+    checksum: Option<[u8; 32]>,
+    result_cache: Option<Mutex<LruCache<Integer, Integer>>>,
+    #[cfg(feature = "constant-time")]
+    mode: ExponentMode,
+    #[cfg(feature = "shm")]
+    shared: Option<shared::SharedSegment>,
+}
+
+unsafe fn get_empty_gmpmee_fpowm_tab() -> gmpmee_fpowm_tab {
+    gmpmee_fpowm_tab {
+        spowm_table: gmpmee_spowm_tab {
+            len: 0,
+            block_width: 0,
+            tabs_len: 0,
+            tabs: std::ptr::null_mut(),
+            modulus: Integer::new().into_raw(),
+        },
+        stretch: 0,
+    }
+}
+
+impl FPowmTable {
+    /// Wrap `gmpmee_init``
+    ///
+    /// Return an error if the modulus is not larger than 1, or if the block width is zero or not
+    /// smaller than the number of bits of `usize`
+    pub fn init(
+        modulus: &Integer,
+        block_width: usize,
+        exponent_bitlen: usize,
+    ) -> Result<Self, GmpMEEError> {
+        check_modulus(modulus)?;
+        check_block_width(block_width)?;
+        let block_width_i64 =
+            usize_to_size_t_type(block_width).map_err(|e| FPownError::ExponentCast {
+                method: "FPowmTable::init",
+                variable: "block_width",
+                source: e,
+            })?;
+        let exponent_bitlen_i64 =
+            usize_to_size_t_type(exponent_bitlen).map_err(|e| FPownError::ExponentCast {
+                method: "FPowmTable::init",
+                variable: "exponent_bitlen",
+                source: e,
+            })?;
+        unsafe {
+            let mut tab = get_empty_gmpmee_fpowm_tab();
+            let t_ptr = &mut tab;
+            gmpmee_fpowm_init(
+                t_ptr,
+                modulus.as_raw(),
+                block_width_i64,
+                exponent_bitlen_i64,
+            );
+            Ok(Self {
+                inner: *t_ptr,
+                modulus: modulus.clone(),
+                base: None,
+                block_width,
+                exponent_bitlen,
+                inverse: None,
+                extension: None,
+                checksum: None,
+                result_cache: None,
+                #[cfg(feature = "constant-time")]
+                mode: ExponentMode::Public,
+                #[cfg(feature = "shm")]
+                shared: None,
+            })
+        }
+    }
+
+    /// Wrap `gmpmee_init_precomp``
+    ///
+    /// Return an error if the modulus is not larger than 1, or if the block width is zero or not
+    /// smaller than the number of bits of `usize`
+    pub fn init_precomp(
+        base: &Integer,
+        modulus: &Integer,
+        block_width: usize,
+        exponent_bitlen: usize,
+    ) -> Result<Self, GmpMEEError> {
+        check_modulus(modulus)?;
+        check_block_width(block_width)?;
+        let block_width_i64 =
+            usize_to_size_t_type(block_width).map_err(|e| FPownError::ExponentCast {
+                method: "FPowmTable::init_precomp",
+                variable: "block_width",
+                source: e,
+            })?;
+        let exponent_bitlen_i64 =
+            usize_to_size_t_type(exponent_bitlen).map_err(|e| FPownError::ExponentCast {
+                method: "FPowmTable::init_precomp",
+                variable: "exponent_bitlen",
+                source: e,
+            })?;
+        timed_span!(
+            "fpowm_precomp",
+            modulus_bits = modulus.significant_bits(),
+            block_width = block_width,
+            exponent_bitlen = exponent_bitlen
+        );
+        unsafe {
+            let mut tab = get_empty_gmpmee_fpowm_tab();
+            let t_ptr = &mut tab;
+            telemetry::record_precomp(|| {
+                gmpmee_fpowm_init_precomp(
+                    t_ptr,
+                    base.as_raw(),
+                    modulus.as_raw(),
+                    block_width_i64,
+                    exponent_bitlen_i64,
+                )
+            });
+            Ok(Self {
+                inner: *t_ptr,
+                modulus: modulus.clone(),
+                base: Some(base.clone()),
+                block_width,
+                exponent_bitlen,
+                inverse: None,
+                extension: None,
+                checksum: None,
+                result_cache: None,
+                #[cfg(feature = "constant-time")]
+                mode: ExponentMode::Public,
+                #[cfg(feature = "shm")]
+                shared: None,
+            })
+        }
+    }
+
+    /// Initialize the table with the precomputation for the base, using the parameters set
+    /// with [set_defaults]
+    pub fn init_precomp_default(base: &Integer, modulus: &Integer) -> Result<Self, GmpMEEError> {
+        let defaults = defaults();
+        Self::init_precomp(
+            base,
+            modulus,
+            defaults.block_width,
+            defaults.exponent_bitlen_policy.exponent_bitlen(modulus),
+        )
+    }
+
+    /// Initialize the table for a base of the group defined by the modulus
+    ///
+    /// The exponent bit length is the bit length of the modulus and the block width is the
+    /// default block width (see [set_defaults])
+    pub fn for_group(base: &Integer, modulus: &Integer) -> Result<Self, GmpMEEError> {
+        Self::init_precomp(
+            base,
+            modulus,
+            defaults().block_width,
+            modulus.significant_bits() as usize,
+        )
+    }
+
+    /// Initialize the table for a base of the subgroup of the given order
+    ///
+    /// The exponent bit length is the bit length of the order, since the exponents are expected
+    /// to be reduced modulo the order. The block width is the default block width (see [set_defaults])
+    pub fn for_subgroup(
+        base: &Integer,
+        modulus: &Integer,
+        order: &Integer,
+    ) -> Result<Self, GmpMEEError> {
+        Self::init_precomp(
+            base,
+            modulus,
+            defaults().block_width,
+            order.significant_bits() as usize,
+        )
+    }
+
+    /// Initialize the table with the precomputation for the base, and a companion table for the
+    /// inverse of the base with the same configuration (see [FPowmTable::fpowm_inv])
+    ///
+    /// Return an error if the base is not invertible modulo the modulus
+    pub fn init_precomp_with_inverse(
+        base: &Integer,
+        modulus: &Integer,
+        block_width: usize,
+        exponent_bitlen: usize,
+    ) -> Result<Self, GmpMEEError> {
+        let inverse_base = base
+            .invert_ref(modulus)
+            .map(Integer::from)
+            .ok_or(FPownError::NotInvertible)?;
+        let mut tab = Self::init_precomp(base, modulus, block_width, exponent_bitlen)?;
+        tab.inverse = Some(Box::new(Self::init_precomp(
+            &inverse_base,
+            modulus,
+            block_width,
+            exponent_bitlen,
+        )?));
+        Ok(tab)
+    }
+
+    /// Wrap `gmpmee_precomp``
+    ///
+    /// The entries are computed in place, reusing the memory already allocated for the table. Nothing
+    /// is done if the base is the current base of the table.
+    ///
+    /// If the table has a companion table for the inverse, it is recomputed for the inverse of the new
+    /// base. If the new base is not invertible, the companion table is removed. The extension for the
+    /// high bits (see [FPowmTable::extend_bitlen]) is recomputed as well.
+    pub fn precomp(&mut self, base: &Integer) {
+        if self.base.as_ref() == Some(base) {
+            return;
+        }
+        timed_span!(
+            "fpowm_precomp",
+            modulus_bits = self.modulus.significant_bits(),
+            block_width = self.block_width,
+            exponent_bitlen = self.exponent_bitlen
+        );
+        #[cfg(feature = "shm")]
+        self.detach_shared();
+        telemetry::record_precomp(|| unsafe {
+            gmpmee_fpowm_precomp(&mut self.inner, base.as_raw())
+        });
+        self.base = Some(base.clone());
+        self.checksum = None;
+        self.clear_result_cache();
+        if self.extension.is_some() {
+            let high_base = self.high_base(base);
+            if let Some(extension) = self.extension.as_mut() {
+                extension.precomp(&high_base);
+            }
+        }
+        if let Some(mut inverse) = self.inverse.take()
+            && let Some(inverse_base) = base.invert_ref(&self.modulus).map(Integer::from)
+        {
+            inverse.precomp(&inverse_base);
+            self.inverse = Some(inverse);
+        }
+    }
+
+    /// Wrap `gmpmee_fpowm``
+    ///
+    /// The cache of the results (see [FPowmTable::with_result_cache]) is not used in the mode
+    /// `ExponentMode::Secret` (feature `constant-time`), so that no secret exponent is kept in memory.
+    pub fn fpowm(&self, exponent: &Integer) -> Integer {
+        telemetry::record_exponentiations(Exponentiation::Fpowm, 1);
+        self.fpowm_cached(exponent)
+    }
+
+    /// Calculate the exponentiation with the cache of the results, without counting it in the
+    /// metrics
+    fn fpowm_cached(&self, exponent: &Integer) -> Integer {
+        match &self.result_cache {
+            Some(cache) if !self.has_secret_exponents() => {
+                if let Some(res) = cache
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .get(exponent)
+                {
+                    return res.clone();
+                }
+                let res = self.fpowm_uncached(exponent);
+                cache
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(exponent.clone(), res.clone());
+                res
+            }
+            _ => self.fpowm_uncached(exponent),
+        }
+    }
+
+    /// Return `true` if the exponents used with the table are secret
+    fn has_secret_exponents(&self) -> bool {
+        #[cfg(feature = "constant-time")]
+        return self.mode == ExponentMode::Secret;
+        #[cfg(not(feature = "constant-time"))]
+        false
+    }
+
+    /// Calculate the exponentiation without the cache of the results, for the exponents that must
+    /// not be kept in memory
+    pub(crate) fn fpowm_uncached(&self, exponent: &Integer) -> Integer {
+        #[cfg(feature = "constant-time")]
+        if self.mode == ExponentMode::Secret
+            && exponent.cmp0() == Ordering::Greater
+            && let Some(base) = &self.base
+        {
+            return Integer::from(base.secure_pow_mod_ref(exponent, &self.modulus));
+        }
+        if let Some(extension) = &self.extension
+            && exponent.cmp0() == Ordering::Greater
+            && exponent.significant_bits() as usize > self.exponent_bitlen
+        {
+            let bitlen = self.exponent_bitlen as u32;
+            let low = Integer::from(exponent.keep_bits_ref(bitlen));
+            let high = Integer::from(exponent >> bitlen);
+            let mut res = self.gmpmee_fpowm(&low) * extension.fpowm_cached(&high);
+            res %= &self.modulus;
+            return res;
+        }
+        self.gmpmee_fpowm(exponent)
+    }
+
+    fn gmpmee_fpowm(&self, exponent: &Integer) -> Integer {
+        let mut res = Integer::new();
+        unsafe {
+            let z_ptr = res.as_raw_mut();
+            gmpmee_fpowm(z_ptr, &self.inner, exponent.as_raw());
+        }
+        res
+    }
+
+    /// Keep the results of the last `capacity` distinct exponents in a cache
+    ///
+    /// The cache is useful if the same exponents are used repeatedly. When the cache is full, the
+    /// least recently used result is removed. The cache is cleared if the base or the modulus changes,
+    /// and it is not used in the mode `ExponentMode::Secret` (feature `constant-time`).
+    pub fn with_result_cache(mut self, capacity: usize) -> Self {
+        self.result_cache = Some(Mutex::new(LruCache::new(capacity)));
+        self
+    }
+
+    /// Capacity of the cache of the results, if any (see [FPowmTable::with_result_cache])
+    pub fn result_cache_capacity(&self) -> Option<usize> {
+        self.result_cache
+            .as_ref()
+            .map(|c| c.lock().unwrap_or_else(|e| e.into_inner()).capacity())
+    }
+
+    /// Remove all the results from the cache of the results
+    pub fn clear_result_cache(&self) {
+        if let Some(cache) = &self.result_cache {
+            cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+    }
+
+    /// Extend the table to exponents of `new_bitlen` bits, reusing the existing entries
+    ///
+    /// The existing entries are used for the low bits of the exponents, and a table is precomputed
+    /// only for the missing high bits, with the base `base^(2^exponent_bitlen)`. Nothing is done if
+    /// `new_bitlen` is not larger than the current exponent bit length. The companion table for the
+    /// inverse is extended as well. The extended tables cannot be serialized.
+    pub fn extend_bitlen(&mut self, new_bitlen: usize) -> Result<(), GmpMEEError> {
+        if new_bitlen <= self.exponent_bitlen() {
+            return Ok(());
+        }
+        let base = self.base.as_ref().ok_or(FPownError::NotPrecomputed)?;
+        let high_bitlen = new_bitlen - self.exponent_bitlen;
+        match self.extension.as_mut() {
+            Some(extension) => extension.extend_bitlen(high_bitlen)?,
+            None => {
+                let extension = Self::init_precomp(
+                    &self.high_base(base),
+                    &self.modulus,
+                    self.block_width,
+                    high_bitlen,
+                )?;
+                self.extension = Some(Box::new(extension));
+            }
+        }
+        #[cfg(feature = "constant-time")]
+        if let Some(extension) = self.extension.as_mut() {
+            extension.mode = self.mode;
+        }
+        if let Some(inverse) = self.inverse.as_mut() {
+            inverse.extend_bitlen(new_bitlen)?;
+        }
+        self.checksum = None;
+        Ok(())
+    }
+
+    /// Base of the extension for the high bits: `base^(2^exponent_bitlen)`
+    fn high_base(&self, base: &Integer) -> Integer {
+        let mut high_base = base.clone();
+        for _ in 0..self.exponent_bitlen {
+            high_base.square_mut();
+            high_base %= &self.modulus;
+        }
+        high_base
+    }
+
+    /// Initialize the table again for a new modulus and new parameters
+    ///
+    /// If the block width and the exponent bit length do not change, the memory allocated for the
+    /// entries is reused and only the modulus is replaced. Otherwise the table is allocated again.
+    /// In both cases, the precomputation must be done again with [FPowmTable::precomp]. The companion
+    /// table for the inverse and the extension of [FPowmTable::extend_bitlen] are removed.
+    ///
+    /// The mode of the exponents is kept. In the mode `ExponentMode::Secret` (feature
+    /// `constant-time`), the new modulus must be odd, and the table is not modified otherwise.
+    pub fn reinit(
+        &mut self,
+        modulus: &Integer,
+        block_width: usize,
+        exponent_bitlen: usize,
+    ) -> Result<(), GmpMEEError> {
+        check_modulus(modulus)?;
+        #[cfg(feature = "constant-time")]
+        if self.mode == ExponentMode::Secret && modulus.is_even() {
+            return Err(FPownError::EvenModulus.into());
+        }
+        if block_width == self.block_width && exponent_bitlen == self.exponent_bitlen {
+            let table_modulus =
+                unsafe { &mut *(&mut self.inner.spowm_table.modulus as *mut _ as *mut Integer) };
+            table_modulus.assign(modulus);
+            self.modulus.assign(modulus);
+            #[cfg(feature = "shm")]
+            self.detach_shared();
+            self.base = None;
+            self.inverse = None;
+            self.extension = None;
+            self.checksum = None;
+            self.clear_result_cache();
+        } else {
+            #[cfg(feature = "constant-time")]
+            let mode = self.mode;
+            let capacity = self.result_cache_capacity();
+            *self = Self::init(modulus, block_width, exponent_bitlen)?;
+            #[cfg(feature = "constant-time")]
+            {
+                self.mode = mode;
+            }
+            if let Some(capacity) = capacity {
+                self.result_cache = Some(Mutex::new(LruCache::new(capacity)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Calculate the exponentiation of the inverse of the base, using the companion table
+    ///
+    /// Return `None` if the table has been initialized without the companion table
+    pub fn fpowm_inv(&self, exponent: &Integer) -> Option<Integer> {
+        let inverse = self.inverse.as_ref()?;
+        telemetry::record_exponentiations(Exponentiation::FpowmInv, 1);
+        Some(inverse.fpowm_cached(exponent))
+    }
+
+    /// Return `true` if the table has a companion table for the inverse of the base
+    pub fn has_inverse(&self) -> bool {
+        self.inverse.is_some()
+    }
+
+    /// Set the mode of the exponents used with the table (and its companion table for the inverse)
+    ///
+    /// In the mode [ExponentMode::Secret], the modulus must be odd.
+    #[cfg(feature = "constant-time")]
+    pub fn set_exponent_mode(&mut self, mode: ExponentMode) -> Result<(), GmpMEEError> {
+        if mode == ExponentMode::Secret && self.modulus.is_even() {
+            return Err(FPownError::EvenModulus.into());
+        }
+        self.mode = mode;
+        if let Some(inverse) = self.inverse.as_mut() {
+            inverse.mode = mode;
+        }
+        Ok(())
+    }
+
+    /// Mode of the exponents used with the table
+    #[cfg(feature = "constant-time")]
+    pub fn exponent_mode(&self) -> ExponentMode {
+        self.mode
+    }
+
+    /// Base of the table, if the precomputation has been done
+    pub fn base(&self) -> Option<&Integer> {
+        self.base.as_ref()
+    }
+
+    /// Modulus of the table
+    pub fn modulus(&self) -> &Integer {
+        &self.modulus
+    }
+
+    /// Block width used for the table
+    pub fn block_width(&self) -> usize {
+        self.block_width
+    }
+
+    /// Exponent bit length for which the table is optimized (including the extension of
+    /// [FPowmTable::extend_bitlen])
+    pub fn exponent_bitlen(&self) -> usize {
+        self.exponent_bitlen + self.extension.as_ref().map_or(0, |e| e.exponent_bitlen())
+    }
+
+    /// Calculate `gmpmee_fpowm` and wipe the exponent from memory afterwards (only with the feature
+    /// `zeroize`)
+    ///
+    /// The cache of the results is not used, so that the exponent is not kept in it.
+    #[cfg(feature = "zeroize")]
+    pub fn fpowm_zeroizing(&self, mut exponent: Integer) -> Integer {
+        telemetry::record_exponentiations(Exponentiation::FpowmZeroizing, 1);
+        let res = self.fpowm_uncached(&exponent);
+        crate::secret::zeroize_integer(&mut exponent);
+        res
+    }
+
+    /// Estimation of the memory used by the table in bytes (including the companion table for the
+    /// inverse and the extension)
+    pub fn memory_size(&self) -> usize {
+        let entries: usize = self
+            .entries()
+            .iter()
+            .map(|e| std::mem::size_of::<Integer>() + e.capacity() / 8)
+            .sum();
+        entries
+            + self.inverse.as_ref().map_or(0, |t| t.memory_size())
+            + self.extension.as_ref().map_or(0, |t| t.memory_size())
+    }
+
+    /// Number of entries of each sub-table of the underlying spowm table
+    fn tab_sizes(&self) -> Vec<usize> {
+        let table = &self.inner.spowm_table;
+        let len = table.len as usize;
+        let block_width = table.block_width as usize;
+        (0..table.tabs_len as usize)
+            .map(|i| 1usize << block_width.min(len - i * block_width))
+            .collect()
+    }
+
+    /// References to the entries of the precomputed table
+    fn entries(&self) -> Vec<&Integer> {
+        let tabs = self.inner.spowm_table.tabs;
+        self.tab_sizes()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, size)| unsafe {
+                std::slice::from_raw_parts(*tabs.add(i) as *const Integer, size).iter()
+            })
+            .collect()
+    }
+
+    /// Mutable references to the entries of the precomputed table
+    fn entries_mut(&mut self) -> Vec<&mut Integer> {
+        let tabs = self.inner.spowm_table.tabs;
+        self.tab_sizes()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, size)| unsafe {
+                std::slice::from_raw_parts_mut(*tabs.add(i) as *mut Integer, size).iter_mut()
+            })
+            .collect()
+    }
+
+    /// Wipe the entries of the table and the base from memory
+    #[cfg(feature = "zeroize")]
+    fn zeroize_entries(&mut self) {
+        for entry in self.entries_mut() {
+            crate::secret::zeroize_integer(entry);
+        }
+        if let Some(base) = self.base.as_mut() {
+            crate::secret::zeroize_integer(base);
+        }
+    }
+}
+
+/// Incomplete computation of an exponentiation with a table, returned by [Pow] for `&FPowmTable`
+///
+/// As for the incomplete computation values of `rug`, the result is obtained with
+/// `Integer::from`, [Assign] or [Complete]
+/// ```
+/// use rug::{Integer, ops::Pow};
+/// use rug_gmpmee::fpowm::FPowmTable;
+/// let p = Integer::from(13);
+/// let b = Integer::from(7);
+/// let e = Integer::from(4);
+/// let tab = FPowmTable::init_precomp(&b, &p, 16, 16).unwrap();
+/// assert_eq!(Integer::from((&tab).pow(&e)), b.pow_mod(&e, &p).unwrap());
+/// ```
+pub struct FPowmIncomplete<'a> {
+    table: &'a FPowmTable,
+    exponent: &'a Integer,
+}
+
+impl FixedBaseExp for FPowmTable {
+    fn modulus(&self) -> &Integer {
+        FPowmTable::modulus(self)
+    }
+
+    fn fpowm(&self, exponent: &Integer) -> Integer {
+        FPowmTable::fpowm(self, exponent)
+    }
+}
+
+impl<'a> Pow<&'a Integer> for &'a FPowmTable {
+    type Output = FPowmIncomplete<'a>;
+
+    fn pow(self, exponent: &'a Integer) -> FPowmIncomplete<'a> {
+        FPowmIncomplete {
+            table: self,
+            exponent,
+        }
+    }
+}
+
+impl Assign<FPowmIncomplete<'_>> for Integer {
+    fn assign(&mut self, src: FPowmIncomplete<'_>) {
+        *self = src.table.fpowm(src.exponent);
+    }
+}
+
+impl From<FPowmIncomplete<'_>> for Integer {
+    fn from(src: FPowmIncomplete<'_>) -> Self {
+        src.table.fpowm(src.exponent)
+    }
+}
+
+impl Complete for FPowmIncomplete<'_> {
+    type Completed = Integer;
+
+    fn complete(self) -> Integer {
+        Integer::from(self)
+    }
+}
+
+/// Tables of the same fixed base for several exponent bit lengths
+///
+/// Each tier is defined by an exponent bit length and a block width, so that the tiers for the short
+/// exponents can use smaller tables. Each exponentiation uses the smallest tier covering the bit length
+/// of the exponent. If the exponent is longer than all the tiers, the largest tier is used.
+pub struct TieredFPowmTable {
+    tables: Vec<FPowmTable>,
+}
+
+impl TieredFPowmTable {
+    /// Initialize the tables with the precomputation for the base
+    ///
+    /// `tiers` contains the pairs `(exponent_bitlen, block_width)`. The list cannot be empty.
+    pub fn init_precomp(
+        base: &Integer,
+        modulus: &Integer,
+        tiers: &[(usize, usize)],
+    ) -> Result<Self, GmpMEEError> {
+        if tiers.is_empty() {
+            return Err(FPownError::NoTier.into());
+        }
+        let mut sorted_tiers = tiers.to_vec();
+        sorted_tiers.sort_unstable();
+        sorted_tiers.dedup_by_key(|(bitlen, _)| *bitlen);
+        let tables = sorted_tiers
+            .iter()
+            .map(|(bitlen, block_width)| {
+                FPowmTable::init_precomp(base, modulus, *block_width, *bitlen)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { tables })
+    }
+
+    /// Exponent bit lengths of the tiers, in increasing order
+    pub fn exponent_bitlens(&self) -> Vec<usize> {
+        self.tables.iter().map(|t| t.exponent_bitlen()).collect()
+    }
+
+    /// Calculate `gmpmee_fpowm` with the smallest tier covering the exponent
+    pub fn fpowm(&self, exponent: &Integer) -> Integer {
+        let bitlen = exponent.significant_bits() as usize;
+        self.tables
+            .iter()
+            .find(|t| t.exponent_bitlen() >= bitlen)
+            .unwrap_or_else(|| self.tables.last().unwrap())
+            .fpowm(exponent)
+    }
+}
+
+/// Set of tables for several fixed bases with the same modulus
+///
+/// All the tables use the same block width and exponent bit length. The bases are identified by their
+/// index in the set. Each table keeps its own base and its own copy of the modulus, which the set
+/// only uses to initialize the tables of the new bases.
+pub struct FPowmSet {
+    modulus: Integer,
+    tables: Vec<FPowmTable>,
+    block_width: usize,
+    exponent_bitlen: usize,
+}
+
+impl FPowmSet {
+    /// Create an empty set for the modulus
+    pub fn new(modulus: &Integer, block_width: usize, exponent_bitlen: usize) -> Self {
+        Self {
+            modulus: modulus.clone(),
+            tables: vec![],
+            block_width,
+            exponent_bitlen,
+        }
+    }
+
+    /// Create the set and initialize the tables with the precomputation for each base
+    pub fn init_precomp(
+        bases: &[Integer],
+        modulus: &Integer,
+        block_width: usize,
+        exponent_bitlen: usize,
+    ) -> Result<Self, GmpMEEError> {
+        let mut set = Self::new(modulus, block_width, exponent_bitlen);
+        for base in bases {
+            set.push(base)?;
+        }
+        Ok(set)
+    }
+
+    /// Add a base to the set and return its index
+    pub fn push(&mut self, base: &Integer) -> Result<usize, GmpMEEError> {
+        self.tables.push(FPowmTable::init_precomp(
+            base,
+            &self.modulus,
+            self.block_width,
+            self.exponent_bitlen,
+        )?);
+        Ok(self.tables.len() - 1)
+    }
+
+    /// Number of bases in the set
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// Return `true` if the set contains no base
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Modulus of all the tables
+    pub fn modulus(&self) -> &Integer {
+        &self.modulus
+    }
+
+    /// Base at the given index
+    pub fn base(&self, base_index: usize) -> Option<&Integer> {
+        self.tables.get(base_index).and_then(FPowmTable::base)
+    }
+
+    /// Table of the base at the given index
+    pub fn table(&self, base_index: usize) -> Option<&FPowmTable> {
+        self.tables.get(base_index)
+    }
+
+    /// Calculate `gmpmee_fpowm` for the base at the given index
+    pub fn powm(&self, base_index: usize, exponent: &Integer) -> Result<Integer, GmpMEEError> {
+        self.table(base_index)
+            .map(|t| t.fpowm(exponent))
+            .ok_or_else(|| {
+                FPownError::BaseIndex {
+                    index: base_index,
+                    len: self.len(),
+                }
+                .into()
+            })
+    }
+}
+
+impl Drop for FPowmTable {
+    fn drop(&mut self) {
+        #[cfg(feature = "shm")]
+        self.detach_shared();
+        #[cfg(feature = "zeroize")]
+        self.zeroize_entries();
+        unsafe { gmpmee_fpowm_clear(&mut self.inner) }
+    }
+}
+
+/// Window size used by GMP in `mpz_powm` for an exponent of the given bit length
+pub(crate) fn pow_mod_window_size(exponent_bitlen: usize) -> usize {
+    const THRESHOLDS: [usize; 9] = [7, 25, 81, 241, 673, 1793, 4609, 11521, 28161];
+    THRESHOLDS
+        .iter()
+        .position(|&t| exponent_bitlen <= t)
+        .unwrap_or(THRESHOLDS.len())
+        + 1
+}
+
+/// Estimate if the precomputation of a table is worth it, compared to calling `pow_mod` directly
+/// `expected_calls` times
+///
+/// The estimation is based on a cost model counting the modular multiplications (a squaring is
+/// counted as a multiplication):
+/// - `pow_mod` needs `exponent_bitlen` squarings and around `exponent_bitlen / (w + 1)` multiplications,
+///   where `w` is the window size chosen by GMP
+/// - The precomputation needs `exponent_bitlen` squarings for the bases and one multiplication
+///   (and the initialization) for each of the `2^block_width` entries of the table
+/// - Each call of `fpowm` needs `exponent_bitlen / block_width` squarings and multiplications
+///
+/// The size of the modulus is used to weight the initialization of the entries, which is not
+/// negligible for small moduli.
+pub fn worth_precomputing(
+    modulus_bits: usize,
+    exponent_bitlen: usize,
+    expected_calls: usize,
+    block_width: usize,
+) -> bool {
+    if modulus_bits == 0
+        || exponent_bitlen == 0
+        || expected_calls == 0
+        || block_width == 0
+        || block_width >= usize::BITS as usize
+    {
+        return false;
+    }
+    let limbs = modulus_bits.div_ceil(64) as f64;
+    let mul = limbs * limbs;
+    let calls = expected_calls as f64;
+    let bitlen = exponent_bitlen as f64;
+    let window = pow_mod_window_size(exponent_bitlen);
+    let pow_mod_call =
+        (bitlen + bitlen / (window + 1) as f64 + (1usize << (window - 1)) as f64) * mul;
+    let precomp = bitlen * mul + (1usize << block_width) as f64 * (mul + limbs);
+    let fpowm_call = 2.0 * exponent_bitlen.div_ceil(block_width) as f64 * mul;
+    precomp + calls * fpowm_call < calls * pow_mod_call
+}
+
+unsafe impl Sync for FPowmTable {}
+unsafe impl Send for FPowmTable {}
+
+/// Tables of the bases 4 and 9 modulo 23, shared by the tests of the schemes with two tables
+#[cfg(test)]
+pub(crate) fn test_tables() -> (Integer, Integer, Integer, FPowmTable, FPowmTable) {
+    let p = Integer::from(23);
+    let (first, second) = (Integer::from(4), Integer::from(9));
+    let first_table = FPowmTable::init_precomp(&first, &p, 4, 8).unwrap();
+    let second_table = FPowmTable::init_precomp(&second, &p, 4, 8).unwrap();
+    (p, first, second, first_table, second_table)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rug::rand::RandState;
+    use std::time::SystemTime;
+
+    impl FPowmTable {
+        /// Number of results in the cache of the results
+        fn cached_results(&self) -> usize {
+            self.result_cache
+                .as_ref()
+                .map_or(0, |cache| cache.lock().unwrap().len())
+        }
+    }
+
+    #[test]
+    fn test_init() {
+        let res = FPowmTable::init(&Integer::from(11), 16, 16);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_init_precomp() {
+        let res = FPowmTable::init_precomp(&Integer::from(8), &Integer::from(11), 16, 16);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_init_block_width() {
+        let p = Integer::from(11);
+        for block_width in [0, usize::BITS as usize, usize::MAX] {
+            assert_eq!(
+                FPowmTable::init(&p, block_width, 16).err(),
+                Some(FPownError::BlockWidth(block_width).into())
+            );
+            assert_eq!(
+                FPowmTable::init_precomp(&Integer::from(8), &p, block_width, 16).err(),
+                Some(FPownError::BlockWidth(block_width).into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_precomp() {
+        let mut res = FPowmTable::init(&Integer::from(11), 16, 16).unwrap();
+        res.precomp(&Integer::from(8));
+    }
+
+    #[test]
+    fn test_worth_precomputing() {
+        assert!(worth_precomputing(3072, 3072, 1000, 8));
+        assert!(!worth_precomputing(3072, 3072, 1, 8));
+        assert!(!worth_precomputing(3072, 256, 10, 20));
+        assert!(!worth_precomputing(3072, 3072, 0, 8));
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert!(set_defaults(0, ExponentBitlenPolicy::Modulus).is_err());
+        set_defaults(DEFAULT_BLOCK_WIDTH, ExponentBitlenPolicy::Modulus).unwrap();
+        assert_eq!(defaults().block_width, DEFAULT_BLOCK_WIDTH);
+        assert_eq!(
+            ExponentBitlenPolicy::Fixed(256).exponent_bitlen(&Integer::from(13)),
+            256
+        );
+        let p = Integer::from(13);
+        let b = Integer::from(7);
+        let e = Integer::from(4);
+        let tab = FPowmTable::init_precomp_default(&b, &p).unwrap();
+        assert_eq!(tab.fpowm(&e), b.pow_mod(&e, &p).unwrap())
+    }
+
+    #[test]
+    fn test_for_group() {
+        let p = Integer::from(23);
+        let q = Integer::from(11);
+        let g = Integer::from(4);
+        let e = Integer::from(21);
+        let tab = FPowmTable::for_group(&g, &p).unwrap();
+        assert_eq!(tab.fpowm(&e), g.clone().pow_mod(&e, &p).unwrap());
+        let tab = FPowmTable::for_subgroup(&g, &p, &q).unwrap();
+        let e = Integer::from(9);
+        assert_eq!(tab.fpowm(&e), g.pow_mod(&e, &p).unwrap());
+    }
+
+    #[test]
+    fn test_tiered() {
+        let p = Integer::from(
+            Integer::parse_radix(
+                "CE9E0307D2AE75BDBEEC3E0A6E71A279417B56C955C602FFFD067586BACFDAC3",
+                16,
+            )
+            .unwrap(),
+        );
+        let mut rand = RandState::new();
+        let b = Integer::from(Integer::random_bits(255, &mut rand));
+        assert!(TieredFPowmTable::init_precomp(&b, &p, &[]).is_err());
+        let tab = TieredFPowmTable::init_precomp(&b, &p, &[(256, 8), (32, 4)]).unwrap();
+        assert_eq!(tab.exponent_bitlens(), vec![32, 256]);
+        for bits in [16, 200, 512] {
+            let e = Integer::from(Integer::random_bits(bits, &mut rand));
+            assert_eq!(tab.fpowm(&e), b.clone().pow_mod(&e, &p).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_fpowm_set() {
+        let p = Integer::from(23);
+        let bases = [Integer::from(4), Integer::from(9), Integer::from(13)];
+        let set = FPowmSet::init_precomp(&bases, &p, 4, 8).unwrap();
+        assert_eq!(set.len(), 3);
+        let e = Integer::from(7);
+        for (i, b) in bases.iter().enumerate() {
+            assert_eq!(set.powm(i, &e).unwrap(), b.clone().pow_mod(&e, &p).unwrap());
+        }
+        assert_eq!(set.base(1), Some(&bases[1]));
+        assert_eq!(set.table(2).unwrap().modulus(), set.modulus());
+        assert!(set.base(3).is_none());
+        assert!(set.powm(3, &e).is_err());
+    }
+
+    #[test]
+    fn test_fpowm_inv() {
+        let p = Integer::from(23);
+        let b = Integer::from(7);
+        let b_inv = b.clone().invert(&p).unwrap();
+        let e = Integer::from(5);
+        let mut tab = FPowmTable::init_precomp_with_inverse(&b, &p, 4, 8).unwrap();
+        assert!(tab.has_inverse());
+        assert_eq!(tab.fpowm_inv(&e).unwrap(), b_inv.pow_mod(&e, &p).unwrap());
+        tab.precomp(&Integer::from(3));
+        let inv_3 = Integer::from(3).invert(&p).unwrap();
+        assert_eq!(tab.fpowm_inv(&e).unwrap(), inv_3.pow_mod(&e, &p).unwrap());
+        assert!(FPowmTable::init_precomp_with_inverse(&Integer::from(46), &p, 4, 8).is_err());
+        let tab = FPowmTable::init_precomp(&b, &p, 4, 8).unwrap();
+        assert!(tab.fpowm_inv(&e).is_none());
+    }
+
+    #[test]
+    fn test_pow() {
+        let p = Integer::from(1019);
+        let b = Integer::from(7);
+        let e = Integer::from(1000);
+        let tab = FPowmTable::init_precomp(&b, &p, 4, 16).unwrap();
+        let expected = b.pow_mod(&e, &p).unwrap();
+        assert_eq!(Integer::from((&tab).pow(&e)), expected);
+        assert_eq!((&tab).pow(&e).complete(), expected);
+        let mut res = Integer::new();
+        res.assign((&tab).pow(&e));
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn test_result_cache() {
+        let p = Integer::from(1019);
+        let b = Integer::from(7);
+        let tab = FPowmTable::init_precomp(&b, &p, 4, 16)
+            .unwrap()
+            .with_result_cache(2);
+        assert_eq!(tab.result_cache_capacity(), Some(2));
+        for e in [5u32, 6, 5, 7, 6, 5] {
+            let e = Integer::from(e);
+            assert_eq!(tab.fpowm(&e), b.clone().pow_mod(&e, &p).unwrap());
+        }
+        assert_eq!(tab.cached_results(), 2);
+        let mut tab = tab;
+        let b = Integer::from(3);
+        tab.precomp(&b);
+        let e = Integer::from(5);
+        assert_eq!(tab.fpowm(&e), b.pow_mod(&e, &p).unwrap());
+        tab.clear_result_cache();
+        assert!(
+            FPowmTable::init(&p, 4, 16)
+                .unwrap()
+                .result_cache_capacity()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_reinit() {
+        let b = Integer::from(7);
+        let e = Integer::from(1000);
+        let mut tab = FPowmTable::init_precomp_with_inverse(&b, &Integer::from(23), 4, 16).unwrap();
+        let p = Integer::from(1019);
+        tab.reinit(&p, 4, 16).unwrap();
+        assert_eq!(tab.modulus(), &p);
+        assert!(tab.base().is_none());
+        assert!(!tab.has_inverse());
+        tab.precomp(&b);
+        assert_eq!(tab.fpowm(&e), b.clone().pow_mod(&e, &p).unwrap());
+        let p = Integer::from(1031);
+        tab.reinit(&p, 5, 12).unwrap();
+        assert_eq!(tab.block_width(), 5);
+        assert_eq!(tab.exponent_bitlen(), 12);
+        tab.precomp(&b);
+        assert_eq!(tab.fpowm(&e), b.pow_mod(&e, &p).unwrap());
+    }
+
+    #[test]
+    fn test_extend_bitlen() {
+        let p = Integer::from(1019);
+        let b = Integer::from(7);
+        let mut tab = FPowmTable::init_precomp_with_inverse(&b, &p, 4, 8).unwrap();
+        tab.extend_bitlen(4).unwrap();
+        assert_eq!(tab.exponent_bitlen(), 8);
+        tab.extend_bitlen(20).unwrap();
+        assert_eq!(tab.exponent_bitlen(), 20);
+        tab.extend_bitlen(40).unwrap();
+        assert_eq!(tab.exponent_bitlen(), 40);
+        let b_inv = b.clone().invert(&p).unwrap();
+        for e in [0u64, 5, 255, 256, 123456, 987654321012] {
+            let e = Integer::from(e);
+            assert_eq!(tab.fpowm(&e), b.clone().pow_mod(&e, &p).unwrap());
+            assert_eq!(
+                tab.fpowm_inv(&e).unwrap(),
+                b_inv.clone().pow_mod(&e, &p).unwrap()
+            );
+        }
+        assert!(tab.verify_integrity().is_ok());
+        assert!(tab.to_bytes().is_err());
+        let b = Integer::from(3);
+        tab.precomp(&b);
+        let e = Integer::from(987654321012u64);
+        assert_eq!(tab.fpowm(&e), b.pow_mod(&e, &p).unwrap());
+        assert!(
+            FPowmTable::init(&p, 4, 8)
+                .unwrap()
+                .extend_bitlen(16)
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "constant-time")]
+    #[test]
+    fn test_secret_mode() {
+        let p = Integer::from(23);
+        let b = Integer::from(7);
+        let e = Integer::from(5);
+        let mut tab = FPowmTable::init_precomp_with_inverse(&b, &p, 4, 8).unwrap();
+        tab.set_exponent_mode(ExponentMode::Secret).unwrap();
+        assert_eq!(tab.exponent_mode(), ExponentMode::Secret);
+        assert_eq!(tab.fpowm(&e), b.clone().pow_mod(&e, &p).unwrap());
+        let b_inv = b.clone().invert(&p).unwrap();
+        assert_eq!(tab.fpowm_inv(&e).unwrap(), b_inv.pow_mod(&e, &p).unwrap());
+        let mut tab =
+            FPowmTable::init_precomp(&Integer::from(3), &Integer::from(22), 4, 8).unwrap();
+        assert!(tab.set_exponent_mode(ExponentMode::Secret).is_err());
+        let mut tab = FPowmTable::init_precomp(&b, &p, 4, 8)
+            .unwrap()
+            .with_result_cache(4);
+        tab.set_exponent_mode(ExponentMode::Secret).unwrap();
+        assert_eq!(tab.fpowm(&e), b.clone().pow_mod(&e, &p).unwrap());
+        assert_eq!(tab.cached_results(), 0);
+        for (block_width, exponent_bitlen) in [(4, 8), (5, 12)] {
+            assert_eq!(
+                tab.reinit(&Integer::from(22), block_width, exponent_bitlen),
+                Err(FPownError::EvenModulus.into())
+            );
+            assert_eq!(tab.modulus(), &p);
+            assert_eq!(tab.fpowm(&e), b.clone().pow_mod(&e, &p).unwrap());
+        }
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_fpowm_zeroizing() {
+        let p = Integer::from(23);
+        let b = Integer::from(7);
+        let e = Integer::from(5);
+        let tab = FPowmTable::init_precomp(&b, &p, 4, 8)
+            .unwrap()
+            .with_result_cache(4);
+        assert_eq!(tab.fpowm_zeroizing(e.clone()), b.pow_mod(&e, &p).unwrap());
+        assert_eq!(tab.cached_results(), 0);
+        assert_eq!(tab.tab_sizes(), vec![16]);
+    }
+
+    #[test]
+    fn test_fpown() {
+        let p = Integer::from(13);
+        let b = Integer::from(7);
+        let e = Integer::from(4);
+        let tab = FPowmTable::init_precomp(&b, &p, 16, 16).unwrap();
+        let res = tab.fpowm(&e);
+        assert_eq!(res, b.pow_mod(&e, &p).unwrap())
+    }
+
+    #[test]
+    fn test_fpown_big() {
+        let p =  Integer::from(Integer::parse_radix(
+            "CE9E0307D2AE75BDBEEC3E0A6E71A279417B56C955C602FFFD067586BACFDAC3BCC49A49EB4D126F5E9255E57C14F3E09492B6496EC8AC1366FC4BB7F678573FA2767E6547FA727FC0E631AA6F155195C035AF7273F31DFAE1166D1805C8522E95F9AF9CE33239BF3B68111141C20026673A6C8B9AD5FA8372ED716799FE05C0BB6EAF9FCA1590BD9644DBEFAA77BA01FD1C0D4F2D53BAAE965B1786EC55961A8E2D3E4FE8505914A408D50E6B99B71CDA78D8F9AF1A662512F8C4C3A9E72AC72D40AE5D4A0E6571135CBBAAE08C7A2AA0892F664549FA7EEC81BA912743F3E584AC2B2092243C4A17EC98DF079D8EECB8B885E6BBAFA452AAFA8CB8C08024EFF28DE4AF4AC710DCD3D66FD88212101BCB412BCA775F94A2DCE18B1A6452D4CF818B6D099D4505E0040C57AE1F3E84F2F8E07A69C0024C05ACE05666A6B63B0695904478487E78CD0704C14461F24636D7A3F267A654EEDCF8789C7F627C72B4CBD54EED6531C0E54E325D6F09CB648AE9185A7BDA6553E40B125C78E5EAA867", 16
+        ).unwrap());
+        let mut rand = RandState::new();
+        let b = Integer::from(Integer::random_bits(2048, &mut rand));
+        let e = Integer::from(Integer::random_bits(1024, &mut rand));
+        let tab = FPowmTable::init_precomp(&b, &p, 16, 16).unwrap();
+        let res = tab.fpowm(&e);
+        assert_eq!(res, b.pow_mod(&e, &p).unwrap())
+    }
+
+    #[test]
+    fn test_performance() {
+        let p =  Integer::from(Integer::parse_radix(
+            "CE9E0307D2AE75BDBEEC3E0A6E71A279417B56C955C602FFFD067586BACFDAC3BCC49A49EB4D126F5E9255E57C14F3E09492B6496EC8AC1366FC4BB7F678573FA2767E6547FA727FC0E631AA6F155195C035AF7273F31DFAE1166D1805C8522E95F9AF9CE33239BF3B68111141C20026673A6C8B9AD5FA8372ED716799FE05C0BB6EAF9FCA1590BD9644DBEFAA77BA01FD1C0D4F2D53BAAE965B1786EC55961A8E2D3E4FE8505914A408D50E6B99B71CDA78D8F9AF1A662512F8C4C3A9E72AC72D40AE5D4A0E6571135CBBAAE08C7A2AA0892F664549FA7EEC81BA912743F3E584AC2B2092243C4A17EC98DF079D8EECB8B885E6BBAFA452AAFA8CB8C08024EFF28DE4AF4AC710DCD3D66FD88212101BCB412BCA775F94A2DCE18B1A6452D4CF818B6D099D4505E0040C57AE1F3E84F2F8E07A69C0024C05ACE05666A6B63B0695904478487E78CD0704C14461F24636D7A3F267A654EEDCF8789C7F627C72B4CBD54EED6531C0E54E325D6F09CB648AE9185A7BDA6553E40B125C78E5EAA867", 16
+        ).unwrap());
+        let mut rand = RandState::new();
+        let b = Integer::from(Integer::random_bits(2048, &mut rand));
+        let b2 = b.clone();
+        let e = Integer::from(Integer::random_bits(1024, &mut rand));
+        let begin_rug = SystemTime::now();
+        let res_rug = b2.pow_mod(&e, &p).unwrap();
+        let duration_rug = begin_rug.elapsed().unwrap();
+        //let begin_fpowm_with_precomp = SystemTime::now();
+        let tab = FPowmTable::init_precomp(&b, &p, 16, 1024).unwrap();
+        let begin_fpowm = SystemTime::now();
+        let res_fpowm = tab.fpowm(&e);
+        let duration_fpowm = begin_fpowm.elapsed().unwrap();
+        //let duration_fpowm_with_precomp = begin_fpowm_with_precomp.elapsed().unwrap();
+        assert_eq!(res_fpowm, res_rug);
+        assert!(
+            duration_rug > duration_fpowm,
+            "The duration of fpown (={} ms) is bigger than duration with rug (={} ms)",
+            duration_fpowm.as_millis(),
+            duration_rug.as_millis()
+        );
+        /*println!("Duration rug: {} micro s", duration_rug.as_micros());
+        println!("Duration fpowm: {} micro s", duration_fpowm.as_micros());
+        println!(
+            "Duration fpowm with init: {} micro s",
+            duration_fpowm_with_precomp.as_micros()
+        );*/
+    }
+}
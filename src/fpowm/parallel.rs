@@ -0,0 +1,145 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Adapter for the parallel iterators of rayon (only with the feature `rayon`)
+
+use super::FPowmTable;
+use crate::trace::timed_span;
+use rayon::iter::{
+    IndexedParallelIterator, ParallelIterator,
+    plumbing::{Consumer, ProducerCallback, UnindexedConsumer},
+};
+use rug::Integer;
+
+/// Parallel iterator calculating the exponentiation with a table for each exponent
+///
+/// It is created with [FPowmParallelIterator::fpowm_with]. The order of the exponents is kept.
+pub struct FPowmWith<'a, I> {
+    base: I,
+    table: &'a FPowmTable,
+}
+
+impl<'a, I> ParallelIterator for FPowmWith<'a, I>
+where
+    I: ParallelIterator<Item = &'a Integer>,
+{
+    type Item = Integer;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        timed_span!(
+            "fpowm_parallel",
+            count = self.base.opt_len(),
+            modulus_bits = self.table.modulus().significant_bits()
+        );
+        let table = self.table;
+        self.base
+            .map(move |e| table.fpowm(e))
+            .drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        self.base.opt_len()
+    }
+}
+
+impl<'a, I> IndexedParallelIterator for FPowmWith<'a, I>
+where
+    I: IndexedParallelIterator<Item = &'a Integer>,
+{
+    fn drive<C>(self, consumer: C) -> C::Result
+    where
+        C: Consumer<Self::Item>,
+    {
+        timed_span!(
+            "fpowm_parallel",
+            count = self.base.len(),
+            modulus_bits = self.table.modulus().significant_bits()
+        );
+        let table = self.table;
+        self.base.map(move |e| table.fpowm(e)).drive(consumer)
+    }
+
+    fn len(&self) -> usize {
+        self.base.len()
+    }
+
+    fn with_producer<CB>(self, callback: CB) -> CB::Output
+    where
+        CB: ProducerCallback<Self::Item>,
+    {
+        timed_span!(
+            "fpowm_parallel",
+            count = self.base.len(),
+            modulus_bits = self.table.modulus().significant_bits()
+        );
+        let table = self.table;
+        self.base
+            .map(move |e| table.fpowm(e))
+            .with_producer(callback)
+    }
+}
+
+/// Extension of the parallel iterators over exponents
+/// ```
+/// use rayon::prelude::*;
+/// use rug::Integer;
+/// use rug_gmpmee::fpowm::{FPowmParallelIterator, FPowmTable};
+/// let p = Integer::from(1019);
+/// let b = Integer::from(7);
+/// let tab = FPowmTable::init_precomp(&b, &p, 4, 16).unwrap();
+/// let exponents: Vec<Integer> = (0..100u32).map(Integer::from).collect();
+/// let res: Vec<Integer> = exponents.par_iter().fpowm_with(&tab).collect();
+/// assert_eq!(res[10], b.pow_mod(&exponents[10], &p).unwrap());
+/// ```
+pub trait FPowmParallelIterator<'a>: ParallelIterator<Item = &'a Integer> {
+    /// Calculate the exponentiation with the table for each exponent of the iterator
+    fn fpowm_with(self, table: &'a FPowmTable) -> FPowmWith<'a, Self> {
+        FPowmWith { base: self, table }
+    }
+}
+
+impl<'a, I> FPowmParallelIterator<'a> for I where I: ParallelIterator<Item = &'a Integer> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_fpowm_with() {
+        let p = Integer::from(1019);
+        let b = Integer::from(7);
+        let tab = FPowmTable::init_precomp(&b, &p, 4, 16).unwrap();
+        let exponents: Vec<Integer> = (0..200u32).map(|i| Integer::from(i * 37)).collect();
+        let res: Vec<Integer> = exponents.par_iter().fpowm_with(&tab).collect();
+        assert_eq!(res.len(), exponents.len());
+        for (r, e) in res.iter().zip(exponents.iter()) {
+            assert_eq!(r, &b.clone().pow_mod(e, &p).unwrap());
+        }
+        let enumerated: Vec<(usize, Integer)> =
+            exponents.par_iter().fpowm_with(&tab).enumerate().collect();
+        assert_eq!(enumerated[5], (5, res[5].clone()));
+        let filtered: Vec<Integer> = exponents
+            .par_iter()
+            .filter(|e| e.is_even())
+            .fpowm_with(&tab)
+            .collect();
+        assert_eq!(filtered.len(), 100);
+    }
+}
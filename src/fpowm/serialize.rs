@@ -0,0 +1,621 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Serialization of the precomputed tables
+//!
+//! The format is the following (the numbers are encoded in little endian):
+//! - The magic bytes `GMPMEEFT` and the version of the format (`u32`)
+//! - The block width and the exponent bit length (`u64`)
+//! - The modulus and the base
+//! - The number of entries (`u64`), followed by the entries
+//! - The SHA-256 digest of all the previous bytes
+//!
+//! Each integer is encoded with its length in bytes (`u64`), followed by its absolute value in
+//! big endian. The companion table of the inverse and the exponent mode are not serialized.
+//!
+//! The modulus must be odd and larger than 1, which is checked when writing the table as well.
+//! The header is validated before any allocation: the number of entries must also match the block
+//! width. The table is only allocated once all the
+//! entries have been read, so that the memory used is bounded by the size of the input.
+
+use super::{FPowmTable, FPownError, check_block_width, check_modulus};
+use crate::GmpMEEError;
+use rug::{Integer, integer::Order};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 8] = b"GMPMEEFT";
+const VERSION: u32 = 1;
+/// Maximal size of the modulus in bytes accepted when reading a table
+pub(super) const MAX_MODULUS_BYTES: usize = 1 << 20;
+/// Maximal number of entries allocated in advance when reading a table
+const MAX_PREALLOCATED_ENTRIES: usize = 1 << 16;
+/// Maximal exponent bit length accepted when reading a table
+pub(super) const MAX_EXPONENT_BITLEN: usize = 8 * MAX_MODULUS_BYTES;
+
+/// Layout of a table allocated by `gmpmee_fpowm_init`
+///
+/// Return the stretch (number of bits of the exponents covered by each base) and the number of
+/// entries of each sub-table of the underlying spowm table, which has one base per bit of the
+/// block width.
+pub(super) fn table_layout(block_width: usize, exponent_bitlen: usize) -> (usize, Vec<usize>) {
+    // gmpmee_fpowm_init calls gmpmee_spowm_init with `block_width` bases and the same block width,
+    // hence a single sub-table
+    (
+        exponent_bitlen.div_ceil(block_width),
+        vec![1 << block_width],
+    )
+}
+
+/// Check that the modulus of a table can be serialized, i.e. that it is odd and larger than 1
+pub(super) fn check_serialized_modulus(modulus: &Integer) -> Result<(), GmpMEEError> {
+    if *modulus <= 1 || modulus.is_even() {
+        return Err(FPownError::Serialization(
+            "the modulus must be odd and larger than 1".to_string(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Check the parameters read in the header of a table, before allocating it
+pub(super) fn check_header(
+    modulus: &Integer,
+    block_width: usize,
+    exponent_bitlen: usize,
+    nb_entries: usize,
+) -> Result<(), GmpMEEError> {
+    check_block_width(block_width)?;
+    check_serialized_modulus(modulus)?;
+    if exponent_bitlen > MAX_EXPONENT_BITLEN {
+        return Err(FPownError::Serialization(format!(
+            "exponent bit length {exponent_bitlen} is larger than the maximum of {MAX_EXPONENT_BITLEN}"
+        ))
+        .into());
+    }
+    let expected: usize = table_layout(block_width, exponent_bitlen).1.iter().sum();
+    if nb_entries != expected {
+        return Err(FPownError::Serialization(format!(
+            "{nb_entries} entries given, {expected} expected"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+pub(super) fn io_error(e: std::io::Error) -> GmpMEEError {
+    FPownError::Serialization(e.to_string()).into()
+}
+
+/// Writer computing the digest of the written bytes
+pub(crate) struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    pub(crate) fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), GmpMEEError> {
+        self.hasher.update(bytes);
+        self.inner.write_all(bytes).map_err(io_error)
+    }
+
+    pub(crate) fn write_u32(&mut self, value: u32) -> Result<(), GmpMEEError> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    pub(crate) fn write_u64(&mut self, value: u64) -> Result<(), GmpMEEError> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    pub(crate) fn write_integer(&mut self, value: &Integer) -> Result<(), GmpMEEError> {
+        let digits = value.to_digits::<u8>(Order::Msf);
+        self.write_u64(digits.len() as u64)?;
+        self.write_bytes(&digits)
+    }
+
+    /// Write the header of a table
+    pub(crate) fn write_header(
+        &mut self,
+        block_width: usize,
+        exponent_bitlen: usize,
+        modulus: &Integer,
+        base: &Integer,
+        nb_entries: usize,
+    ) -> Result<(), GmpMEEError> {
+        self.write_bytes(MAGIC)?;
+        self.write_u32(VERSION)?;
+        self.write_u64(block_width as u64)?;
+        self.write_u64(exponent_bitlen as u64)?;
+        self.write_integer(modulus)?;
+        self.write_integer(base)?;
+        self.write_u64(nb_entries as u64)
+    }
+
+    /// Write the digest at the end and return it with the inner writer
+    pub(crate) fn finish(mut self) -> Result<(W, [u8; 32]), GmpMEEError> {
+        let digest: [u8; 32] = self.hasher.finalize().into();
+        self.inner.write_all(&digest).map_err(io_error)?;
+        self.inner.flush().map_err(io_error)?;
+        Ok((self.inner, digest))
+    }
+}
+
+/// Reader computing the digest of the read bytes
+pub(crate) struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn read_bytes(&mut self, buf: &mut [u8]) -> Result<(), GmpMEEError> {
+        self.inner.read_exact(buf).map_err(io_error)?;
+        self.hasher.update(&*buf);
+        Ok(())
+    }
+
+    fn read_u32(&mut self) -> Result<u32, GmpMEEError> {
+        let mut buf = [0u8; 4];
+        self.read_bytes(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, GmpMEEError> {
+        let mut buf = [0u8; 8];
+        self.read_bytes(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn read_usize(&mut self) -> Result<usize, GmpMEEError> {
+        usize::try_from(self.read_u64()?)
+            .map_err(|e| FPownError::Serialization(e.to_string()).into())
+    }
+
+    pub(crate) fn read_integer(&mut self, max_len: usize) -> Result<Integer, GmpMEEError> {
+        let len = self.read_usize()?;
+        if len > max_len {
+            return Err(FPownError::Serialization(format!(
+                "integer of {len} bytes is larger than the maximum of {max_len} bytes"
+            ))
+            .into());
+        }
+        let mut digits = vec![0u8; len];
+        self.read_bytes(&mut digits)?;
+        Ok(Integer::from_digits(&digits, Order::Msf))
+    }
+
+    /// Read the header of a table
+    ///
+    /// Return the block width, the exponent bit length, the modulus, the base and the number
+    /// of entries
+    pub(crate) fn read_header(
+        &mut self,
+    ) -> Result<(usize, usize, Integer, Integer, usize), GmpMEEError> {
+        let mut magic = [0u8; 8];
+        self.read_bytes(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(FPownError::Serialization("wrong magic bytes".to_string()).into());
+        }
+        let version = self.read_u32()?;
+        if version != VERSION {
+            return Err(FPownError::Serialization(format!("unsupported version {version}")).into());
+        }
+        let block_width = self.read_usize()?;
+        check_block_width(block_width)?;
+        let exponent_bitlen = self.read_usize()?;
+        let modulus = self.read_integer(MAX_MODULUS_BYTES)?;
+        let base = self.read_integer(MAX_MODULUS_BYTES)?;
+        let nb_entries = self.read_usize()?;
+        check_header(&modulus, block_width, exponent_bitlen, nb_entries)?;
+        Ok((block_width, exponent_bitlen, modulus, base, nb_entries))
+    }
+
+    /// Read the digest at the end and compare it with the digest of the read bytes
+    pub(crate) fn finish(mut self) -> Result<[u8; 32], GmpMEEError> {
+        let digest: [u8; 32] = self.hasher.finalize_reset().into();
+        let mut stored = [0u8; 32];
+        self.inner.read_exact(&mut stored).map_err(io_error)?;
+        if digest != stored {
+            return Err(FPownError::Integrity("the checksum does not match".to_string()).into());
+        }
+        Ok(digest)
+    }
+}
+
+impl FPowmTable {
+    /// Write the table to `writer`
+    ///
+    /// The serialized table ends with a SHA-256 checksum of the modulus, the base, the
+    /// parameters and the entries, which is verified by [FPowmTable::read_from]. The
+    /// precomputation must have been done, and the modulus must be odd.
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), GmpMEEError> {
+        self.write_with_digest(writer).map(|_| ())
+    }
+
+    /// Serialize the table in a vector of bytes (see [FPowmTable::write_to])
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GmpMEEError> {
+        let mut bytes = vec![];
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Read a table written with [FPowmTable::write_to]
+    ///
+    /// Return an error if the data are malformed or if the checksum does not match. The table is
+    /// allocated only after all the entries have been read and the checksum has been verified.
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, GmpMEEError> {
+        let mut reader = HashingReader::new(reader);
+        let (block_width, exponent_bitlen, modulus, base, nb_entries) = reader.read_header()?;
+        let max_len = modulus.significant_digits::<u8>();
+        // The number of entries is only trusted when the entries have actually been read
+        let mut entries = Vec::with_capacity(nb_entries.min(MAX_PREALLOCATED_ENTRIES));
+        for _ in 0..nb_entries {
+            entries.push(reader.read_integer(max_len)?);
+        }
+        let checksum = reader.finish()?;
+        let mut table = Self::init(&modulus, block_width, exponent_bitlen)?;
+        if table.tab_sizes().iter().sum::<usize>() != nb_entries {
+            return Err(FPownError::Serialization(format!(
+                "the layout of the table does not match the {nb_entries} entries"
+            ))
+            .into());
+        }
+        for (entry, mut value) in table.entries_mut().into_iter().zip(entries) {
+            std::mem::swap(entry, &mut value);
+        }
+        table.base = Some(base);
+        table.checksum = Some(checksum);
+        Ok(table)
+    }
+
+    /// Deserialize a table from bytes (see [FPowmTable::read_from])
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GmpMEEError> {
+        Self::read_from(bytes)
+    }
+
+    /// Checksum recorded when the table has been read
+    ///
+    /// Return `None` if the table has not been read with [FPowmTable::read_from], or if the
+    /// precomputation has been done again since then
+    pub fn checksum(&self) -> Option<[u8; 32]> {
+        self.checksum
+    }
+
+    /// Verify the integrity of the table
+    ///
+    /// If the table has been read, the checksum is calculated again and compared with the
+    /// checksum of the serialized table. In any case, the entries corresponding to a single
+    /// power of the base are recalculated, which costs about `exponent_bitlen` squarings.
+    pub fn verify_integrity(&self) -> Result<(), GmpMEEError> {
+        if let Some(checksum) = self.checksum
+            && self.write_with_digest(std::io::sink())? != checksum
+        {
+            return Err(FPownError::Integrity("the checksum does not match".to_string()).into());
+        }
+        let base = self.base.as_ref().ok_or(FPownError::NotPrecomputed)?;
+        let stretch = self.inner.stretch as usize;
+        let entries = self.entries();
+        let mut power = Integer::from(base % &self.modulus);
+        if power.cmp0() == std::cmp::Ordering::Less {
+            power += &self.modulus;
+        }
+        let mut offset = 0;
+        for size in self.tab_sizes() {
+            if *entries[offset] != 1 {
+                return Err(FPownError::Integrity("wrong neutral entry".to_string()).into());
+            }
+            let mut index = 1;
+            while index < size {
+                if *entries[offset + index] != power {
+                    return Err(
+                        FPownError::Integrity(format!("wrong entry {}", offset + index)).into(),
+                    );
+                }
+                for _ in 0..stretch {
+                    power.square_mut();
+                    power %= &self.modulus;
+                }
+                index <<= 1;
+            }
+            offset += size;
+        }
+        match &self.extension {
+            Some(extension) => extension.verify_integrity(),
+            None => Ok(()),
+        }
+    }
+
+    /// Precompute the table for the base and write it directly to `writer`, without keeping the
+    /// whole table in memory
+    ///
+    /// The output is identical to [FPowmTable::init_precomp] followed by [FPowmTable::write_to].
+    /// Only `2^in_memory_bits` entries are kept in memory during the generation (at most the size
+    /// of the table), at the cost of a few additional multiplications. Return the checksum of the
+    /// written table.
+    ///
+    /// The parameters are validated as by [FPowmTable::init_precomp] and [FPowmTable::read_from],
+    /// before anything is computed or written.
+    pub fn write_precomp<W: Write>(
+        base: &Integer,
+        modulus: &Integer,
+        block_width: usize,
+        exponent_bitlen: usize,
+        in_memory_bits: usize,
+        writer: W,
+    ) -> Result<[u8; 32], GmpMEEError> {
+        check_block_width(block_width)?;
+        check_modulus(modulus)?;
+        let (stretch, tab_sizes) = table_layout(block_width, exponent_bitlen);
+        check_header(
+            modulus,
+            block_width,
+            exponent_bitlen,
+            tab_sizes.iter().sum(),
+        )?;
+
+        let nb_bases = tab_sizes.iter().map(|s| s.trailing_zeros() as usize).sum();
+        let mut bases = Vec::with_capacity(nb_bases);
+        let mut power = Integer::from(base % modulus);
+        if power.cmp0() == std::cmp::Ordering::Less {
+            power += modulus;
+        }
+        for _ in 0..nb_bases {
+            bases.push(power.clone());
+            for _ in 0..stretch {
+                power.square_mut();
+                power %= modulus;
+            }
+        }
+
+        let mut writer = HashingWriter::new(writer);
+        writer.write_header(
+            block_width,
+            exponent_bitlen,
+            modulus,
+            base,
+            tab_sizes.iter().sum(),
+        )?;
+        let mut bases = bases.as_slice();
+        for size in tab_sizes {
+            let width = size.trailing_zeros() as usize;
+            let (tab_bases, rest) = bases.split_at(width);
+            bases = rest;
+            let low_width = in_memory_bits.min(width);
+            // Entries for the bases of the low bits, kept in memory
+            let mut low = Vec::with_capacity(1 << low_width);
+            low.push(Integer::from(1));
+            for (j, b) in tab_bases.iter().take(low_width).enumerate() {
+                for k in 0..(1usize << j) {
+                    let mut entry = Integer::from(&low[k] * b);
+                    entry %= modulus;
+                    low.push(entry);
+                }
+            }
+            for high in 0..(1usize << (width - low_width)) {
+                let mut high_product = Integer::from(1);
+                for (j, b) in tab_bases[low_width..].iter().enumerate() {
+                    if (high >> j) & 1 == 1 {
+                        high_product *= b;
+                        high_product %= modulus;
+                    }
+                }
+                for entry in low.iter() {
+                    let mut entry = Integer::from(entry * &high_product);
+                    entry %= modulus;
+                    writer.write_integer(&entry)?;
+                }
+            }
+        }
+        writer.finish().map(|(_, digest)| digest)
+    }
+
+    fn write_with_digest<W: Write>(&self, writer: W) -> Result<[u8; 32], GmpMEEError> {
+        if self.extension.is_some() {
+            return Err(FPownError::Serialization(
+                "an extended table cannot be serialized".to_string(),
+            )
+            .into());
+        }
+        check_serialized_modulus(&self.modulus)?;
+        let base = self.base.as_ref().ok_or(FPownError::NotPrecomputed)?;
+        let entries = self.entries();
+        let mut writer = HashingWriter::new(writer);
+        writer.write_header(
+            self.block_width,
+            self.exponent_bitlen,
+            &self.modulus,
+            base,
+            entries.len(),
+        )?;
+        for entry in entries {
+            writer.write_integer(entry)?;
+        }
+        writer.finish().map(|(_, digest)| digest)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rug::Assign;
+
+    #[test]
+    fn test_round_trip() {
+        let p = Integer::from(1019);
+        let b = Integer::from(7);
+        let tab = FPowmTable::init_precomp(&b, &p, 4, 20).unwrap();
+        assert!(tab.checksum().is_none());
+        assert!(tab.verify_integrity().is_ok());
+        let bytes = tab.to_bytes().unwrap();
+        let read = FPowmTable::from_bytes(&bytes).unwrap();
+        assert!(read.checksum().is_some());
+        assert!(read.verify_integrity().is_ok());
+        assert_eq!(read.base(), Some(&b));
+        assert_eq!(read.modulus(), &p);
+        assert_eq!(read.block_width(), 4);
+        assert_eq!(read.exponent_bitlen(), 20);
+        for e in [0u32, 1, 5, 1000, 123456] {
+            let e = Integer::from(e);
+            assert_eq!(read.fpowm(&e), b.clone().pow_mod(&e, &p).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_corrupted() {
+        let p = Integer::from(1019);
+        let b = Integer::from(7);
+        let tab = FPowmTable::init_precomp(&b, &p, 4, 20).unwrap();
+        let mut bytes = tab.to_bytes().unwrap();
+        let pos = bytes.len() - 33;
+        bytes[pos] ^= 1;
+        assert!(matches!(
+            FPowmTable::from_bytes(&bytes),
+            Err(GmpMEEError::FPowmParameters(FPownError::Integrity(_)))
+        ));
+        assert!(FPowmTable::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(FPowmTable::init(&p, 4, 20).unwrap().to_bytes().is_err());
+    }
+
+    #[test]
+    fn test_even_modulus() {
+        let b = Integer::from(5);
+        let tab = FPowmTable::init_precomp(&b, &Integer::from(28), 4, 8).unwrap();
+        assert!(matches!(
+            tab.to_bytes(),
+            Err(GmpMEEError::FPowmParameters(FPownError::Serialization(_)))
+        ));
+        let mut bytes = vec![];
+        assert!(tab.write_to(&mut bytes).is_err());
+        assert!(bytes.is_empty());
+        let bytes = FPowmTable::init_precomp(&b, &Integer::from(27), 4, 8)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        assert!(FPowmTable::from_bytes(&bytes).is_ok());
+    }
+
+    #[test]
+    fn test_malformed_header() {
+        let p = Integer::from(1019);
+        let b = Integer::from(7);
+        let bytes = FPowmTable::init_precomp(&b, &p, 4, 20)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        // Header: magic (8), version (4), block width (8), exponent bit length (8), then the
+        // modulus (length 8 and 2 bytes)
+        let with = |pos: usize, value: &[u8]| {
+            let mut bytes = bytes.clone();
+            bytes[pos..pos + value.len()].copy_from_slice(value);
+            FPowmTable::from_bytes(&bytes)
+        };
+        assert_eq!(
+            with(12, &0u64.to_le_bytes()).err(),
+            Some(FPownError::BlockWidth(0).into())
+        );
+        assert!(with(12, &60u64.to_le_bytes()).is_err());
+        assert!(with(20, &u64::MAX.to_le_bytes()).is_err());
+        assert!(with(36, &[0, 0]).is_err());
+        assert!(with(36, &[0, 1]).is_err());
+        assert!(with(36, &[3, 250]).is_err());
+        let nb_entries_pos = 36 + 2 + 8 + 1;
+        assert!(with(nb_entries_pos, &(1u64 << 40).to_le_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_table_layout() {
+        for (block_width, exponent_bitlen) in [(4, 20), (5, 12), (8, 3), (16, 16)] {
+            let tab = FPowmTable::init(&Integer::from(1019), block_width, exponent_bitlen).unwrap();
+            let (stretch, tab_sizes) = table_layout(block_width, exponent_bitlen);
+            assert_eq!(stretch, tab.inner.stretch as usize);
+            assert_eq!(tab_sizes, tab.tab_sizes());
+        }
+    }
+
+    #[test]
+    fn test_write_precomp() {
+        let p = Integer::from(1019);
+        let b = Integer::from(7);
+        let expected = FPowmTable::init_precomp(&b, &p, 4, 20)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        for in_memory_bits in [0, 2, 4, 10] {
+            let mut bytes = vec![];
+            let checksum =
+                FPowmTable::write_precomp(&b, &p, 4, 20, in_memory_bits, &mut bytes).unwrap();
+            assert_eq!(bytes, expected);
+            let read = FPowmTable::from_bytes(&bytes).unwrap();
+            assert_eq!(read.checksum(), Some(checksum));
+        }
+    }
+
+    #[test]
+    fn test_write_precomp_invalid() {
+        let b = Integer::from(7);
+        let write = |modulus: u32, exponent_bitlen: usize| {
+            let mut bytes = vec![];
+            let res = FPowmTable::write_precomp(
+                &b,
+                &Integer::from(modulus),
+                4,
+                exponent_bitlen,
+                2,
+                &mut bytes,
+            );
+            // Nothing is written for invalid parameters
+            assert_eq!(bytes.is_empty(), res.is_err());
+            res
+        };
+        assert_eq!(write(0, 20).err(), Some(FPownError::ModulusTooSmall.into()));
+        assert_eq!(write(1, 20).err(), Some(FPownError::ModulusTooSmall.into()));
+        assert!(matches!(
+            write(1018, 20),
+            Err(GmpMEEError::FPowmParameters(FPownError::Serialization(_)))
+        ));
+        assert!(matches!(
+            write(1019, MAX_EXPONENT_BITLEN + 1),
+            Err(GmpMEEError::FPowmParameters(FPownError::Serialization(_)))
+        ));
+        assert!(write(1019, 20).is_ok());
+    }
+
+    #[test]
+    fn test_verify_integrity_modified() {
+        let p = Integer::from(1019);
+        let b = Integer::from(7);
+        let bytes = FPowmTable::init_precomp(&b, &p, 4, 20)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        let mut read = FPowmTable::from_bytes(&bytes).unwrap();
+        read.entries_mut()[2].assign(5);
+        assert!(read.verify_integrity().is_err());
+    }
+}
@@ -0,0 +1,343 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Tables shared between processes through a memory mapped segment (only with the feature `shm`)
+//!
+//! One process writes the table in a segment with [FPowmTable::write_shared], usually in a shared
+//! memory file system like `/dev/shm`. The other processes map the segment read-only with
+//! [FPowmTable::open_shared]: the entries of the table point directly to the limbs in the segment, so
+//! that the memory of the entries is shared by all the processes instead of being copied in each of
+//! them.
+//!
+//! The segment contains the limbs in the native representation of GMP. It can only be used on a
+//! machine with the same limb size and byte order as the machine that has written it. The format is
+//! the following (the numbers are encoded in native byte order):
+//! - The magic bytes `GMPMEESH`, the version of the format, the size of a limb in bytes and the
+//!   marker `0x01020304` for the byte order (`u32`)
+//! - The block width, the exponent bit length and the number of entries (`u64`)
+//! - The lengths in bytes of the modulus and of the base (`u64`), followed by their absolute values
+//!   in big endian, padded with zeros to a multiple of 8 bytes
+//! - For each entry, its size in limbs (`i64`, negative for a negative entry) followed by its limbs
+//! - The SHA-256 digest of all the previous bytes
+//!
+//! The digest and the header are verified before the table is allocated, with the same rules as
+//! for the serialized tables.
+
+use super::{
+    FPowmTable, FPownError,
+    serialize::{
+        HashingWriter, MAX_MODULUS_BYTES, check_header, check_serialized_modulus, io_error,
+    },
+};
+use crate::GmpMEEError;
+use gmp_mpfr_sys::gmp::{limb_t, mpz_t};
+use memmap2::Mmap;
+use rug::{Integer, integer::Order};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::ffi::c_int;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::ptr::NonNull;
+
+const MAGIC: &[u8; 8] = b"GMPMEESH";
+const VERSION: u32 = 2;
+const DIGEST_BYTES: usize = 32;
+const BYTE_ORDER_MARKER: u32 = 0x0102_0304;
+const LIMB_BYTES: usize = std::mem::size_of::<limb_t>();
+
+/// Mapping of a segment, whose limbs are used by the entries of a table
+///
+/// The original content of the entries, allocated by GMPMEE, is kept to be restored before the
+/// table is cleared or modified.
+pub(super) struct SharedSegment {
+    _map: Mmap,
+    originals: Vec<mpz_t>,
+}
+
+fn segment_error(msg: impl Into<String>) -> GmpMEEError {
+    FPownError::Serialization(msg.into()).into()
+}
+
+/// Cursor over the bytes of a segment
+struct SegmentReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SegmentReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], GmpMEEError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| segment_error("the segment is truncated"))?;
+        let res = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(res)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, GmpMEEError> {
+        Ok(u32::from_ne_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, GmpMEEError> {
+        Ok(u64::from_ne_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn read_usize(&mut self) -> Result<usize, GmpMEEError> {
+        usize::try_from(self.read_u64()?).map_err(|e| segment_error(e.to_string()))
+    }
+
+    fn skip_padding(&mut self) -> Result<(), GmpMEEError> {
+        self.take(self.pos.next_multiple_of(8) - self.pos)?;
+        Ok(())
+    }
+}
+
+fn padding(len: usize) -> usize {
+    len.next_multiple_of(8) - len
+}
+
+impl FPowmTable {
+    /// Write the table in a segment at `path`, to be mapped by other processes with
+    /// [FPowmTable::open_shared]
+    ///
+    /// The segment is written in a temporary file, which is renamed at the end, so that the other
+    /// processes never map an incomplete segment. The precomputation must have been done. The
+    /// companion table of the inverse is not written, and the extended tables cannot be written.
+    pub fn write_shared(&self, path: impl AsRef<Path>) -> Result<(), GmpMEEError> {
+        let path = path.as_ref();
+        let base = self.base.as_ref().ok_or(FPownError::NotPrecomputed)?;
+        if self.extension.is_some() {
+            return Err(segment_error("an extended table cannot be shared"));
+        }
+        check_serialized_modulus(&self.modulus)?;
+        let modulus_digits = self.modulus.to_digits::<u8>(Order::Msf);
+        let base_digits = base.to_digits::<u8>(Order::Msf);
+        let entries = self.entries();
+        let tmp_path = path.with_extension("tmp");
+        let file = File::create(&tmp_path).map_err(io_error)?;
+        let mut writer = HashingWriter::new(BufWriter::new(file));
+        let mut write = |bytes: &[u8]| writer.write_bytes(bytes);
+        write(MAGIC)?;
+        write(&VERSION.to_ne_bytes())?;
+        write(&(LIMB_BYTES as u32).to_ne_bytes())?;
+        write(&BYTE_ORDER_MARKER.to_ne_bytes())?;
+        write(&0u32.to_ne_bytes())?;
+        for value in [self.block_width, self.exponent_bitlen, entries.len()] {
+            write(&(value as u64).to_ne_bytes())?;
+        }
+        for digits in [&modulus_digits, &base_digits] {
+            write(&(digits.len() as u64).to_ne_bytes())?;
+        }
+        for digits in [&modulus_digits, &base_digits] {
+            write(digits)?;
+            write(&vec![0u8; padding(digits.len())])?;
+        }
+        for entry in entries {
+            let limbs = entry.as_limbs();
+            let size = match entry.cmp0() {
+                Ordering::Less => -(limbs.len() as i64),
+                _ => limbs.len() as i64,
+            };
+            write(&size.to_ne_bytes())?;
+            for limb in limbs {
+                write(&limb.to_ne_bytes())?;
+            }
+        }
+        writer.finish()?;
+        fs::rename(&tmp_path, path).map_err(io_error)
+    }
+
+    /// Map the segment at `path`, written with [FPowmTable::write_shared], and return a table using
+    /// its entries
+    ///
+    /// The segment is mapped read-only and must not be modified or truncated while it is mapped. The
+    /// table can be used as any other table. If it is precomputed again for another base, the
+    /// segment is released and the table gets its own entries.
+    /// ```
+    /// use rug::Integer;
+    /// use rug_gmpmee::fpowm::FPowmTable;
+    /// let (b, p, e) = (Integer::from(7), Integer::from(1019), Integer::from(1000));
+    /// let path = std::env::temp_dir().join("rug-gmpmee-doc-shared.fpowm");
+    /// FPowmTable::init_precomp(&b, &p, 4, 16).unwrap().write_shared(&path).unwrap();
+    /// let tab = FPowmTable::open_shared(&path).unwrap();
+    /// assert!(tab.is_shared());
+    /// assert_eq!(tab.fpowm(&e), b.pow_mod(&e, &p).unwrap());
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn open_shared(path: impl AsRef<Path>) -> Result<Self, GmpMEEError> {
+        let file = File::open(path).map_err(io_error)?;
+        // Safety: the documentation requires that the segment is not modified while it is mapped
+        let map = unsafe { Mmap::map(&file) }.map_err(io_error)?;
+        let content_len = map
+            .len()
+            .checked_sub(DIGEST_BYTES)
+            .ok_or_else(|| segment_error("the segment is truncated"))?;
+        let (content, digest) = map.split_at(content_len);
+        if Sha256::digest(content).as_slice() != digest {
+            return Err(FPownError::Integrity("the checksum does not match".to_string()).into());
+        }
+        let mut reader = SegmentReader {
+            bytes: content,
+            pos: 0,
+        };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(segment_error("wrong magic bytes"));
+        }
+        let version = reader.read_u32()?;
+        if version != VERSION {
+            return Err(segment_error(format!("unsupported version {version}")));
+        }
+        if reader.read_u32()? as usize != LIMB_BYTES || reader.read_u32()? != BYTE_ORDER_MARKER {
+            return Err(segment_error(
+                "the segment has been written on a machine with another limb representation",
+            ));
+        }
+        reader.read_u32()?;
+        let block_width = reader.read_usize()?;
+        let exponent_bitlen = reader.read_usize()?;
+        let nb_entries = reader.read_usize()?;
+        let modulus_len = reader.read_usize()?;
+        let base_len = reader.read_usize()?;
+        if modulus_len > MAX_MODULUS_BYTES || base_len > MAX_MODULUS_BYTES {
+            return Err(segment_error("the modulus or the base is too large"));
+        }
+        let modulus = Integer::from_digits(reader.take(modulus_len)?, Order::Msf);
+        reader.skip_padding()?;
+        let base = Integer::from_digits(reader.take(base_len)?, Order::Msf);
+        reader.skip_padding()?;
+        check_header(&modulus, block_width, exponent_bitlen, nb_entries)?;
+        // Each entry takes at least the 8 bytes of its size
+        if nb_entries > (content.len() - reader.pos) / 8 {
+            return Err(segment_error("the segment is truncated"));
+        }
+        let mut limbs = Vec::with_capacity(nb_entries);
+        for _ in 0..nb_entries {
+            let size = i64::from_ne_bytes(reader.take(8)?.try_into().unwrap());
+            let len = usize::try_from(size.unsigned_abs())
+                .ok()
+                .filter(|&len| c_int::try_from(len).is_ok())
+                .ok_or_else(|| segment_error(format!("wrong size of entry: {size}")))?;
+            let bytes = reader.take(len * LIMB_BYTES)?;
+            if bytes.len() >= LIMB_BYTES
+                && bytes[bytes.len() - LIMB_BYTES..].iter().all(|&b| b == 0)
+            {
+                return Err(segment_error("an entry is not normalized"));
+            }
+            limbs.push((size, bytes.as_ptr()));
+        }
+        let mut table = Self::init(&modulus, block_width, exponent_bitlen)?;
+        if table.tab_sizes().iter().sum::<usize>() != nb_entries {
+            return Err(segment_error(format!(
+                "wrong number of entries: {nb_entries}"
+            )));
+        }
+        let mut originals = Vec::with_capacity(nb_entries);
+        for (entry, (size, ptr)) in table.entries_mut().into_iter().zip(limbs) {
+            let raw = entry.as_raw_mut();
+            // Safety: the limbs are aligned (the mapping is page aligned and all the offsets are
+            // multiples of 8), remain valid as long as the mapping is kept in the table, and are only
+            // read by GMP, since the entries are not modified anymore
+            unsafe {
+                originals.push(*raw);
+                if size != 0 {
+                    (*raw).size = size as c_int;
+                    (*raw).alloc = size.unsigned_abs() as c_int;
+                    (*raw).d = NonNull::new_unchecked(ptr as *mut limb_t);
+                }
+            }
+        }
+        table.base = Some(base);
+        table.shared = Some(SharedSegment {
+            _map: map,
+            originals,
+        });
+        Ok(table)
+    }
+
+    /// Return `true` if the entries of the table are in a segment mapped with
+    /// [FPowmTable::open_shared]
+    pub fn is_shared(&self) -> bool {
+        self.shared.is_some()
+    }
+
+    /// Restore the entries allocated by GMPMEE and release the segment, if the table is shared
+    ///
+    /// The entries must be computed again after the call.
+    pub(super) fn detach_shared(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            for (entry, original) in self.entries_mut().into_iter().zip(shared.originals) {
+                unsafe { *entry.as_raw_mut() = original }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shared() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("rug-gmpmee-shared-{}.fpowm", std::process::id()));
+        let (b, p) = (Integer::from(7), Integer::from(1019));
+        let tab = FPowmTable::init_precomp(&b, &p, 4, 16).unwrap();
+        tab.write_shared(&path).unwrap();
+        let shared = FPowmTable::open_shared(&path).unwrap();
+        let other = FPowmTable::open_shared(&path).unwrap();
+        assert!(shared.is_shared() && !tab.is_shared());
+        assert_eq!(shared.base(), Some(&b));
+        assert!(shared.verify_integrity().is_ok());
+        for e in [0u32, 1, 1000, 65535] {
+            let e = Integer::from(e);
+            assert_eq!(shared.fpowm(&e), tab.fpowm(&e));
+            assert_eq!(other.fpowm(&e), tab.fpowm(&e));
+        }
+        drop(other);
+        let mut shared = shared;
+        let b2 = Integer::from(3);
+        shared.precomp(&b2);
+        assert!(!shared.is_shared());
+        let e = Integer::from(1000);
+        assert_eq!(shared.fpowm(&e), b2.pow_mod(&e, &p).unwrap());
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[8] = 2;
+        fs::write(&path, &bytes).unwrap();
+        assert!(FPowmTable::open_shared(&path).is_err());
+        fs::write(&path, &bytes[..100]).unwrap();
+        assert!(FPowmTable::open_shared(&path).is_err());
+        tab.write_shared(&path).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        let last_entry = bytes.len() - DIGEST_BYTES - 1;
+        bytes[last_entry] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            FPowmTable::open_shared(&path),
+            Err(GmpMEEError::FPowmParameters(FPownError::Integrity(_)))
+        ));
+        fs::remove_file(&path).unwrap();
+        assert!(
+            FPowmTable::init(&p, 4, 16)
+                .unwrap()
+                .write_shared(&path)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,845 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Generation of the parameters of the groups used by the Schnorr and ElGamal schemes (see
+//! [GroupParams]), possibly derived from a public seed (see [SeededGroupParams])
+//!
+//! The module contains as well the helpers for the elements of these groups: sampling, validation
+//! of the generators and of the quadratic residues, square roots and hashing to the group.
+
+use crate::{
+    GmpMEEError,
+    fpowm::FPowmTable,
+    miller_rabin::{
+        GroupReport, MillerRabinError, SafePrimeSearch, miller_rabin, random_safe_prime,
+        verify_group, verify_subgroup,
+    },
+    parallel::map_chunks,
+    standard_groups::StandardGroup,
+};
+use rug::{Integer, integer::Order, rand::RandState};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum GroupError {
+    #[error("The order {q} is not greater than 2 or does not divide {p}-1")]
+    InvalidOrder { p: Integer, q: Integer },
+    #[error("No standard group is named {name}")]
+    UnknownGroup { name: String },
+    #[error("The parameters of the standard group {name} are not valid")]
+    InvalidStandardGroup { name: String },
+    #[error("No generator of the subgroup of order {q} modulo {p} has been found")]
+    NoGenerator { p: Integer, q: Integer },
+}
+
+/// Number of random elements raised to the cofactor by [find_generator] before giving up
+///
+/// For a prime `p`, an element gives a generator with a probability of at least `2/3`.
+const GENERATOR_ATTEMPTS: u32 = 128;
+
+/// Bit length of the output of the hash function (SHA-256)
+const OUTLEN: u32 = 256;
+
+/// Length in bytes of the seeds drawn by [SeededGroupParams::generate]
+const SEED_BYTES: usize = 32;
+
+/// Additional bits of the hash outputs reduced modulo an integer, so that the statistical distance
+/// of the result to the uniform distribution is smaller than `2^-HASH_MARGIN_BITS`
+const HASH_MARGIN_BITS: u32 = 128;
+
+/// Parameters of a group: a safe prime `p`, the prime order `q = (p-1)/2` of the subgroup of the
+/// quadratic residues modulo `p`, and a generator `g` of this subgroup
+///
+/// The standard groups of RFC 5114 (see [Self::standard]) have instead a prime order `q` dividing
+/// `p-1`, which is not `(p-1)/2`.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::groups::GroupParams;
+/// let group = GroupParams::generate(256, 16, &mut RandState::new()).unwrap();
+/// assert_eq!(group.p.significant_bits(), 256);
+/// assert!(group.verify(16).is_valid());
+/// let tab = group.table().unwrap();
+/// assert_eq!(tab.fpowm(&group.q), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GroupParams {
+    /// Safe prime modulus
+    pub p: Integer,
+    /// Prime order of the subgroup, `(p-1)/2`
+    pub q: Integer,
+    /// Generator of the subgroup
+    pub g: Integer,
+}
+
+impl GroupParams {
+    /// Generate the parameters with a random safe prime `p` of `bits` bits, tested with `reps`
+    /// rounds (see [random_safe_prime]), and a random generator `g`
+    ///
+    /// Return an error if `bits` is smaller than 4: the subgroup of order 2 modulo the safe prime
+    /// 5 has no generator different from `p-1`.
+    pub fn generate(bits: u32, reps: i32, rand: &mut RandState<'_>) -> Result<Self, GmpMEEError> {
+        if bits < 4 {
+            return Err(MillerRabinError::TooFewBits { bits }.into());
+        }
+        let p = random_safe_prime(bits, reps, rand)?;
+        Ok(Self::with_random_generator(p, rand))
+    }
+
+    /// Parameters for the safe prime `p` (at least 5), with the generator `g = h^2 mod p` for a
+    /// random `h` in `[2, p-2]`
+    ///
+    /// Since `q` is prime, every quadratic residue different from 1 generates the subgroup.
+    fn with_random_generator(p: Integer, rand: &mut RandState<'_>) -> Self {
+        let q = Integer::from(&p - 1u32) >> 1u32;
+        let h = Integer::from(&p - 3u32).random_below(rand) + 2u32;
+        let g = h.square() % &p;
+        Self { p, q, g }
+    }
+
+    /// Parameters of the standard group with the given name or identifier (see
+    /// [StandardGroup::from_name]), validated with `reps` rounds of Miller-Rabin
+    ///
+    /// The validation checks that `p` and `q` are prime, that `q` divides `p-1` and that `g`
+    /// generates the subgroup of order `q`. Return an error if no group has this name or if the
+    /// validation fails.
+    /// ```
+    /// use rug_gmpmee::groups::GroupParams;
+    /// let group = GroupParams::standard("modp-2048", 16).unwrap();
+    /// assert_eq!(group.p.significant_bits(), 2048);
+    /// assert!(GroupParams::standard("modp-1", 16).is_err());
+    /// ```
+    pub fn standard(name: &str, reps: i32) -> Result<Self, GmpMEEError> {
+        let group = Self::from(name.parse::<StandardGroup>()?);
+        match group.is_prime_order_group(reps) {
+            true => Ok(group),
+            false => Err(GroupError::InvalidStandardGroup {
+                name: name.to_string(),
+            }
+            .into()),
+        }
+    }
+
+    /// Check that `p` and `q` are prime, that `q` divides `p-1` and that `g` generates the subgroup
+    /// of order `q` (see [is_generator]), with `reps` rounds of Miller-Rabin
+    ///
+    /// Contrary to [Self::verify], `p` is not required to be a safe prime.
+    pub fn is_prime_order_group(&self, reps: i32) -> bool {
+        miller_rabin(&self.p, reps)
+            && miller_rabin(&self.q, reps)
+            && cofactor(&self.p, &self.q).is_ok()
+            && is_generator(&self.g, &self.p, &self.q)
+    }
+
+    /// Verify the parameters with `reps` rounds of Miller-Rabin, with [verify_group] if
+    /// `q = (p-1)/2` and with [verify_subgroup] otherwise (e.g. for the groups of RFC 5114)
+    pub fn verify(&self, reps: i32) -> GroupReport {
+        match self.q == Integer::from(&self.p - 1u32) >> 1u32 {
+            true => verify_group(&self.p, &self.q, &self.g, reps),
+            false => verify_subgroup(&self.p, &self.q, &self.g, reps),
+        }
+    }
+
+    /// Precomputed table for the generator (see [FPowmTable::for_subgroup])
+    pub fn table(&self) -> Result<FPowmTable, GmpMEEError> {
+        FPowmTable::for_subgroup(&self.g, &self.p, &self.q)
+    }
+
+    /// Uniform exponent in `[0, q[`
+    pub fn random_exponent(&self, rand: &mut RandState<'_>) -> Integer {
+        Integer::from(self.q.random_below_ref(rand))
+    }
+
+    /// Uniform element of the subgroup of order `q` different from 1, computed as
+    /// `r^((p-1)/q) mod p` for a uniform `r` in `[1, p-1]`, drawn again while the element is 1
+    ///
+    /// The map `r -> r^((p-1)/q)` sends exactly `(p-1)/q` elements of `[1, p-1]` to each element
+    /// of the subgroup, so the output is uniform without computing `g^x` for a random `x`. For a
+    /// safe prime `p`, the element is `r^2 mod p`. Return an error if `q` is smaller than 3 or
+    /// does not divide `p-1`.
+    pub fn random_element(&self, rand: &mut RandState<'_>) -> Result<Integer, GmpMEEError> {
+        let cofactor = cofactor(&self.p, &self.q)?;
+        Ok(random_subgroup_element(&self.p, &cofactor, rand))
+    }
+
+    /// `count` uniform elements of the subgroup of order `q` (see [Self::random_element])
+    pub fn random_elements(
+        &self,
+        count: usize,
+        rand: &mut RandState<'_>,
+    ) -> Result<Vec<Integer>, GmpMEEError> {
+        let cofactor = cofactor(&self.p, &self.q)?;
+        Ok((0..count)
+            .map(|_| random_subgroup_element(&self.p, &cofactor, rand))
+            .collect())
+    }
+
+    /// Hash of the message to an exponent in `[0, q[` with the domain separation tag (see
+    /// [hash_to_integer])
+    pub fn hash_to_exponent(&self, dst: &[u8], message: &[u8]) -> Integer {
+        hash_to_integer(dst, message, &self.q)
+    }
+
+    /// Hash of the message to an element of the subgroup of order `q` with the domain separation
+    /// tag (see [hash_to_subgroup])
+    ///
+    /// Return an error if `q` is smaller than 3 or does not divide `p-1`.
+    pub fn hash_to_element(&self, dst: &[u8], message: &[u8]) -> Result<Integer, GmpMEEError> {
+        hash_to_subgroup(dst, message, &self.p, &self.q)
+    }
+}
+
+impl From<StandardGroup> for GroupParams {
+    /// Parameters of the standard group, without validation
+    fn from(group: StandardGroup) -> Self {
+        Self {
+            p: group.modulus().clone(),
+            q: group.order().clone(),
+            g: group.generator().clone(),
+        }
+    }
+}
+
+/// Group parameters derived deterministically from a public seed
+///
+/// The seed is expanded with SHA-256 into the start of a safe prime search and into the generator,
+/// so that anybody can re-derive the parameters from the published seed and confirm them (see
+/// [Self::verify]).
+/// ```
+/// use rug_gmpmee::groups::SeededGroupParams;
+/// let params = SeededGroupParams::from_seed(256, b"election 2026", 16).unwrap();
+/// assert_eq!(params.group.p.significant_bits(), 256);
+/// assert!(params.group.verify(16).is_valid());
+/// assert!(params.verify(16));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SeededGroupParams {
+    /// Parameters of the group
+    pub group: GroupParams,
+    /// Public seed
+    pub seed: Vec<u8>,
+    /// Counter of the start of the safe prime search
+    pub counter: u32,
+}
+
+/// SHA-256 expansion of the seed to `bits` bits, for the given label and index
+///
+/// The block `j` is the hash of `seed || label || index || j`, with the index and `j` written as
+/// 32-bit big-endian integers, and the blocks are concatenated with the first one as the most
+/// significant.
+fn expand(seed: &[u8], label: &[u8], index: u32, bits: u32) -> Integer {
+    let blocks = bits.div_ceil(OUTLEN);
+    let mut bytes = Vec::with_capacity(blocks as usize * OUTLEN as usize / 8);
+    for j in 0..blocks {
+        bytes.extend(
+            Sha256::new()
+                .chain_update(seed)
+                .chain_update(label)
+                .chain_update(index.to_be_bytes())
+                .chain_update(j.to_be_bytes())
+                .finalize(),
+        );
+    }
+    Integer::from_digits(&bytes, Order::Msf).keep_bits(bits)
+}
+
+impl SeededGroupParams {
+    /// Generate the parameters of a safe prime `p` of `bits` bits from a random seed of 256 bits
+    /// drawn from `rand`
+    ///
+    /// Return an error if `bits` is smaller than 4.
+    pub fn generate(bits: u32, reps: i32, rand: &mut RandState<'_>) -> Result<Self, GmpMEEError> {
+        let seed = Integer::from(Integer::random_bits(8 * SEED_BYTES as u32, rand));
+        let mut bytes = vec![0; SEED_BYTES];
+        let digits = seed.to_digits::<u8>(Order::Lsf);
+        bytes[..digits.len()].copy_from_slice(&digits);
+        Self::from_seed(bits, &bytes, reps)
+    }
+
+    /// Derive the parameters of a safe prime `p` of `bits` bits from the seed, with `reps` rounds
+    /// of Miller-Rabin
+    ///
+    /// For the counter `0, 1, ...`, the seed is expanded to a start of `bits` bits with the most
+    /// significant bit set, and `p` is the first safe prime found from this start (see
+    /// [SafePrimeSearch]). The counter moves on while the safe prime found has more than `bits`
+    /// bits. The generator is the square modulo `p` of the first expansion of the seed different
+    /// from `0`, `1` and `p-1` modulo `p`.
+    ///
+    /// Return an error if `bits` is smaller than 4 or if the seed is empty.
+    pub fn from_seed(bits: u32, seed: &[u8], reps: i32) -> Result<Self, GmpMEEError> {
+        if bits < 4 {
+            return Err(MillerRabinError::TooFewBits { bits }.into());
+        }
+        if seed.is_empty() {
+            return Err(MillerRabinError::EmptySeed.into());
+        }
+        let lower = Integer::from(1) << (bits - 1);
+        let (p, counter) = (0..)
+            .find_map(|counter| {
+                let start = expand(seed, b"modulus", counter, bits - 1) + &lower;
+                let p = SafePrimeSearch::new(&start, reps).next_safe_prime();
+                (p.significant_bits() == bits).then_some((p, counter))
+            })
+            .expect("a start must lead to a safe prime of the requested size");
+        let p_minus_1 = Integer::from(&p - 1u32);
+        let h = (0..)
+            .map(|index| expand(seed, b"generator", index, bits + OUTLEN) % &p)
+            .find(|h| *h > 1 && *h != p_minus_1)
+            .expect("an expansion must differ from 0, 1 and p-1");
+        let q = p_minus_1 >> 1u32;
+        let g = h.square() % &p;
+        Ok(Self {
+            group: GroupParams { p, q, g },
+            seed: seed.to_vec(),
+            counter,
+        })
+    }
+
+    /// Verify that the parameters are derived from the seed, with `reps` rounds of Miller-Rabin
+    ///
+    /// The bit length of `p` gives the requested size.
+    pub fn verify(&self, reps: i32) -> bool {
+        let bits = self.group.p.significant_bits();
+        matches!(Self::from_seed(bits, &self.seed, reps), Ok(params) if params == *self)
+    }
+}
+
+/// Check that `g` generates the subgroup of prime order `q` modulo `p`
+///
+/// The elements `0`, `1` and `p-1` are rejected, and `g^q mod p` must be `1`. Since `q` is prime,
+/// every other element of the subgroup is a generator.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::groups::is_generator;
+/// let (p, q) = (Integer::from(23), Integer::from(11));
+/// assert!(is_generator(&Integer::from(2), &p, &q));
+/// assert!(!is_generator(&Integer::from(5), &p, &q));
+/// assert!(!is_generator(&Integer::from(1), &p, &q));
+/// ```
+pub fn is_generator(g: &Integer, p: &Integer, q: &Integer) -> bool {
+    *g > 1
+        && *g < Integer::from(p - 1u32)
+        && g.pow_mod_ref(q, p)
+            .is_some_and(|pow| Integer::from(pow) == 1)
+}
+
+/// Cofactor `(p-1)/q` of the subgroup of order `q` modulo `p`
+///
+/// Return an error if `q` is smaller than 3 or does not divide `p-1`.
+pub(crate) fn cofactor(p: &Integer, q: &Integer) -> Result<Integer, GmpMEEError> {
+    let p_minus_1 = Integer::from(p - 1u32);
+    if *q < 3 || !p_minus_1.is_divisible(q) {
+        return Err(GroupError::InvalidOrder {
+            p: p.clone(),
+            q: q.clone(),
+        }
+        .into());
+    }
+    Ok(p_minus_1 / q)
+}
+
+/// Element `r^cofactor mod p` different from 1 for a uniform `r` in `[1, p-1]`
+fn random_subgroup_element(p: &Integer, cofactor: &Integer, rand: &mut RandState<'_>) -> Integer {
+    let range = Integer::from(p - 1u32);
+    loop {
+        let r = Integer::from(range.random_below_ref(rand)) + 1u32;
+        let element = match *cofactor == 2 {
+            true => r.square() % p,
+            false => r.pow_mod(cofactor, p).expect("p is positive"),
+        };
+        if element != 1 {
+            return element;
+        }
+    }
+}
+
+/// Find a random generator of the subgroup of prime order `q` modulo `p`
+///
+/// The generator is `h^((p-1)/q) mod p` for the first random `h` in `[2, p-2]` giving a generator
+/// (see [is_generator]). Return an error if `q` is smaller than 3 or does not divide `p-1`, or if
+/// no generator has been found in 128 random elements, e.g. if `p` is not prime.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::groups::{find_generator, is_generator};
+/// let (p, q) = (Integer::from(23), Integer::from(11));
+/// let g = find_generator(&p, &q, &mut RandState::new()).unwrap();
+/// assert!(is_generator(&g, &p, &q));
+/// ```
+pub fn find_generator(
+    p: &Integer,
+    q: &Integer,
+    rand: &mut RandState<'_>,
+) -> Result<Integer, GmpMEEError> {
+    let cofactor = cofactor(p, q)?;
+    let range = Integer::from(p - 3u32);
+    for _ in 0..GENERATOR_ATTEMPTS {
+        let h = Integer::from(range.random_below_ref(rand)) + 2u32;
+        let g = h.pow_mod(&cofactor, p).expect("p is greater than 3");
+        if is_generator(&g, p, q) {
+            return Ok(g);
+        }
+    }
+    Err(GroupError::NoGenerator {
+        p: p.clone(),
+        q: q.clone(),
+    }
+    .into())
+}
+
+/// Prefix of the inputs of the hash functions: the length of the tag as a 32-bit big-endian
+/// integer, the tag and the kind of the output, so that different tags or kinds never give the
+/// same input
+fn tagged(dst: &[u8], kind: u8) -> Vec<u8> {
+    let len = u32::try_from(dst.len()).expect("the tag is shorter than 4 GiB");
+    let mut prefix = len.to_be_bytes().to_vec();
+    prefix.extend_from_slice(dst);
+    prefix.push(kind);
+    prefix
+}
+
+/// Hash of the message to an integer in `[0, modulus[` with the domain separation tag `dst`
+///
+/// The message is expanded with SHA-256 to 128 bits more than the modulus, and the expansion is
+/// reduced modulo the modulus. Panics if the modulus is not positive.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::groups::hash_to_integer;
+/// let q = Integer::from(1019);
+/// let e = hash_to_integer(b"challenge", b"transcript", &q);
+/// assert!(e < q);
+/// assert_eq!(e, hash_to_integer(b"challenge", b"transcript", &q));
+/// ```
+pub fn hash_to_integer(dst: &[u8], message: &[u8], modulus: &Integer) -> Integer {
+    assert!(*modulus > 0, "the modulus must be positive");
+    let bits = modulus.significant_bits() + HASH_MARGIN_BITS;
+    expand(&tagged(dst, 0), message, 0, bits) % modulus
+}
+
+/// Hash of the message to an element of the subgroup of order `q` modulo `p` with the domain
+/// separation tag `dst`
+///
+/// The element is `h^((p-1)/q) mod p`, i.e. `h^2 mod p` for a safe prime `p`, where `h` is the
+/// expansion of the message reduced modulo `p` (see [hash_to_integer]). The expansions with the
+/// indices `1, 2, ...` are used while the element is `0` or `1`. Return an error if `q` is smaller
+/// than 3 or does not divide `p-1`.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::groups::{hash_to_subgroup, is_quadratic_residue};
+/// let (p, q) = (Integer::from(1019), Integer::from(509));
+/// let h = hash_to_subgroup(b"generator", b"h", &p, &q).unwrap();
+/// assert!(is_quadratic_residue(&h, &p) && h != 1);
+/// ```
+pub fn hash_to_subgroup(
+    dst: &[u8],
+    message: &[u8],
+    p: &Integer,
+    q: &Integer,
+) -> Result<Integer, GmpMEEError> {
+    let cofactor = cofactor(p, q)?;
+    let prefix = tagged(dst, 1);
+    let bits = p.significant_bits() + HASH_MARGIN_BITS;
+    Ok((0..)
+        .map(|index| {
+            let h = expand(&prefix, message, index, bits) % p;
+            h.pow_mod(&cofactor, p).expect("p is positive")
+        })
+        .find(|g| *g > 1)
+        .expect("an expansion must give an element different from 0 and 1"))
+}
+
+/// Check that `element` is a quadratic residue modulo the odd prime `p`, i.e. that it lies in
+/// `[1, p-1]` with the Legendre symbol `(element/p) = 1`
+///
+/// For a safe prime `p`, the quadratic residues form the subgroup of order `q = (p-1)/2`.
+pub fn is_quadratic_residue(element: &Integer, p: &Integer) -> bool {
+    *element > 0 && element < p && element.jacobi(p) == 1
+}
+
+/// Check that each of the `elements` is a quadratic residue modulo the odd prime `p` (see
+/// [is_quadratic_residue])
+///
+/// The elements are split in one chunk per available core, and the Legendre symbols of the chunks
+/// are computed in parallel.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::groups::are_quadratic_residues;
+/// let elements = [1, 2, 5, 0, 22, 23, 13].map(Integer::from);
+/// assert_eq!(
+///     are_quadratic_residues(&elements, &Integer::from(23)),
+///     [true, true, false, false, false, false, true]
+/// );
+/// ```
+pub fn are_quadratic_residues(elements: &[Integer], p: &Integer) -> Vec<bool> {
+    map_chunks(elements, |e| is_quadratic_residue(e, p))
+}
+
+/// Square root of `a` modulo the odd prime `p`, or `None` if `a` is not a square modulo `p`
+///
+/// The root is computed as `a^((p+1)/4) mod p` if `p = 3 mod 4`, and with the algorithm of
+/// Tonelli-Shanks otherwise. Of the two roots `r` and `p-r`, the smaller one is returned.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::groups::sqrt_mod;
+/// assert_eq!(sqrt_mod(&Integer::from(2), &Integer::from(23)), Some(Integer::from(5)));
+/// assert_eq!(sqrt_mod(&Integer::from(5), &Integer::from(23)), None);
+/// assert_eq!(sqrt_mod(&Integer::from(10), &Integer::from(13)), Some(Integer::from(6)));
+/// ```
+pub fn sqrt_mod(a: &Integer, p: &Integer) -> Option<Integer> {
+    let pow =
+        |base: &Integer, e: &Integer| Integer::from(base.pow_mod_ref(e, p).expect("p is positive"));
+    let mut a = Integer::from(a % p);
+    if a < 0 {
+        a += p;
+    }
+    if a == 0 {
+        return Some(a);
+    }
+    if a.jacobi(p) != 1 {
+        return None;
+    }
+    let root = match p.mod_u(4) == 3 {
+        true => pow(&a, &(Integer::from(p + 1u32) >> 2u32)),
+        false => {
+            // p-1 = q*2^s with q odd
+            let p_minus_1 = Integer::from(p - 1u32);
+            let s = p_minus_1.find_one(0).expect("p-1 is positive");
+            let q = Integer::from(&p_minus_1 >> s);
+            let z = (2u32..)
+                .map(Integer::from)
+                .find(|z| z.jacobi(p) == -1)
+                .expect("a non-residue exists modulo an odd prime");
+            let (mut m, mut c) = (s, pow(&z, &q));
+            let mut t = pow(&a, &q);
+            let mut root = pow(&a, &(Integer::from(&q + 1u32) >> 1u32));
+            while t != 1 {
+                // least i with t^(2^i) = 1, which is smaller than m
+                let mut i = 0;
+                let mut t2 = t.clone();
+                while t2 != 1 {
+                    t2 = t2.square() % p;
+                    i += 1;
+                }
+                let b = pow(&c, &(Integer::from(1) << (m - i - 1)));
+                m = i;
+                c = Integer::from(b.square_ref()) % p;
+                t = t * &c % p;
+                root = root * b % p;
+            }
+            root
+        }
+    };
+    let other = Integer::from(p - &root);
+    Some(root.min(other))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::miller_rabin::GroupCheck;
+    use rug::integer::IsPrime;
+
+    #[test]
+    fn test_generate() {
+        let mut rand = RandState::new();
+        for bits in [4, 5, 64, 512] {
+            let group = GroupParams::generate(bits, 16, &mut rand).unwrap();
+            assert_eq!(group.p.significant_bits(), bits);
+            assert_eq!(group.q, Integer::from(&group.p - 1u32) >> 1u32);
+            assert!(group.verify(16).is_valid(), "{group:?}");
+            let e = Integer::from(Integer::random_bits(bits, &mut rand));
+            assert_eq!(
+                group.table().unwrap().fpowm(&e),
+                group.g.clone().pow_mod(&e, &group.p).unwrap()
+            );
+        }
+        assert!(GroupParams::generate(3, 16, &mut rand).is_err());
+    }
+
+    #[test]
+    fn test_random() {
+        let mut rand = RandState::new();
+        let group = GroupParams::generate(5, 16, &mut rand).unwrap();
+        let (p, q) = (group.p.to_u32().unwrap(), group.q.to_u32().unwrap());
+        let residues = (2..p - 1).map(|r| r * r % p).collect::<Vec<_>>();
+        let mut exponents = vec![0; q as usize];
+        let mut elements = vec![0; p as usize];
+        for _ in 0..100 * q {
+            exponents[group.random_exponent(&mut rand).to_usize().unwrap()] += 1;
+        }
+        for e in group.random_elements(100 * q as usize, &mut rand).unwrap() {
+            elements[e.to_usize().unwrap()] += 1;
+        }
+        assert!(exponents.iter().all(|&c| c > 0));
+        for (e, &c) in elements.iter().enumerate() {
+            assert_eq!(c > 0, residues.contains(&(e as u32)), "{e}");
+        }
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        for e in group.random_elements(20, &mut rand).unwrap() {
+            assert!(e > 1 && e < group.p);
+            assert_eq!(e.pow_mod(&group.q, &group.p).unwrap(), 1);
+        }
+        assert!(group.random_exponent(&mut rand) < group.q);
+        assert!(group.random_elements(0, &mut rand).unwrap().is_empty());
+        let invalid = GroupParams {
+            q: Integer::from(11),
+            ..group
+        };
+        assert_eq!(
+            invalid.random_element(&mut rand),
+            Err(GroupError::InvalidOrder {
+                p: invalid.p.clone(),
+                q: invalid.q.clone()
+            }
+            .into())
+        );
+        assert!(invalid.random_elements(1, &mut rand).is_err());
+    }
+
+    #[test]
+    fn test_quadratic_residues() {
+        let mut rand = RandState::new();
+        let p = Integer::from(23);
+        let residues = (1..23).map(|r| r * r % 23).collect::<Vec<_>>();
+        let elements = (-3..30).map(Integer::from).collect::<Vec<_>>();
+        let expected = (-3..30)
+            .map(|e| e > 0 && residues.contains(&e))
+            .collect::<Vec<_>>();
+        assert_eq!(are_quadratic_residues(&elements, &p), expected);
+        assert!(are_quadratic_residues(&[], &p).is_empty());
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        let mut elements = group.random_elements(100, &mut rand).unwrap();
+        elements.extend((0..100).map(|_| &group.p - group.random_element(&mut rand).unwrap()));
+        let res = are_quadratic_residues(&elements, &group.p);
+        assert!(res[..100].iter().all(|&r| r));
+        assert!(res[100..].iter().all(|&r| !r));
+    }
+
+    #[test]
+    fn test_sqrt_mod() {
+        for p in [3u32, 5, 7, 13, 17, 23, 41, 97, 113, 193, 257, 65537] {
+            // smallest root of each square
+            let mut roots = vec![None; p as usize];
+            for r in (0..p).rev() {
+                roots[(u64::from(r) * u64::from(r) % u64::from(p)) as usize] = Some(r);
+            }
+            let pi = Integer::from(p);
+            for (a, root) in roots.into_iter().enumerate() {
+                let res = sqrt_mod(&Integer::from(a), &pi);
+                assert_eq!(res, root.map(Integer::from), "{a} {p}");
+            }
+        }
+        let p = Integer::from(13);
+        assert_eq!(sqrt_mod(&Integer::from(-3), &p), Some(Integer::from(6)));
+        assert_eq!(sqrt_mod(&Integer::from(23), &p), Some(Integer::from(6)));
+        let mut rand = RandState::new();
+        // p = 1 mod 2^200 exercises many iterations of Tonelli-Shanks
+        let p = (1u32..)
+            .map(|k| (Integer::from(k) << 200u32) + 1u32)
+            .find(|p| p.is_probably_prime(30) != IsPrime::No)
+            .unwrap();
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        for p in [p, group.p] {
+            for _ in 0..20 {
+                let r = Integer::from(p.random_below_ref(&mut rand));
+                let a = Integer::from(r.square_ref()) % &p;
+                let root = sqrt_mod(&a, &p).unwrap();
+                assert_eq!(Integer::from(root.square_ref()) % &p, a);
+                assert!(root == r || root == Integer::from(&p - &r));
+                assert!(root <= Integer::from(&p >> 1u32));
+            }
+        }
+    }
+
+    #[test]
+    fn test_hash() {
+        let mut rand = RandState::new();
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        let (p, q) = (&group.p, &group.q);
+        let mut exponents = Vec::new();
+        let mut elements = Vec::new();
+        for (dst, message) in [
+            (&b"a"[..], &b"bc"[..]),
+            (b"ab", b"c"),
+            (b"", b"abc"),
+            (b"abc", b""),
+        ] {
+            let e = group.hash_to_exponent(dst, message);
+            assert!(e < *q);
+            assert_eq!(e, hash_to_integer(dst, message, q));
+            let h = group.hash_to_element(dst, message).unwrap();
+            assert!(is_generator(&h, p, q));
+            assert_eq!(h, hash_to_subgroup(dst, message, p, q).unwrap());
+            exponents.push(e);
+            elements.push(h);
+        }
+        for i in 0..exponents.len() {
+            for j in 0..i {
+                assert_ne!(exponents[i], exponents[j]);
+                assert_ne!(elements[i], elements[j]);
+            }
+        }
+        // Schnorr group with q dividing p-1 = 2*3*5*7
+        let (p, q) = (Integer::from(211), Integer::from(7));
+        for message in 0u8..50 {
+            let h = hash_to_subgroup(b"dst", &[message], &p, &q).unwrap();
+            assert!(is_generator(&h, &p, &q));
+            assert!(hash_to_integer(b"dst", &[message], &q) < 7);
+        }
+        assert!(hash_to_subgroup(b"dst", b"", &p, &Integer::from(11)).is_err());
+        let invalid = GroupParams {
+            q: Integer::from(11),
+            ..group
+        };
+        assert_eq!(
+            invalid.hash_to_element(b"dst", b""),
+            Err(GroupError::InvalidOrder {
+                p: invalid.p.clone(),
+                q: invalid.q.clone()
+            }
+            .into())
+        );
+    }
+
+    #[test]
+    fn test_standard() {
+        for group in StandardGroup::ALL {
+            let params = GroupParams::standard(group.name(), 16).unwrap();
+            assert_eq!(params, GroupParams::from(group));
+            assert_eq!(
+                GroupParams::standard(group.identifier(), 16).unwrap(),
+                params
+            );
+        }
+        assert!(
+            GroupParams::standard("modp-3072", 16)
+                .unwrap()
+                .verify(16)
+                .is_valid()
+        );
+        assert_eq!(
+            GroupParams::standard("modp-1", 16),
+            Err(GroupError::UnknownGroup {
+                name: "modp-1".to_string()
+            }
+            .into())
+        );
+    }
+
+    #[test]
+    fn test_rfc5114() {
+        let mut rand = RandState::new();
+        for group in [
+            StandardGroup::Modp1024S160,
+            StandardGroup::Modp2048S224,
+            StandardGroup::Modp2048S256,
+        ] {
+            let params = GroupParams::from(group);
+            assert_ne!(params.q, Integer::from(&params.p - 1u32) >> 1u32);
+            let report = params.verify(16);
+            assert!(report.is_valid(), "{report}");
+            for e in params.random_elements(5, &mut rand).unwrap() {
+                assert!(is_generator(&e, &params.p, &params.q));
+            }
+            let mut forged = params.clone();
+            forged.g = Integer::from(2);
+            assert_eq!(forged.verify(16).failed(), &[GroupCheck::GeneratorOrder]);
+            forged.q += 2u32;
+            assert!(!forged.verify(16).passed(GroupCheck::QDividesPMinusOne));
+        }
+    }
+
+    #[test]
+    fn test_generator() {
+        let mut rand = RandState::new();
+        let (p, q) = (Integer::from(23), Integer::from(11));
+        let residues = (2..22).map(|r| r * r % 23).collect::<Vec<_>>();
+        for g in 0..30 {
+            let expected = g != 1 && residues.contains(&g);
+            assert_eq!(is_generator(&Integer::from(g), &p, &q), expected, "{g}");
+        }
+        for _ in 0..20 {
+            let g = find_generator(&p, &q, &mut rand).unwrap();
+            assert!(residues.contains(&g.to_u32().unwrap()) && g != 1);
+        }
+        // Schnorr group with q dividing p-1 = 2*3*5*7
+        let (p, q) = (Integer::from(211), Integer::from(7));
+        let g = find_generator(&p, &q, &mut rand).unwrap();
+        assert!(is_generator(&g, &p, &q));
+        assert!(!is_generator(&Integer::from(210), &p, &Integer::from(2)));
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        let g = find_generator(&group.p, &group.q, &mut rand).unwrap();
+        assert!(is_generator(&g, &group.p, &group.q));
+        assert!(is_generator(&group.g, &group.p, &group.q));
+        assert!(!is_generator(
+            &Integer::from(&group.p - 1u32),
+            &group.p,
+            &group.q
+        ));
+        // 8 is not prime, only 1 has an order dividing 7
+        assert_eq!(
+            find_generator(&Integer::from(8), &Integer::from(7), &mut rand),
+            Err(GroupError::NoGenerator {
+                p: Integer::from(8),
+                q: Integer::from(7)
+            }
+            .into())
+        );
+        for (p, q) in [(23, 2), (23, 5), (211, 1)] {
+            assert_eq!(
+                find_generator(&Integer::from(p), &Integer::from(q), &mut rand),
+                Err(GroupError::InvalidOrder {
+                    p: Integer::from(p),
+                    q: Integer::from(q)
+                }
+                .into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_seeded() {
+        let mut rand = RandState::new();
+        for bits in [4, 5, 64, 512] {
+            let params = SeededGroupParams::generate(bits, 16, &mut rand).unwrap();
+            assert_eq!(params.group.p.significant_bits(), bits);
+            assert!(params.group.verify(16).is_valid(), "{params:?}");
+            assert!(params.verify(16));
+            assert_eq!(
+                SeededGroupParams::from_seed(bits, &params.seed, 16).unwrap(),
+                params
+            );
+            let mut forged = params.clone();
+            forged.seed[0] ^= 1;
+            assert!(!forged.verify(16) || bits < 8);
+            let mut forged = params;
+            forged.group.g = forged.group.g.square() % &forged.group.p;
+            assert!(!forged.verify(16));
+        }
+        let params = SeededGroupParams::from_seed(128, b"seed", 16).unwrap();
+        assert_ne!(
+            SeededGroupParams::from_seed(128, b"seee", 16).unwrap(),
+            params
+        );
+        assert_eq!(
+            SeededGroupParams::from_seed(64, &[], 16),
+            Err(MillerRabinError::EmptySeed.into())
+        );
+        assert_eq!(
+            SeededGroupParams::from_seed(3, b"seed", 16),
+            Err(MillerRabinError::TooFewBits { bits: 3 }.into())
+        );
+    }
+}
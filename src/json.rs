@@ -0,0 +1,296 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module for the JSON import and export of the group parameters, the descriptors of the tables
+//! and the verification reports (only with the feature `json`)
+//!
+//! The integers are written as hexadecimal strings, as in the configuration of the cache, so that
+//! the tooling written in other languages does not depend on the size of the numbers of JSON.
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::{groups::GroupParams, json::{from_json, to_json}};
+//! let group = GroupParams {
+//!     p: Integer::from(23),
+//!     q: Integer::from(11),
+//!     g: Integer::from(4),
+//! };
+//! let json = to_json(&group);
+//! assert_eq!(json, r#"{"p":"17","q":"b","g":"4"}"#);
+//! assert_eq!(from_json::<GroupParams>(&json).unwrap(), group);
+//! ```
+
+use crate::{
+    GmpMEEError,
+    fpowm::FPowmTable,
+    groups::GroupParams,
+    miller_rabin::{GroupCheck, GroupReport},
+};
+use rug::Integer;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::DeserializeOwned};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum JsonError {
+    #[error("Invalid JSON: {0}")]
+    Invalid(String),
+}
+
+/// JSON of the value
+pub fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("the types of the crate must be serializable")
+}
+
+/// Value read from the JSON
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, GmpMEEError> {
+    serde_json::from_str(json).map_err(|e| JsonError::Invalid(e.to_string()).into())
+}
+
+fn to_hex(value: &Integer) -> String {
+    format!("{value:x}")
+}
+
+fn parse_hex<E: serde::de::Error>(key: &str, value: &str) -> Result<Integer, E> {
+    crate::parse_hex(value)
+        .map_err(|e| E::custom(format!("{key} is not a hexadecimal number: {e}")))
+}
+
+/// Group parameters as written in JSON
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawGroupParams {
+    p: String,
+    q: String,
+    g: String,
+}
+
+impl Serialize for GroupParams {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RawGroupParams {
+            p: to_hex(&self.p),
+            q: to_hex(&self.q),
+            g: to_hex(&self.g),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// The parameters are not verified (see [GroupParams::verify])
+impl<'de> Deserialize<'de> for GroupParams {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawGroupParams::deserialize(deserializer)?;
+        Ok(Self {
+            p: parse_hex("p", &raw.p)?,
+            q: parse_hex("q", &raw.q)?,
+            g: parse_hex("g", &raw.g)?,
+        })
+    }
+}
+
+/// Descriptor of a precomputed table, enough to compute it again
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TableDescriptor {
+    pub base: Integer,
+    pub modulus: Integer,
+    pub block_width: usize,
+    pub exponent_bitlen: usize,
+}
+
+impl TableDescriptor {
+    /// Descriptor of the table, if the precomputation has been done
+    pub fn of(table: &FPowmTable) -> Option<Self> {
+        Some(Self {
+            base: table.base()?.clone(),
+            modulus: table.modulus().clone(),
+            block_width: table.block_width(),
+            exponent_bitlen: table.exponent_bitlen(),
+        })
+    }
+
+    /// Compute the table of the descriptor (see [FPowmTable::init_precomp])
+    pub fn table(&self) -> Result<FPowmTable, GmpMEEError> {
+        FPowmTable::init_precomp(
+            &self.base,
+            &self.modulus,
+            self.block_width,
+            self.exponent_bitlen,
+        )
+    }
+}
+
+/// Descriptor of a table as written in JSON
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTableDescriptor {
+    base: String,
+    modulus: String,
+    block_width: usize,
+    exponent_bitlen: usize,
+}
+
+impl Serialize for TableDescriptor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RawTableDescriptor {
+            base: to_hex(&self.base),
+            modulus: to_hex(&self.modulus),
+            block_width: self.block_width,
+            exponent_bitlen: self.exponent_bitlen,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TableDescriptor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawTableDescriptor::deserialize(deserializer)?;
+        Ok(Self {
+            base: parse_hex("base", &raw.base)?,
+            modulus: parse_hex("modulus", &raw.modulus)?,
+            block_width: raw.block_width,
+            exponent_bitlen: raw.exponent_bitlen,
+        })
+    }
+}
+
+/// Identifier of the check in JSON
+fn check_id(check: GroupCheck) -> &'static str {
+    match check {
+        GroupCheck::PPrime => "p_prime",
+        GroupCheck::QHalfOfP => "q_half_of_p",
+        GroupCheck::QDividesPMinusOne => "q_divides_p_minus_one",
+        GroupCheck::QPrime => "q_prime",
+        GroupCheck::GeneratorInRange => "generator_in_range",
+        GroupCheck::GeneratorOrder => "generator_order",
+        GroupCheck::GeneratorNotTrivial => "generator_not_trivial",
+    }
+}
+
+impl Serialize for GroupCheck {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(check_id(*self))
+    }
+}
+
+impl<'de> Deserialize<'de> for GroupCheck {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        GroupCheck::ALL
+            .into_iter()
+            .find(|&c| check_id(c) == id)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown check {id}")))
+    }
+}
+
+/// Report as written in JSON, with the failed checks
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawGroupReport {
+    valid: bool,
+    failed: Vec<GroupCheck>,
+}
+
+/// Written as `{"valid": bool, "failed": [checks]}`
+impl Serialize for GroupReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RawGroupReport {
+            valid: self.is_valid(),
+            failed: self.failed().to_vec(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// The checks are not run again, `valid` must only agree with the failed checks
+impl<'de> Deserialize<'de> for GroupReport {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawGroupReport::deserialize(deserializer)?;
+        if raw.valid != raw.failed.is_empty() {
+            return Err(serde::de::Error::custom(
+                "valid does not agree with the failed checks",
+            ));
+        }
+        Ok(GroupReport::from_failed(&raw.failed))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::standard_groups::StandardGroup;
+
+    #[test]
+    fn test_group_params() {
+        let group = GroupParams::from(StandardGroup::Modp2048S256);
+        assert_eq!(from_json::<GroupParams>(&to_json(&group)).unwrap(), group);
+        let json = r#"{"p": "0x17", "q": "B", "g": "4"}"#;
+        assert_eq!(
+            from_json::<GroupParams>(json).unwrap(),
+            GroupParams {
+                p: Integer::from(23),
+                q: Integer::from(11),
+                g: Integer::from(4)
+            }
+        );
+        assert!(from_json::<GroupParams>(r#"{"p": "17", "q": "b"}"#).is_err());
+        assert!(from_json::<GroupParams>(r#"{"p": "17", "q": "b", "g": "x"}"#).is_err());
+        assert!(from_json::<GroupParams>(r#"{"p": "17", "q": "b", "g": "-4"}"#).is_err());
+        assert!(from_json::<GroupParams>(r#"{"p": "17", "q": "b", "g": "4", "h": "2"}"#).is_err());
+    }
+
+    #[test]
+    fn test_table_descriptor() {
+        let table =
+            FPowmTable::init_precomp(&Integer::from(7), &Integer::from(1019), 4, 16).unwrap();
+        let descriptor = TableDescriptor::of(&table).unwrap();
+        let json = to_json(&descriptor);
+        assert_eq!(
+            json,
+            r#"{"base":"7","modulus":"3fb","block_width":4,"exponent_bitlen":16}"#
+        );
+        let read = from_json::<TableDescriptor>(&json).unwrap();
+        assert_eq!(read, descriptor);
+        let e = Integer::from(12345);
+        assert_eq!(read.table().unwrap().fpowm(&e), table.fpowm(&e));
+    }
+
+    #[test]
+    fn test_report() {
+        let group = GroupParams::from(StandardGroup::Modp2048);
+        assert_eq!(to_json(&group.verify(16)), r#"{"valid":true,"failed":[]}"#);
+        let report = crate::miller_rabin::verify_group(
+            &Integer::from(23),
+            &Integer::from(11),
+            &Integer::from(5),
+            16,
+        );
+        assert_eq!(
+            to_json(&report),
+            r#"{"valid":false,"failed":["generator_order"]}"#
+        );
+        for check in GroupCheck::ALL {
+            assert_eq!(from_json::<GroupCheck>(&to_json(&check)).unwrap(), check);
+        }
+        assert!(from_json::<GroupCheck>(r#""unknown""#).is_err());
+        assert_eq!(from_json::<GroupReport>(&to_json(&report)).unwrap(), report);
+        assert_eq!(
+            from_json::<GroupReport>(r#"{"valid":false,"failed":["q_prime","p_prime","q_prime"]}"#)
+                .unwrap()
+                .failed(),
+            &[GroupCheck::PPrime, GroupCheck::QPrime]
+        );
+        assert!(from_json::<GroupReport>(r#"{"valid":true,"failed":["q_prime"]}"#).is_err());
+        assert!(from_json::<GroupReport>(r#"{"valid":false,"failed":[]}"#).is_err());
+    }
+}
@@ -24,6 +24,17 @@
 //! - Multi-exponentation (`spowm`)
 //! - Fixed base exponentiation (`fpowm`). It contains a possibility to cache the precomputation table
 //! - Miller-Rabin primality test
+//! - Sieve of the small primes (`small_primes`)
+//! - ElGamal encryption using the precomputed tables
+//! - Pedersen commitments using the precomputed tables (`pedersen`)
+//! - Reduction of many integers by a common modulus (`reduce`)
+//! - Additive blinding of secret exponents (`blinding`)
+//! - Byte-tree format of Verificatum (`bytetree`)
+//! - Batch verification of Schnorr signatures (`schnorr`) and of Chaum-Pedersen proofs (`chaum_pedersen`)
+//! - Standard MODP groups of RFC 3526 and RFC 5114, with precomputed tables for their generators
+//! - Generation of the parameters of Schnorr and ElGamal groups (`groups`)
+//! - Diffie-Hellman parameters of OpenSSL in PKCS#3 or X9.42 (`dhparam`)
+//! - Context bundling the tables, the exponentiations and the sampling of a modulus (`context`)
 //!
 //! The rub-gmpmee crate is free software: you can redistribute it and/or modify it under the terms of the
 //! GNU Lesser General Public License as published by the Free Software Foundation, either version 3 of the License,
@@ -31,13 +42,133 @@
 //!
 //! # Using rug-gmpmee
 //! See the [gmpmee-sys](https://docs.rs/gmpmee-sys) crate.
+//!
+//! # Platform support
+//! GMPMEE is not linked on the WebAssembly targets (`wasm32-unknown-unknown`, `wasm32-wasip1`): the
+//! feature `fallback` is required there, and the crate runs on its pure Rust backend whatever the
+//! feature `gmpmee`. The feature `shm` is not available. The integers are still the ones of rug, so
+//! that gmp-mpfr-sys must be able to build GMP for the target. On `wasm32-unknown-unknown`, which
+//! has no threads, the batch operations and the prime searches run on the calling thread, and the
+//! background initialization of the caches is done before returning. The functions with a deadline
+//! and the progress of the safe prime generation panic there, since `std::time::Instant` is not
+//! implemented, as well as the durations recorded with the features `tracing` and `metrics`.
+//!
+//! # Features
+//! - `ark-ff`: Conversions between [rug::Integer] and the elements of the prime fields of arkworks
+//!   (see `field`)
+//! - `async`: Wrappers of the precomputation, the prime generation and `spowm` running in the
+//!   blocking threads of tokio (see `nonblocking`)
+//! - `cache` (enabled by default): Caches of precomputed tables with a process-global state (static,
+//!   named, thread and global caches, see `fpowm`). Without this feature, the crate keeps no table in
+//!   a global state. The remaining global state is the default tuning parameters of
+//!   `fpowm::set_defaults`, the small primes and the primorial computed once for the prime tests, and
+//!   the random state of each thread used by the Miller-Rabin tests without an explicit random state
+//! - `capi`: C API of the multi-exponentiation and of the tables, declared in
+//!   `include/rug_gmpmee.h` (see `capi`)
+//! - `config`: Initialization of the static cache from a TOML or JSON file, or from environment
+//!   variables (see `fpowm::cache_init_from_config`)
+//! - `constant-time`: Exponentiation with `mpz_powm_sec` for the tables used with secret exponents
+//!   (see `fpowm::ExponentMode`)
+//! - `fallback`: Pure Rust implementations of `spowm` and of the fixed base exponentiation with
+//!   the same API as GMPMEE (see `fallback`), also used by the whole crate without the feature
+//!   `gmpmee`
+//! - `ff`: Conversions between [rug::Integer] and the elements of the prime fields of ff (see
+//!   `field`)
+//! - `gmpmee` (enabled by default): Exponentiations and prime tests of the native library GMPMEE
+//!   (through gmpmee-sys), except on wasm32. Without this feature or on wasm32, the feature
+//!   `fallback` is required
+//! - `json`: JSON import and export of the group parameters, the descriptors of the tables and the
+//!   verification reports (see `json`)
+//! - `metrics`: Counters of the exponentiations and of the cache lookups, and histograms of the
+//!   batch sizes and of the precomputation durations, published with the crate `metrics`
+//! - `num-bigint`: Conversions between the integers of num-bigint and [rug::Integer], and
+//!   exponentiations of `BigUint` (see `bigint`)
+//! - `protobuf`: Protobuf encoding of the group parameters and the precomputed tables (see
+//!   `proto`)
+//! - `rand_core`: Random states of rug drawing their bits from a generator of `rand_core` (see
+//!   `rng`)
+//! - `rayon`: Adapter to calculate the exponentiations of a parallel iterator with a table
+//!   (see `fpowm::FPowmParallelIterator`)
+//! - `shm`: Tables written in a segment (e.g. in `/dev/shm`) mapped read-only by several processes
+//!   (see `fpowm::FPowmTable::open_shared`)
+//! - `tracing`: Spans with the sizes of the operands and the durations around the precomputations,
+//!   `spowm`, the batches of exponentiations and the prime searches
+//! - `zeroize`: Wipe the precomputed tables and the secret exponents from memory (see `secret`)
 
+#[cfg(feature = "num-bigint")]
+pub mod bigint;
+pub mod blinding;
+pub mod bytetree;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod chaum_pedersen;
+pub mod context;
+pub mod dhparam;
+pub mod elgamal;
+#[cfg(feature = "fallback")]
+pub mod fallback;
+#[cfg(any(feature = "ark-ff", feature = "ff"))]
+pub mod field;
 pub mod fpowm;
+pub mod groups;
+#[cfg(feature = "json")]
+pub mod json;
+mod lru;
 pub mod miller_rabin;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+mod parallel;
+pub mod pedersen;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+pub mod reduce;
+#[cfg(feature = "rand_core")]
+pub mod rng;
+pub mod schnorr;
+#[cfg(feature = "zeroize")]
+pub mod secret;
+pub mod small_primes;
 pub mod spown;
+pub mod standard_groups;
+mod telemetry;
+mod trace;
+#[cfg(not(any(
+    all(feature = "gmpmee", not(target_arch = "wasm32")),
+    feature = "fallback"
+)))]
+compile_error!("the feature `fallback` is required without the feature `gmpmee` and on wasm32");
+#[cfg(all(feature = "shm", target_arch = "wasm32"))]
+compile_error!("the feature `shm` is not available on wasm32");
+
+#[cfg(feature = "num-bigint")]
+use bigint::BigIntError;
+use blinding::BlindingError;
+use bytetree::ByteTreeError;
+use dhparam::DhParamError;
+use elgamal::ElGamalError;
+#[cfg(all(
+    feature = "fallback",
+    any(not(feature = "gmpmee"), target_arch = "wasm32")
+))]
+use fallback::sys;
+#[cfg(any(feature = "ark-ff", feature = "ff"))]
+use field::FieldError;
+#[cfg(feature = "cache")]
+use fpowm::CacheError;
 use fpowm::FPownError;
+#[cfg(all(feature = "gmpmee", not(target_arch = "wasm32")))]
+use gmpmee_sys as sys;
+use groups::GroupError;
+#[cfg(feature = "json")]
+use json::JsonError;
+use miller_rabin::{CertificateError, MillerRabinError, PrimalityError};
+#[cfg(feature = "async")]
+use nonblocking::AsyncError;
+use pedersen::PedersenError;
+#[cfg(feature = "protobuf")]
+use proto::ProtoError;
 use spown::SPownError;
-use std::num::TryFromIntError;
+use std::{ffi::c_long, num::TryFromIntError};
 use thiserror::Error;
 
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
@@ -46,6 +177,42 @@ pub enum GmpMEEError {
     SPowmParameters(#[from] SPownError),
     #[error("Error in parameters of fpown: {0}")]
     FPowmParameters(#[from] FPownError),
+    #[error("Error in parameters of ElGamal: {0}")]
+    ElGamalParameters(#[from] ElGamalError),
+    #[error("Error in parameters of Pedersen: {0}")]
+    PedersenParameters(#[from] PedersenError),
+    #[error("Error in the blinding of the exponent: {0}")]
+    Blinding(#[from] BlindingError),
+    #[cfg(feature = "cache")]
+    #[error("Error in the cache: {0}")]
+    Cache(#[from] CacheError),
+    #[error("Error in parameters of Miller-Rabin: {0}")]
+    MillerRabinParameters(#[from] MillerRabinError),
+    #[error("Error in the primality certificate: {0}")]
+    Certificate(#[from] CertificateError),
+    #[error("Error in the primality test: {0}")]
+    Primality(#[from] PrimalityError),
+    #[error("Error in the group parameters: {0}")]
+    Group(#[from] GroupError),
+    #[error("Error in the byte tree: {0}")]
+    ByteTree(#[from] ByteTreeError),
+    #[error("Error in the Diffie-Hellman parameters: {0}")]
+    DhParam(#[from] DhParamError),
+    #[cfg(feature = "async")]
+    #[error("Error in the asynchronous task: {0}")]
+    Async(#[from] AsyncError),
+    #[cfg(feature = "json")]
+    #[error("Error in the JSON: {0}")]
+    Json(#[from] JsonError),
+    #[cfg(feature = "protobuf")]
+    #[error("Error in the protobuf encoding: {0}")]
+    Proto(#[from] ProtoError),
+    #[cfg(feature = "num-bigint")]
+    #[error("Error in the conversion to num-bigint: {0}")]
+    BigInt(#[from] BigIntError),
+    #[cfg(any(feature = "ark-ff", feature = "ff"))]
+    #[error("Error in the conversion to a prime field: {0}")]
+    Field(#[from] FieldError),
     #[error("{msg}: {source}")]
     Cast {
         msg: String,
@@ -53,12 +220,41 @@ pub enum GmpMEEError {
     },
 }
 
-#[cfg(target_family = "windows")]
-fn usize_to_size_t_type(n: usize) -> Result<i32, TryFromIntError> {
+/// Cast to the `long` of C, which has 32 bits on Windows and on wasm32
+fn usize_to_size_t_type(n: usize) -> Result<c_long, TryFromIntError> {
     n.try_into()
 }
 
-#[cfg(not(target_family = "windows"))]
-fn usize_to_size_t_type(n: usize) -> Result<i64, TryFromIntError> {
-    n.try_into()
+/// Parse a non-negative hexadecimal number of the configuration of the cache or of the JSON
+///
+/// The surrounding whitespace and the prefix `0x` or `0X` are ignored. Return the reason of the
+/// failure otherwise.
+#[cfg(any(feature = "config", feature = "json"))]
+fn parse_hex(value: &str) -> Result<rug::Integer, String> {
+    let hex = value.trim();
+    let hex = hex
+        .strip_prefix("0x")
+        .or_else(|| hex.strip_prefix("0X"))
+        .unwrap_or(hex);
+    if hex.starts_with(['-', '+']) {
+        return Err("the sign is not allowed".to_string());
+    }
+    rug::Integer::from_str_radix(hex, 16).map_err(|e| e.to_string())
+}
+
+#[cfg(all(test, any(feature = "config", feature = "json")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("17").unwrap(), 23);
+        assert_eq!(parse_hex(" 0x17\n").unwrap(), 23);
+        assert_eq!(parse_hex("0XfF").unwrap(), 255);
+        assert!(parse_hex("-17").is_err());
+        assert!(parse_hex("0x-17").is_err());
+        assert!(parse_hex("+17").is_err());
+        assert!(parse_hex("0x").is_err());
+        assert!(parse_hex("x").is_err());
+    }
 }
@@ -0,0 +1,139 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Bounded cache with least recently used eviction
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Cache keeping at most `capacity` entries, evicting the least recently used one
+pub(crate) struct LruCache<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.values().map(|(v, _)| v)
+    }
+
+    /// Return the value for the key and mark it as the most recently used
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    /// Return the mutable value for the key and mark it as the most recently used
+    pub(crate) fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let tick = self.next_tick();
+        let (value, last_used) = self.entries.get_mut(key)?;
+        let key = self.order.remove(last_used).unwrap();
+        self.order.insert(tick, key);
+        *last_used = tick;
+        Some(value)
+    }
+
+    /// Insert the value, evicting the least recently used entries if the capacity is exceeded
+    ///
+    /// Return the evicted values
+    pub(crate) fn insert(&mut self, key: K, value: V) -> Vec<V> {
+        if self.capacity == 0 {
+            return vec![value];
+        }
+        let tick = self.next_tick();
+        if let Some((_, last_used)) = self.entries.get(&key) {
+            self.order.remove(last_used);
+        }
+        self.order.insert(tick, key.clone());
+        let mut evicted: Vec<V> = self
+            .entries
+            .insert(key, (value, tick))
+            .map(|(v, _)| v)
+            .into_iter()
+            .collect();
+        while self.entries.len() > self.capacity {
+            evicted.extend(self.pop_lru());
+        }
+        evicted
+    }
+
+    /// Remove the least recently used entry
+    pub(crate) fn pop_lru(&mut self) -> Option<V> {
+        let (_, key) = self.order.pop_first()?;
+        self.entries.remove(&key).map(|(v, _)| v)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lru() {
+        let mut cache = LruCache::new(2);
+        assert!(cache.insert(1, "a").is_empty());
+        assert!(cache.insert(2, "b").is_empty());
+        assert_eq!(cache.get(&1), Some(&"a"));
+        assert_eq!(cache.insert(3, "c"), vec!["b"]);
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.insert(1, "d"), vec!["a"]);
+        assert_eq!(cache.pop_lru(), Some("c"));
+        cache.clear();
+        assert_eq!(cache.pop_lru(), None);
+        let mut cache = LruCache::new(0);
+        assert_eq!(cache.insert(1, "a"), vec!["a"]);
+        assert_eq!(cache.capacity(), 0);
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut cache = LruCache::new(2);
+        assert!(cache.insert(1, "a").is_empty());
+        assert!(cache.insert(2, "b").is_empty());
+        *cache.get_mut(&1).unwrap() = "e";
+        assert_eq!(cache.get(&1), Some(&"e"));
+        assert_eq!(cache.get_mut(&3), None);
+        assert_eq!(cache.insert(3, "c"), vec!["b"]);
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Backends of the primality tests behind the trait [PrimalityTest]
+
+use super::{
+    FALLBACK_REPS, TRIAL_DIVISION_PRIMES, TrialDivisionResult, checked_reps,
+    is_prime_deterministic, miller_rabin, miller_rabin_safe, strong_probable_prime, trial_division,
+};
+use crate::GmpMEEError;
+use rug::Integer;
+use std::num::NonZeroU32;
+
+/// Primality test of an integer
+///
+/// The code validating parameters can be written once for any backend, which can be swapped or
+/// mocked in tests. A closure `Fn(&Integer) -> bool` is a primality test.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::{BailliePSW, DeterministicTest, PrimalityTest};
+/// fn check_modulus(test: &impl PrimalityTest, p: &Integer) -> bool {
+///     p.significant_bits() >= 10 && test.is_safe_prime(p)
+/// }
+/// let p = Integer::from(1019);
+/// assert!(check_modulus(&BailliePSW, &p));
+/// assert!(check_modulus(&DeterministicTest, &p));
+/// assert!(!check_modulus(&|_: &Integer| false, &p));
+/// ```
+pub trait PrimalityTest {
+    /// Return `true` if `n` is (probably) prime
+    fn is_prime(&self, n: &Integer) -> bool;
+
+    /// Return `true` if `p` and `(p-1)/2` are (probably) prime
+    fn is_safe_prime(&self, p: &Integer) -> bool {
+        *p > 4 && p.is_odd() && self.is_prime(p) && self.is_prime(&Integer::from(p >> 1))
+    }
+}
+
+impl<F: Fn(&Integer) -> bool> PrimalityTest for F {
+    fn is_prime(&self, n: &Integer) -> bool {
+        self(n)
+    }
+}
+
+/// Miller-Rabin test of GMPMEE with a fixed number of rounds (see [miller_rabin])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MillerRabinTest {
+    reps: i32,
+}
+
+impl MillerRabinTest {
+    /// New test with `reps` rounds
+    ///
+    /// Return an error if `reps` is greater than [super::MAX_REPS].
+    pub fn new(reps: NonZeroU32) -> Result<Self, GmpMEEError> {
+        Ok(Self {
+            reps: checked_reps(reps)?,
+        })
+    }
+
+    /// Number of rounds of the test
+    pub fn reps(&self) -> i32 {
+        self.reps
+    }
+}
+
+impl Default for MillerRabinTest {
+    /// Test with [FALLBACK_REPS] rounds
+    fn default() -> Self {
+        Self {
+            reps: FALLBACK_REPS,
+        }
+    }
+}
+
+impl PrimalityTest for MillerRabinTest {
+    fn is_prime(&self, n: &Integer) -> bool {
+        miller_rabin(n, self.reps)
+    }
+
+    fn is_safe_prime(&self, p: &Integer) -> bool {
+        miller_rabin_safe(p, self.reps)
+    }
+}
+
+/// Test with fixed witnesses, deterministic under [super::DETERMINISTIC_BOUND] (see
+/// [is_prime_deterministic])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeterministicTest;
+
+impl PrimalityTest for DeterministicTest {
+    fn is_prime(&self, n: &Integer) -> bool {
+        is_prime_deterministic(n)
+    }
+}
+
+/// Test of Baillie-PSW: a strong probable prime test to the base 2 followed by a strong Lucas
+/// probable prime test with the parameters of Selfridge
+///
+/// No composite passing the test is known, and there is none below `2^64`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BailliePSW;
+
+impl PrimalityTest for BailliePSW {
+    fn is_prime(&self, n: &Integer) -> bool {
+        if *n < 2 {
+            return false;
+        }
+        if n.is_even() {
+            return *n == 2;
+        }
+        match trial_division(n, TRIAL_DIVISION_PRIMES) {
+            TrialDivisionResult::Prime => true,
+            TrialDivisionResult::Composite { .. } => false,
+            TrialDivisionResult::Inconclusive => {
+                strong_probable_prime(n, &[Integer::from(2)]) && strong_lucas(n)
+            }
+        }
+    }
+}
+
+/// `x mod n` in `[0, n[`
+fn reduce(x: Integer, n: &Integer) -> Integer {
+    let mut x = x % n;
+    if x < 0 {
+        x += n;
+    }
+    x
+}
+
+/// `x/2 mod n` for an odd `n`
+fn half(x: Integer, n: &Integer) -> Integer {
+    let x = reduce(x, n);
+    match x.is_odd() {
+        true => (x + n) >> 1u32,
+        false => x >> 1u32,
+    }
+}
+
+/// Strong Lucas probable prime test of the odd `n > 5`, with `P = 1` and `Q = (1-D)/4` where `D` is
+/// the first of `5, -7, 9, -11, ...` with the Jacobi symbol `(D/n) = -1`
+fn strong_lucas(n: &Integer) -> bool {
+    // No D exists for a square
+    if n.is_perfect_square() {
+        return false;
+    }
+    let mut d = 5i64;
+    loop {
+        match Integer::from(d).jacobi(n) {
+            -1 => break,
+            // |D| < n, so that D and n have a common factor
+            0 if *n > d.unsigned_abs() => return false,
+            _ => d = if d > 0 { -d - 2 } else { -d + 2 },
+        }
+    }
+    let q = (1 - d) / 4;
+    // n+1 = k·2^s with k odd
+    let n_plus_1 = Integer::from(n + 1u32);
+    let s = n_plus_1.find_one(0).unwrap_or(0);
+    let k = n_plus_1 >> s;
+    // U_j, V_j and Q^j mod n, from j = 1 to j = k with the bits of k
+    let (mut u, mut v, mut qj) = (
+        Integer::from(1),
+        Integer::from(1),
+        reduce(Integer::from(q), n),
+    );
+    for i in (0..k.significant_bits() - 1).rev() {
+        u = Integer::from(&u * &v) % n;
+        v = reduce(v.square() - Integer::from(&qj << 1u32), n);
+        qj = qj.square() % n;
+        if k.get_bit(i) {
+            let next_u = half(Integer::from(&u + &v), n);
+            v = half(d * u + v, n);
+            u = next_u;
+            qj = reduce(qj * q, n);
+        }
+    }
+    if u == 0 {
+        return true;
+    }
+    // V_(k·2^r) for r in [0, s[
+    for _ in 0..s {
+        if v == 0 {
+            return true;
+        }
+        v = reduce(v.square() - Integer::from(&qj << 1u32), n);
+        qj = qj.square() % n;
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::miller_rabin::{MAX_REPS, small_primes};
+
+    #[test]
+    fn test_strong_lucas() {
+        // Strong Lucas pseudoprimes below 30000 (OEIS A217255)
+        let pseudoprimes = [5459, 5777, 10877, 16109, 18971, 22499, 24569, 25199];
+        let small = small_primes();
+        for n in (7u32..30000).step_by(2) {
+            let expected = small.binary_search(&n).is_ok() || pseudoprimes.contains(&n);
+            assert_eq!(strong_lucas(&Integer::from(n)), expected, "{n}");
+        }
+    }
+
+    #[test]
+    fn test_backends() {
+        let tests: [&dyn PrimalityTest; 4] = [
+            &MillerRabinTest::default(),
+            &MillerRabinTest::new(NonZeroU32::new(16).unwrap()).unwrap(),
+            &DeterministicTest,
+            &BailliePSW,
+        ];
+        let prime = (Integer::from(1) << 127) - 1u32;
+        // Strong pseudoprime to the first 12 prime bases
+        let composite = Integer::from(318_665_857_834_031_151_167_461u128);
+        for test in tests {
+            assert!(test.is_prime(&Integer::from(2)));
+            assert!(test.is_prime(&prime));
+            assert!(test.is_safe_prime(&Integer::from(1019)));
+            assert!(!test.is_safe_prime(&Integer::from(1013)));
+            assert!(!test.is_prime(&Integer::from(1)));
+            assert!(!test.is_prime(&composite));
+            assert!(!test.is_prime(&Integer::from(5459)));
+        }
+        assert!(BailliePSW.is_prime(&Integer::from(1_000_003)));
+        assert!(!BailliePSW.is_prime(&Integer::from(1_000_003u64 * 1_000_033)));
+        assert!(MillerRabinTest::new(NonZeroU32::new(MAX_REPS + 1).unwrap()).is_err());
+        let mock = |n: &Integer| *n == 7 || *n == 3;
+        assert!(mock.is_safe_prime(&Integer::from(7)));
+        assert!(!mock.is_safe_prime(&Integer::from(11)));
+    }
+}
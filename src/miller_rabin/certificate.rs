@@ -0,0 +1,275 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Primality certificates of Pocklington (see [PrimalityCertificate])
+
+use super::{DETERMINISTIC_BOUND, is_prime_deterministic, miller_rabin_auto, small_primes};
+use crate::GmpMEEError;
+use rug::Integer;
+use thiserror::Error;
+
+/// Maximal number of bases tried for the witness of a prime factor
+const MAX_WITNESS_TRIES: u32 = 1000;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CertificateError {
+    #[error("{n} is not prime")]
+    NotPrime { n: Integer },
+    #[error("The factorization of {n}-1 found by trial division is not large enough")]
+    NotEnoughFactors { n: Integer },
+}
+
+/// Certificate of the primality of a number `n`, which can be verified by anybody without trusting
+/// a probabilistic test:
+/// - Under [DETERMINISTIC_BOUND], the deterministic test with the fixed witnesses is a proof.
+/// - Otherwise the certificate uses the theorem of Pocklington: if `n-1 = F·R` with `F > sqrt(n)`
+///   and, for each prime `q` dividing `F`, there is a witness `a` with `a^(n-1) = 1 mod n` and
+///   `gcd(a^((n-1)/q) - 1, n) = 1`, then `n` is prime. The primes `q` have their own certificates.
+///   With `F = n-1`, this is a certificate of Pratt.
+///
+/// The generation needs to factor `n-1` far enough. It divides `n-1` by the small primes (see
+/// [small_primes]) and accepts at most one large prime cofactor, certified recursively. This is the
+/// case of the primes constructed for that purpose (e.g. with the method of Maurer), but not of an
+/// arbitrary safe prime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrimalityCertificate {
+    /// `n` is smaller than [DETERMINISTIC_BOUND] and is proven by the deterministic test
+    Small { n: Integer },
+    /// `n` is proven by the theorem of Pocklington with the prime factors of `n-1`
+    Pocklington {
+        n: Integer,
+        factors: Vec<PocklingtonFactor>,
+    },
+}
+
+/// Prime factor `q` of `n-1` in a certificate of Pocklington, with the witness `a`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PocklingtonFactor {
+    /// Certificate of the prime factor
+    pub certificate: PrimalityCertificate,
+    /// Witness `a` with `a^(n-1) = 1 mod n` and `gcd(a^((n-1)/q) - 1, n) = 1`
+    pub witness: Integer,
+}
+
+fn is_small(n: &Integer) -> bool {
+    n.to_u128().is_some_and(|v| v < DETERMINISTIC_BOUND)
+}
+
+/// Check the conditions of Pocklington for the prime factor `q` of `n-1` and the witness `a`
+fn is_witness(n: &Integer, n_minus_1: &Integer, q: &Integer, a: &Integer) -> bool {
+    let Some(y) = a.pow_mod_ref(n_minus_1, n).map(Integer::from) else {
+        return false;
+    };
+    let e = Integer::from(n_minus_1 / q);
+    let z = Integer::from(a.pow_mod_ref(&e, n).unwrap()) - 1u32;
+    y == 1 && z.gcd(n) == 1
+}
+
+impl PrimalityCertificate {
+    /// Generate the certificate of `n`
+    ///
+    /// Return an error if `n` is not prime, or if `n-1` cannot be factored far enough (see
+    /// [PrimalityCertificate]).
+    /// ```
+    /// use rug::Integer;
+    /// use rug_gmpmee::miller_rabin::PrimalityCertificate;
+    /// // 2^89 - 1 is prime and 2^89 - 2 = 2 * 3 * 5 * 17 * 23 * 89 * 353 * 397 * 683 * 2113 * 2931542417
+    /// let p = (Integer::from(1) << 89) - 1u32;
+    /// let certificate = PrimalityCertificate::generate(&p).unwrap();
+    /// assert!(certificate.verify());
+    /// assert!(PrimalityCertificate::generate(&(p + 2u32)).is_err());
+    /// ```
+    pub fn generate(n: &Integer) -> Result<Self, GmpMEEError> {
+        if is_small(n) {
+            return match is_prime_deterministic(n) {
+                true => Ok(Self::Small { n: n.clone() }),
+                false => Err(CertificateError::NotPrime { n: n.clone() }.into()),
+            };
+        }
+        if !miller_rabin_auto(n) {
+            return Err(CertificateError::NotPrime { n: n.clone() }.into());
+        }
+        let n_minus_1 = Integer::from(n - 1u32);
+        let mut rest = n_minus_1.clone();
+        let mut certificates = vec![];
+        for &d in small_primes() {
+            if rest == 1 {
+                break;
+            }
+            if rest.is_divisible_u(d) {
+                rest = rest.remove_factor(&Integer::from(d)).0;
+                certificates.push(Self::Small {
+                    n: Integer::from(d),
+                });
+            }
+        }
+        if rest > 1
+            && miller_rabin_auto(&rest)
+            && let Ok(certificate) = Self::generate(&rest)
+        {
+            certificates.push(certificate);
+            rest = Integer::from(1);
+        }
+        let f = Integer::from(&n_minus_1 / &rest);
+        if Integer::from(f.square_ref()) <= *n {
+            return Err(CertificateError::NotEnoughFactors { n: n.clone() }.into());
+        }
+        let factors = certificates
+            .into_iter()
+            .map(|certificate| {
+                (2..MAX_WITNESS_TRIES + 2)
+                    .map(Integer::from)
+                    .find(|a| is_witness(n, &n_minus_1, certificate.n(), a))
+                    .map(|witness| PocklingtonFactor {
+                        certificate,
+                        witness,
+                    })
+                    .ok_or_else(|| CertificateError::NotPrime { n: n.clone() })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::Pocklington {
+            n: n.clone(),
+            factors,
+        })
+    }
+
+    /// Number whose primality is certified
+    pub fn n(&self) -> &Integer {
+        match self {
+            Self::Small { n } | Self::Pocklington { n, .. } => n,
+        }
+    }
+
+    /// Verify the certificate, recursively for the prime factors
+    pub fn verify(&self) -> bool {
+        match self {
+            Self::Small { n } => is_small(n) && is_prime_deterministic(n),
+            Self::Pocklington { n, factors } => {
+                if *n < 3 || n.is_even() {
+                    return false;
+                }
+                let n_minus_1 = Integer::from(n - 1u32);
+                // A factor appearing twice does not divide the rest
+                let mut rest = n_minus_1.clone();
+                for factor in factors {
+                    let q = factor.certificate.n();
+                    if !rest.is_divisible(q)
+                        || !factor.certificate.verify()
+                        || !is_witness(n, &n_minus_1, q, &factor.witness)
+                    {
+                        return false;
+                    }
+                    rest = rest.remove_factor(q).0;
+                }
+                Integer::from(&n_minus_1 / &rest).square() > *n
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::miller_rabin::miller_rabin;
+
+    /// Smallest prime `k·m + 1` with an even `k`
+    fn prime_from(m: &Integer) -> Integer {
+        (1u32..)
+            .map(|k| Integer::from(m * (2 * k)) + 1u32)
+            .find(|n| miller_rabin(n, 40))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_certificate() {
+        // p - 1 is a product of small primes
+        let smooth = Integer::from(Integer::primorial(110));
+        let p = prime_from(&smooth);
+        assert!(p.significant_bits() > 128);
+        let certificate = PrimalityCertificate::generate(&p).unwrap();
+        assert!(matches!(
+            certificate,
+            PrimalityCertificate::Pocklington { .. }
+        ));
+        assert_eq!(certificate.n(), &p);
+        assert!(certificate.verify());
+        // q - 1 has the large prime factor p, certified recursively
+        let q = prime_from(&p);
+        let certificate = PrimalityCertificate::generate(&q).unwrap();
+        assert!(certificate.verify());
+        let PrimalityCertificate::Pocklington { factors, .. } = &certificate else {
+            panic!("Pocklington certificate expected");
+        };
+        assert!(factors.iter().any(|f| f.certificate.n() == &p));
+        let small = PrimalityCertificate::generate(&Integer::from(1019)).unwrap();
+        assert_eq!(
+            small,
+            PrimalityCertificate::Small {
+                n: Integer::from(1019)
+            }
+        );
+        assert!(small.verify());
+    }
+
+    #[test]
+    fn test_certificate_errors() {
+        // 2^127 - 2 has the factors 92737, 649657 and 77158673929 above the small primes
+        let mersenne = (Integer::from(1) << 127) - 1u32;
+        assert!(matches!(
+            PrimalityCertificate::generate(&mersenne),
+            Err(GmpMEEError::Certificate(
+                CertificateError::NotEnoughFactors { .. }
+            ))
+        ));
+        assert!(matches!(
+            PrimalityCertificate::generate(&Integer::from(1023)),
+            Err(GmpMEEError::Certificate(CertificateError::NotPrime { .. }))
+        ));
+        assert!(PrimalityCertificate::generate(&(mersenne + 2u32)).is_err());
+    }
+
+    #[test]
+    fn test_forged_certificate() {
+        let p = prime_from(&Integer::from(Integer::primorial(110)));
+        let certificate = PrimalityCertificate::generate(&p).unwrap();
+        let PrimalityCertificate::Pocklington { n, mut factors } = certificate else {
+            panic!("Pocklington certificate expected");
+        };
+        let forged = PrimalityCertificate::Pocklington {
+            n: Integer::from(&n + 2u32),
+            factors: factors.clone(),
+        };
+        assert!(!forged.verify());
+        factors[0].witness = Integer::from(1);
+        assert!(
+            !PrimalityCertificate::Pocklington {
+                n: n.clone(),
+                factors: factors.clone()
+            }
+            .verify()
+        );
+        factors.truncate(1);
+        assert!(!PrimalityCertificate::Pocklington { n, factors }.verify());
+        assert!(
+            !PrimalityCertificate::Small {
+                n: Integer::from(1023)
+            }
+            .verify()
+        );
+        let large = Integer::from(DETERMINISTIC_BOUND) + 1u32;
+        assert!(!PrimalityCertificate::Small { n: large }.verify());
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Generation of the domain parameters `(p, q)` of DSA or Schnorr groups from a seed (see
+//! [DomainParameters])
+
+use super::{MillerRabinError, miller_rabin};
+use crate::GmpMEEError;
+use rug::{Integer, integer::Order, rand::RandState};
+use sha2::{Digest, Sha256};
+
+/// Bit length of the output of the hash function (SHA-256)
+pub(super) const OUTLEN: u32 = 256;
+
+/// Domain parameters `(p, q)` with `q` dividing `p-1`, generated from a seed with the method of
+/// FIPS 186-4 (appendix A.1.1.2) and SHA-256
+///
+/// The seed and the counter of the candidate `p` permit anybody to verify that the parameters were
+/// derived from the seed (see [Self::verify]).
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::miller_rabin::DomainParameters;
+/// let mut rand = RandState::new();
+/// let params = DomainParameters::generate(512, 160, 16, &mut rand).unwrap();
+/// assert_eq!(params.p.significant_bits(), 512);
+/// assert_eq!(params.q.significant_bits(), 160);
+/// assert!(Integer::from(&params.p - 1u32).is_divisible(&params.q));
+/// assert!(params.verify(16));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainParameters {
+    /// Prime modulus of `l` bits
+    pub p: Integer,
+    /// Prime of `n` bits dividing `p-1`
+    pub q: Integer,
+    /// Domain parameter seed
+    pub seed: Vec<u8>,
+    /// Counter of the candidate `p`, in `[0, 4l[`
+    pub counter: u32,
+}
+
+/// Check the bit lengths `l` of `p` and `n` of `q` and the bit length of the seed
+fn check_sizes(l: u32, n: u32, seedlen: u32) -> Result<(), GmpMEEError> {
+    match (2..=OUTLEN).contains(&n) && l > n && seedlen >= n {
+        true => Ok(()),
+        false => Err(MillerRabinError::InvalidDomainSizes { l, n, seedlen }.into()),
+    }
+}
+
+/// `value mod 2^seedlen` written on `seedlen` bits, with `seedlen` a multiple of 8
+pub(super) fn seed_bytes(value: &Integer, seedlen: u32) -> Vec<u8> {
+    let digits = Integer::from(value.keep_bits_ref(seedlen)).to_digits::<u8>(Order::Msf);
+    let mut bytes = vec![0; seedlen as usize / 8 - digits.len()];
+    bytes.extend(digits);
+    bytes
+}
+
+/// SHA-256 of `value mod 2^seedlen` written on `seedlen` bits (see [seed_bytes])
+pub(super) fn hash(value: &Integer, seedlen: u32) -> Integer {
+    Integer::from_digits(&Sha256::digest(seed_bytes(value, seedlen))[..], Order::Msf)
+}
+
+impl DomainParameters {
+    /// Generate domain parameters with `p` of `l` bits and `q` of `n` bits, with `reps` rounds of
+    /// Miller-Rabin for the primality of `p` and `q`
+    ///
+    /// The seeds of `n` bits (rounded up to a multiple of 8) are drawn from `rand` until the
+    /// parameters are found. Return an error if `n` is not in `[2, 256]` or if `l <= n`.
+    pub fn generate(
+        l: u32,
+        n: u32,
+        reps: i32,
+        rand: &mut RandState<'_>,
+    ) -> Result<Self, GmpMEEError> {
+        let seedlen = 8 * n.div_ceil(8);
+        check_sizes(l, n, seedlen)?;
+        loop {
+            let seed = seed_bytes(&Integer::from(Integer::random_bits(seedlen, rand)), seedlen);
+            if let Some(params) = Self::from_seed(l, n, &seed, reps)? {
+                return Ok(params);
+            }
+        }
+    }
+
+    /// Derive the domain parameters with `p` of `l` bits and `q` of `n` bits from the seed
+    ///
+    /// Return `None` if the `q` derived from the seed is not prime, or if no candidate `p` is prime
+    /// among the `4l` candidates. Return an error if `n` is not in `[2, 256]`, if `l <= n` or if
+    /// the seed has less than `n` bits.
+    pub fn from_seed(l: u32, n: u32, seed: &[u8], reps: i32) -> Result<Option<Self>, GmpMEEError> {
+        let seedlen = u32::try_from(8 * seed.len()).unwrap_or(u32::MAX);
+        check_sizes(l, n, seedlen)?;
+        let seed_value = Integer::from_digits(seed, Order::Msf);
+        let u = hash(&seed_value, seedlen).keep_bits(n - 1);
+        let q = (Integer::from(1) << (n - 1)) + 1u32 + u.clone() - u32::from(u.is_odd());
+        if !miller_rabin(&q, reps) {
+            return Ok(None);
+        }
+        // p is made of blocks+1 outputs of the hash, the last one truncated to last_bits bits
+        let blocks = l.div_ceil(OUTLEN) - 1;
+        let last_bits = l - 1 - blocks * OUTLEN;
+        let lower = Integer::from(1) << (l - 1);
+        let two_q = Integer::from(&q << 1u32);
+        let mut offset = Integer::from(&seed_value + 1u32);
+        for counter in 0..4 * l {
+            let mut w = Integer::new();
+            for j in 0..=blocks {
+                let v = hash(&offset, seedlen);
+                offset += 1u32;
+                w += match j == blocks {
+                    true => v.keep_bits(last_bits),
+                    false => v,
+                } << (j * OUTLEN);
+            }
+            let x = w + &lower;
+            let c = Integer::from(&x % &two_q);
+            let p = x - c + 1u32;
+            if p >= lower && miller_rabin(&p, reps) {
+                return Ok(Some(Self {
+                    p,
+                    q,
+                    seed: seed.to_vec(),
+                    counter,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Verify that the parameters are derived from the seed, with `reps` rounds of Miller-Rabin
+    ///
+    /// The bit lengths of `p` and `q` give `l` and `n`.
+    pub fn verify(&self, reps: i32) -> bool {
+        let (l, n) = (self.p.significant_bits(), self.q.significant_bits());
+        matches!(Self::from_seed(l, n, &self.seed, reps), Ok(Some(params)) if params == *self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_domain_parameters() {
+        let mut rand = RandState::new();
+        for (l, n) in [(512, 160), (1024, 160), (600, 224)] {
+            let params = DomainParameters::generate(l, n, 16, &mut rand).unwrap();
+            assert_eq!(params.p.significant_bits(), l);
+            assert_eq!(params.q.significant_bits(), n);
+            assert_eq!(params.seed.len() as u32 * 8, n);
+            assert!(params.counter < 4 * l);
+            assert!(Integer::from(&params.p - 1u32).is_divisible(&params.q));
+            assert!(miller_rabin(&params.p, 16) && miller_rabin(&params.q, 16));
+            assert!(params.verify(16));
+            let mut forged = params.clone();
+            forged.counter += 1;
+            assert!(!forged.verify(16));
+            let mut forged = params.clone();
+            forged.seed[0] ^= 1;
+            assert!(!forged.verify(16));
+            let mut forged = params;
+            forged.p += Integer::from(&forged.q << 1u32);
+            assert!(!forged.verify(16));
+        }
+        for (l, n, seedlen) in [
+            (512, 1, 8),
+            (512, 257, 264),
+            (160, 160, 160),
+            (512, 160, 152),
+        ] {
+            assert_eq!(
+                DomainParameters::from_seed(l, n, &vec![0; seedlen as usize / 8], 16),
+                Err(MillerRabinError::InvalidDomainSizes { l, n, seedlen }.into())
+            );
+        }
+    }
+}
@@ -0,0 +1,580 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Generation of random safe primes, searched in parallel
+
+use super::{MillerRabinError, SafePrimeSearch, auto_reps, miller_rabin_with_rand, seeded_rand};
+use crate::{
+    GmpMEEError,
+    parallel::{THREADS, available_workers},
+    trace::timed_span,
+};
+use rug::{Integer, rand::RandState};
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Bit length of the seeds of the workers, drawn from the random state of the caller
+const SEED_BITS: u32 = 256;
+
+/// Number of candidates drawn per bit by [RsaPrimeGenerator] before giving up
+///
+/// About one odd candidate in `bits * ln(2) / 2` is prime, the bound is only reached when no
+/// prime of `bits` bits meets the constraints (e.g. 3 bits with `p = 3 mod 4` and `e = 3`).
+const ATTEMPTS_PER_BIT: u64 = 1000;
+
+/// Random safe prime of exactly `bits` bits, tested with `reps` rounds
+///
+/// The candidates are searched by one worker per available core (see [SafePrimeGenerator]). The
+/// workers are seeded from `rand`, however the result depends on which worker wins the race.
+///
+/// Return an error if `bits` is smaller than 3.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::miller_rabin::{miller_rabin_safe, random_safe_prime};
+/// let mut rand = RandState::new();
+/// let p = random_safe_prime(128, 16, &mut rand).unwrap();
+/// assert_eq!(p.significant_bits(), 128);
+/// assert!(miller_rabin_safe(&p, 16));
+/// ```
+pub fn random_safe_prime(
+    bits: u32,
+    reps: i32,
+    rand: &mut RandState<'_>,
+) -> Result<Integer, GmpMEEError> {
+    let outcome = SafePrimeGenerator::new(bits, reps).generate(rand)?;
+    Ok(outcome
+        .expect("a worker must have found a safe prime")
+        .prime)
+}
+
+/// Random safe prime like [random_safe_prime], or `None` if none has been found within `timeout`
+///
+/// The latency is bounded for the interactive tools and the services (see
+/// [SafePrimeGenerator::with_deadline]).
+/// ```
+/// use rug::rand::RandState;
+/// use rug_gmpmee::miller_rabin::random_safe_prime_with_deadline;
+/// use std::time::Duration;
+/// let mut rand = RandState::new();
+/// let p = random_safe_prime_with_deadline(64, 16, Duration::from_secs(60), &mut rand).unwrap();
+/// assert_eq!(p.unwrap().significant_bits(), 64);
+/// let p = random_safe_prime_with_deadline(4096, 16, Duration::ZERO, &mut rand).unwrap();
+/// assert_eq!(p, None);
+/// ```
+pub fn random_safe_prime_with_deadline(
+    bits: u32,
+    reps: i32,
+    timeout: Duration,
+    rand: &mut RandState<'_>,
+) -> Result<Option<Integer>, GmpMEEError> {
+    timed_span!(
+        "random_safe_prime",
+        bits = bits,
+        timeout_ms = timeout.as_millis() as u64
+    );
+    let outcome = SafePrimeGenerator::new(bits, reps)
+        .with_deadline(timeout)
+        .generate(rand)?;
+    Ok(outcome.map(|o| o.prime))
+}
+
+/// Outcome of a search of [SafePrimeGenerator]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafePrimeOutcome {
+    /// Safe prime found by the winning worker
+    pub prime: Integer,
+    /// Index of the winning worker
+    pub worker: usize,
+    /// Number of candidates tested by all the workers
+    pub candidates: u64,
+}
+
+/// State of a running search of [SafePrimeGenerator], reported to the progress callback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafePrimeProgress {
+    /// Number of candidates tested by all the workers
+    pub candidates: u64,
+    /// Time elapsed since the start of the search
+    pub elapsed: Duration,
+    /// Index of the worker reporting the progress
+    pub worker: usize,
+    /// Candidate tested by the worker
+    pub candidate: Integer,
+}
+
+/// Period (in candidates) and callback of the progress
+type Progress<'a> = (u64, &'a (dyn Fn(&SafePrimeProgress) + Sync));
+
+/// Parallel search of a random safe prime of exactly `bits` bits
+///
+/// Each worker searches from its own random starting points. The first worker finding a safe
+/// prime sets a shared flag, which stops the other workers. The search can also be cancelled by the
+/// caller with a flag (see [Self::with_cancel_flag]), and report its progress to a callback (see
+/// [Self::with_progress]).
+///
+/// On the targets without threads (`wasm32-unknown-unknown`), a single worker searches on the
+/// calling thread, whatever the number of workers set.
+/// ```
+/// use rug::rand::RandState;
+/// use rug_gmpmee::miller_rabin::SafePrimeGenerator;
+/// use std::num::NonZeroUsize;
+/// let outcome = SafePrimeGenerator::new(128, 16)
+///     .with_workers(NonZeroUsize::new(2).unwrap())
+///     .generate(&mut RandState::new())
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(outcome.prime.significant_bits(), 128);
+/// assert!(outcome.worker < 2 && outcome.candidates > 0);
+/// ```
+#[derive(Clone)]
+pub struct SafePrimeGenerator<'a> {
+    bits: u32,
+    reps: i32,
+    workers: usize,
+    cancel: Option<&'a AtomicBool>,
+    deadline: Option<Duration>,
+    progress: Option<Progress<'a>>,
+}
+
+impl<'a> SafePrimeGenerator<'a> {
+    /// Search of a safe prime of `bits` bits, tested with `reps` rounds, with one worker per
+    /// available core
+    pub fn new(bits: u32, reps: i32) -> Self {
+        Self {
+            bits,
+            reps,
+            workers: available_workers(),
+            cancel: None,
+            deadline: None,
+            progress: None,
+        }
+    }
+
+    /// Set the number of workers
+    pub fn with_workers(mut self, workers: NonZeroUsize) -> Self {
+        self.workers = workers.get();
+        self
+    }
+
+    /// Stop the search when `cancel` is set by the caller (e.g. from another thread)
+    pub fn with_cancel_flag(mut self, cancel: &'a AtomicBool) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Stop the search when `timeout` has elapsed since the start of [Self::generate]
+    pub fn with_deadline(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(timeout);
+        self
+    }
+
+    /// Call `callback` every `every` candidates tested by all the workers (e.g. to show the
+    /// progress or to detect a stall)
+    ///
+    /// The callback is called by the worker testing the candidate, which waits for its return.
+    /// ```
+    /// use rug::rand::RandState;
+    /// use rug_gmpmee::miller_rabin::{SafePrimeGenerator, SafePrimeProgress};
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// let calls = AtomicU64::new(0);
+    /// let callback = |_: &SafePrimeProgress| {
+    ///     calls.fetch_add(1, Ordering::Relaxed);
+    /// };
+    /// let outcome = SafePrimeGenerator::new(256, 16)
+    ///     .with_progress(10, &callback)
+    ///     .generate(&mut RandState::new())
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert_eq!(calls.load(Ordering::Relaxed), outcome.candidates / 10);
+    /// ```
+    pub fn with_progress(
+        mut self,
+        every: u64,
+        callback: &'a (dyn Fn(&SafePrimeProgress) + Sync),
+    ) -> Self {
+        self.progress = Some((every.max(1), callback));
+        self
+    }
+
+    /// Run the search, with the workers seeded from `rand`
+    ///
+    /// Return `None` if the search has been cancelled or has reached its deadline before a safe
+    /// prime has been found, and an error if `bits` is smaller than 3.
+    pub fn generate(
+        &self,
+        rand: &mut RandState<'_>,
+    ) -> Result<Option<SafePrimeOutcome>, GmpMEEError> {
+        let workers = match THREADS {
+            true => self.workers,
+            false => 1,
+        };
+        timed_span!("safe_prime_search", bits = self.bits, workers = workers);
+        if self.bits < 3 {
+            return Err(MillerRabinError::TooFewBits { bits: self.bits }.into());
+        }
+        if self.bits == 3 {
+            // The searches start at 11, the safe primes 5 and 7 are drawn directly
+            let prime = Integer::from(Integer::random_bits(1, rand)) * 2u32 + 5u32;
+            return Ok(Some(SafePrimeOutcome {
+                prime,
+                worker: 0,
+                candidates: 1,
+            }));
+        }
+        let seeds: Vec<Integer> = (0..workers)
+            .map(|_| Integer::from(Integer::random_bits(SEED_BITS, rand)))
+            .collect();
+        let found = AtomicBool::new(false);
+        let candidates = AtomicU64::new(0);
+        // The time is only read when it is needed, e.g. not on the targets without clock
+        let start = (self.deadline.is_some() || self.progress.is_some()).then(Instant::now);
+        let state = |worker| SearchState {
+            worker,
+            start,
+            found: &found,
+            candidates: &candidates,
+        };
+        let prime = match seeds.as_slice() {
+            [seed] => self.search(seed, state(0)).map(|prime| (0, prime)),
+            _ => thread::scope(|s| {
+                let handles: Vec<_> = seeds
+                    .iter()
+                    .enumerate()
+                    .map(|(worker, seed)| {
+                        let state = state(worker);
+                        s.spawn(move || self.search(seed, state))
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .enumerate()
+                    .find_map(|(worker, h)| h.join().unwrap().map(|prime| (worker, prime)))
+            }),
+        };
+        Ok(prime.map(|(worker, prime)| SafePrimeOutcome {
+            prime,
+            worker,
+            candidates: candidates.into_inner(),
+        }))
+    }
+
+    /// `true` if a worker has found a safe prime, if the caller has cancelled the search or if the
+    /// deadline has passed
+    fn is_stopped(&self, state: &SearchState) -> bool {
+        state.found.load(Ordering::Relaxed)
+            || self.cancel.is_some_and(|c| c.load(Ordering::Relaxed))
+            || self
+                .deadline
+                .zip(state.start)
+                .is_some_and(|(d, start)| start.elapsed() >= d)
+    }
+
+    /// Count the tested candidate, and call the progress callback if required
+    fn count_candidate(&self, state: &SearchState, candidate: &Integer) {
+        let candidates = state.candidates.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some((every, callback)) = self.progress
+            && candidates.is_multiple_of(every)
+        {
+            callback(&SafePrimeProgress {
+                candidates,
+                elapsed: state.start.map_or(Duration::ZERO, |start| start.elapsed()),
+                worker: state.worker,
+                candidate: candidate.clone(),
+            });
+        }
+    }
+
+    /// Search of a worker, from random starting points, until a safe prime is found or the search
+    /// is stopped
+    ///
+    /// Only the worker setting `found` returns its safe prime.
+    fn search(&self, seed: &Integer, state: SearchState) -> Option<Integer> {
+        let found = state.found;
+        let mut rand = seeded_rand(seed);
+        let top = Integer::from(1) << (self.bits - 1);
+        let mut prime = None;
+        'search: while !self.is_stopped(&state) {
+            let start = Integer::from(Integer::random_bits(self.bits - 1, &mut rand)) + &top;
+            let search_seed = Integer::from(Integer::random_bits(SEED_BITS, &mut rand));
+            let mut search =
+                SafePrimeSearch::with_rand(&start, self.reps, seeded_rand(&search_seed));
+            while search.candidate().significant_bits() <= self.bits {
+                if self.is_stopped(&state) {
+                    break 'search;
+                }
+                self.count_candidate(&state, search.candidate());
+                if let Some(p) = search.step() {
+                    prime = found
+                        .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
+                        .is_ok()
+                        .then_some(p);
+                    break 'search;
+                }
+            }
+        }
+        prime
+    }
+}
+
+/// State of the search shared by the workers
+#[derive(Clone, Copy)]
+struct SearchState<'s> {
+    worker: usize,
+    /// Start of the search, if the deadline or the progress need it
+    start: Option<Instant>,
+    found: &'s AtomicBool,
+    candidates: &'s AtomicU64,
+}
+
+/// Random prime `p` of exactly `bits` bits with `gcd(p-1, e) = 1`, e.g. a prime factor of an RSA
+/// modulus with the public exponent `e`
+///
+/// See [RsaPrimeGenerator] for the number of rounds and for the primes `p = 3 mod 4`.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::miller_rabin::{miller_rabin, random_prime_with_coprime};
+/// let e = Integer::from(65537);
+/// let p = random_prime_with_coprime(512, &e, &mut RandState::new()).unwrap();
+/// assert_eq!(p.significant_bits(), 512);
+/// assert!(miller_rabin(&p, 16));
+/// assert_eq!(Integer::from(&p - 1u32).gcd(&e), 1);
+/// ```
+pub fn random_prime_with_coprime(
+    bits: u32,
+    e: &Integer,
+    rand: &mut RandState<'_>,
+) -> Result<Integer, GmpMEEError> {
+    timed_span!(
+        "random_prime_with_coprime",
+        bits = bits,
+        e_bits = e.significant_bits()
+    );
+    RsaPrimeGenerator::new(bits, e).generate(rand)
+}
+
+/// Generator of random primes `p` of exactly `bits` bits with `gcd(p-1, e) = 1` for a public
+/// exponent `e`
+///
+/// The candidates are drawn at random until one is found, up to `1000 * bits` candidates. By
+/// default, they are tested with the number of rounds of [auto_reps] for `bits`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsaPrimeGenerator {
+    bits: u32,
+    e: Integer,
+    reps: i32,
+    blum: bool,
+}
+
+impl RsaPrimeGenerator {
+    /// Generator of primes of `bits` bits for the public exponent `e`
+    pub fn new(bits: u32, e: &Integer) -> Self {
+        Self {
+            bits,
+            e: e.clone(),
+            reps: auto_reps(bits),
+            blum: false,
+        }
+    }
+
+    /// Set the number of rounds of Miller-Rabin
+    pub fn with_reps(mut self, reps: i32) -> Self {
+        self.reps = reps;
+        self
+    }
+
+    /// Generate only primes `p = 3 mod 4` (e.g. for a Blum integer)
+    pub fn with_blum(mut self) -> Self {
+        self.blum = true;
+        self
+    }
+
+    /// Generate a prime with the candidates drawn from `rand`
+    ///
+    /// Return an error if `bits` is smaller than 2, if `e` is even or smaller than 3, or if no
+    /// prime has been found in `1000 * bits` candidates (e.g. if no prime of `bits` bits meets the
+    /// constraints).
+    pub fn generate(&self, rand: &mut RandState<'_>) -> Result<Integer, GmpMEEError> {
+        timed_span!("prime_search", bits = self.bits);
+        if self.bits < 2 {
+            return Err(MillerRabinError::TooFewPrimeBits { bits: self.bits }.into());
+        }
+        if self.e < 3 || self.e.is_even() {
+            return Err(MillerRabinError::InvalidPublicExponent { e: self.e.clone() }.into());
+        }
+        let top = Integer::from(1) << (self.bits - 1);
+        let low: u32 = if self.blum { 3 } else { 1 };
+        let attempts = ATTEMPTS_PER_BIT * u64::from(self.bits);
+        for _ in 0..attempts {
+            let p = Integer::from(Integer::random_bits(self.bits - 1, rand)) | &top | low;
+            if Integer::from(&p - 1u32).gcd(&self.e) == 1
+                && miller_rabin_with_rand(&p, self.reps, rand)
+            {
+                return Ok(p);
+            }
+        }
+        Err(MillerRabinError::NoCoprimePrime {
+            bits: self.bits,
+            attempts,
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::miller_rabin::{miller_rabin, miller_rabin_safe};
+
+    #[test]
+    fn test_random_safe_prime() {
+        let mut rand = RandState::new();
+        for bits in [4, 5, 6, 64, 256] {
+            let p = random_safe_prime(bits, 16, &mut rand).unwrap();
+            assert_eq!(p.significant_bits(), bits);
+            assert!(miller_rabin_safe(&p, 16));
+        }
+        assert_eq!(random_safe_prime(4, 16, &mut rand).unwrap(), 11);
+        assert_eq!(random_safe_prime(5, 16, &mut rand).unwrap(), 23);
+        for _ in 0..8 {
+            let p = random_safe_prime(3, 16, &mut rand).unwrap();
+            assert!(p == 5 || p == 7);
+            assert!(miller_rabin_safe(&p, 16));
+        }
+        assert_eq!(
+            random_safe_prime(2, 16, &mut rand),
+            Err(GmpMEEError::MillerRabinParameters(
+                MillerRabinError::TooFewBits { bits: 2 }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_generator() {
+        let mut rand = RandState::new();
+        for workers in [1, 3] {
+            let outcome = SafePrimeGenerator::new(64, 16)
+                .with_workers(NonZeroUsize::new(workers).unwrap())
+                .generate(&mut rand)
+                .unwrap()
+                .unwrap();
+            assert_eq!(outcome.prime.significant_bits(), 64);
+            assert!(miller_rabin_safe(&outcome.prime, 16));
+            assert!(outcome.worker < workers);
+            assert!(outcome.candidates >= 1);
+        }
+        let cancel = AtomicBool::new(true);
+        let generator = SafePrimeGenerator::new(256, 16).with_cancel_flag(&cancel);
+        assert_eq!(generator.generate(&mut rand), Ok(None));
+        cancel.store(false, Ordering::Relaxed);
+        assert!(generator.generate(&mut rand).unwrap().is_some());
+        assert!(SafePrimeGenerator::new(2, 16).generate(&mut rand).is_err());
+    }
+
+    #[test]
+    fn test_rsa_prime() {
+        let mut rand = RandState::new();
+        for (bits, e) in [(2, 3), (10, 3), (64, 65537), (512, 3)] {
+            let e = Integer::from(e);
+            for blum in [false, true] {
+                let mut generator = RsaPrimeGenerator::new(bits, &e).with_reps(16);
+                if blum {
+                    generator = generator.with_blum();
+                }
+                let p = generator.generate(&mut rand).unwrap();
+                assert_eq!(p.significant_bits(), bits);
+                assert!(miller_rabin(&p, 16));
+                assert_eq!(Integer::from(&p - 1u32).gcd(&e), 1);
+                assert!(!blum || p.mod_u(4) == 3);
+            }
+        }
+        assert_eq!(
+            random_prime_with_coprime(1, &Integer::from(3), &mut rand),
+            Err(MillerRabinError::TooFewPrimeBits { bits: 1 }.into())
+        );
+        for e in [1, 2, 65536] {
+            assert_eq!(
+                random_prime_with_coprime(64, &Integer::from(e), &mut rand),
+                Err(MillerRabinError::InvalidPublicExponent {
+                    e: Integer::from(e)
+                }
+                .into())
+            );
+        }
+        assert_eq!(
+            RsaPrimeGenerator::new(3, &Integer::from(3))
+                .with_blum()
+                .generate(&mut rand),
+            Err(MillerRabinError::NoCoprimePrime {
+                bits: 3,
+                attempts: 3000
+            }
+            .into())
+        );
+        assert_eq!(
+            random_prime_with_coprime(4, &Integer::from(15), &mut rand),
+            Err(MillerRabinError::NoCoprimePrime {
+                bits: 4,
+                attempts: 4000
+            }
+            .into())
+        );
+    }
+
+    #[test]
+    fn test_deadline() {
+        let mut rand = RandState::new();
+        let start = Instant::now();
+        let generator = SafePrimeGenerator::new(4096, 16).with_deadline(Duration::from_millis(100));
+        assert_eq!(generator.generate(&mut rand), Ok(None));
+        assert!(start.elapsed() < Duration::from_secs(30));
+        let p = random_safe_prime_with_deadline(128, 16, Duration::from_secs(600), &mut rand);
+        assert!(miller_rabin_safe(&p.unwrap().unwrap(), 16));
+        assert!(random_safe_prime_with_deadline(2, 16, Duration::ZERO, &mut rand).is_err());
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut rand = RandState::new();
+        let reports = std::sync::Mutex::new(vec![]);
+        let callback =
+            |progress: &SafePrimeProgress| reports.lock().unwrap().push(progress.clone());
+        let outcome = SafePrimeGenerator::new(256, 16)
+            .with_workers(NonZeroUsize::new(2).unwrap())
+            .with_progress(5, &callback)
+            .generate(&mut rand)
+            .unwrap()
+            .unwrap();
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(reports.len() as u64, outcome.candidates / 5);
+        let mut counts: Vec<u64> = reports.iter().map(|r| r.candidates).collect();
+        counts.sort();
+        assert!(
+            counts
+                .iter()
+                .enumerate()
+                .all(|(i, c)| *c == 5 * (i as u64 + 1))
+        );
+        for report in reports {
+            assert!(report.worker < 2);
+            assert_eq!(report.candidate.significant_bits(), 256);
+            assert!(report.elapsed <= Duration::from_secs(3600));
+        }
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Verification of the parameters of a group of prime order (see [verify_group] and
+//! [verify_subgroup])
+
+use super::miller_rabin;
+use rug::Integer;
+use std::fmt;
+
+/// Check of the parameters of a group made by [verify_group] or [verify_subgroup]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GroupCheck {
+    /// `p` is prime
+    PPrime,
+    /// `q = (p-1)/2`
+    QHalfOfP,
+    /// `q` is at least 3 and divides `p-1`
+    QDividesPMinusOne,
+    /// `q` is prime
+    QPrime,
+    /// `1 < g < p`
+    GeneratorInRange,
+    /// `g^q = 1 mod p`
+    GeneratorOrder,
+    /// `g^2 != 1 mod p`, i.e. `g` is not of order 1 or 2
+    GeneratorNotTrivial,
+}
+
+impl GroupCheck {
+    /// All the checks, in the order of the reports
+    pub const ALL: [GroupCheck; 7] = [
+        GroupCheck::PPrime,
+        GroupCheck::QHalfOfP,
+        GroupCheck::QDividesPMinusOne,
+        GroupCheck::QPrime,
+        GroupCheck::GeneratorInRange,
+        GroupCheck::GeneratorOrder,
+        GroupCheck::GeneratorNotTrivial,
+    ];
+}
+
+impl fmt::Display for GroupCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GroupCheck::PPrime => "p is not prime",
+            GroupCheck::QHalfOfP => "q is not (p-1)/2",
+            GroupCheck::QDividesPMinusOne => "q is smaller than 3 or does not divide p-1",
+            GroupCheck::QPrime => "q is not prime",
+            GroupCheck::GeneratorInRange => "g is not in ]1, p[",
+            GroupCheck::GeneratorOrder => "g^q is not 1 mod p",
+            GroupCheck::GeneratorNotTrivial => "g^2 is 1 mod p",
+        })
+    }
+}
+
+/// Report of [verify_group] or [verify_subgroup] with the checks which failed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupReport {
+    failed: Vec<GroupCheck>,
+}
+
+impl GroupReport {
+    /// `true` if all the checks passed
+    pub fn is_valid(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Checks which failed, in the order of [GroupCheck::ALL]
+    pub fn failed(&self) -> &[GroupCheck] {
+        &self.failed
+    }
+
+    /// `true` if the check passed
+    pub fn passed(&self, check: GroupCheck) -> bool {
+        !self.failed.contains(&check)
+    }
+
+    /// Report with the given failed checks, sorted in the order of [GroupCheck::ALL]
+    #[cfg(feature = "json")]
+    pub(crate) fn from_failed(failed: &[GroupCheck]) -> Self {
+        report(
+            GroupCheck::ALL
+                .into_iter()
+                .map(|c| (c, !failed.contains(&c))),
+        )
+    }
+}
+
+impl fmt::Display for GroupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.failed.as_slice() {
+            [] => f.write_str("valid group"),
+            failed => {
+                let msgs: Vec<String> = failed.iter().map(|c| c.to_string()).collect();
+                f.write_str(&msgs.join(", "))
+            }
+        }
+    }
+}
+
+/// Verify the parameters `(p, q, g)` of the subgroup of order `q` of the integers modulo the safe
+/// prime `p`, with `reps` rounds of Miller-Rabin for the primality of `p` and `q`
+///
+/// All the checks of [GroupCheck] but [GroupCheck::QDividesPMinusOne], implied by
+/// [GroupCheck::QHalfOfP], are made, so that the report contains all the failures. The checks of
+/// `g` fail if `p < 3`.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::{GroupCheck, verify_group};
+/// let (p, q) = (Integer::from(1019), Integer::from(509));
+/// assert!(verify_group(&p, &q, &Integer::from(4), 16).is_valid());
+/// // 2 is not a quadratic residue modulo 1019, so its order is 1018
+/// let report = verify_group(&p, &q, &Integer::from(2), 16);
+/// assert_eq!(report.failed(), &[GroupCheck::GeneratorOrder]);
+/// ```
+pub fn verify_group(p: &Integer, q: &Integer, g: &Integer, reps: i32) -> GroupReport {
+    let half = Integer::from(p - 1u32) >> 1u32;
+    report(
+        [
+            (GroupCheck::PPrime, miller_rabin(p, reps)),
+            (GroupCheck::QHalfOfP, *q == half),
+            (GroupCheck::QPrime, miller_rabin(q, reps)),
+        ]
+        .into_iter()
+        .chain(generator_checks(p, q, g)),
+    )
+}
+
+/// Verify the parameters `(p, q, g)` of the subgroup of prime order `q` dividing `p-1` of the
+/// integers modulo the prime `p`, e.g. for the groups of RFC 5114, with `reps` rounds of
+/// Miller-Rabin for the primality of `p` and `q`
+///
+/// The checks are those of [verify_group], with [GroupCheck::QDividesPMinusOne] instead of
+/// [GroupCheck::QHalfOfP].
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::{GroupCheck, verify_subgroup};
+/// // p-1 = 2*3*5*7
+/// let (p, q) = (Integer::from(211), Integer::from(7));
+/// assert!(verify_subgroup(&p, &q, &Integer::from(58), 16).is_valid());
+/// let report = verify_subgroup(&p, &Integer::from(11), &Integer::from(58), 16);
+/// assert_eq!(report.failed(), &[GroupCheck::QDividesPMinusOne, GroupCheck::GeneratorOrder]);
+/// ```
+pub fn verify_subgroup(p: &Integer, q: &Integer, g: &Integer, reps: i32) -> GroupReport {
+    let divides = *q >= 3 && Integer::from(p - 1u32).is_divisible(q);
+    report(
+        [
+            (GroupCheck::PPrime, miller_rabin(p, reps)),
+            (GroupCheck::QDividesPMinusOne, divides),
+            (GroupCheck::QPrime, miller_rabin(q, reps)),
+        ]
+        .into_iter()
+        .chain(generator_checks(p, q, g)),
+    )
+}
+
+/// Checks of the generator `g` of the subgroup of order `q` modulo `p`, which fail if `p < 3`
+fn generator_checks(p: &Integer, q: &Integer, g: &Integer) -> [(GroupCheck, bool); 3] {
+    let modulus_ok = *p > 2;
+    let pow = |e: &Integer| modulus_ok.then(|| g.clone().pow_mod(e, p).ok()).flatten();
+    let g_order = pow(q);
+    let g_square = pow(&Integer::from(2));
+    [
+        (GroupCheck::GeneratorInRange, modulus_ok && *g > 1 && g < p),
+        (GroupCheck::GeneratorOrder, g_order.is_some_and(|r| r == 1)),
+        (
+            GroupCheck::GeneratorNotTrivial,
+            g_square.is_some_and(|r| r != 1),
+        ),
+    ]
+}
+
+/// Report of the checks which failed, given in the order of [GroupCheck::ALL]
+fn report(results: impl Iterator<Item = (GroupCheck, bool)>) -> GroupReport {
+    GroupReport {
+        failed: results
+            .filter_map(|(check, ok)| (!ok).then_some(check))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::standard_groups::StandardGroup;
+
+    #[test]
+    fn test_verify_group() {
+        let group = StandardGroup::Modp2048;
+        let report = verify_group(group.modulus(), group.order(), group.generator(), 16);
+        assert!(report.is_valid(), "{report}");
+        let (p, q) = (Integer::from(1019), Integer::from(509));
+        assert!(verify_group(&p, &q, &Integer::from(4), 16).is_valid());
+        let report = verify_group(&p, &q, &Integer::from(1018), 16);
+        assert_eq!(
+            report.failed(),
+            &[GroupCheck::GeneratorOrder, GroupCheck::GeneratorNotTrivial]
+        );
+        let report = verify_group(&p, &q, &Integer::from(1), 16);
+        assert_eq!(
+            report.failed(),
+            &[
+                GroupCheck::GeneratorInRange,
+                GroupCheck::GeneratorNotTrivial
+            ]
+        );
+        // g = 0 mod p is not of order 2
+        assert_eq!(
+            verify_group(&p, &q, &p, 16).failed(),
+            &[GroupCheck::GeneratorInRange, GroupCheck::GeneratorOrder]
+        );
+        // 1013 is prime but 506 is not
+        let report = verify_group(
+            &Integer::from(1013),
+            &Integer::from(506),
+            &Integer::from(4),
+            16,
+        );
+        assert!(report.passed(GroupCheck::PPrime) && report.passed(GroupCheck::QHalfOfP));
+        assert!(!report.passed(GroupCheck::QPrime));
+        let report = verify_group(&p, &Integer::from(511), &Integer::from(4), 16);
+        assert_eq!(
+            report.failed()[..2],
+            [GroupCheck::QHalfOfP, GroupCheck::QPrime]
+        );
+        assert!(
+            !verify_group(&p, &q, &Integer::from(4), 16)
+                .failed()
+                .contains(&GroupCheck::QDividesPMinusOne)
+        );
+        let report = verify_group(&Integer::from(0), &q, &Integer::from(4), 16);
+        assert!(!report.is_valid() && !report.passed(GroupCheck::GeneratorOrder));
+        assert_eq!(
+            report.to_string().split(", ").count(),
+            report.failed().len()
+        );
+    }
+
+    #[test]
+    fn test_verify_subgroup() {
+        let group = StandardGroup::Modp2048S224;
+        let report = verify_subgroup(group.modulus(), group.order(), group.generator(), 16);
+        assert!(report.is_valid(), "{report}");
+        let report = verify_group(group.modulus(), group.order(), group.generator(), 16);
+        assert_eq!(report.failed(), &[GroupCheck::QHalfOfP]);
+        let (p, q) = (Integer::from(211), Integer::from(7));
+        // 2 has order 210
+        assert_eq!(
+            verify_subgroup(&p, &q, &Integer::from(2), 16).failed(),
+            &[GroupCheck::GeneratorOrder]
+        );
+        // 2 divides 210 but is too small, 15 is not prime
+        assert_eq!(
+            verify_subgroup(&p, &Integer::from(2), &Integer::from(210), 16).failed(),
+            &[
+                GroupCheck::QDividesPMinusOne,
+                GroupCheck::GeneratorNotTrivial
+            ]
+        );
+        assert_eq!(
+            verify_subgroup(&p, &Integer::from(15), &Integer::from(58), 16).failed(),
+            &[GroupCheck::QPrime, GroupCheck::GeneratorOrder]
+        );
+    }
+}
@@ -0,0 +1,1983 @@
+use crate::sys::{
+    gmpmee_millerrabin_clear, gmpmee_millerrabin_init, gmpmee_millerrabin_next_cand,
+    gmpmee_millerrabin_once, gmpmee_millerrabin_reps_rs, gmpmee_millerrabin_rs,
+    gmpmee_millerrabin_safe_clear, gmpmee_millerrabin_safe_init,
+    gmpmee_millerrabin_safe_next_cand, gmpmee_millerrabin_safe_reps_rs,
+    gmpmee_millerrabin_safe_rs, gmpmee_millerrabin_safe_state, gmpmee_millerrabin_state,
+};
+use crate::{
+    parallel::{available_workers, THREADS},
+    trace::timed_span,
+    GmpMEEError,
+};
+use rug::{integer::IsPrime, rand::RandState, Assign, Integer};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    mem::MaybeUninit,
+    num::NonZeroU32,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+mod backend;
+mod certificate;
+mod domain;
+mod generate;
+mod group;
+mod provable;
+mod wheel;
+pub use backend::{BailliePSW, DeterministicTest, MillerRabinTest, PrimalityTest};
+pub use certificate::{CertificateError, PocklingtonFactor, PrimalityCertificate};
+pub use domain::DomainParameters;
+pub use generate::{
+    RsaPrimeGenerator, SafePrimeGenerator, SafePrimeOutcome, SafePrimeProgress,
+    random_prime_with_coprime, random_safe_prime, random_safe_prime_with_deadline,
+};
+pub use group::{GroupCheck, GroupReport, verify_group, verify_subgroup};
+pub use provable::ShaweTaylorPrime;
+pub use wheel::{DEFAULT_WHEEL_PRIMES, MAX_WHEEL_PRIMES, Wheel, WheelCandidates};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MillerRabinError {
+    #[error("Number of rounds {reps} is greater than the maximum {MAX_REPS}")]
+    TooManyReps { reps: u32 },
+    #[error("No safe prime of {bits} bits can be generated")]
+    TooFewBits { bits: u32 },
+    #[error("Error bound {max_error} is not in ]0, 1[")]
+    InvalidErrorBound { max_error: String },
+    #[error("Wheel of {primes} primes is not supported (1 to {MAX_WHEEL_PRIMES} primes)")]
+    InvalidWheel { primes: usize },
+    #[error("No prime of the required form has {bits} bits (at least 2 bits are required)")]
+    TooFewPrimeBits { bits: u32 },
+    #[error("Public exponent {e} is not odd and greater than 2")]
+    InvalidPublicExponent { e: Integer },
+    #[error("No prime of {bits} bits coprime to the exponent in {attempts} candidates")]
+    NoCoprimePrime { bits: u32, attempts: u64 },
+    #[error("The seed is empty")]
+    EmptySeed,
+    #[error(
+        "Domain parameters of {l} and {n} bits with a seed of {seedlen} bits are not supported"
+    )]
+    InvalidDomainSizes { l: u32, n: u32, seedlen: u32 },
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PrimalityError {
+    #[error("{n} is smaller than 2, which is neither prime nor composite")]
+    TooSmall { n: Integer },
+    #[error("Number of rounds {reps} is not in [1, {MAX_REPS}]")]
+    InvalidReps { reps: i32 },
+}
+
+/// Answer of [check_prime]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primality {
+    /// `n` is prime, proven by the deterministic test (under [DETERMINISTIC_BOUND])
+    Prime,
+    /// `n` passed all the rounds of the probabilistic test
+    ProbablyPrime,
+    /// `n` is composite
+    Composite,
+}
+
+/// Maximal number of rounds accepted by the functions taking a typed number of rounds (e.g.
+/// [try_miller_rabin])
+pub const MAX_REPS: u32 = 1000;
+
+/// Check the number of rounds and convert it for GMPMEE
+fn checked_reps(reps: NonZeroU32) -> Result<i32, GmpMEEError> {
+    match reps.get() {
+        r if r > MAX_REPS => Err(MillerRabinError::TooManyReps { reps: r }.into()),
+        r => Ok(r as i32),
+    }
+}
+
+/// Number of small primes used by the prefilter of the tests (see [trial_division])
+pub const TRIAL_DIVISION_PRIMES: usize = 256;
+
+/// Primes smaller than `2^16`, sieved at the first call (see [crate::small_primes])
+pub fn small_primes() -> &'static [u32] {
+    crate::small_primes::table()
+}
+
+/// Bound of the primes of the primorial used by [passes_primorial_filter]
+pub const PRIMORIAL_BOUND: u32 = 10_000;
+
+static PRIMORIAL: OnceLock<Integer> = OnceLock::new();
+
+/// Product of the primes smaller than [PRIMORIAL_BOUND], calculated at the first call
+fn primorial() -> &'static Integer {
+    PRIMORIAL.get_or_init(|| Integer::from(Integer::primorial(PRIMORIAL_BOUND - 1)))
+}
+
+/// Return `false` if `n` is divisible by a prime smaller than [PRIMORIAL_BOUND] other than `n`
+/// itself
+///
+/// A single gcd with the primorial replaces the divisions by each of the primes. The searches of
+/// primes ([MillerRabinSearch] and [SafePrimeSearch]) use it before calling GMPMEE.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::passes_primorial_filter;
+/// assert!(passes_primorial_filter(&Integer::from(1019)));
+/// assert!(!passes_primorial_filter(&Integer::from(9973 * 10007)));
+/// ```
+pub fn passes_primorial_filter(n: &Integer) -> bool {
+    Integer::from(n.gcd_ref(primorial())) == 1
+        || n.to_u32()
+            .is_some_and(|v| v < PRIMORIAL_BOUND && small_primes().binary_search(&v).is_ok())
+}
+
+/// Result of the trial division by the small primes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrialDivisionResult {
+    /// `n` is one of the small primes, or is smaller than the square of the last prime tried
+    Prime,
+    /// `n` is divisible by the small prime `divisor`
+    Composite { divisor: u32 },
+    /// No small prime divides `n`, which must be tested further
+    Inconclusive,
+}
+
+/// Trial division of `n` by the first `k` small primes (at most the number of [small_primes])
+///
+/// The tests of this module use it with [TRIAL_DIVISION_PRIMES] primes before calling GMPMEE, so
+/// that most of the composites are rejected without any exponentiation. `n < 2` is inconclusive.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::{TrialDivisionResult, trial_division};
+/// assert_eq!(
+///     trial_division(&Integer::from(1023), 10),
+///     TrialDivisionResult::Composite { divisor: 3 }
+/// );
+/// assert_eq!(trial_division(&Integer::from(1019), 10), TrialDivisionResult::Inconclusive);
+/// assert_eq!(trial_division(&Integer::from(1019), 20), TrialDivisionResult::Prime);
+/// ```
+pub fn trial_division(n: &Integer, k: usize) -> TrialDivisionResult {
+    if *n < 2 {
+        return TrialDivisionResult::Inconclusive;
+    }
+    let primes = small_primes();
+    let primes = &primes[..k.min(primes.len())];
+    for &p in primes {
+        if n.is_divisible_u(p) {
+            return match *n == p {
+                true => TrialDivisionResult::Prime,
+                false => TrialDivisionResult::Composite { divisor: p },
+            };
+        }
+    }
+    match primes.last() {
+        Some(&p) if *n < u64::from(p) * u64::from(p) => TrialDivisionResult::Prime,
+        _ => TrialDivisionResult::Inconclusive,
+    }
+}
+
+/// Result of a Miller-Rabin test reporting the witness of the compositeness
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MillerRabinResult {
+    /// `n` passed all the rounds
+    ProbablyPrime,
+    /// `n` failed the round with the base `witness`, which proves that `n` is composite
+    Composite { witness: Integer },
+    /// `n` is smaller than 2, i.e. neither prime nor composite
+    NotPrime,
+}
+
+impl MillerRabinResult {
+    /// `true` if `n` passed all the rounds
+    pub fn is_probably_prime(&self) -> bool {
+        matches!(self, Self::ProbablyPrime)
+    }
+
+    /// Witness of the compositeness, if `n` is composite
+    pub fn witness(&self) -> Option<&Integer> {
+        match self {
+            Self::Composite { witness } => Some(witness),
+            _ => None,
+        }
+    }
+}
+
+/// Statistics of a primality test or of a search of primes
+///
+/// The rounds are only counted by [miller_rabin_with_stats] and by the searches with the round
+/// statistics enabled (e.g. [MillerRabinSearch::with_round_stats]), which execute the rounds one by
+/// one instead of calling GMPMEE once for all the rounds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrimalityStats {
+    /// Number of candidates tested
+    pub candidates: u64,
+    /// Number of candidates rejected by the sieve (trial division or primorial) without any round
+    pub sieved: u64,
+    /// Number of rounds of Miller-Rabin executed
+    pub rounds: u64,
+}
+
+/// Wrap `gmpmee_millerrabin_once`, i.e. one round with the base `base`
+fn millerrabin_once(state: &mut gmpmee_millerrabin_state, base: &Integer) -> bool {
+    !matches!(
+        unsafe { gmpmee_millerrabin_once(state, base.as_raw()) },
+        0
+    )
+}
+
+/// `reps` rounds with bases drawn uniformly in `[2, n-2]` from `rand`, stopping at the first
+/// failure
+///
+/// The executed rounds are added to `rounds`. `n` must be at least 5.
+fn random_rounds(
+    state: &mut gmpmee_millerrabin_state,
+    reps: i32,
+    rand: &mut RandState<'_>,
+    rounds: &mut u64,
+) -> bool {
+    let n = unsafe { &*(&raw const state.n).cast::<Integer>() };
+    let bound = Integer::from(n - 3u32);
+    let mut base = Integer::new();
+    (0..reps).all(|_| {
+        *rounds += 1;
+        base.assign(bound.random_below_ref(rand));
+        base += 2u32;
+        millerrabin_once(state, &base)
+    })
+}
+
+/// Wrapper of `gmpmee_millerrabin_state`, cleared when dropped
+struct MillerRabinState(gmpmee_millerrabin_state);
+
+impl MillerRabinState {
+    /// Wrap `gmpmee_millerrabin_init`
+    fn init(n: &Integer) -> Self {
+        let mut state = MaybeUninit::uninit();
+        unsafe {
+            gmpmee_millerrabin_init(state.as_mut_ptr(), n.as_raw());
+            Self(state.assume_init())
+        }
+    }
+
+    /// Wrap `gmpmee_millerrabin_once`, i.e. one round with the base `base`
+    fn once(&mut self, base: &Integer) -> bool {
+        millerrabin_once(&mut self.0, base)
+    }
+
+    /// Wrap `gmpmee_millerrabin_reps_rs`, i.e. `reps` rounds with bases drawn from `rand`
+    fn reps(&mut self, reps: i32, rand: &mut RandState<'_>) -> bool {
+        !matches!(
+            unsafe { gmpmee_millerrabin_reps_rs(rand.as_raw_mut(), &mut self.0, reps) },
+            0
+        )
+    }
+
+    /// `reps` rounds with bases drawn from `rand`, counted in `rounds` (see [random_rounds])
+    fn rounds(&mut self, reps: i32, rand: &mut RandState<'_>, rounds: &mut u64) -> bool {
+        random_rounds(&mut self.0, reps, rand, rounds)
+    }
+
+    /// Wrap `gmpmee_millerrabin_next_cand`, i.e. move to the next odd candidate
+    fn next_cand(&mut self) {
+        unsafe { gmpmee_millerrabin_next_cand(&mut self.0) }
+    }
+
+    /// Candidate currently tested
+    fn candidate(&self) -> &Integer {
+        unsafe { &*(&raw const self.0.n).cast::<Integer>() }
+    }
+}
+
+impl Drop for MillerRabinState {
+    fn drop(&mut self) {
+        unsafe { gmpmee_millerrabin_clear(&mut self.0) }
+    }
+}
+
+/// Wrapper of `gmpmee_millerrabin_safe_state`, cleared when dropped
+struct SafeMillerRabinState(gmpmee_millerrabin_safe_state);
+
+impl SafeMillerRabinState {
+    /// Wrap `gmpmee_millerrabin_safe_init`
+    fn init(n: &Integer) -> Self {
+        let mut state = MaybeUninit::uninit();
+        unsafe {
+            gmpmee_millerrabin_safe_init(state.as_mut_ptr(), n.as_raw());
+            Self(state.assume_init())
+        }
+    }
+
+    /// Wrap `gmpmee_millerrabin_safe_reps_rs`, i.e. `reps` rounds for `n` and `(n-1)/2`
+    fn reps(&mut self, reps: i32, rand: &mut RandState<'_>) -> bool {
+        !matches!(
+            unsafe { gmpmee_millerrabin_safe_reps_rs(rand.as_raw_mut(), &mut self.0, reps) },
+            0
+        )
+    }
+
+    /// `reps` rounds for `n` and then for `(n-1)/2`, counted in `rounds` (see [random_rounds])
+    fn rounds(&mut self, reps: i32, rand: &mut RandState<'_>, rounds: &mut u64) -> bool {
+        random_rounds(&mut self.0.nstate, reps, rand, rounds)
+            && random_rounds(&mut self.0.mstate, reps, rand, rounds)
+    }
+
+    /// Wrap `gmpmee_millerrabin_safe_next_cand`, i.e. move to the next candidate
+    fn next_cand(&mut self) {
+        unsafe { gmpmee_millerrabin_safe_next_cand(&mut self.0) }
+    }
+
+    /// Candidate currently tested
+    fn candidate(&self) -> &Integer {
+        unsafe { &*(&raw const self.0.nstate.n).cast::<Integer>() }
+    }
+}
+
+impl Drop for SafeMillerRabinState {
+    fn drop(&mut self) {
+        unsafe { gmpmee_millerrabin_safe_clear(&mut self.0) }
+    }
+}
+
+thread_local! {
+    /// Random state of the functions without an explicit random state (e.g. [miller_rabin]),
+    /// initialized at its first use in each thread
+    static THREAD_RAND: RefCell<RandState<'static>> = RefCell::new(RandState::default());
+}
+
+/// Call `f` with the random state of the thread
+///
+/// A new random state is used if the state of the thread is already borrowed.
+fn with_thread_rand<T>(f: impl FnOnce(&mut RandState<'_>) -> T) -> T {
+    THREAD_RAND.with(|rand| match rand.try_borrow_mut() {
+        Ok(mut rand) => f(&mut rand),
+        Err(_) => f(&mut RandState::default()),
+    })
+}
+
+pub fn miller_rabin(n: &Integer, reps: i32) -> bool {
+    with_thread_rand(|rand| miller_rabin_with_rand(n, reps, rand))
+}
+
+pub fn miller_rabin_safe(n: &Integer, reps: i32) -> bool {
+    with_thread_rand(|rand| miller_rabin_safe_with_rand(n, reps, rand))
+}
+
+/// Answer of the tests for `n < 2` (not prime) and for an even `n` (prime only for 2), without
+/// calling GMPMEE
+fn trivial_answer(n: &Integer) -> Option<bool> {
+    if *n < 2 {
+        return Some(false);
+    }
+    n.is_even().then(|| *n == 2)
+}
+
+/// Miller-Rabin test of `n` with `reps` rounds, whose witnesses are drawn from `rand`
+///
+/// The random state can be seeded by the caller and reused for many tests. `n < 2` is not prime,
+/// and an even `n` is prime only if `n = 2`.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::miller_rabin::miller_rabin_with_rand;
+/// let mut rand = RandState::new();
+/// rand.seed(&Integer::from(42));
+/// assert!(miller_rabin_with_rand(&Integer::from(1019), 16, &mut rand));
+/// assert!(!miller_rabin_with_rand(&Integer::from(1023), 16, &mut rand));
+/// ```
+pub fn miller_rabin_with_rand(n: &Integer, reps: i32, rand: &mut RandState<'_>) -> bool {
+    if let Some(answer) = trivial_answer(n) {
+        return answer;
+    }
+    match trial_division(n, TRIAL_DIVISION_PRIMES) {
+        TrialDivisionResult::Prime => return true,
+        TrialDivisionResult::Composite { .. } => return false,
+        TrialDivisionResult::Inconclusive => {}
+    }
+    !matches!(
+        unsafe { gmpmee_millerrabin_rs(rand.as_raw_mut(), n.as_raw(), reps) },
+        0
+    )
+}
+
+/// Test if `n` is a safe prime, i.e. `n` and `(n-1)/2` are probably prime, with `reps` rounds whose
+/// witnesses are drawn from `rand`
+///
+/// The safe primes smaller than 8 (5 and 7) and the even `n` are answered without calling GMPMEE.
+pub fn miller_rabin_safe_with_rand(n: &Integer, reps: i32, rand: &mut RandState<'_>) -> bool {
+    if let Some(answer) = trivial_safe_answer(n) {
+        return answer;
+    }
+    !matches!(
+        unsafe { gmpmee_millerrabin_safe_rs(rand.as_raw_mut(), n.as_raw(), reps) },
+        0
+    )
+}
+
+/// Answer of the safe prime tests settled without any round: `n < 8`, an even `n`, or the trial
+/// division of `n` and `(n-1)/2`
+fn trivial_safe_answer(n: &Integer) -> Option<bool> {
+    if *n < 8 {
+        return Some(*n == 5 || *n == 7);
+    }
+    if n.is_even() {
+        return Some(false);
+    }
+    let half = Integer::from(n - 1u32) >> 1;
+    match (
+        trial_division(n, TRIAL_DIVISION_PRIMES),
+        trial_division(&half, TRIAL_DIVISION_PRIMES),
+    ) {
+        (TrialDivisionResult::Composite { .. }, _) | (_, TrialDivisionResult::Composite { .. }) => {
+            Some(false)
+        }
+        (TrialDivisionResult::Prime, TrialDivisionResult::Prime) => Some(true),
+        _ => None,
+    }
+}
+
+/// `reps` rounds for `n` with bases drawn from `rand`, stopped as soon as `failed` is set
+///
+/// `failed` is set if a round fails.
+fn rounds_until_failure(
+    n: &Integer,
+    reps: i32,
+    rand: &mut RandState<'_>,
+    failed: &AtomicBool,
+) -> bool {
+    let mut state = MillerRabinState::init(n);
+    let bound = Integer::from(n - 3u32);
+    let passed = (0..reps).all(|_| {
+        !failed.load(Ordering::Relaxed) && state.once(&(bound.clone().random_below(rand) + 2u32))
+    });
+    if !passed {
+        failed.store(true, Ordering::Relaxed);
+    }
+    passed
+}
+
+/// Safe prime test of `n` like [miller_rabin_safe], testing `n` and `(n-1)/2` on two threads
+pub fn miller_rabin_safe_parallel(n: &Integer, reps: i32) -> bool {
+    with_thread_rand(|rand| miller_rabin_safe_parallel_with_rand(n, reps, rand))
+}
+
+/// Safe prime test of `n` like [miller_rabin_safe_with_rand], testing `n` and `(n-1)/2` on two
+/// threads
+///
+/// The witnesses of `n` are drawn from `rand`, and those of `(n-1)/2` from a random state seeded
+/// with `rand`. Both tests stop at the first failed round of either, so that the latency is about
+/// the one of a single test.
+///
+/// On the targets without threads (`wasm32-unknown-unknown`), `(n-1)/2` is tested after `n` on the
+/// calling thread.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::miller_rabin::miller_rabin_safe_parallel_with_rand;
+/// let mut rand = RandState::new();
+/// assert!(miller_rabin_safe_parallel_with_rand(&Integer::from(1019), 16, &mut rand));
+/// assert!(!miller_rabin_safe_parallel_with_rand(&Integer::from(1013), 16, &mut rand));
+/// ```
+pub fn miller_rabin_safe_parallel_with_rand(
+    n: &Integer,
+    reps: i32,
+    rand: &mut RandState<'_>,
+) -> bool {
+    if let Some(answer) = trivial_safe_answer(n) {
+        return answer;
+    }
+    let half = Integer::from(n - 1u32) >> 1;
+    let mut half_rand = seeded_rand(&Integer::from(Integer::random_bits(64, rand)));
+    let failed = AtomicBool::new(false);
+    if !THREADS {
+        return rounds_until_failure(n, reps, rand, &failed)
+            && rounds_until_failure(&half, reps, &mut half_rand, &failed);
+    }
+    thread::scope(|s| {
+        let half_test = s.spawn(|| rounds_until_failure(&half, reps, &mut half_rand, &failed));
+        let n_passed = rounds_until_failure(n, reps, rand, &failed);
+        let half_passed = half_test.join().expect("the test of (n-1)/2 panicked");
+        n_passed && half_passed
+    })
+}
+
+/// Test if `q` is a Sophie Germain prime, i.e. `q` and `2q+1` are probably prime, with `reps`
+/// rounds
+///
+/// The trial division is shared between `q` and `2q+1`: the remainder `r` of `q` by a small prime
+/// gives the remainder `2r+1` of `2q+1`.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::is_sophie_germain;
+/// assert!(is_sophie_germain(&Integer::from(509), 16));
+/// assert!(!is_sophie_germain(&Integer::from(1019), 16));
+/// ```
+pub fn is_sophie_germain(q: &Integer, reps: i32) -> bool {
+    if *q < 2 {
+        return false;
+    }
+    let p = Integer::from(q << 1) + 1u32;
+    let primes = &small_primes()[..TRIAL_DIVISION_PRIMES];
+    for &d in primes {
+        let r = q.mod_u(d);
+        if (r == 0 && *q != d) || ((2 * r + 1).is_multiple_of(d) && p != d) {
+            return false;
+        }
+    }
+    let last = u64::from(primes[primes.len() - 1]);
+    if p < last * last {
+        return true;
+    }
+    with_thread_rand(|rand| {
+        !matches!(
+            unsafe { gmpmee_millerrabin_safe_rs(rand.as_raw_mut(), p.as_raw(), reps) },
+            0
+        )
+    })
+}
+
+/// Miller-Rabin test of `n` like [miller_rabin_with_rand], reporting the statistics of the test
+///
+/// The rounds are executed one by one, so that the rounds stopped by a failure are not counted.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::miller_rabin::miller_rabin_with_stats;
+/// let mut rand = RandState::new();
+/// let p = (Integer::from(1) << 127) - 1u32;
+/// let (is_prime, stats) = miller_rabin_with_stats(&p, 16, &mut rand);
+/// assert!(is_prime);
+/// assert_eq!((stats.candidates, stats.sieved, stats.rounds), (1, 0, 16));
+/// let (is_prime, stats) = miller_rabin_with_stats(&(p + 2u32), 16, &mut rand);
+/// assert!(!is_prime);
+/// assert_eq!((stats.sieved, stats.rounds), (1, 0));
+/// ```
+pub fn miller_rabin_with_stats(
+    n: &Integer,
+    reps: i32,
+    rand: &mut RandState<'_>,
+) -> (bool, PrimalityStats) {
+    let mut stats = PrimalityStats {
+        candidates: 1,
+        ..Default::default()
+    };
+    let answer = match trivial_answer(n) {
+        Some(answer) => Some(answer),
+        None => match trial_division(n, TRIAL_DIVISION_PRIMES) {
+            TrialDivisionResult::Prime => Some(true),
+            TrialDivisionResult::Composite { .. } => Some(false),
+            TrialDivisionResult::Inconclusive => None,
+        },
+    };
+    let is_prime = match answer {
+        Some(is_prime) => {
+            stats.sieved = u64::from(!is_prime);
+            is_prime
+        }
+        None => MillerRabinState::init(n).rounds(reps, rand, &mut stats.rounds),
+    };
+    (is_prime, stats)
+}
+
+/// Miller-Rabin test of `n` with `reps` rounds, with the number of rounds validated
+///
+/// Return an error if `reps` is greater than [MAX_REPS]. A zero or negative number of rounds,
+/// whose behaviour in GMPMEE is not defined, cannot be expressed.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::try_miller_rabin;
+/// use std::num::NonZeroU32;
+/// let reps = NonZeroU32::new(16).unwrap();
+/// assert!(try_miller_rabin(&Integer::from(1019), reps).unwrap());
+/// assert!(try_miller_rabin(&Integer::from(1019), NonZeroU32::MAX).is_err());
+/// ```
+pub fn try_miller_rabin(n: &Integer, reps: NonZeroU32) -> Result<bool, GmpMEEError> {
+    let reps = checked_reps(reps)?;
+    Ok(miller_rabin(n, reps))
+}
+
+/// Primality test of `n` with `reps` rounds, distinguishing the invalid inputs from the composites
+///
+/// Under [DETERMINISTIC_BOUND], the answer is proven (see [is_prime_deterministic]) and `reps` is
+/// only validated. Return an error if `n < 2` or if `reps` is not in `[1, MAX_REPS]`.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::{Primality, PrimalityError, check_prime};
+/// assert_eq!(check_prime(&Integer::from(1019), 16), Ok(Primality::Prime));
+/// assert_eq!(check_prime(&Integer::from(1023), 16), Ok(Primality::Composite));
+/// assert_eq!(
+///     check_prime(&Integer::from(-3), 16),
+///     Err(PrimalityError::TooSmall { n: Integer::from(-3) })
+/// );
+/// ```
+pub fn check_prime(n: &Integer, reps: i32) -> Result<Primality, PrimalityError> {
+    if !(1..=MAX_REPS as i32).contains(&reps) {
+        return Err(PrimalityError::InvalidReps { reps });
+    }
+    if *n < 2 {
+        return Err(PrimalityError::TooSmall { n: n.clone() });
+    }
+    Ok(primality(n, reps))
+}
+
+/// Primality of `n`, proven under [DETERMINISTIC_BOUND] and tested with `reps` rounds above
+fn primality(n: &Integer, reps: i32) -> Primality {
+    let deterministic = n.to_u128().is_some_and(|v| v < DETERMINISTIC_BOUND);
+    match deterministic {
+        true if is_prime_deterministic(n) => Primality::Prime,
+        false if miller_rabin(n, reps) => Primality::ProbablyPrime,
+        _ => Primality::Composite,
+    }
+}
+
+impl From<Primality> for IsPrime {
+    fn from(primality: Primality) -> Self {
+        match primality {
+            Primality::Prime => IsPrime::Yes,
+            Primality::ProbablyPrime => IsPrime::Probably,
+            Primality::Composite => IsPrime::No,
+        }
+    }
+}
+
+/// Primality test of `n` with `reps` rounds, with the semantics of [Integer::is_probably_prime]
+///
+/// The answer is [IsPrime::Yes] when it is proven, i.e. under [DETERMINISTIC_BOUND] (which covers
+/// the numbers settled by the trial division), [IsPrime::Probably] when `n` passed the rounds of
+/// Miller-Rabin, and [IsPrime::No] when `n` is composite or smaller than 2.
+/// ```
+/// use rug::{Integer, integer::IsPrime};
+/// use rug_gmpmee::miller_rabin::is_probably_prime;
+/// assert_eq!(is_probably_prime(&Integer::from(1019), 16), IsPrime::Yes);
+/// assert_eq!(is_probably_prime(&((Integer::from(1) << 127) - 1u32), 16), IsPrime::Probably);
+/// assert_eq!(is_probably_prime(&Integer::from(1023), 16), IsPrime::No);
+/// ```
+pub fn is_probably_prime(n: &Integer, reps: i32) -> IsPrime {
+    match *n < 2 {
+        true => IsPrime::No,
+        false => primality(n, reps).into(),
+    }
+}
+
+/// Safe prime test of `n` with `reps` rounds, with the number of rounds validated (see
+/// [try_miller_rabin])
+pub fn try_miller_rabin_safe(n: &Integer, reps: NonZeroU32) -> Result<bool, GmpMEEError> {
+    let reps = checked_reps(reps)?;
+    Ok(miller_rabin_safe(n, reps))
+}
+
+/// Random state of the Mersenne Twister seeded with `seed`
+///
+/// The Mersenne Twister of GMP does not depend on the platform, so that the witnesses are the same on
+/// every machine.
+fn seeded_rand(seed: &Integer) -> RandState<'static> {
+    let mut rand = RandState::new_mersenne_twister();
+    rand.seed(seed);
+    rand
+}
+
+/// Miller-Rabin test of `n` with `reps` rounds, whose witnesses are derived deterministically from
+/// `seed`
+///
+/// The same seed gives the same witnesses, and thus the same result, on every machine, which
+/// permits to reproduce a test (e.g. for an audit)
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::miller_rabin_seeded;
+/// let seed = Integer::from(2024);
+/// assert!(miller_rabin_seeded(&Integer::from(1019), 16, &seed));
+/// ```
+pub fn miller_rabin_seeded(n: &Integer, reps: i32, seed: &Integer) -> bool {
+    miller_rabin_with_rand(n, reps, &mut seeded_rand(seed))
+}
+
+/// Safe prime test of `n` with `reps` rounds, whose witnesses are derived deterministically from
+/// `seed` (see [miller_rabin_seeded])
+pub fn miller_rabin_safe_seeded(n: &Integer, reps: i32, seed: &Integer) -> bool {
+    miller_rabin_safe_with_rand(n, reps, &mut seeded_rand(seed))
+}
+
+/// Search of probable primes among successive odd candidates
+///
+/// The state of GMPMEE is reused from one candidate to the next, so that the setup of each
+/// candidate is not recomputed from scratch. The search is also an [Iterator] over the probable
+/// primes.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::MillerRabinSearch;
+/// let mut search = MillerRabinSearch::new(&Integer::from(1000), 16);
+/// assert_eq!(search.next_probable_prime(), 1009);
+/// assert_eq!(search.next_probable_prime(), 1013);
+/// let primes: Vec<Integer> = search.take(2).collect();
+/// assert_eq!(primes, [1019, 1021]);
+/// ```
+pub struct MillerRabinSearch {
+    state: MillerRabinState,
+    reps: i32,
+    rand: RandState<'static>,
+    stats: PrimalityStats,
+    count_rounds: bool,
+    wheel: Option<WheelCandidates>,
+}
+
+impl MillerRabinSearch {
+    /// Start the search at `start` with `reps` rounds per candidate
+    ///
+    /// The first candidate is the smallest odd integer greater or equal than `start` and 3. The
+    /// prime 2 is then never returned.
+    pub fn new(start: &Integer, reps: i32) -> Self {
+        Self::with_rand(start, reps, RandState::default())
+    }
+
+    /// Start the search like [Self::new], with the witnesses drawn from `rand`
+    pub fn with_rand(start: &Integer, reps: i32, rand: RandState<'static>) -> Self {
+        let mut first = start.clone().max(Integer::from(3));
+        if first.is_even() {
+            first += 1;
+        }
+        Self {
+            state: MillerRabinState::init(&first),
+            reps,
+            rand,
+            stats: PrimalityStats::default(),
+            count_rounds: false,
+            wheel: None,
+        }
+    }
+
+    /// Start the search at `start` like [Self::new], with the candidates of `wheel` only
+    ///
+    /// The primes of the wheel are never returned.
+    /// ```
+    /// use rug::Integer;
+    /// use rug_gmpmee::miller_rabin::{MillerRabinSearch, Wheel};
+    /// let mut search = MillerRabinSearch::with_wheel(&Integer::from(1000), 16, &Wheel::default());
+    /// assert_eq!(search.next_probable_prime(), 1009);
+    /// assert_eq!(search.candidate(), &1013);
+    /// ```
+    pub fn with_wheel(start: &Integer, reps: i32, wheel: &Wheel) -> Self {
+        let mut candidates = wheel.candidates(start);
+        Self {
+            state: MillerRabinState::init(&candidates.next_candidate()),
+            reps,
+            rand: RandState::default(),
+            stats: PrimalityStats::default(),
+            count_rounds: false,
+            wheel: Some(candidates),
+        }
+    }
+
+    /// Next candidate to be tested
+    pub fn candidate(&self) -> &Integer {
+        self.state.candidate()
+    }
+
+    /// Statistics of the search since its start
+    ///
+    /// The rounds are counted only if the round statistics are enabled with
+    /// [Self::with_round_stats].
+    pub fn stats(&self) -> PrimalityStats {
+        self.stats
+    }
+
+    /// Count the rounds in the statistics of the search
+    ///
+    /// The rounds are then executed one by one, which is slower than the rounds executed by GMPMEE
+    /// at once.
+    pub fn with_round_stats(mut self) -> Self {
+        self.count_rounds = true;
+        self
+    }
+
+    /// Return the next probable prime, i.e. the first candidate passing the test, and move to
+    /// the following candidate
+    pub fn next_probable_prime(&mut self) -> Integer {
+        while !self.is_candidate_prime() {
+            self.next_cand();
+        }
+        let prime = self.candidate().clone();
+        self.next_cand();
+        prime
+    }
+
+    /// Return the next probable prime like [Self::next_probable_prime], or `None` if none has
+    /// been found within `timeout`
+    ///
+    /// The search can be resumed from the first candidate not tested.
+    /// ```
+    /// use rug::Integer;
+    /// use rug_gmpmee::miller_rabin::MillerRabinSearch;
+    /// use std::time::Duration;
+    /// let mut search = MillerRabinSearch::new(&Integer::from(1000), 16);
+    /// let prime = search.next_probable_prime_with_deadline(Duration::from_secs(60));
+    /// assert_eq!(prime, Some(Integer::from(1009)));
+    /// ```
+    pub fn next_probable_prime_with_deadline(&mut self, timeout: Duration) -> Option<Integer> {
+        let start = Instant::now();
+        while !self.is_candidate_prime() {
+            self.next_cand();
+            if start.elapsed() >= timeout {
+                return None;
+            }
+        }
+        let prime = self.candidate().clone();
+        self.next_cand();
+        Some(prime)
+    }
+
+    /// Move to the next candidate, of the wheel if any
+    fn next_cand(&mut self) {
+        match &mut self.wheel {
+            Some(candidates) => self.state = MillerRabinState::init(&candidates.next_candidate()),
+            None => self.state.next_cand(),
+        }
+    }
+
+    /// Test the candidate, after the trial division by the small primes
+    fn is_candidate_prime(&mut self) -> bool {
+        self.stats.candidates += 1;
+        let sieved = match trial_division(self.candidate(), TRIAL_DIVISION_PRIMES) {
+            TrialDivisionResult::Prime => return true,
+            TrialDivisionResult::Composite { .. } => true,
+            TrialDivisionResult::Inconclusive => !passes_primorial_filter(self.candidate()),
+        };
+        if sieved {
+            self.stats.sieved += 1;
+            return false;
+        }
+        match self.count_rounds {
+            true => self
+                .state
+                .rounds(self.reps, &mut self.rand, &mut self.stats.rounds),
+            false => self.state.reps(self.reps, &mut self.rand),
+        }
+    }
+}
+
+impl Iterator for MillerRabinSearch {
+    type Item = Integer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_probable_prime())
+    }
+}
+
+/// Search of safe primes `p`, i.e. `p` and `(p-1)/2` are probably prime, among successive
+/// candidates
+///
+/// The candidate `p` and `(p-1)/2` are tested together by GMPMEE, reusing the states from one
+/// candidate to the next. The search is also an [Iterator] over the safe primes.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::SafePrimeSearch;
+/// let mut search = SafePrimeSearch::new(&Integer::from(1000), 16);
+/// assert_eq!(search.next_safe_prime(), 1019);
+/// assert_eq!(search.next_safe_prime(), 1187);
+/// ```
+pub struct SafePrimeSearch {
+    state: SafeMillerRabinState,
+    reps: i32,
+    rand: RandState<'static>,
+    stats: PrimalityStats,
+    count_rounds: bool,
+}
+
+impl SafePrimeSearch {
+    /// Start the search at `start` with `reps` rounds per candidate
+    ///
+    /// The first candidate is the smallest integer `p` greater or equal than `start` and 11 with
+    /// `p = 3 mod 4`, since every safe prime greater than 7 has this form. The safe primes 5 and 7
+    /// are then never returned.
+    pub fn new(start: &Integer, reps: i32) -> Self {
+        Self::with_rand(start, reps, RandState::default())
+    }
+
+    /// Start the search like [Self::new], with the witnesses drawn from `rand`
+    pub fn with_rand(start: &Integer, reps: i32, rand: RandState<'static>) -> Self {
+        let mut first = start.clone().max(Integer::from(11));
+        first += (3 + 4 - first.mod_u(4)) % 4;
+        Self {
+            state: SafeMillerRabinState::init(&first),
+            reps,
+            rand,
+            stats: PrimalityStats::default(),
+            count_rounds: false,
+        }
+    }
+
+    /// Next candidate to be tested
+    pub fn candidate(&self) -> &Integer {
+        self.state.candidate()
+    }
+
+    /// Statistics of the search since its start
+    ///
+    /// The rounds are counted only if the round statistics are enabled with
+    /// [Self::with_round_stats].
+    pub fn stats(&self) -> PrimalityStats {
+        self.stats
+    }
+
+    /// Count the rounds in the statistics of the search
+    ///
+    /// The rounds are then executed one by one, which is slower than the rounds executed by GMPMEE
+    /// at once.
+    pub fn with_round_stats(mut self) -> Self {
+        self.count_rounds = true;
+        self
+    }
+
+    /// Return the next safe prime, i.e. the first candidate passing the test, and move to the
+    /// following candidate
+    pub fn next_safe_prime(&mut self) -> Integer {
+        timed_span!(
+            "next_safe_prime",
+            bits = self.candidate().significant_bits(),
+            reps = self.reps
+        );
+        loop {
+            if let Some(prime) = self.step() {
+                return prime;
+            }
+        }
+    }
+
+    /// Return the next safe prime like [Self::next_safe_prime], or `None` if none has been found
+    /// within `timeout`
+    ///
+    /// The search can be resumed from the first candidate not tested.
+    pub fn next_safe_prime_with_deadline(&mut self, timeout: Duration) -> Option<Integer> {
+        timed_span!(
+            "next_safe_prime",
+            bits = self.candidate().significant_bits(),
+            reps = self.reps,
+            timeout_ms = timeout.as_millis() as u64
+        );
+        let start = Instant::now();
+        loop {
+            if let Some(prime) = self.step() {
+                return Some(prime);
+            }
+            if start.elapsed() >= timeout {
+                return None;
+            }
+        }
+    }
+
+    /// Test the candidate and move to the following one, returning the candidate if it is a safe
+    /// prime
+    fn step(&mut self) -> Option<Integer> {
+        let prime = self
+            .is_candidate_safe_prime()
+            .then(|| self.candidate().clone());
+        self.state.next_cand();
+        prime
+    }
+
+    /// Test the candidate, after the primorial filter of the candidate and of its half
+    fn is_candidate_safe_prime(&mut self) -> bool {
+        self.stats.candidates += 1;
+        let half = Integer::from(self.candidate() >> 1);
+        if !passes_primorial_filter(self.candidate()) || !passes_primorial_filter(&half) {
+            self.stats.sieved += 1;
+            return false;
+        }
+        match self.count_rounds {
+            true => self
+                .state
+                .rounds(self.reps, &mut self.rand, &mut self.stats.rounds),
+            false => self.state.reps(self.reps, &mut self.rand),
+        }
+    }
+}
+
+impl Iterator for SafePrimeSearch {
+    type Item = Integer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_safe_prime())
+    }
+}
+
+/// Miller-Rabin test of `n` with `reps` rounds, whose witnesses are drawn from `rand`, returning
+/// the witness that proves that `n` is composite
+///
+/// The bases are drawn uniformly in `[2, n-2]`. An even `n` greater than 2 has the witness 2.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::miller_rabin::{MillerRabinResult, miller_rabin_witness};
+/// let mut rand = RandState::new();
+/// assert_eq!(
+///     miller_rabin_witness(&Integer::from(1019), 16, &mut rand),
+///     MillerRabinResult::ProbablyPrime
+/// );
+/// let res = miller_rabin_witness(&Integer::from(1023), 16, &mut rand);
+/// assert!(res.witness().is_some());
+/// ```
+pub fn miller_rabin_witness(n: &Integer, reps: i32, rand: &mut RandState<'_>) -> MillerRabinResult {
+    if *n < 2 {
+        return MillerRabinResult::NotPrime;
+    }
+    if *n < 4 {
+        return MillerRabinResult::ProbablyPrime;
+    }
+    if n.is_even() {
+        return MillerRabinResult::Composite {
+            witness: Integer::from(2),
+        };
+    }
+    let mut state = MillerRabinState::init(n);
+    let bound = Integer::from(n - 3u32);
+    for _ in 0..reps {
+        let witness = bound.clone().random_below(rand) + 2u32;
+        if !state.once(&witness) {
+            return MillerRabinResult::Composite { witness };
+        }
+    }
+    MillerRabinResult::ProbablyPrime
+}
+
+/// Index and witness of the first composite of `values`, tested with `reps` rounds, or `None` if
+/// all the values are probably prime
+///
+/// The values are tested in parallel by one worker per available core, each one in increasing order
+/// of the index, and the workers skip the values after the first composite found. With a single
+/// worker, the values are tested on the calling thread. The witness is the one of
+/// [miller_rabin_witness], and 0 for a value smaller than 2 (which is not prime).
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::{find_composite, strong_probable_prime};
+/// let values = [1019, 1021, 1023, 1031, 1035].map(Integer::from);
+/// let (index, witness) = find_composite(&values, 16).unwrap();
+/// assert_eq!(index, 2);
+/// assert!(!strong_probable_prime(&values[2], &[witness]));
+/// assert_eq!(find_composite(&values[..2], 16), None);
+/// ```
+pub fn find_composite(values: &[Integer], reps: i32) -> Option<(usize, Integer)> {
+    let workers = available_workers().min(values.len());
+    let seeds: Vec<Integer> = with_thread_rand(|rand| {
+        (0..workers)
+            .map(|_| Integer::from(Integer::random_bits(64, rand)))
+            .collect()
+    });
+    let first = AtomicUsize::new(usize::MAX);
+    let found = Mutex::new(None);
+    let search = |worker: usize, seed: &Integer| {
+        let mut rand = seeded_rand(seed);
+        for index in (worker..values.len()).step_by(workers) {
+            if index > first.load(Ordering::Relaxed) {
+                break;
+            }
+            let witness = match miller_rabin_witness(&values[index], reps, &mut rand) {
+                MillerRabinResult::ProbablyPrime => continue,
+                MillerRabinResult::Composite { witness } => witness,
+                MillerRabinResult::NotPrime => Integer::new(),
+            };
+            let mut found = found.lock().expect("a worker panicked");
+            if first.fetch_min(index, Ordering::Relaxed) > index {
+                *found = Some((index, witness));
+            }
+            break;
+        }
+    };
+    match seeds.as_slice() {
+        [seed] => search(0, seed),
+        _ => thread::scope(|s| {
+            for (worker, seed) in seeds.iter().enumerate() {
+                let search = &search;
+                s.spawn(move || search(worker, seed));
+            }
+        }),
+    }
+    found.into_inner().expect("a worker panicked")
+}
+
+/// Strong probable prime test of `n` to each of the `bases`, i.e. the round of Miller-Rabin with
+/// exactly these witnesses, without any random number
+///
+/// The bases are reduced modulo `n`, and a base divisible by `n` is skipped. `n < 2` is not prime,
+/// and an even `n` is prime only if `n = 2`.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::strong_probable_prime;
+/// // 2047 = 23 * 89 is a strong pseudoprime to the base 2
+/// let n = Integer::from(2047);
+/// assert!(strong_probable_prime(&n, &[Integer::from(2)]));
+/// assert!(!strong_probable_prime(&n, &[Integer::from(2), Integer::from(3)]));
+/// ```
+pub fn strong_probable_prime(n: &Integer, bases: &[Integer]) -> bool {
+    if let Some(answer) = trivial_answer(n) {
+        return answer;
+    }
+    if *n == 3 {
+        return true;
+    }
+    let mut state = MillerRabinState::init(n);
+    bases.iter().all(|a| {
+        let mut base = Integer::from(a % n);
+        if base < 0 {
+            base += n;
+        }
+        base == 0 || state.once(&base)
+    })
+}
+
+/// Bases giving a deterministic test for `n < 2^64` (Sinclair)
+const WITNESSES_64: [u32; 7] = [2, 325, 9375, 28178, 450775, 9780504, 1795265022];
+
+/// Bases (the first 13 primes) giving a deterministic test for `n < DETERMINISTIC_BOUND` (Sorenson
+/// and Webster)
+const WITNESSES_82: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+/// Bound (about `3.3·10^24`, below `2^82`) under which [is_prime_deterministic] is deterministic
+pub const DETERMINISTIC_BOUND: u128 = 3_317_044_064_679_887_385_961_981;
+
+/// Number of rounds of [is_prime_deterministic] for `n` greater or equal than [DETERMINISTIC_BOUND]
+pub const FALLBACK_REPS: i32 = 40;
+
+/// Primality test of `n`, deterministic for `n < DETERMINISTIC_BOUND`
+///
+/// Under the bound, the test uses a fixed set of witnesses (7 bases for `n < 2^64`, the first 13
+/// primes otherwise), which is proven to detect every composite. Above, it falls back to
+/// [miller_rabin] with [FALLBACK_REPS] rounds.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::is_prime_deterministic;
+/// assert!(is_prime_deterministic(&Integer::from(1019)));
+/// // Strong pseudoprime to the bases 2, 3, 5 and 7
+/// assert!(!is_prime_deterministic(&Integer::from(3_215_031_751u64)));
+/// ```
+pub fn is_prime_deterministic(n: &Integer) -> bool {
+    let Some(value) = n.to_u128().filter(|v| *v < DETERMINISTIC_BOUND) else {
+        return *n > 0 && miller_rabin(n, FALLBACK_REPS);
+    };
+    if value < 4 {
+        return value > 1;
+    }
+    if value.is_multiple_of(2) {
+        return false;
+    }
+    let witnesses: &[u32] = match u64::try_from(value) {
+        Ok(_) => &WITNESSES_64,
+        Err(_) => &WITNESSES_82,
+    };
+    let mut state = MillerRabinState::init(n);
+    witnesses.iter().all(|a| {
+        let base = Integer::from(*a) % n;
+        base == 0 || state.once(&base)
+    })
+}
+
+/// Number of rounds for a random candidate of at least the given bit length, for an error
+/// probability smaller than `2^-80` (Handbook of Applied Cryptography, table 4.4)
+const AUTO_REPS: [(u32, i32); 12] = [
+    (1300, 2),
+    (850, 3),
+    (650, 4),
+    (550, 5),
+    (450, 6),
+    (400, 7),
+    (350, 8),
+    (300, 9),
+    (250, 12),
+    (200, 15),
+    (150, 18),
+    (100, 27),
+];
+
+/// Number of rounds chosen by [miller_rabin_auto] for a number of `bits` bits
+///
+/// The number of rounds follows the table 4.4 of the Handbook of Applied Cryptography, for an
+/// error probability smaller than `2^-80` for a random candidate, as used for the generation of
+/// primes. Under 100 bits, [FALLBACK_REPS] rounds are used.
+pub fn auto_reps(bits: u32) -> i32 {
+    AUTO_REPS
+        .iter()
+        .find(|(min_bits, _)| bits >= *min_bits)
+        .map_or(FALLBACK_REPS, |(_, reps)| *reps)
+}
+
+/// Number of rounds for an error probability smaller than `2^-error_bits` for any input
+///
+/// Each round detects a composite with a probability at least `3/4`, so that the bound holds also
+/// for numbers of unknown origin (e.g. received from another party, like in FIPS 186-5).
+pub fn worst_case_reps(error_bits: u32) -> i32 {
+    error_bits.div_ceil(2).clamp(1, MAX_REPS) as i32
+}
+
+/// Primality test of `n`, with the number of rounds chosen from the bit length of `n`
+///
+/// Under [DETERMINISTIC_BOUND], the test is deterministic (see [is_prime_deterministic]).
+/// Otherwise the number of rounds is given by [auto_reps], which supposes that `n` is a random
+/// candidate. Use [miller_rabin_auto_with_error] for numbers of unknown origin.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::miller_rabin_auto;
+/// let p = Integer::from(Integer::u_pow_u(2, 127)) - 1;
+/// assert!(miller_rabin_auto(&p));
+/// assert!(!miller_rabin_auto(&(p + 2)));
+/// ```
+pub fn miller_rabin_auto(n: &Integer) -> bool {
+    match n.to_u128().is_some_and(|v| v < DETERMINISTIC_BOUND) {
+        true => is_prime_deterministic(n),
+        false => miller_rabin(n, auto_reps(n.significant_bits())),
+    }
+}
+
+/// Primality test of `n` with an error probability smaller than `2^-error_bits` for any input (see
+/// [worst_case_reps])
+pub fn miller_rabin_auto_with_error(n: &Integer, error_bits: u32) -> bool {
+    match n.to_u128().is_some_and(|v| v < DETERMINISTIC_BOUND) {
+        true => is_prime_deterministic(n),
+        false => miller_rabin(n, worst_case_reps(error_bits)),
+    }
+}
+
+/// Smallest probable prime greater than `n`
+///
+/// The search reuses the state of GMPMEE between the candidates (see [MillerRabinSearch]), with
+/// the number of rounds given by [auto_reps].
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::next_prime;
+/// assert_eq!(next_prime(&Integer::from(1000)), 1009);
+/// assert_eq!(next_prime(&Integer::from(1009)), 1013);
+/// assert_eq!(next_prime(&Integer::from(-5)), 2);
+/// ```
+pub fn next_prime(n: &Integer) -> Integer {
+    timed_span!("next_prime", bits = n.significant_bits());
+    primes_from(&Integer::from(n + 1u32)).next().unwrap()
+}
+
+/// Iterator over the probable primes greater or equal than `n`, in increasing order
+///
+/// See [next_prime] for the search.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::primes_from;
+/// let primes: Vec<Integer> = primes_from(&Integer::from(0)).take(5).collect();
+/// assert_eq!(primes, [2, 3, 5, 7, 11]);
+/// ```
+pub fn primes_from(n: &Integer) -> impl Iterator<Item = Integer> {
+    let two = (*n <= 2).then(|| Integer::from(2));
+    two.into_iter()
+        .chain(MillerRabinSearch::new(n, auto_reps(n.significant_bits())))
+}
+
+/// Number of integers sieved at once by [primes_in_range]
+const SEGMENT_LEN: u32 = 1 << 16;
+
+/// Iterator of [primes_in_range], sieving the interval segment by segment
+struct PrimesInRange {
+    next: Integer,
+    hi: Integer,
+    pending: VecDeque<Integer>,
+}
+
+impl PrimesInRange {
+    /// Sieve the next segment with the small primes, and keep the primes of the segment
+    fn sieve_segment(&mut self) {
+        let start = self.next.clone();
+        let len = Integer::from(&self.hi - &start)
+            .to_u32()
+            .map_or(SEGMENT_LEN, |l| l.min(SEGMENT_LEN)) as usize;
+        self.next += len;
+        let mut is_composite = vec![false; len];
+        for &p in small_primes() {
+            let mut offset = ((p - start.mod_u(p)) % p) as usize;
+            // p itself is in the segment
+            if start <= p {
+                offset += p as usize;
+            }
+            (offset..len)
+                .step_by(p as usize)
+                .for_each(|i| is_composite[i] = true);
+        }
+        let last = u64::from(*small_primes().last().unwrap());
+        self.pending.extend(
+            (0..len)
+                .filter(|i| !is_composite[*i])
+                .map(|i| Integer::from(&start + i as u32))
+                .filter(|n| *n < last * last || miller_rabin_auto(n)),
+        );
+    }
+}
+
+impl Iterator for PrimesInRange {
+    type Item = Integer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(prime) = self.pending.pop_front() {
+                return Some(prime);
+            }
+            if self.next >= self.hi {
+                return None;
+            }
+            self.sieve_segment();
+        }
+    }
+}
+
+/// Iterator over the probable primes `p` with `lo <= p < hi`, in increasing order
+///
+/// The interval is sieved by segments with the small primes (see [small_primes]), and the
+/// remaining candidates are confirmed by [miller_rabin_auto]. The candidates smaller than the
+/// square of the largest small prime are proven prime by the sieve.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::primes_in_range;
+/// let primes: Vec<Integer> = primes_in_range(&Integer::from(1000), &Integer::from(1030)).collect();
+/// assert_eq!(primes, [1009, 1013, 1019, 1021]);
+/// ```
+pub fn primes_in_range(lo: &Integer, hi: &Integer) -> impl Iterator<Item = Integer> {
+    PrimesInRange {
+        next: lo.clone().max(Integer::from(2)),
+        hi: hi.clone(),
+        pending: VecDeque::new(),
+    }
+}
+
+/// Number of bits of security of the error bound `max_error`, i.e. `ceil(-log2(max_error))`
+fn error_bound_bits(max_error: f64) -> Result<u32, GmpMEEError> {
+    if !(max_error > 0.0 && max_error < 1.0) {
+        return Err(MillerRabinError::InvalidErrorBound {
+            max_error: max_error.to_string(),
+        }
+        .into());
+    }
+    Ok((-max_error.log2()).ceil() as u32)
+}
+
+/// Primality test of `n` with an error probability smaller than `max_error` (e.g. `2^-128`) for
+/// any input
+///
+/// The bound is converted into the number of rounds (see [worst_case_reps]). Under
+/// [DETERMINISTIC_BOUND], the test is deterministic and has no error. Return an error if
+/// `max_error` is not in `]0, 1[`.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::is_prime_with_error_bound;
+/// let p = Integer::from(Integer::u_pow_u(2, 127)) - 1;
+/// assert!(is_prime_with_error_bound(&p, 2f64.powi(-128)).unwrap());
+/// assert!(is_prime_with_error_bound(&p, 1.5).is_err());
+/// ```
+pub fn is_prime_with_error_bound(n: &Integer, max_error: f64) -> Result<bool, GmpMEEError> {
+    let error_bits = error_bound_bits(max_error)?;
+    Ok(miller_rabin_auto_with_error(n, error_bits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::standard_groups::StandardGroup;
+    use rug_miller_rabin::is_prime;
+    use std::time::SystemTime;
+
+    const K: i32 = 16;
+
+    #[test]
+    fn test_prime() {
+        let prime = Integer::from(0x7fff_ffffu64);
+        assert!(miller_rabin(&prime, K));
+    }
+
+    #[test]
+    fn test_composite() {
+        let composite = Integer::from(0xffff_ffff_ffff_ffffu64);
+        assert!(!miller_rabin(&composite, K));
+    }
+
+    #[test]
+    fn test_small_primes() {
+        for prime in &[2u8, 3u8, 5u8, 7u8, 11u8, 13u8] {
+            assert!(miller_rabin(&Integer::from(*prime), K));
+        }
+    }
+
+    #[test]
+    fn test_big_mersenne_prime() {
+        let prime = Integer::from(
+            Integer::parse_radix(b"170141183460469231731687303715884105727", 10).unwrap(),
+        );
+
+        assert!(miller_rabin(&prime, K));
+    }
+
+    #[test]
+    fn test_big_wagstaff_prime() {
+        let prime = Integer::from(
+            Integer::parse_radix(b"56713727820156410577229101238628035243", 10).unwrap(),
+        );
+
+        assert!(miller_rabin(&prime, K));
+    }
+
+    #[test]
+    fn test_big_composite() {
+        let prime = Integer::from(
+            Integer::parse_radix(b"170141183460469231731687303715884105725", 10).unwrap(),
+        );
+
+        assert!(!miller_rabin(&prime, K));
+    }
+
+    const BIG_PRIMES: [&str;4] = [
+        "CE9E0307D2AE75BDBEEC3E0A6E71A279417B56C955C602FFFD067586BACFDAC3BCC49A49EB4D126F5E9255E57C14F3E09492B6496EC8AC1366FC4BB7F678573FA2767E6547FA727FC0E631AA6F155195C035AF7273F31DFAE1166D1805C8522E95F9AF9CE33239BF3B68111141C20026673A6C8B9AD5FA8372ED716799FE05C0BB6EAF9FCA1590BD9644DBEFAA77BA01FD1C0D4F2D53BAAE965B1786EC55961A8E2D3E4FE8505914A408D50E6B99B71CDA78D8F9AF1A662512F8C4C3A9E72AC72D40AE5D4A0E6571135CBBAAE08C7A2AA0892F664549FA7EEC81BA912743F3E584AC2B2092243C4A17EC98DF079D8EECB8B885E6BBAFA452AAFA8CB8C08024EFF28DE4AF4AC710DCD3D66FD88212101BCB412BCA775F94A2DCE18B1A6452D4CF818B6D099D4505E0040C57AE1F3E84F2F8E07A69C0024C05ACE05666A6B63B0695904478487E78CD0704C14461F24636D7A3F267A654EEDCF8789C7F627C72B4CBD54EED6531C0E54E325D6F09CB648AE9185A7BDA6553E40B125C78E5EAA867",
+        "5FFB3E665707B0D9C5D3856B9B67D4751425AEB6575F97F697E446856FFCF159105FECE66D2CDE9DEA958966FE67A0D51ECDFC0FCAD3EACA293485FA2FBCC9DF3B055DE51F14B82EA39D3331C6E6B753C331E06DC8F1F0558EFF0D7F928C0EA6961DD02CFC898ECAE9BFA18919F5113B702964B06E58987CEFFEE05F4BBE4CA3F3D702F528B5540D92947F781B12D67E7A4AE1D5AEAF8BB703789C1574B52381908496060E0150CB55A6D1069B02DA73952E7E8B67C9C0E41A89F5E8C5452510DFCADC3276D26010A2C1F4CD18C07BD2B0F8CEA28DE21AA73D1426E3F5862D02EE2C42B636E4679D2BDA16C336C2FA29E8DEC663088BFDB035205785077BB6B01E3D183E05C42A1AAEAC1B3BA635D8911C704C033C15243DDCC44570EDAA6F651FF61BA698664D391698292C2834E9095B17EB3AC38819BE50BA08F417FBF3F3DBAA7A64F9D0E24D50AF0685074D82D17544010B68295BC07340B46519B184E9E0C01513C57E78E07C7D19C0E0A2ED0432449110DCB0766B6A30B2F02BDAAF75",
+        "BEDCDE3405B8A18D6C7615FCFF97DB1C29CD2CA69F1BB1432E690E1E947836FC1DE9160D5C2ADEE52ED244F7997ECCE19FF979D00CC3CCE3784DA6C6495D0D87337B24ABB0FD848C79EBBCF298349396FAE4031A3B7EC2BF313CAEF36AB191CAD36D4AEFDFFA87F72DAACB2EA854FFFCCC66E99C2896911EBA93341C006DD3AA4DD06B432B2D3FCD79B5F7C61DED181B734B2DC1C869E498B2647E8C4301DBFD1787F1C7F5E687D118F2A5D410DB73689586377AA9273DEEC051B60DB813DD0C22FAD561BABE3C59CC67EB284387EE6D3F8C38F6A0B34DE82CEF929B853C3B1A52C6CD6B87AA0A882C30F8B716B3687CCB8EB9EC1BF67407C5142315D2BDFFA5D37E0ADB968593BC66A999695DF11B0164B21A62F7A0A7006D49EF8DEB31408E66AD53A4A6BE38F20EF09C84C729A9544EDF854274DC2120CAFA1BC08E20E7C7F1969DCD4C2C08DCB8AB419B6A8B22F1D6F183B1912E54B045C84E95E668D282073EF9216E3106C173FF9A1D29DC445059491209FA9540D06B666611EB5ECE77",
+        "5F6E6F1A02DC50C6B63B0AFE7FCBED8E14E696534F8DD8A19734870F4A3C1B7E0EF48B06AE156F729769227BCCBF6670CFFCBCE80661E671BC26D36324AE86C399BD9255D87EC2463CF5DE794C1A49CB7D72018D1DBF615F989E5779B558C8E569B6A577EFFD43FB96D56597542A7FFE663374CE144B488F5D499A0E0036E9D526E835A195969FE6BCDAFBE30EF68C0DB9A596E0E434F24C59323F462180EDFE8BC3F8E3FAF343E88C7952EA086DB9B44AC31BBD54939EF76028DB06DC09EE86117D6AB0DD5F1E2CE633F59421C3F7369FC61C7B5059A6F41677C94DC29E1D8D296366B5C3D5054416187C5B8B59B43E65C75CF60DFB3A03E28A118AE95EFFD2E9BF056DCB42C9DE3354CCB4AEF88D80B2590D317BD0538036A4F7C6F598A0473356A9D2535F1C7907784E426394D4AA276FC2A13A6E1090657D0DE0471073E3F8CB4EE6A616046E5C55A0CDB5459178EB78C1D8C8972A5822E4274AF3346941039F7C90B7188360B9FFCD0E94EE22282CA48904FD4AA06835B33308F5AF673B"
+    ];
+
+    const BIG_COMPOSITE: [&str;4] = [
+        "CE8E0307D2AE75BDBEEC3E0A6E71A279417B56C955C602FFFD067586BACFDAC3BCC49A49EB4D126F5E9255E57C14F3E09492B6496EC8AC1366FC4BB7F678573FA2767E6547FA727FC0E631AA6F155195C035AF7273F31DFAE1166D1805C8522E95F9AF9CE33239BF3B68111141C20026673A6C8B9AD5FA8372ED716799FE05C0BB6EAF9FCA1590BD9644DBEFAA77BA01FD1C0D4F2D53BAAE965B1786EC55961A8E2D3E4FE8505914A408D50E6B99B71CDA78D8F9AF1A662512F8C4C3A9E72AC72D40AE5D4A0E6571135CBBAAE08C7A2AA0892F664549FA7EEC81BA912743F3E584AC2B2092243C4A17EC98DF079D8EECB8B885E6BBAFA452AAFA8CB8C08024EFF28DE4AF4AC710DCD3D66FD88212101BCB412BCA775F94A2DCE18B1A6452D4CF818B6D099D4505E0040C57AE1F3E84F2F8E07A69C0024C05ACE05666A6B63B0695904478487E78CD0704C14461F24636D7A3F267A654EEDCF8789C7F627C72B4CBD54EED6531C0E54E325D6F09CB648AE9185A7BDA6553E40B125C78E5EAA867",
+        "5FFB4E665707B0D9C5D3856B9B67D4751425AEB6575F97F697E446856FFCF159105FECE66D2CDE9DEA958966FE67A0D51ECDFC0FCAD3EACA293485FA2FBCC9DF3B055DE51F14B82EA39D3331C6E6B753C331E06DC8F1F0558EFF0D7F928C0EA6961DD02CFC898ECAE9BFA18919F5113B702964B06E58987CEFFEE05F4BBE4CA3F3D702F528B5540D92947F781B12D67E7A4AE1D5AEAF8BB703789C1574B52381908496060E0150CB55A6D1069B02DA73952E7E8B67C9C0E41A89F5E8C5452510DFCADC3276D26010A2C1F4CD18C07BD2B0F8CEA28DE21AA73D1426E3F5862D02EE2C42B636E4679D2BDA16C336C2FA29E8DEC663088BFDB035205785077BB6B01E3D183E05C42A1AAEAC1B3BA635D8911C704C033C15243DDCC44570EDAA6F651FF61BA698664D391698292C2834E9095B17EB3AC38819BE50BA08F417FBF3F3DBAA7A64F9D0E24D50AF0685074D82D17544010B68295BC07340B46519B184E9E0C01513C57E78E07C7D19C0E0A2ED0432449110DCB0766B6A30B2F02BDAAF75",
+        "BEDCDE2405B8A18D6C7615FCFF97DB1C29CD2CA69F1BB1432E690E1E947836FC1DE9160D5C2ADEE52ED244F7997ECCE19FF979D00CC3CCE3784DA6C6495D0D87337B24ABB0FD848C79EBBCF298349396FAE4031A3B7EC2BF313CAEF36AB191CAD36D4AEFDFFA87F72DAACB2EA854FFFCCC66E99C2896911EBA93341C006DD3AA4DD06B432B2D3FCD79B5F7C61DED181B734B2DC1C869E498B2647E8C4301DBFD1787F1C7F5E687D118F2A5D410DB73689586377AA9273DEEC051B60DB813DD0C22FAD561BABE3C59CC67EB284387EE6D3F8C38F6A0B34DE82CEF929B853C3B1A52C6CD6B87AA0A882C30F8B716B3687CCB8EB9EC1BF67407C5142315D2BDFFA5D37E0ADB968593BC66A999695DF11B0164B21A62F7A0A7006D49EF8DEB31408E66AD53A4A6BE38F20EF09C84C729A9544EDF854274DC2120CAFA1BC08E20E7C7F1969DCD4C2C08DCB8AB419B6A8B22F1D6F183B1912E54B045C84E95E668D282073EF9216E3106C173FF9A1D29DC445059491209FA9540D06B666611EB5ECE77",
+        "5F6E6F2A02DC50C6B63B0AFE7FCBED8E14E696534F8DD8A19734870F4A3C1B7E0EF48B06AE156F729769227BCCBF6670CFFCBCE80661E671BC26D36324AE86C399BD9255D87EC2463CF5DE794C1A49CB7D72018D1DBF615F989E5779B558C8E569B6A577EFFD43FB96D56597542A7FFE663374CE144B488F5D499A0E0036E9D526E835A195969FE6BCDAFBE30EF68C0DB9A596E0E434F24C59323F462180EDFE8BC3F8E3FAF343E88C7952EA086DB9B44AC31BBD54939EF76028DB06DC09EE86117D6AB0DD5F1E2CE633F59421C3F7369FC61C7B5059A6F41677C94DC29E1D8D296366B5C3D5054416187C5B8B59B43E65C75CF60DFB3A03E28A118AE95EFFD2E9BF056DCB42C9DE3354CCB4AEF88D80B2590D317BD0538036A4F7C6F598A0473356A9D2535F1C7907784E426394D4AA276FC2A13A6E1090657D0DE0471073E3F8CB4EE6A616046E5C55A0CDB5459178EB78C1D8C8972A5822E4274AF3346941039F7C90B7188360B9FFCD0E94EE22282CA48904FD4AA06835B33308F5AF673B"
+    ];
+
+    #[test]
+    fn test_3072_prime() {
+        for p_str in BIG_PRIMES {
+            let p = Integer::from_str_radix(p_str, 16).unwrap();
+            assert!(miller_rabin(&p, K));
+        }
+    }
+
+    #[test]
+    fn test_3072_composite() {
+        for p_str in BIG_COMPOSITE {
+            let p = Integer::from_str_radix(p_str, 16).unwrap();
+            assert!(!miller_rabin(&p, K));
+        }
+    }
+
+    #[test]
+    fn test_with_rand() {
+        let mut rand = RandState::new();
+        rand.seed(&Integer::from(1234));
+        let prime = Integer::from(0x7fff_ffffu64);
+        let composite = Integer::from(0xffff_ffff_ffff_ffffu64);
+        for _ in 0..10 {
+            assert!(miller_rabin_with_rand(&prime, K, &mut rand));
+            assert!(!miller_rabin_with_rand(&composite, K, &mut rand));
+        }
+        assert!(miller_rabin_safe_with_rand(&Integer::from(1019), K, &mut rand));
+        assert!(!miller_rabin_safe_with_rand(&Integer::from(1021), K, &mut rand));
+    }
+
+    #[test]
+    fn test_seeded() {
+        let seed = Integer::from(987654321);
+        // 3215031751 = 151 * 751 * 28351 is a strong pseudoprime to the bases 2, 3, 5 and 7
+        let pseudoprime = Integer::from(3_215_031_751u64);
+        let results: Vec<bool> = (1..8)
+            .map(|reps| miller_rabin_seeded(&pseudoprime, reps, &seed))
+            .collect();
+        let again: Vec<bool> = (1..8)
+            .map(|reps| miller_rabin_seeded(&pseudoprime, reps, &seed))
+            .collect();
+        assert_eq!(results, again);
+        assert!(!miller_rabin_seeded(&pseudoprime, K, &seed));
+        assert!(miller_rabin_seeded(&Integer::from(0x7fff_ffffu64), K, &seed));
+        assert!(miller_rabin_safe_seeded(&Integer::from(1019), K, &seed));
+    }
+
+    /// Strong probable prime test of the odd `n` with the base `a`
+    fn is_strong_probable_prime(n: &Integer, a: &Integer) -> bool {
+        let n_minus_1 = Integer::from(n - 1u32);
+        let k = n_minus_1.find_one(0).unwrap();
+        let q = Integer::from(&n_minus_1 >> k);
+        let mut y = a.clone().pow_mod(&q, n).unwrap();
+        if y == 1 || y == n_minus_1 {
+            return true;
+        }
+        for _ in 1..k {
+            y = y.pow_mod(&Integer::from(2), n).unwrap();
+            if y == n_minus_1 {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn test_witness() {
+        let mut rand = RandState::new();
+        let pseudoprime = Integer::from(3_215_031_751u64);
+        for n in [pseudoprime, Integer::from(0xffff_ffff_ffff_ffffu64), Integer::from(1023)] {
+            let res = miller_rabin_witness(&n, K, &mut rand);
+            let witness = res.witness().unwrap();
+            assert!(*witness >= 2 && *witness <= Integer::from(&n - 2u32));
+            assert!(!is_strong_probable_prime(&n, witness));
+        }
+        for n in [2u32, 3, 5, 1019, 0x7fff_ffff] {
+            assert!(miller_rabin_witness(&Integer::from(n), K, &mut rand).is_probably_prime());
+        }
+        assert_eq!(
+            miller_rabin_witness(&Integer::from(1024), K, &mut rand),
+            MillerRabinResult::Composite {
+                witness: Integer::from(2)
+            }
+        );
+        assert_eq!(
+            miller_rabin_witness(&Integer::from(1), K, &mut rand),
+            MillerRabinResult::NotPrime
+        );
+        assert_eq!(
+            miller_rabin_witness(&Integer::from(-7), K, &mut rand),
+            MillerRabinResult::NotPrime
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let start = Integer::from(1_000_000);
+        let mut search = MillerRabinSearch::new(&start, K);
+        let mut expected = start.next_prime();
+        for _ in 0..20 {
+            assert_eq!(search.next_probable_prime(), expected);
+            assert!(*search.candidate() > expected && search.candidate().is_odd());
+            expected = expected.next_prime();
+        }
+        let primes: Vec<Integer> = MillerRabinSearch::new(&Integer::from(0), K).take(4).collect();
+        assert_eq!(primes, [3, 5, 7, 11]);
+        assert_eq!(MillerRabinSearch::new(&Integer::from(8), K).candidate(), &9);
+
+        let start = Integer::from(1_000_000);
+        let mut search = MillerRabinSearch::new(&start, K);
+        let mut wheel_search = MillerRabinSearch::with_wheel(&start, K, &Wheel::default());
+        for _ in 0..20 {
+            assert_eq!(wheel_search.next_probable_prime(), search.next_probable_prime());
+        }
+        assert!(wheel_search.stats().candidates < search.stats().candidates);
+        let wheel = Wheel::new(2).unwrap();
+        let primes: Vec<Integer> = MillerRabinSearch::with_wheel(&Integer::from(0), K, &wheel)
+            .take(4)
+            .collect();
+        assert_eq!(primes, [5, 7, 11, 13]);
+    }
+
+    #[test]
+    fn test_safe_search() {
+        let is_safe = |p: &Integer| {
+            p.is_probably_prime(K as u32) != IsPrime::No
+                && Integer::from(p >> 1).is_probably_prime(K as u32) != IsPrime::No
+        };
+        let mut search = SafePrimeSearch::new(&Integer::from(100_000), K);
+        let mut p = Integer::from(100_000);
+        for _ in 0..10 {
+            while !is_safe(&p) {
+                p += 1;
+            }
+            assert_eq!(search.next_safe_prime(), p);
+            p += 1;
+        }
+        let primes: Vec<Integer> = SafePrimeSearch::new(&Integer::from(0), K).take(3).collect();
+        assert_eq!(primes, [11, 23, 47]);
+        assert_eq!(SafePrimeSearch::new(&Integer::from(1000), K).candidate(), &1003);
+    }
+
+    #[test]
+    fn test_deterministic() {
+        for n in [2u64, 3, 5, 7, 11, 1019, 0x7fff_ffff, (1 << 61) - 1, u64::MAX - 58] {
+            assert!(is_prime_deterministic(&Integer::from(n)), "{n}");
+        }
+        // Strong pseudoprimes to the first 4, 9 and 12 prime bases
+        for n in [3_215_031_751u128, 3_825_123_056_546_413_051, 318_665_857_834_031_151_167_461] {
+            assert!(!is_prime_deterministic(&Integer::from(n)), "{n}");
+        }
+        for n in [-7i64, -1, 0, 1, 4, 9, 1023, 0x7fff_ffff * 3] {
+            assert!(!is_prime_deterministic(&Integer::from(n)), "{n}");
+        }
+        for start in [Integer::from(u64::MAX), Integer::from(1) << 80] {
+            let p = start.next_prime();
+            assert!(is_prime_deterministic(&p));
+            assert!(!is_prime_deterministic(&(p * 3u32)));
+        }
+    }
+
+    #[test]
+    fn test_trial_division() {
+        let primes = small_primes();
+        assert_eq!(primes.len(), 6542);
+        assert_eq!(&primes[..6], &[2, 3, 5, 7, 11, 13]);
+        assert_eq!(*primes.last().unwrap(), 65521);
+        for p in [2u32, 3, 65521] {
+            let n = Integer::from(p);
+            assert_eq!(trial_division(&n, primes.len()), TrialDivisionResult::Prime);
+            assert_eq!(
+                trial_division(&(n * 65521u32), primes.len()),
+                TrialDivisionResult::Composite { divisor: p }
+            );
+        }
+        let prime = Integer::from(0x7fff_ffffu64);
+        assert_eq!(trial_division(&prime, 10), TrialDivisionResult::Inconclusive);
+        assert_eq!(
+            trial_division(&prime, TRIAL_DIVISION_PRIMES),
+            TrialDivisionResult::Inconclusive
+        );
+        assert_eq!(trial_division(&prime, usize::MAX), TrialDivisionResult::Prime);
+        assert_eq!(trial_division(&Integer::from(1), 10), TrialDivisionResult::Inconclusive);
+        assert_eq!(trial_division(&Integer::from(10), 0), TrialDivisionResult::Inconclusive);
+        for n in [2u32, 3, 5, 1019, 0x7fff_ffff] {
+            assert!(miller_rabin(&Integer::from(n), K));
+        }
+        for n in [4u32, 9, 1023, 65521 * 65521] {
+            assert!(!miller_rabin(&Integer::from(n), K));
+        }
+    }
+
+    #[test]
+    fn test_primorial_filter() {
+        assert_eq!(primorial().significant_bits(), 14_277);
+        for p in [2u32, 3, 9973] {
+            let n = Integer::from(p);
+            assert!(passes_primorial_filter(&n));
+            assert!(!passes_primorial_filter(&(n * 1_000_003u32)));
+        }
+        for n in [1u32, 10007, 1_000_003, 10007 * 10009] {
+            assert!(passes_primorial_filter(&Integer::from(n)));
+        }
+        for n in [0i32, 15, 9999, -7] {
+            assert!(!passes_primorial_filter(&Integer::from(n)));
+        }
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        let mut rand = RandState::new();
+        for n in [i64::MIN, -3, -2, -1, 0, 1] {
+            let n = Integer::from(n);
+            assert!(!miller_rabin_with_rand(&n, K, &mut rand));
+            assert!(!miller_rabin_safe_with_rand(&n, K, &mut rand));
+        }
+        assert!(miller_rabin_with_rand(&Integer::from(2), K, &mut rand));
+        assert!(!miller_rabin_safe_with_rand(&Integer::from(2), K, &mut rand));
+        let big_even = Integer::from(1) << 3072;
+        for n in [Integer::from(4), Integer::from(1024), big_even] {
+            assert!(!miller_rabin_with_rand(&n, K, &mut rand));
+            assert!(!miller_rabin_safe_with_rand(&n, K, &mut rand));
+        }
+        for n in [3u32, 5, 7, 11] {
+            let n = Integer::from(n);
+            assert!(miller_rabin_with_rand(&n, K, &mut rand));
+            assert_eq!(miller_rabin_safe_with_rand(&n, K, &mut rand), n != 3);
+        }
+    }
+
+    #[test]
+    fn test_typed_reps() {
+        let prime = Integer::from(0x7fff_ffffu64);
+        for reps in [1, 16, MAX_REPS] {
+            let reps = NonZeroU32::new(reps).unwrap();
+            assert!(try_miller_rabin(&prime, reps).unwrap());
+            assert!(!try_miller_rabin(&Integer::from(1023), reps).unwrap());
+            assert!(try_miller_rabin_safe(&Integer::from(1019), reps).unwrap());
+        }
+        let too_many = NonZeroU32::new(MAX_REPS + 1).unwrap();
+        assert_eq!(
+            try_miller_rabin(&prime, too_many),
+            Err(GmpMEEError::MillerRabinParameters(
+                MillerRabinError::TooManyReps { reps: MAX_REPS + 1 }
+            ))
+        );
+        assert!(try_miller_rabin_safe(&prime, too_many).is_err());
+    }
+
+    #[test]
+    fn test_auto() {
+        assert_eq!(auto_reps(64), FALLBACK_REPS);
+        assert_eq!(auto_reps(100), 27);
+        assert_eq!(auto_reps(512), 6);
+        assert_eq!(auto_reps(1024), 4);
+        assert_eq!(auto_reps(3072), 2);
+        assert_eq!(worst_case_reps(0), 1);
+        assert_eq!(worst_case_reps(80), 40);
+        assert_eq!(worst_case_reps(101), 51);
+        assert_eq!(worst_case_reps(u32::MAX), MAX_REPS as i32);
+        for p_str in BIG_PRIMES {
+            let p = Integer::from_str_radix(p_str, 16).unwrap();
+            assert!(miller_rabin_auto(&p));
+            assert!(miller_rabin_auto_with_error(&p, 128));
+        }
+        for p_str in BIG_COMPOSITE {
+            let p = Integer::from_str_radix(p_str, 16).unwrap();
+            assert!(!miller_rabin_auto(&p));
+            assert!(!miller_rabin_auto_with_error(&p, 128));
+        }
+        assert!(miller_rabin_auto(&Integer::from(1019)));
+        assert!(!miller_rabin_auto(&Integer::from(3_215_031_751u64)));
+        assert!(!miller_rabin_auto_with_error(&Integer::from(1), 128));
+    }
+
+    #[test]
+    fn test_next_prime() {
+        for start in [Integer::from(0), Integer::from(1_000_000), Integer::from(1) << 512] {
+            let mut expected = start.clone();
+            let mut p = start.clone();
+            for q in primes_from(&start).take(10) {
+                expected = expected.next_prime();
+                assert_eq!(q, expected);
+                p = next_prime(&p);
+                assert_eq!(p, expected);
+            }
+        }
+        assert_eq!(primes_from(&Integer::from(7)).next().unwrap(), 7);
+        assert_eq!(next_prime(&Integer::from(7)), 11);
+        assert_eq!(next_prime(&Integer::from(2)), 3);
+        assert_eq!(next_prime(&Integer::from(-100)), 2);
+    }
+
+    #[test]
+    fn test_sophie_germain() {
+        let first = [2u32, 3, 5, 11, 23, 29, 41, 53, 83, 89, 113, 131, 173, 179, 191, 233, 239];
+        let found: Vec<u32> = (0..240)
+            .filter(|q| is_sophie_germain(&Integer::from(*q), K))
+            .collect();
+        assert_eq!(found, first);
+        for p_str in BIG_PRIMES {
+            let p = Integer::from_str_radix(p_str, 16).unwrap();
+            let q = Integer::from(&p >> 1);
+            assert_eq!(is_sophie_germain(&q, K), miller_rabin_safe(&p, K));
+        }
+        assert!(!is_sophie_germain(&Integer::from(-11), K));
+    }
+
+    #[test]
+    fn test_error_bound() {
+        assert_eq!(error_bound_bits(2f64.powi(-128)), Ok(128));
+        assert_eq!(error_bound_bits(1e-6), Ok(20));
+        assert_eq!(error_bound_bits(0.5), Ok(1));
+        assert_eq!(error_bound_bits(f64::MIN_POSITIVE), Ok(1022));
+        for max_error in [0.0, 1.0, -0.5, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                is_prime_with_error_bound(&Integer::from(1019), max_error),
+                Err(GmpMEEError::MillerRabinParameters(
+                    MillerRabinError::InvalidErrorBound { .. }
+                ))
+            ));
+        }
+        for p_str in BIG_PRIMES {
+            let p = Integer::from_str_radix(p_str, 16).unwrap();
+            assert!(is_prime_with_error_bound(&p, 2f64.powi(-128)).unwrap());
+        }
+        for p_str in BIG_COMPOSITE {
+            let p = Integer::from_str_radix(p_str, 16).unwrap();
+            assert!(!is_prime_with_error_bound(&p, 2f64.powi(-128)).unwrap());
+        }
+        assert!(is_prime_with_error_bound(&Integer::from(1019), 0.1).unwrap());
+    }
+
+    #[test]
+    fn test_check_prime() {
+        assert_eq!(check_prime(&Integer::from(2), 1), Ok(Primality::Prime));
+        assert_eq!(check_prime(&Integer::from(4), 1), Ok(Primality::Composite));
+        let pseudoprime = Integer::from(3_215_031_751u64);
+        assert_eq!(check_prime(&pseudoprime, K), Ok(Primality::Composite));
+        for p_str in BIG_PRIMES {
+            let p = Integer::from_str_radix(p_str, 16).unwrap();
+            assert_eq!(check_prime(&p, K), Ok(Primality::ProbablyPrime));
+        }
+        for p_str in BIG_COMPOSITE {
+            let p = Integer::from_str_radix(p_str, 16).unwrap();
+            assert_eq!(check_prime(&p, K), Ok(Primality::Composite));
+        }
+        for n in [-5, 0, 1] {
+            assert_eq!(
+                check_prime(&Integer::from(n), K),
+                Err(PrimalityError::TooSmall { n: Integer::from(n) })
+            );
+        }
+        for reps in [i32::MIN, -1, 0, MAX_REPS as i32 + 1] {
+            assert_eq!(
+                check_prime(&Integer::from(1019), reps),
+                Err(PrimalityError::InvalidReps { reps })
+            );
+        }
+        let error: GmpMEEError = PrimalityError::InvalidReps { reps: 0 }.into();
+        assert!(matches!(error, GmpMEEError::Primality(_)));
+    }
+
+    #[test]
+    fn test_strong_probable_prime() {
+        let bases = |b: &[i64]| b.iter().map(|a| Integer::from(*a)).collect::<Vec<_>>();
+        let pseudoprime = Integer::from(3_215_031_751u64);
+        assert!(strong_probable_prime(&pseudoprime, &bases(&[2, 3, 5, 7])));
+        assert!(!strong_probable_prime(&pseudoprime, &bases(&[2, 3, 5, 7, 11])));
+        let prime = Integer::from(0x7fff_ffffu64);
+        assert!(strong_probable_prime(&prime, &bases(&[2, 3, -5, 0x7fff_ffff, 1 << 40])));
+        assert!(strong_probable_prime(&prime, &[]));
+        let composite = Integer::from(0xffff_ffff_ffff_ffffu64);
+        assert!(!strong_probable_prime(&composite, &bases(&[-2])));
+        for n in [-7, 0, 1, 4, 1024] {
+            assert!(!strong_probable_prime(&Integer::from(n), &bases(&[2])));
+        }
+        for n in [2, 3, 5] {
+            assert!(strong_probable_prime(&Integer::from(n), &bases(&[2, 3, 5])));
+        }
+    }
+
+    #[test]
+    fn test_primes_in_range() {
+        let range = |lo: Integer, hi: Integer| {
+            let mut expected = vec![];
+            let mut p = Integer::from(&lo - 1u32).max(Integer::from(1)).next_prime();
+            while p < hi {
+                expected.push(p.clone());
+                p = p.next_prime();
+            }
+            assert_eq!(primes_in_range(&lo, &hi).collect::<Vec<_>>(), expected);
+        };
+        range(Integer::from(-10), Integer::from(100));
+        range(Integer::from(2), Integer::from(3));
+        range(Integer::from(65_000), Integer::from(200_000));
+        range(Integer::from(u32::MAX - 100_000), Integer::from(u32::MAX) + 100_000u32);
+        range(Integer::from(1) << 256, (Integer::from(1) << 256) + 5000u32);
+        assert_eq!(primes_in_range(&Integer::from(100), &Integer::from(50)).count(), 0);
+        assert_eq!(primes_in_range(&Integer::from(24), &Integer::from(29)).count(), 0);
+    }
+
+    #[test]
+    fn test_thread_rand() {
+        let p = (Integer::from(1) << 127) - 1u32;
+        // The state of the thread is borrowed by the outer call
+        assert!(with_thread_rand(|rand| {
+            miller_rabin_with_rand(&p, K, rand) && miller_rabin(&p, K) && !miller_rabin_safe(&p, K)
+        }));
+        std::thread::scope(|s| {
+            let handles: Vec<_> = (0..4).map(|_| s.spawn(|| miller_rabin(&p, K))).collect();
+            assert!(handles.into_iter().all(|h| h.join().unwrap()));
+        });
+    }
+
+    #[test]
+    fn test_safe_parallel() {
+        let mut rand = RandState::new();
+        let p = StandardGroup::Modp2048.modulus().clone();
+        for n in [
+            p.clone(),
+            p - 2u32,
+            Integer::from(1019),
+            Integer::from(1013),
+            Integer::from(7),
+            Integer::from(4),
+            (Integer::from(1) << 127) - 1u32,
+        ] {
+            assert_eq!(
+                miller_rabin_safe_parallel_with_rand(&n, K, &mut rand),
+                miller_rabin_safe(&n, K),
+                "{n}"
+            );
+        }
+        assert!(miller_rabin_safe_parallel(&Integer::from(2_147_483_579u32), K));
+    }
+
+    #[test]
+    fn test_is_probably_prime() {
+        // rug tests the absolute value of a negative n
+        for v in 0..5000 {
+            let n = Integer::from(v);
+            assert_eq!(is_probably_prime(&n, K), n.is_probably_prime(30), "{n}");
+        }
+        let n = Integer::from(DETERMINISTIC_BOUND - 1);
+        assert_ne!(is_probably_prime(&n, K), IsPrime::Probably);
+        let p = StandardGroup::Modp2048.modulus();
+        assert_eq!(is_probably_prime(p, K), IsPrime::Probably);
+        assert_eq!(is_probably_prime(&Integer::from(p * 3u32), K), IsPrime::No);
+        assert_eq!(is_probably_prime(&Integer::from(-7), K), IsPrime::No);
+        assert_eq!(IsPrime::from(Primality::Prime), IsPrime::Yes);
+    }
+
+    #[test]
+    fn test_find_composite() {
+        let mut values: Vec<Integer> = primes_from(&(Integer::from(1) << 128)).take(40).collect();
+        assert_eq!(find_composite(&values, K), None);
+        values[31] *= 3u32;
+        values[7] = Integer::from(1_000_003u64 * 1_000_033);
+        let (index, witness) = find_composite(&values, K).unwrap();
+        assert_eq!(index, 7);
+        assert!(!strong_probable_prime(&values[7], &[witness]));
+        values[5] = Integer::from(1);
+        assert_eq!(find_composite(&values, K), Some((5, Integer::new())));
+        assert_eq!(find_composite(&values[12..], K).unwrap().0, 19);
+        assert_eq!(find_composite(&[Integer::from(4)], K), Some((0, Integer::from(2))));
+        assert_eq!(find_composite(&[], K), None);
+    }
+
+    #[test]
+    fn test_deadline() {
+        let start = Integer::from(1) << 4096;
+        let mut search = MillerRabinSearch::new(&start, K);
+        assert_eq!(search.next_probable_prime_with_deadline(Duration::ZERO), None);
+        assert!(search.stats().candidates >= 1);
+        let mut search = SafePrimeSearch::new(&start, K);
+        assert_eq!(search.next_safe_prime_with_deadline(Duration::from_millis(10)), None);
+        let candidate = search.candidate().clone();
+        assert!(candidate > start);
+        let mut search = SafePrimeSearch::new(&Integer::from(1000), K);
+        let p = search.next_safe_prime_with_deadline(Duration::from_secs(60));
+        assert_eq!(p, Some(Integer::from(1019)));
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut rand = RandState::new();
+        let (is_prime, stats) = miller_rabin_with_stats(&Integer::from(1009), K, &mut rand);
+        assert!(is_prime);
+        assert_eq!(stats, PrimalityStats { candidates: 1, sieved: 0, rounds: 0 });
+        let composite = Integer::from(1_000_003u64 * 1_000_033);
+        let (is_prime, stats) = miller_rabin_with_stats(&composite, K, &mut rand);
+        assert!(!is_prime);
+        assert!(stats.rounds >= 1 && stats.rounds <= K as u64);
+        let (is_prime, stats) = miller_rabin_with_stats(&Integer::from(-4), K, &mut rand);
+        assert!(!is_prime);
+        assert_eq!(stats.sieved, 1);
+
+        let mut search = MillerRabinSearch::new(&(Integer::from(1) << 256), K);
+        search.next_probable_prime();
+        assert!(search.stats().candidates >= 1);
+        assert_eq!(search.stats().rounds, 0);
+        let mut search = MillerRabinSearch::new(&(Integer::from(1) << 256), K).with_round_stats();
+        let p = search.next_probable_prime();
+        let stats = search.stats();
+        let tested = stats.candidates - stats.sieved;
+        assert!(p > (Integer::from(1) << 256));
+        assert!(tested >= 1 && stats.rounds >= K as u64 && stats.rounds <= tested * K as u64);
+        let mut search = SafePrimeSearch::new(&(Integer::from(1) << 64), K).with_round_stats();
+        search.next_safe_prime();
+        let stats = search.stats();
+        assert!(stats.sieved > 0 && stats.sieved < stats.candidates);
+        assert!(stats.rounds >= 2 * K as u64);
+    }
+
+    #[test]
+    fn test_safe_prime() {
+        let p =  Integer::from(Integer::parse_radix(
+            "CE9E0307D2AE75BDBEEC3E0A6E71A279417B56C955C602FFFD067586BACFDAC3BCC49A49EB4D126F5E9255E57C14F3E09492B6496EC8AC1366FC4BB7F678573FA2767E6547FA727FC0E631AA6F155195C035AF7273F31DFAE1166D1805C8522E95F9AF9CE33239BF3B68111141C20026673A6C8B9AD5FA8372ED716799FE05C0BB6EAF9FCA1590BD9644DBEFAA77BA01FD1C0D4F2D53BAAE965B1786EC55961A8E2D3E4FE8505914A408D50E6B99B71CDA78D8F9AF1A662512F8C4C3A9E72AC72D40AE5D4A0E6571135CBBAAE08C7A2AA0892F664549FA7EEC81BA912743F3E584AC2B2092243C4A17EC98DF079D8EECB8B885E6BBAFA452AAFA8CB8C08024EFF28DE4AF4AC710DCD3D66FD88212101BCB412BCA775F94A2DCE18B1A6452D4CF818B6D099D4505E0040C57AE1F3E84F2F8E07A69C0024C05ACE05666A6B63B0695904478487E78CD0704C14461F24636D7A3F267A654EEDCF8789C7F627C72B4CBD54EED6531C0E54E325D6F09CB648AE9185A7BDA6553E40B125C78E5EAA867", 16
+        ).unwrap());
+        assert!(miller_rabin(&p, K));
+        assert!(miller_rabin_safe(&p, K));
+    }
+
+    #[test]
+    fn test_performance() {
+        let p =  Integer::from(Integer::parse_radix(
+            "CE9E0307D2AE75BDBEEC3E0A6E71A279417B56C955C602FFFD067586BACFDAC3BCC49A49EB4D126F5E9255E57C14F3E09492B6496EC8AC1366FC4BB7F678573FA2767E6547FA727FC0E631AA6F155195C035AF7273F31DFAE1166D1805C8522E95F9AF9CE33239BF3B68111141C20026673A6C8B9AD5FA8372ED716799FE05C0BB6EAF9FCA1590BD9644DBEFAA77BA01FD1C0D4F2D53BAAE965B1786EC55961A8E2D3E4FE8505914A408D50E6B99B71CDA78D8F9AF1A662512F8C4C3A9E72AC72D40AE5D4A0E6571135CBBAAE08C7A2AA0892F664549FA7EEC81BA912743F3E584AC2B2092243C4A17EC98DF079D8EECB8B885E6BBAFA452AAFA8CB8C08024EFF28DE4AF4AC710DCD3D66FD88212101BCB412BCA775F94A2DCE18B1A6452D4CF818B6D099D4505E0040C57AE1F3E84F2F8E07A69C0024C05ACE05666A6B63B0695904478487E78CD0704C14461F24636D7A3F267A654EEDCF8789C7F627C72B4CBD54EED6531C0E54E325D6F09CB648AE9185A7BDA6553E40B125C78E5EAA867", 16
+        ).unwrap());
+        let begin_rug = SystemTime::now();
+        let res_rug = is_prime(&p, 100);
+        let duration_rug = begin_rug.elapsed().unwrap();
+        let begin_gmpmee = SystemTime::now();
+        let res_gmpmee = miller_rabin(&p, 100);
+        let duration_gmpmee = begin_gmpmee.elapsed().unwrap();
+        assert!(res_rug);
+        assert!(res_gmpmee);
+        /*assert!(
+            duration_rug > duration_gmpmee,
+            "The duration of spown (={} ms) is bigger than duration with rug (={} ms)",
+            duration_gmpmee.as_millis(),
+            duration_rug.as_millis()
+        );*/
+        println!("Duration rug: {} ms", duration_rug.as_millis());
+        println!("Duration gmpmee: {} ms", duration_gmpmee.as_millis());
+    }
+}
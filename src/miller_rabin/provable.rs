@@ -0,0 +1,217 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Provable primes of Shawe-Taylor (see [ShaweTaylorPrime])
+
+use super::{
+    MillerRabinError,
+    domain::{OUTLEN, hash, seed_bytes},
+    is_prime_deterministic,
+};
+use crate::GmpMEEError;
+use rug::{Integer, integer::Order, rand::RandState};
+
+/// Bit length of the seeds drawn by [ShaweTaylorPrime::random]
+const SEED_BITS: u32 = 256;
+
+/// Prime constructed from a seed with the method of Shawe-Taylor (FIPS 186-4, appendix C.6) and
+/// SHA-256
+///
+/// The primality is proven by the construction: the primes of at most 32 bits are tested by the
+/// deterministic test, and a larger prime `c = 2·t·c0 + 1` is proven with the theorem of Pocklington
+/// from a provable prime `c0` of about half its length. Anybody can reproduce the construction from
+/// the seed (see [Self::verify]).
+/// ```
+/// use rug::rand::RandState;
+/// use rug_gmpmee::miller_rabin::{ShaweTaylorPrime, miller_rabin};
+/// let st = ShaweTaylorPrime::random(512, &mut RandState::new()).unwrap();
+/// assert_eq!(st.prime.significant_bits(), 512);
+/// assert!(miller_rabin(&st.prime, 16));
+/// assert!(st.verify());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaweTaylorPrime {
+    /// Provable prime
+    pub prime: Integer,
+    /// Input seed of the construction
+    pub seed: Vec<u8>,
+    /// Seed at the end of the construction (e.g. for the derivation of further values)
+    pub prime_seed: Vec<u8>,
+    /// Number of candidates of the last step of the construction
+    pub prime_gen_counter: u32,
+}
+
+impl ShaweTaylorPrime {
+    /// Construct the provable prime of `length` bits from the seed
+    ///
+    /// Return `None` if the construction fails, which happens when too many candidates are
+    /// rejected. Return an error if `length` is smaller than 2 or if the seed is empty.
+    pub fn from_seed(length: u32, seed: &[u8]) -> Result<Option<Self>, GmpMEEError> {
+        if length < 2 {
+            return Err(MillerRabinError::TooFewPrimeBits { bits: length }.into());
+        }
+        if seed.is_empty() {
+            return Err(MillerRabinError::EmptySeed.into());
+        }
+        let seedlen = u32::try_from(8 * seed.len()).unwrap_or(u32::MAX);
+        let input_seed = Integer::from_digits(seed, Order::Msf);
+        Ok(
+            st_random_prime(length, &input_seed, seedlen).map(|(prime, prime_seed, counter)| {
+                Self {
+                    prime,
+                    seed: seed.to_vec(),
+                    prime_seed: seed_bytes(&prime_seed, seedlen),
+                    prime_gen_counter: counter,
+                }
+            }),
+        )
+    }
+
+    /// Construct a provable prime of `length` bits from random seeds of 256 bits drawn from `rand`
+    ///
+    /// Return an error if `length` is smaller than 2.
+    pub fn random(length: u32, rand: &mut RandState<'_>) -> Result<Self, GmpMEEError> {
+        loop {
+            let seed = seed_bytes(
+                &Integer::from(Integer::random_bits(SEED_BITS, rand)),
+                SEED_BITS,
+            );
+            if let Some(st) = Self::from_seed(length, &seed)? {
+                return Ok(st);
+            }
+        }
+    }
+
+    /// Verify that the prime is constructed from the seed
+    pub fn verify(&self) -> bool {
+        matches!(
+            Self::from_seed(self.prime.significant_bits(), &self.seed),
+            Ok(Some(st)) if st == *self
+        )
+    }
+}
+
+/// `ceil(a/b)` for `a, b > 0`
+fn ceil_div(a: &Integer, b: &Integer) -> Integer {
+    (Integer::from(a + b) - 1u32) / b
+}
+
+/// Concatenation of the hashes of `prime_seed + i` for `i` in `[0, blocks]`, the first one as the
+/// least significant, and move `prime_seed` after the last one
+fn hash_blocks(prime_seed: &mut Integer, blocks: u32, seedlen: u32) -> Integer {
+    let mut x = Integer::new();
+    for i in 0..=blocks {
+        x += hash(&Integer::from(&*prime_seed + i), seedlen) << (i * OUTLEN);
+    }
+    *prime_seed += blocks + 1;
+    x
+}
+
+/// Procedure `ST_Random_Prime` for `length >= 2`, returning the prime, the prime seed and the
+/// prime generation counter, or `None` in case of failure
+fn st_random_prime(
+    length: u32,
+    input_seed: &Integer,
+    seedlen: u32,
+) -> Option<(Integer, Integer, u32)> {
+    let top = Integer::from(1) << (length - 1);
+    if length < 33 {
+        let mut prime_seed = input_seed.clone();
+        for counter in 1..=4 * length + 1 {
+            let c = hash(&prime_seed, seedlen) ^ hash(&Integer::from(&prime_seed + 1u32), seedlen);
+            let c = (c.keep_bits(length - 1) + &top) | 1u32;
+            prime_seed += 2u32;
+            if is_prime_deterministic(&c) {
+                return Some((c, prime_seed, counter));
+            }
+        }
+        return None;
+    }
+    let (c0, mut prime_seed, mut counter) =
+        st_random_prime(length.div_ceil(2) + 1, input_seed, seedlen)?;
+    let blocks = length.div_ceil(OUTLEN) - 1;
+    let old_counter = counter;
+    let x = hash_blocks(&mut prime_seed, blocks, seedlen).keep_bits(length - 1) + &top;
+    let two_c0 = Integer::from(&c0 << 1u32);
+    let bound = Integer::from(1) << length;
+    let mut t = ceil_div(&x, &two_c0);
+    loop {
+        if Integer::from(&t * &two_c0) + 1u32 > bound {
+            t = ceil_div(&top, &two_c0);
+        }
+        let c = Integer::from(&t * &two_c0) + 1u32;
+        counter += 1;
+        let a = hash_blocks(&mut prime_seed, blocks, seedlen) % Integer::from(&c - 3u32) + 2u32;
+        // Pocklington: z = a^(2t) has the order c0 > sqrt(c) modulo c
+        let z = a.pow_mod(&Integer::from(&t << 1u32), &c).ok()?;
+        if Integer::from(&z - 1u32).gcd(&c) == 1 && z.pow_mod(&c0, &c).is_ok_and(|v| v == 1) {
+            return Some((c, prime_seed, counter));
+        }
+        if counter >= 4 * length + old_counter {
+            return None;
+        }
+        t += 1u32;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::miller_rabin::miller_rabin;
+
+    #[test]
+    fn test_shawe_taylor() {
+        let seed = [1u8; 32];
+        let seed_value = Integer::from_digits(&seed, Order::Msf);
+        for (length, prime, counter, offset) in [
+            (32, "936e8441", 1, 2u32),
+            (64, "ad88929834c943c5", 27, 0x29),
+            (
+                256,
+                "e7a36fc7420da8b1a510e2e6c6271397b73b202666394112ab68d52f5113458d",
+                99,
+                0x73,
+            ),
+        ] {
+            let st = ShaweTaylorPrime::from_seed(length, &seed).unwrap().unwrap();
+            assert_eq!(st.prime, Integer::from_str_radix(prime, 16).unwrap());
+            assert_eq!(st.prime_gen_counter, counter);
+            assert_eq!(
+                st.prime_seed,
+                seed_bytes(&Integer::from(&seed_value + offset), 256)
+            );
+            assert!(st.verify());
+        }
+        let mut rand = RandState::new();
+        for length in [2, 3, 33, 100, 1024] {
+            let st = ShaweTaylorPrime::random(length, &mut rand).unwrap();
+            assert_eq!(st.prime.significant_bits(), length);
+            assert!(miller_rabin(&st.prime, 16));
+            assert!(st.verify());
+            let mut forged = st;
+            forged.seed[0] ^= 1;
+            assert!(!forged.verify());
+        }
+        assert_eq!(
+            ShaweTaylorPrime::from_seed(1, &seed),
+            Err(MillerRabinError::TooFewPrimeBits { bits: 1 }.into())
+        );
+        assert_eq!(
+            ShaweTaylorPrime::from_seed(64, &[]),
+            Err(MillerRabinError::EmptySeed.into())
+        );
+    }
+}
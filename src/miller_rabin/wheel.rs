@@ -0,0 +1,165 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Wheels skipping the candidates divisible by the first primes (see [Wheel])
+
+use super::{MillerRabinError, small_primes};
+use crate::GmpMEEError;
+use rug::Integer;
+
+/// Maximal number of primes of a [Wheel] (`2·3·5·7·11·13 = 30030`, with 5760 residues)
+pub const MAX_WHEEL_PRIMES: usize = 6;
+
+/// Number of primes of the default wheel (`2·3·5·7 = 210`, with 48 residues)
+pub const DEFAULT_WHEEL_PRIMES: usize = 4;
+
+/// Wheel of the first primes: the candidates are the integers coprime to the product of the primes
+/// (the modulus), i.e. the integers congruent to one of the residues modulo the modulus
+///
+/// The candidates skip the obviously composite integers before any trial division. The primes of
+/// the wheel themselves are never candidates.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::miller_rabin::Wheel;
+/// let wheel = Wheel::default();
+/// assert_eq!(wheel.modulus(), 210);
+/// assert_eq!(wheel.residues().len(), 48);
+/// let candidates: Vec<Integer> = wheel.candidates(&Integer::from(200)).take(4).collect();
+/// assert_eq!(candidates, [209, 211, 221, 223]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wheel {
+    modulus: u32,
+    residues: Vec<u32>,
+}
+
+impl Wheel {
+    /// Wheel of the first `primes` primes
+    ///
+    /// Return an error if `primes` is not in `[1, MAX_WHEEL_PRIMES]`.
+    pub fn new(primes: usize) -> Result<Self, GmpMEEError> {
+        if !(1..=MAX_WHEEL_PRIMES).contains(&primes) {
+            return Err(MillerRabinError::InvalidWheel { primes }.into());
+        }
+        let primes = &small_primes()[..primes];
+        let modulus: u32 = primes.iter().product();
+        let residues = (1..modulus)
+            .filter(|r| primes.iter().all(|p| !r.is_multiple_of(*p)))
+            .collect();
+        Ok(Self { modulus, residues })
+    }
+
+    /// Product of the primes of the wheel
+    pub fn modulus(&self) -> u32 {
+        self.modulus
+    }
+
+    /// Residues modulo [Self::modulus] of the candidates, in increasing order
+    pub fn residues(&self) -> &[u32] {
+        &self.residues
+    }
+
+    /// Candidates of the wheel greater or equal than `start` and 2, in increasing order
+    pub fn candidates(&self, start: &Integer) -> WheelCandidates {
+        let start = start.clone().max(Integer::from(2));
+        let r = start.mod_u(self.modulus);
+        let base = start - r;
+        match self.residues.iter().position(|res| *res >= r) {
+            Some(index) => WheelCandidates {
+                wheel: self.clone(),
+                base,
+                index,
+            },
+            None => WheelCandidates {
+                base: base + self.modulus,
+                wheel: self.clone(),
+                index: 0,
+            },
+        }
+    }
+}
+
+impl Default for Wheel {
+    /// Wheel of the first [DEFAULT_WHEEL_PRIMES] primes
+    fn default() -> Self {
+        Self::new(DEFAULT_WHEEL_PRIMES).expect("the default wheel is valid")
+    }
+}
+
+/// Infinite iterator over the candidates of a [Wheel] (see [Wheel::candidates])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WheelCandidates {
+    wheel: Wheel,
+    /// Multiple of the modulus of the wheel
+    base: Integer,
+    index: usize,
+}
+
+impl WheelCandidates {
+    /// Return the next candidate
+    pub fn next_candidate(&mut self) -> Integer {
+        let candidate = Integer::from(&self.base + self.wheel.residues[self.index]);
+        self.index += 1;
+        if self.index == self.wheel.residues.len() {
+            self.index = 0;
+            self.base += self.wheel.modulus;
+        }
+        candidate
+    }
+}
+
+impl Iterator for WheelCandidates {
+    type Item = Integer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_candidate())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wheel() {
+        for primes in 1..=MAX_WHEEL_PRIMES {
+            let wheel = Wheel::new(primes).unwrap();
+            let wheel_primes = &small_primes()[..primes];
+            let phi: u32 = wheel_primes.iter().map(|p| p - 1).product();
+            assert_eq!(wheel.residues().len(), phi as usize);
+            let start = Integer::from(1_000_000);
+            let expected: Vec<Integer> = (1_000_000u32..)
+                .filter(|n| wheel_primes.iter().all(|p| !n.is_multiple_of(*p)))
+                .take(100)
+                .map(Integer::from)
+                .collect();
+            let candidates: Vec<Integer> = wheel.candidates(&start).take(100).collect();
+            assert_eq!(candidates, expected);
+        }
+        let wheel = Wheel::default();
+        assert_eq!(wheel.candidates(&Integer::from(-5)).next().unwrap(), 11);
+        assert_eq!(wheel.candidates(&Integer::from(210)).next().unwrap(), 211);
+        assert_eq!(wheel.candidates(&Integer::from(420)).next().unwrap(), 421);
+        assert_eq!(wheel.candidates(&Integer::from(418)).next().unwrap(), 419);
+        assert!(matches!(
+            Wheel::new(0),
+            Err(GmpMEEError::MillerRabinParameters(
+                MillerRabinError::InvalidWheel { primes: 0 }
+            ))
+        ));
+        assert!(Wheel::new(MAX_WHEEL_PRIMES + 1).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Wrappers of the long-running operations for asynchronous code (only with the feature `async`)
+//!
+//! Each operation runs in the pool of blocking threads of tokio ([tokio::task::spawn_blocking]),
+//! so that the executor of an asynchronous service is not blocked by a precomputation, a prime
+//! generation or a large multi-exponentiation. The arguments are owned since the tasks can outlive
+//! the caller, and the random states are given back to continue their sequence.
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::nonblocking::spowm;
+//! let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+//! let res = runtime.block_on(spowm(
+//!     vec![Integer::from(2), Integer::from(3)],
+//!     vec![Integer::from(10), Integer::from(5)],
+//!     Integer::from(1019),
+//! ));
+//! assert_eq!(res.unwrap(), 1024 * 243 % 1019);
+//! ```
+
+use crate::{GmpMEEError, fpowm::FPowmTable, groups::GroupParams, miller_rabin, spown};
+use rug::{Integer, rand::RandState};
+use thiserror::Error;
+use tokio::task::spawn_blocking;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AsyncError {
+    #[error("The blocking task failed: {0}")]
+    Join(String),
+}
+
+/// Run the operation in the pool of blocking threads
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, GmpMEEError> + Send + 'static,
+) -> Result<T, GmpMEEError> {
+    spawn_blocking(f)
+        .await
+        .map_err(|e| AsyncError::Join(e.to_string()))?
+}
+
+/// Precompute the table of the base (see [FPowmTable::init_precomp])
+pub async fn init_precomp(
+    base: Integer,
+    modulus: Integer,
+    block_width: usize,
+    exponent_bitlen: usize,
+) -> Result<FPowmTable, GmpMEEError> {
+    blocking(move || FPowmTable::init_precomp(&base, &modulus, block_width, exponent_bitlen)).await
+}
+
+/// Random safe prime of `bits` bits (see [miller_rabin::random_safe_prime]), returned with the
+/// random state
+pub async fn random_safe_prime(
+    bits: u32,
+    reps: i32,
+    mut rand: RandState<'static>,
+) -> Result<(Integer, RandState<'static>), GmpMEEError> {
+    blocking(move || {
+        let p = miller_rabin::random_safe_prime(bits, reps, &mut rand)?;
+        Ok((p, rand))
+    })
+    .await
+}
+
+/// Parameters of a group with a random safe prime of `bits` bits (see [GroupParams::generate]),
+/// returned with the random state
+pub async fn generate_group(
+    bits: u32,
+    reps: i32,
+    mut rand: RandState<'static>,
+) -> Result<(GroupParams, RandState<'static>), GmpMEEError> {
+    blocking(move || {
+        let group = GroupParams::generate(bits, reps, &mut rand)?;
+        Ok((group, rand))
+    })
+    .await
+}
+
+/// Multi exponentiation of the bases and the exponents (see [spown::spowm])
+pub async fn spowm(
+    bases: Vec<Integer>,
+    exponents: Vec<Integer>,
+    modulus: Integer,
+) -> Result<Integer, GmpMEEError> {
+    blocking(move || spown::spowm(&bases, &exponents, &modulus)).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{miller_rabin::miller_rabin, spown::SPownError};
+    use tokio::runtime::{Builder, Runtime};
+
+    fn runtime() -> Runtime {
+        Builder::new_current_thread().build().unwrap()
+    }
+
+    #[test]
+    fn test_operations() {
+        let runtime = runtime();
+        let p = Integer::from(1019);
+        let table = runtime
+            .block_on(init_precomp(Integer::from(7), p.clone(), 4, 16))
+            .unwrap();
+        assert_eq!(
+            table.fpowm(&Integer::from(100)),
+            Integer::from(7).pow_mod(&Integer::from(100), &p).unwrap()
+        );
+        assert_eq!(
+            runtime.block_on(spowm(vec![Integer::from(2)], vec![], p)),
+            Err(SPownError::NotSameLen {
+                base: 1,
+                exponent: 0
+            }
+            .into())
+        );
+        let (p, rand) = runtime
+            .block_on(random_safe_prime(64, 16, RandState::new()))
+            .unwrap();
+        assert!(miller_rabin(&p, 16) && miller_rabin(&(Integer::from(&p - 1) >> 1), 16));
+        let (group, _) = runtime.block_on(generate_group(64, 16, rand)).unwrap();
+        assert!(group.verify(16).is_valid());
+    }
+}
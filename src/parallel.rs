@@ -0,0 +1,80 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Parallel map of a slice over the available cores, shared by the batch operations
+//!
+//! On `wasm32-unknown-unknown`, which has no threads, the parallel operations of the crate run on
+//! the calling thread (see [THREADS]).
+
+use crate::trace::timed_span;
+use std::thread;
+
+/// `false` on the targets without threads, where no thread is spawned
+pub(crate) const THREADS: bool = !cfg!(all(target_arch = "wasm32", target_os = "unknown"));
+
+/// Number of available cores, or 1 on the targets without threads
+pub(crate) fn available_workers() -> usize {
+    match THREADS {
+        true => thread::available_parallelism().map_or(1, |w| w.get()),
+        false => 1,
+    }
+}
+
+/// Map `f` over the items, with the items split in one chunk per available core and the chunks
+/// mapped in parallel
+///
+/// The results are in the order of the items. With a single worker, the items are mapped on the
+/// calling thread. Panics if a worker panics.
+pub(crate) fn map_chunks<T, U, F>(items: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync,
+{
+    let workers = available_workers();
+    timed_span!("parallel_batch", count = items.len(), workers = workers);
+    if workers == 1 {
+        return items.iter().map(f).collect();
+    }
+    let chunk_len = items.len().div_ceil(workers).max(1);
+    let f = &f;
+    thread::scope(|s| {
+        let handles = items
+            .chunks(chunk_len)
+            .map(|chunk| s.spawn(move || chunk.iter().map(f).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("a worker panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_map_chunks() {
+        for len in [0, 1, 7, 1000] {
+            let items = (0..len).collect::<Vec<u64>>();
+            assert_eq!(
+                map_chunks(&items, |i| i * i),
+                items.iter().map(|i| i * i).collect::<Vec<_>>()
+            );
+        }
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module for the Pedersen commitments `g^m * h^r mod p` using precomputed tables for the
+//! generators `g` and `h`
+//!
+//! The tables are built for the subgroup of order `q` of the generators (see
+//! [FPowmTable::for_subgroup]).
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::fpowm::FPowmTable;
+//! use rug_gmpmee::pedersen::commit_batch;
+//! let (p, q) = (Integer::from(1019), Integer::from(509));
+//! let g_table = FPowmTable::for_subgroup(&Integer::from(4), &p, &q).unwrap();
+//! let h_table = FPowmTable::for_subgroup(&Integer::from(9), &p, &q).unwrap();
+//! let messages = [3, 100].map(Integer::from);
+//! let randomness = [5, 7].map(Integer::from);
+//! let cs = commit_batch(&g_table, &h_table, &messages, &randomness).unwrap();
+//! assert_eq!(cs[0], Integer::from(4u32.pow(3) * 9u32.pow(5)) % &p);
+//! ```
+
+use crate::GmpMEEError;
+use crate::fpowm::{FPowmTable, check_same_len, check_same_modulus};
+use crate::parallel::map_chunks;
+use crate::spown::spowm;
+use crate::telemetry::{self, Batch};
+use crate::trace::timed_span;
+use rug::Integer;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PedersenError {
+    #[error("Len of messages {messages} is not the same than len of generators {generators}")]
+    NotSameLenGenerators { messages: usize, generators: usize },
+}
+
+fn commit_unchecked(
+    g_table: &FPowmTable,
+    h_table: &FPowmTable,
+    message: &Integer,
+    randomness: &Integer,
+) -> Integer {
+    (g_table.fpowm(message) * h_table.fpowm(randomness)) % g_table.modulus()
+}
+
+/// Commit to the message with the randomness, using the tables of the generators `g` and `h`
+///
+/// The tables must have the same modulus
+pub fn commit(
+    g_table: &FPowmTable,
+    h_table: &FPowmTable,
+    message: &Integer,
+    randomness: &Integer,
+) -> Result<Integer, GmpMEEError> {
+    check_same_modulus(g_table, h_table)?;
+    Ok(commit_unchecked(g_table, h_table, message, randomness))
+}
+
+/// Commit to each message with the randomness at the same position
+///
+/// The batch is computed table by table: all the powers of `g` in a first pass, then all the
+/// powers of `h`, so that a single table is read by the cores during each pass. The passes are
+/// split in one chunk per available core. The number of messages and of randomness must be the
+/// same.
+pub fn commit_batch(
+    g_table: &FPowmTable,
+    h_table: &FPowmTable,
+    messages: &[Integer],
+    randomness: &[Integer],
+) -> Result<Vec<Integer>, GmpMEEError> {
+    timed_span!(
+        "pedersen_commit_batch",
+        count = messages.len(),
+        modulus_bits = g_table.modulus().significant_bits()
+    );
+    telemetry::record_batch(Batch::PedersenCommit, messages.len());
+    check_same_modulus(g_table, h_table)?;
+    check_same_len(messages.len(), randomness.len())?;
+    let modulus = g_table.modulus();
+    let g_powers = map_chunks(messages, |m| g_table.fpowm(m));
+    let h_powers = map_chunks(randomness, |r| h_table.fpowm(r));
+    Ok(g_powers
+        .into_iter()
+        .zip(h_powers)
+        .map(|(g_m, h_r)| g_m * h_r % modulus)
+        .collect())
+}
+
+/// Commit to the vector of messages with a single randomness, i.e. compute
+/// `g_1^m_1 * ... * g_n^m_n * h^r mod p` with one multi-exponentiation (see [spowm])
+///
+/// The number of messages and of generators must be the same
+pub fn commit_vector(
+    generators: &[Integer],
+    h: &Integer,
+    messages: &[Integer],
+    randomness: &Integer,
+    modulus: &Integer,
+) -> Result<Integer, GmpMEEError> {
+    if messages.len() != generators.len() {
+        return Err(PedersenError::NotSameLenGenerators {
+            messages: messages.len(),
+            generators: generators.len(),
+        }
+        .into());
+    }
+    let bases = [generators, std::slice::from_ref(h)].concat();
+    let exponents = [messages, std::slice::from_ref(randomness)].concat();
+    spowm(&bases, &exponents, modulus)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fpowm::{FPownError, test_tables};
+
+    #[test]
+    fn test_commit() {
+        let (p, g, h, g_table, h_table) = test_tables();
+        let m = Integer::from(3);
+        let r = Integer::from(5);
+        let c = commit(&g_table, &h_table, &m, &r).unwrap();
+        assert_eq!(
+            c,
+            (g.pow_mod(&m, &p).unwrap() * h.pow_mod(&r, &p).unwrap()) % &p
+        );
+        let other = FPowmTable::init_precomp(&Integer::from(9), &Integer::from(29), 4, 8).unwrap();
+        assert_eq!(
+            commit(&g_table, &other, &m, &r),
+            Err(FPownError::DifferentModulus.into())
+        );
+    }
+
+    #[test]
+    fn test_commit_batch() {
+        let (_, _, _, g_table, h_table) = test_tables();
+        let ms = [Integer::from(3), Integer::from(8), Integer::from(13)];
+        let rs = [Integer::from(5), Integer::from(1), Integer::from(10)];
+        let cs = commit_batch(&g_table, &h_table, &ms, &rs).unwrap();
+        for ((m, r), c) in ms.iter().zip(rs.iter()).zip(cs.iter()) {
+            assert_eq!(c, &commit(&g_table, &h_table, m, r).unwrap());
+        }
+        assert_eq!(
+            commit_batch(&g_table, &h_table, &ms, &rs[..2]),
+            Err(FPownError::NotSameLen {
+                inputs: 3,
+                randomness: 2
+            }
+            .into())
+        );
+        assert!(
+            commit_batch(&g_table, &h_table, &[], &[])
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_commit_vector() {
+        let (p, g, h, g_table, h_table) = test_tables();
+        let gs = [g.clone(), Integer::from(2), Integer::from(13)];
+        let ms = [Integer::from(3), Integer::from(8), Integer::from(11)];
+        let r = Integer::from(5);
+        let c = commit_vector(&gs, &h, &ms, &r, &p).unwrap();
+        let expected = gs
+            .iter()
+            .zip(ms.iter())
+            .map(|(g, m)| Integer::from(g.pow_mod_ref(m, &p).unwrap()))
+            .fold(h.clone().pow_mod(&r, &p).unwrap(), |acc, v| (acc * v) % &p);
+        assert_eq!(c, expected);
+        assert_eq!(
+            commit_vector(&gs[..1], &h, &ms[..1], &r, &p).unwrap(),
+            commit(&g_table, &h_table, &ms[0], &r).unwrap()
+        );
+        assert_eq!(
+            commit_vector(&gs, &h, &ms[..2], &r, &p),
+            Err(PedersenError::NotSameLenGenerators {
+                messages: 2,
+                generators: 3
+            }
+            .into())
+        );
+    }
+}
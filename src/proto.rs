@@ -0,0 +1,179 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module for the protobuf encoding of the group parameters and the precomputed tables (only with
+//! the feature `protobuf`)
+//!
+//! The messages are given by the schema [PROTO_SCHEMA], so that they can be exchanged with other
+//! languages, e.g. over gRPC. The integers are encoded as their absolute value in unsigned big
+//! endian. The precomputed table is sent in its serialized form (see [FPowmTable::to_bytes]),
+//! which already contains its parameters.
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::{fpowm::FPowmTable, proto::{decode_table, encode_table}};
+//! let table = FPowmTable::init_precomp(&Integer::from(7), &Integer::from(1019), 4, 16).unwrap();
+//! let decoded = decode_table(&encode_table(&table).unwrap()).unwrap();
+//! assert_eq!(decoded.fpowm(&Integer::from(100)), table.fpowm(&Integer::from(100)));
+//! ```
+
+use crate::{GmpMEEError, fpowm::FPowmTable, groups::GroupParams};
+use prost::Message;
+use rug::{Integer, integer::Order};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProtoError {
+    #[error("Invalid protobuf message: {0}")]
+    Decode(String),
+}
+
+/// Protobuf schema of the messages
+pub const PROTO_SCHEMA: &str = include_str!("../proto/rug_gmpmee.proto");
+
+/// Message `GroupParams` of [PROTO_SCHEMA]
+#[derive(Clone, PartialEq, Message)]
+pub struct ProtoGroupParams {
+    #[prost(bytes = "vec", tag = "1")]
+    pub p: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub q: Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub g: Vec<u8>,
+}
+
+/// Message `FPowmTable` of [PROTO_SCHEMA]
+#[derive(Clone, PartialEq, Message)]
+pub struct ProtoTable {
+    #[prost(bytes = "vec", tag = "1")]
+    pub serialized: Vec<u8>,
+}
+
+fn to_bytes(value: &Integer) -> Vec<u8> {
+    value.to_digits::<u8>(Order::Msf)
+}
+
+fn from_bytes(bytes: &[u8]) -> Integer {
+    Integer::from_digits(bytes, Order::Msf)
+}
+
+fn decode_error(e: prost::DecodeError) -> GmpMEEError {
+    ProtoError::Decode(e.to_string()).into()
+}
+
+impl From<&GroupParams> for ProtoGroupParams {
+    fn from(group: &GroupParams) -> Self {
+        Self {
+            p: to_bytes(&group.p),
+            q: to_bytes(&group.q),
+            g: to_bytes(&group.g),
+        }
+    }
+}
+
+/// The parameters are not verified (see [GroupParams::verify])
+impl From<&ProtoGroupParams> for GroupParams {
+    fn from(message: &ProtoGroupParams) -> Self {
+        Self {
+            p: from_bytes(&message.p),
+            q: from_bytes(&message.q),
+            g: from_bytes(&message.g),
+        }
+    }
+}
+
+impl TryFrom<&FPowmTable> for ProtoTable {
+    type Error = GmpMEEError;
+
+    /// Return an error if the precomputation has not been done
+    fn try_from(table: &FPowmTable) -> Result<Self, Self::Error> {
+        Ok(Self {
+            serialized: table.to_bytes()?,
+        })
+    }
+}
+
+impl TryFrom<&ProtoTable> for FPowmTable {
+    type Error = GmpMEEError;
+
+    /// Return an error if the serialized table is invalid (see [FPowmTable::from_bytes])
+    fn try_from(message: &ProtoTable) -> Result<Self, Self::Error> {
+        FPowmTable::from_bytes(&message.serialized)
+    }
+}
+
+/// Encode the group parameters in protobuf
+pub fn encode_group(group: &GroupParams) -> Vec<u8> {
+    ProtoGroupParams::from(group).encode_to_vec()
+}
+
+/// Decode the group parameters from protobuf
+pub fn decode_group(bytes: &[u8]) -> Result<GroupParams, GmpMEEError> {
+    let message = ProtoGroupParams::decode(bytes).map_err(decode_error)?;
+    Ok(GroupParams::from(&message))
+}
+
+/// Encode the precomputed table in protobuf
+pub fn encode_table(table: &FPowmTable) -> Result<Vec<u8>, GmpMEEError> {
+    Ok(ProtoTable::try_from(table)?.encode_to_vec())
+}
+
+/// Decode the precomputed table from protobuf
+pub fn decode_table(bytes: &[u8]) -> Result<FPowmTable, GmpMEEError> {
+    let message = ProtoTable::decode(bytes).map_err(decode_error)?;
+    FPowmTable::try_from(&message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::standard_groups::StandardGroup;
+
+    #[test]
+    fn test_group() {
+        let group = GroupParams::from(StandardGroup::Modp2048S224);
+        let bytes = encode_group(&group);
+        assert_eq!(decode_group(&bytes).unwrap(), group);
+        // p = 23 is the field 1 of length 1
+        let small = GroupParams {
+            p: Integer::from(23),
+            q: Integer::from(11),
+            g: Integer::from(4),
+        };
+        assert_eq!(
+            encode_group(&small),
+            [0x0a, 0x01, 0x17, 0x12, 0x01, 0x0b, 0x1a, 0x01, 0x04]
+        );
+        assert!(decode_group(&[0x0a, 0x05, 0x17]).is_err());
+    }
+
+    #[test]
+    fn test_table() {
+        let table =
+            FPowmTable::init_precomp(&Integer::from(7), &Integer::from(1019), 4, 16).unwrap();
+        let bytes = encode_table(&table).unwrap();
+        let decoded = decode_table(&bytes).unwrap();
+        let e = Integer::from(54321);
+        assert_eq!(decoded.fpowm(&e), table.fpowm(&e));
+
+        assert_eq!(bytes[0], 0x0a);
+        assert_eq!(decoded.block_width(), 4);
+        assert_eq!(decoded.exponent_bitlen(), 16);
+
+        let mut message = ProtoTable::try_from(&table).unwrap();
+        message.serialized.truncate(10);
+        assert!(FPowmTable::try_from(&message).is_err());
+    }
+}
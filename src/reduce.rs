@@ -0,0 +1,124 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module for the reduction of many integers by a common modulus (see [reduce_batch])
+
+use crate::parallel::map_chunks;
+use rug::Integer;
+
+/// Powers `m, m^2, m^4, ..., m^(2^k)` of the modulus, up to the first one with at least half the
+/// bits of the largest value
+///
+/// A value of `n` bits is reduced by the largest power smaller than it, then by the next smaller
+/// power, and so on. Each division is then balanced, with a dividend about twice as large as the
+/// divisor, and the powers are computed once for all the values.
+struct PowerTree {
+    powers: Vec<Integer>,
+}
+
+impl PowerTree {
+    fn new(modulus: &Integer, max_bits: u32) -> Self {
+        let mut powers = vec![modulus.clone()];
+        // the powers of 1 are all 1
+        while let Some(last) = powers.last()
+            && *last > 1
+            && 2 * last.significant_bits() < max_bits
+        {
+            let next = Integer::from(last.square_ref());
+            powers.push(next);
+        }
+        Self { powers }
+    }
+
+    /// Reduce the value in `[0, modulus[`
+    fn reduce(&self, value: &Integer) -> Integer {
+        let modulus = &self.powers[0];
+        let mut res = value.clone().abs();
+        for power in self.powers.iter().rev() {
+            if res.significant_bits() >= power.significant_bits() {
+                res %= power;
+            }
+        }
+        if *value < 0 && res != 0 {
+            res = modulus - res;
+        }
+        res
+    }
+}
+
+/// Reduce each of the values in `[0, modulus[`
+///
+/// The values are reduced with a tree of the powers of the modulus `m^(2^i)` shared by all the
+/// values, so that a value much larger than the modulus is reduced by balanced divisions (see
+/// [PowerTree]). The values are split in one chunk per available core, reduced in parallel.
+/// Panics if the modulus is not positive.
+/// ```
+/// use rug::Integer;
+/// use rug_gmpmee::reduce::reduce_batch;
+/// let values = [25, -3, 7, 0, 1i64 << 40].map(Integer::from);
+/// assert_eq!(
+///     reduce_batch(&values, &Integer::from(11)),
+///     [3, 8, 7, 0, (1i64 << 40) % 11].map(Integer::from)
+/// );
+/// ```
+pub fn reduce_batch(values: &[Integer], modulus: &Integer) -> Vec<Integer> {
+    assert!(*modulus > 0, "the modulus must be positive");
+    let max_bits = values
+        .iter()
+        .map(|v| v.significant_bits())
+        .max()
+        .unwrap_or(0);
+    let tree = PowerTree::new(modulus, max_bits);
+    map_chunks(values, |v| tree.reduce(v))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rug::rand::RandState;
+
+    fn expected(value: &Integer, modulus: &Integer) -> Integer {
+        let res = Integer::from(value % modulus);
+        match res < 0 {
+            true => res + modulus,
+            false => res,
+        }
+    }
+
+    #[test]
+    fn test_reduce_batch() {
+        let mut rand = RandState::new();
+        for modulus_bits in [1, 8, 64, 2048] {
+            let modulus = Integer::from(Integer::random_bits(modulus_bits, &mut rand)) + 1u32;
+            let values = (0..200u32)
+                .map(|i| {
+                    let v = Integer::from(Integer::random_bits(1 + 97 * i, &mut rand));
+                    match i % 3 {
+                        0 => -v,
+                        _ => v,
+                    }
+                })
+                .chain([Integer::new(), modulus.clone(), -modulus.clone()])
+                .collect::<Vec<_>>();
+            let res = reduce_batch(&values, &modulus);
+            assert_eq!(res.len(), values.len());
+            for (v, r) in values.iter().zip(res.iter()) {
+                assert_eq!(*r, expected(v, &modulus), "{v} {modulus}");
+            }
+        }
+        assert!(reduce_batch(&[], &Integer::from(7)).is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module to drive the random states of rug with a generator of `rand_core` (only with the feature
+//! `rand_core`)
+//!
+//! Any [RngCore] (e.g. `OsRng` or `ChaCha20Rng`) can be wrapped in a [RandState] and used by the
+//! primality and generation APIs taking a random state, so that an application uses a single
+//! source of randomness with its own seeding.
+//! ```
+//! use rand_core::{RngCore, impls};
+//! use rug::Integer;
+//! use rug_gmpmee::{miller_rabin::miller_rabin_with_rand, rng::rand_state_from_rng};
+//! struct Counter(u64);
+//! impl RngCore for Counter {
+//!     fn next_u32(&mut self) -> u32 {
+//!         self.next_u64() as u32
+//!     }
+//!     fn next_u64(&mut self) -> u64 {
+//!         self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1);
+//!         self.0 >> 11
+//!     }
+//!     fn fill_bytes(&mut self, dst: &mut [u8]) {
+//!         impls::fill_bytes_via_next(self, dst)
+//!     }
+//! }
+//! let mut rand = rand_state_from_rng(Counter(42));
+//! assert!(miller_rabin_with_rand(&Integer::from(1019), 16, &mut rand));
+//! ```
+
+use rand_core::RngCore;
+use rug::rand::{RandGen, RandState};
+
+/// Adapter of a generator of `rand_core` to the trait [RandGen] of rug
+///
+/// The random state cannot seed the generator, so that [RandState::seed] has no effect, and it
+/// cannot be cloned.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RngCoreGen<R>(R);
+
+impl<R> RngCoreGen<R> {
+    /// Wrap the generator
+    pub fn new(rng: R) -> Self {
+        Self(rng)
+    }
+
+    /// Return the wrapped generator
+    pub fn into_inner(self) -> R {
+        self.0
+    }
+}
+
+impl<R: RngCore + Send + Sync> RandGen for RngCoreGen<R> {
+    fn r#gen(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+}
+
+/// Random state of rug drawing its bits from the generator `rng`
+pub fn rand_state_from_rng<R: RngCore + Send + Sync + 'static>(rng: R) -> RandState<'static> {
+    RandState::new_custom_boxed(Box::new(RngCoreGen::new(rng)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::miller_rabin::{SafePrimeGenerator, miller_rabin_with_rand};
+    use rand_core::impls;
+    use rug::Integer;
+
+    /// Xorshift generator counting the words drawn
+    struct Xorshift {
+        state: u64,
+        draws: u64,
+    }
+
+    impl RngCore for Xorshift {
+        fn next_u32(&mut self) -> u32 {
+            (self.next_u64() >> 32) as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.draws += 1;
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 7;
+            self.state ^= self.state << 17;
+            self.state
+        }
+
+        fn fill_bytes(&mut self, dst: &mut [u8]) {
+            impls::fill_bytes_via_next(self, dst)
+        }
+    }
+
+    #[test]
+    fn test_rng_core() {
+        let mut xorshift = Xorshift {
+            state: 42,
+            draws: 0,
+        };
+        let mut generator = RngCoreGen::new(&mut xorshift);
+        let p = (Integer::from(1) << 127) - 1u32;
+        assert!(miller_rabin_with_rand(
+            &p,
+            16,
+            &mut RandState::new_custom(&mut generator)
+        ));
+        assert!(generator.into_inner().draws > 0);
+
+        let mut rand = rand_state_from_rng(Xorshift { state: 7, draws: 0 });
+        let outcome = SafePrimeGenerator::new(64, 16)
+            .generate(&mut rand)
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.prime.significant_bits(), 64);
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module for the verification of Schnorr signatures, one by one or by batch with a single
+//! multi-exponentiation (see [batch_verify_schnorr])
+//!
+//! A signature `(r, s, e)` of the public key `pk = g^x` is valid if `g^s = r * pk^e mod p`, where
+//! `r = g^k` is the commitment, `e` the challenge and `s = k + e*x mod q` the response.
+
+use crate::GmpMEEError;
+pub use crate::spown::WEIGHT_BITS;
+use crate::spown::{reduce, spowm};
+use rug::{Integer, rand::RandState};
+
+/// Schnorr signature `(r, s, e)` with the public key `pk` of the signer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchnorrSignature {
+    /// Commitment `r = g^k`
+    pub r: Integer,
+    /// Response `s = k + e*x mod q`
+    pub s: Integer,
+    /// Challenge `e`
+    pub e: Integer,
+    /// Public key `pk = g^x`
+    pub pk: Integer,
+}
+
+/// Verify the signature, i.e. check that `g^s = r * pk^e mod p`
+pub fn verify_schnorr(g: &Integer, p: &Integer, signature: &SchnorrSignature) -> bool {
+    let Some(lhs) = g.pow_mod_ref(&signature.s, p) else {
+        return false;
+    };
+    let Some(pk_e) = signature.pk.pow_mod_ref(&signature.e, p) else {
+        return false;
+    };
+    Integer::from(lhs) == Integer::from(pk_e) * &signature.r % p
+}
+
+/// Verify the signatures by batch in the group of prime order `q` generated by `g` modulo `p`
+///
+/// With random weights `w_i` of [WEIGHT_BITS] bits drawn from `rand`, the function checks that
+/// `g^(sum w_i*s_i mod q) = prod r_i^w_i * pk_i^(w_i*e_i mod q) mod p`, where the right-hand side
+/// is computed with one multi-exponentiation (see [spowm]).
+///
+/// The commitments and the public keys must be elements of the subgroup of order `q`, which has
+/// to be checked beforehand (e.g. with [crate::groups::are_quadratic_residues] for a safe prime
+/// `p`). Otherwise an invalid batch may be accepted. An empty batch is valid.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::schnorr::{SchnorrSignature, batch_verify_schnorr};
+/// let (p, q, g) = (Integer::from(23), Integer::from(11), Integer::from(4));
+/// let (x, k, e) = (Integer::from(7), Integer::from(3), Integer::from(5));
+/// let signature = SchnorrSignature {
+///     r: g.clone().pow_mod(&k, &p).unwrap(),
+///     s: (k + &e * x.clone()) % &q,
+///     e,
+///     pk: g.clone().pow_mod(&x, &p).unwrap(),
+/// };
+/// let mut rand = RandState::new();
+/// assert!(batch_verify_schnorr(&g, &p, &q, &[signature], &mut rand).unwrap());
+/// ```
+pub fn batch_verify_schnorr(
+    g: &Integer,
+    p: &Integer,
+    q: &Integer,
+    signatures: &[SchnorrSignature],
+    rand: &mut RandState<'_>,
+) -> Result<bool, GmpMEEError> {
+    if signatures.is_empty() {
+        return Ok(true);
+    }
+    let mut bases = Vec::with_capacity(2 * signatures.len());
+    let mut exponents = Vec::with_capacity(2 * signatures.len());
+    let mut s_sum = Integer::new();
+    for signature in signatures {
+        let w = Integer::from(Integer::random_bits(WEIGHT_BITS, rand));
+        s_sum += Integer::from(&w * &signature.s);
+        let we = reduce(&Integer::from(&w * &signature.e), q);
+        bases.push(signature.r.clone());
+        exponents.push(w);
+        bases.push(signature.pk.clone());
+        exponents.push(we);
+    }
+    let lhs = g
+        .pow_mod_ref(&reduce(&s_sum, q), p)
+        .map(Integer::from)
+        .unwrap_or_default();
+    Ok(lhs == spowm(&bases, &exponents, p)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::groups::GroupParams;
+
+    fn sign(group: &GroupParams, x: &Integer, rand: &mut RandState<'_>) -> SchnorrSignature {
+        let k = group.random_exponent(rand);
+        let e = group.random_exponent(rand);
+        SchnorrSignature {
+            r: group.g.clone().pow_mod(&k, &group.p).unwrap(),
+            s: (k + Integer::from(&e * x)) % &group.q,
+            e,
+            pk: group.g.clone().pow_mod(x, &group.p).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_batch_verify() {
+        let mut rand = RandState::new();
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        let (g, p, q) = (&group.g, &group.p, &group.q);
+        let signatures = (0..10)
+            .map(|_| {
+                let x = group.random_exponent(&mut rand);
+                sign(&group, &x, &mut rand)
+            })
+            .collect::<Vec<_>>();
+        assert!(signatures.iter().all(|s| verify_schnorr(g, p, s)));
+        assert!(batch_verify_schnorr(g, p, q, &signatures, &mut rand).unwrap());
+        assert!(batch_verify_schnorr(g, p, q, &signatures[..1], &mut rand).unwrap());
+        assert!(batch_verify_schnorr(g, p, q, &[], &mut rand).unwrap());
+        for i in [0, 5, 9] {
+            let mut forged = signatures.clone();
+            forged[i].s += 1;
+            assert!(!verify_schnorr(g, p, &forged[i]));
+            assert!(!batch_verify_schnorr(g, p, q, &forged, &mut rand).unwrap());
+            let mut forged = signatures.clone();
+            forged[i].e += 1;
+            assert!(!batch_verify_schnorr(g, p, q, &forged, &mut rand).unwrap());
+            let mut forged = signatures.clone();
+            forged[i].pk = forged[(i + 1) % 10].pk.clone();
+            assert!(!batch_verify_schnorr(g, p, q, &forged, &mut rand).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_batch_verify_negative() {
+        let mut rand = RandState::new();
+        let group = GroupParams::generate(256, 16, &mut rand).unwrap();
+        let (g, p, q) = (&group.g, &group.p, &group.q);
+        let mut signatures = (0..3)
+            .map(|_| {
+                let x = group.random_exponent(&mut rand);
+                sign(&group, &x, &mut rand)
+            })
+            .collect::<Vec<_>>();
+        // Same challenge and response modulo q, hence still valid
+        signatures[0].e -= q;
+        signatures[1].s -= q;
+        assert!(signatures[0].e < 0 && signatures[1].s < 0);
+        assert!(signatures.iter().all(|s| verify_schnorr(g, p, s)));
+        assert!(batch_verify_schnorr(g, p, q, &signatures, &mut rand).unwrap());
+        signatures[0].e -= 1;
+        assert!(!verify_schnorr(g, p, &signatures[0]));
+        assert!(!batch_verify_schnorr(g, p, q, &signatures, &mut rand).unwrap());
+    }
+}
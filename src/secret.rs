@@ -0,0 +1,105 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Module to wipe secret material from memory (only with the feature `zeroize`)
+//!
+//! The limbs of an [Integer] are overwritten with zeros before the memory is released, so that the
+//! values do not linger in freed memory.
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::fpowm::FPowmTable;
+//! use rug_gmpmee::secret::ZeroizingInteger;
+//! let p = Integer::from(13);
+//! let b = Integer::from(7);
+//! let tab = FPowmTable::init_precomp(&b, &p, 4, 8).unwrap();
+//! let e = ZeroizingInteger::from(Integer::from(4));
+//! assert_eq!(tab.fpowm(&e), b.pow_mod(&e, &p).unwrap());
+//! ```
+
+use rug::Integer;
+use std::ops::Deref;
+use zeroize::Zeroize;
+
+/// Overwrite the value of the integer with zeros
+///
+/// All the allocated limbs are overwritten, and the value of the integer is zero after the call
+pub fn zeroize_integer(value: &mut Integer) {
+    unsafe {
+        let raw = value.as_raw_mut();
+        let alloc = usize::try_from((*raw).alloc).unwrap_or(0);
+        std::slice::from_raw_parts_mut((*raw).d.as_ptr(), alloc).zeroize();
+        (*raw).size = 0;
+    }
+}
+
+/// Integer that is wiped from memory when it is dropped
+///
+/// It dereferences to [Integer], so that it can be used directly as exponent
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ZeroizingInteger(Integer);
+
+impl ZeroizingInteger {
+    /// Wrap the integer
+    pub fn new(value: Integer) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Integer> for ZeroizingInteger {
+    fn from(value: Integer) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Deref for ZeroizingInteger {
+    type Target = Integer;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Zeroize for ZeroizingInteger {
+    fn zeroize(&mut self) {
+        zeroize_integer(&mut self.0)
+    }
+}
+
+impl Drop for ZeroizingInteger {
+    fn drop(&mut self) {
+        self.zeroize()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_zeroize_integer() {
+        let mut value = Integer::from(0x1234_5678_9abc_def0u64) << 200;
+        zeroize_integer(&mut value);
+        assert_eq!(value, 0);
+    }
+
+    #[test]
+    fn test_zeroizing_integer() {
+        let mut value = ZeroizingInteger::from(Integer::from(42));
+        assert_eq!(*value, 42);
+        value.zeroize();
+        assert_eq!(*value, 0);
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Sieve of Eratosthenes for the small primes, used by the prefilters of the primality tests, the
+//! wheels and the primorials
+//!
+//! The primes smaller than [SIEVE_BOUND] are sieved once and kept for the rest of the program. The
+//! larger bounds are sieved by segments.
+//! ```
+//! use rug_gmpmee::small_primes::{primes_below, primes_in_segment};
+//! assert_eq!(&primes_below(20)[..], [2, 3, 5, 7, 11, 13, 17, 19]);
+//! assert_eq!(primes_below(1 << 20).len(), 82025);
+//! let primes = primes_in_segment(1_000_000, 1_000_040);
+//! assert_eq!(primes, [1_000_003, 1_000_033, 1_000_037, 1_000_039]);
+//! ```
+
+use std::{borrow::Cow, sync::OnceLock};
+
+/// Bound of the table of the small primes (see [table])
+pub const SIEVE_BOUND: u32 = 1 << 16;
+
+/// Number of integers sieved at once by [primes_in_segment]
+const SEGMENT_LEN: u64 = 1 << 16;
+
+static TABLE: OnceLock<Vec<u32>> = OnceLock::new();
+
+/// Primes smaller than [SIEVE_BOUND], sieved at the first call
+pub fn table() -> &'static [u32] {
+    TABLE.get_or_init(|| {
+        let bound = SIEVE_BOUND as usize;
+        let mut is_composite = vec![false; bound];
+        let mut primes = vec![];
+        for i in 2..bound {
+            if !is_composite[i] {
+                primes.push(i as u32);
+                (i * i..bound)
+                    .step_by(i)
+                    .for_each(|j| is_composite[j] = true);
+            }
+        }
+        primes
+    })
+}
+
+/// Primes smaller than `n`
+///
+/// The primes are borrowed from the [table] for `n <= SIEVE_BOUND`, and sieved by segments
+/// otherwise.
+pub fn primes_below(n: u32) -> Cow<'static, [u32]> {
+    match n <= SIEVE_BOUND {
+        true => Cow::Borrowed(&table()[..table().partition_point(|p| *p < n)]),
+        false => Cow::Owned(
+            primes_in_segment(2, u64::from(n))
+                .into_iter()
+                .map(|p| p as u32)
+                .collect(),
+        ),
+    }
+}
+
+/// Primes `p` with `lo <= p < hi`, sieved by segments of `2^16` integers
+///
+/// The segments are sieved with the primes up to `sqrt(hi)`, themselves sieved if they exceed the
+/// [table].
+pub fn primes_in_segment(lo: u64, hi: u64) -> Vec<u64> {
+    let lo = lo.max(2);
+    if lo >= hi {
+        return vec![];
+    }
+    let root = (hi - 1).isqrt() + 1;
+    let base: Vec<u64> = match root <= u64::from(SIEVE_BOUND) {
+        true => table().iter().map(|p| u64::from(*p)).collect(),
+        false => primes_in_segment(2, root + 1),
+    };
+    let mut primes = vec![];
+    let mut start = lo;
+    while start < hi {
+        let end = hi.min(start.saturating_add(SEGMENT_LEN));
+        let mut is_composite = vec![false; (end - start) as usize];
+        for &p in base.iter().take_while(|p| **p * **p < end) {
+            let first = (p * p).max(start.div_ceil(p) * p);
+            (first..end)
+                .step_by(p as usize)
+                .for_each(|m| is_composite[(m - start) as usize] = true);
+        }
+        primes.extend((start..end).filter(|m| !is_composite[(m - start) as usize]));
+        start = end;
+    }
+    primes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rug::{Integer, integer::IsPrime};
+
+    #[test]
+    fn test_sieve() {
+        assert_eq!(table().len(), 6542);
+        assert!(primes_below(0).is_empty() && primes_below(2).is_empty());
+        assert_eq!(&primes_below(3)[..], [2]);
+        assert!(matches!(primes_below(SIEVE_BOUND), Cow::Borrowed(p) if p == table()));
+        let primes = primes_below(200_000);
+        assert_eq!(primes.len(), 17984);
+        assert_eq!(&primes[..table().len()], table());
+        assert_eq!(
+            primes_in_segment(0, 30),
+            [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]
+        );
+        assert!(primes_in_segment(100, 100).is_empty() && primes_in_segment(100, 10).is_empty());
+        let (lo, hi) = (1u64 << 40, (1u64 << 40) + 100_000);
+        let expected: Vec<u64> = (lo..hi)
+            .filter(|n| Integer::from(*n).is_probably_prime(30) != IsPrime::No)
+            .collect();
+        assert_eq!(primes_in_segment(lo, hi), expected);
+    }
+}
@@ -15,9 +15,14 @@
 // <https://www.gnu.org/licenses/>.
 
 //! Module to wrap the function `gmpmee_spowm`
-use crate::{GmpMEEError, usize_to_size_t_type};
-use gmpmee_sys::gmpmee_spowm;
-use rug::Integer;
+use crate::{
+    GmpMEEError,
+    sys::gmpmee_spowm,
+    telemetry::{self, Batch, Exponentiation},
+    trace::timed_span,
+    usize_to_size_t_type,
+};
+use rug::{Integer, rand::RandState};
 use thiserror::Error;
 
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
@@ -38,6 +43,11 @@ pub fn spowm(
     exponents: &[Integer],
     modulus: &Integer,
 ) -> Result<Integer, GmpMEEError> {
+    timed_span!(
+        "spowm",
+        count = bases.len(),
+        modulus_bits = modulus.significant_bits()
+    );
     if bases.len() != exponents.len() {
         return Err(SPownError::NotSameLen {
             base: bases.len(),
@@ -45,6 +55,8 @@ pub fn spowm(
         }
         .into());
     }
+    telemetry::record_exponentiations(Exponentiation::Spowm, bases.len());
+    telemetry::record_batch(Batch::Spowm, bases.len());
     let bases_raw = bases.iter().map(|b| b.as_raw()).collect::<Vec<_>>();
     let exponents_raw = exponents.iter().map(|b| b.as_raw()).collect::<Vec<_>>();
     let mut res = Integer::new();
@@ -64,6 +76,119 @@ pub fn spowm(
     Ok(res)
 }
 
+/// Bit length of the random weights of the batch verifications
+///
+/// A batch containing an invalid claim is accepted with a probability of at most
+/// `2^-WEIGHT_BITS` (see [verify_powers], [crate::schnorr] and [crate::chaum_pedersen]).
+pub const WEIGHT_BITS: u32 = 128;
+
+/// Claim `base^exponent = expected mod modulus` checked by [verify_powers]
+pub type PowerClaim = (Integer, Integer, Integer);
+
+/// `value mod modulus` in `[0, modulus[`
+pub(crate) fn reduce(value: &Integer, modulus: &Integer) -> Integer {
+    let res = Integer::from(value % modulus);
+    match res < 0 {
+        true => res + modulus,
+        false => res,
+    }
+}
+
+/// Check the claim directly with `pow_mod`
+fn verify_power((base, exponent, expected): &PowerClaim, modulus: &Integer) -> bool {
+    base.pow_mod_ref(exponent, modulus)
+        .is_some_and(|pow| Integer::from(pow) == reduce(expected, modulus))
+}
+
+/// Check the claims of the indices together with the weights, and split the indices in two halves
+/// if the aggregate check fails, until the failing claims are isolated
+///
+/// Each index comes with the inverse of the expected value of its claim, so that the aggregate
+/// check is a single multi-exponentiation equal to 1.
+fn failing_claims(
+    claims: &[PowerClaim],
+    indices: &[(usize, Integer)],
+    weights: &[Integer],
+    modulus: &Integer,
+    failing: &mut Vec<usize>,
+) -> Result<(), GmpMEEError> {
+    match indices {
+        [] => return Ok(()),
+        [(index, _)] => {
+            if !verify_power(&claims[*index], modulus) {
+                failing.push(*index);
+            }
+            return Ok(());
+        }
+        _ => (),
+    }
+    let (bases, exponents): (Vec<_>, Vec<_>) = indices
+        .iter()
+        .flat_map(|(i, inverse)| {
+            let (base, exponent, _) = &claims[*i];
+            [
+                (base.clone(), Integer::from(exponent * &weights[*i])),
+                (inverse.clone(), weights[*i].clone()),
+            ]
+        })
+        .unzip();
+    if spowm(&bases, &exponents, modulus)? == 1 {
+        return Ok(());
+    }
+    let (left, right) = indices.split_at(indices.len() / 2);
+    failing_claims(claims, left, weights, modulus, failing)?;
+    failing_claims(claims, right, weights, modulus, failing)
+}
+
+/// Check all the claims `base_i^exponent_i = expected_i mod modulus` together, and return the
+/// indices of the failing claims in increasing order (empty if all the claims hold)
+///
+/// With random weights `w_i` of [WEIGHT_BITS] bits drawn from `rand`, the aggregate check is
+/// `prod base_i^(exponent_i*w_i) * expected_i^(-w_i) = 1 mod modulus`, with a single
+/// multi-exponentiation (see [spowm]) of the bases and of the inverses of the expected values. If
+/// it fails, the claims are split in two halves checked in the same way, down to the single claims
+/// that are checked with `pow_mod`. The claims with a negative exponent or with an expected value
+/// not invertible are checked one by one.
+///
+/// The aggregate check is sound for the elements of a group of prime order. For other moduli, the
+/// components of small order of the bases and of the expected values may cancel out, and a failing
+/// claim is then missed with a probability that is not negligible.
+/// ```
+/// use rug::{Integer, rand::RandState};
+/// use rug_gmpmee::spown::verify_powers;
+/// let p = Integer::from(23);
+/// let claims = [(4, 3, 18), (9, 2, 12), (4, 5, 1), (2, 11, 1)]
+///     .map(|(b, e, r)| (Integer::from(b), Integer::from(e), Integer::from(r)));
+/// assert_eq!(verify_powers(&claims, &p, &mut RandState::new()).unwrap(), [2]);
+/// ```
+pub fn verify_powers(
+    claims: &[PowerClaim],
+    modulus: &Integer,
+    rand: &mut RandState<'_>,
+) -> Result<Vec<usize>, GmpMEEError> {
+    timed_span!(
+        "verify_powers",
+        count = claims.len(),
+        modulus_bits = modulus.significant_bits()
+    );
+    let weights = claims
+        .iter()
+        .map(|_| Integer::from(Integer::random_bits(WEIGHT_BITS, rand)))
+        .collect::<Vec<_>>();
+    let mut batched = vec![];
+    let mut failing = vec![];
+    for (i, claim) in claims.iter().enumerate() {
+        match claim.2.invert_ref(modulus).map(Integer::from) {
+            Some(inverse) if claim.1 >= 0 => batched.push((i, inverse)),
+            _ if !verify_power(claim, modulus) => failing.push(i),
+            _ => (),
+        }
+    }
+    failing_claims(claims, &batched, &weights, modulus, &mut failing)?;
+    failing.sort_unstable();
+    Ok(failing)
+}
+
 #[cfg(test)]
 mod test {
     use std::time::SystemTime;
@@ -119,6 +244,40 @@ mod test {
         assert_eq!(res, expected_spown(&bases, &exponents, &modulus))
     }
 
+    #[test]
+    fn test_verify_powers() {
+        let mut rand = RandState::new();
+        let p = Integer::from(1019);
+        let q = Integer::from(509);
+        let g = Integer::from(4);
+        let mut claims = (0..40)
+            .map(|_| {
+                let x = Integer::from(q.random_below_ref(&mut rand));
+                let b = g.clone().pow_mod(&x, &p).unwrap();
+                let e = Integer::from(Integer::random_bits(64, &mut rand));
+                let r = Integer::from(b.pow_mod_ref(&e, &p).unwrap());
+                (b, e, r)
+            })
+            .collect::<Vec<_>>();
+        assert!(verify_powers(&claims, &p, &mut rand).unwrap().is_empty());
+        assert!(verify_powers(&[], &p, &mut rand).unwrap().is_empty());
+        claims[3].2 += &p;
+        claims[5].1 = -claims[5].1.clone();
+        claims[5].2 = claims[5].2.clone().invert(&p).unwrap();
+        // 0 is not invertible, the claim is checked alone
+        claims[6] = (Integer::new(), Integer::from(3), p.clone());
+        assert!(verify_powers(&claims, &p, &mut rand).unwrap().is_empty());
+        for i in [0, 17, 18, 39] {
+            claims[i].2 = claims[i].2.clone() * &g % &p;
+        }
+        claims[5].2 += 1;
+        claims[6].2 += 1;
+        assert_eq!(
+            verify_powers(&claims, &p, &mut rand).unwrap(),
+            [0, 5, 6, 17, 18, 39]
+        );
+    }
+
     #[test]
     fn test_performance() {
         let p =  Integer::from(Integer::parse_radix(
@@ -0,0 +1,395 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Standard MODP groups of [RFC 3526](https://www.rfc-editor.org/rfc/rfc3526) and
+//! [RFC 5114](https://www.rfc-editor.org/rfc/rfc5114), with precomputed tables for their generators
+//!
+//! With the feature `cache`, the tables are calculated the first time they are used, with
+//! [crate::fpowm::FPowmTable::for_subgroup], and kept for the rest of the program
+//! ```
+//! use rug::Integer;
+//! use rug_gmpmee::standard_groups::StandardGroup;
+//! let group = StandardGroup::Modp2048S256;
+//! let e = Integer::from(123456789);
+//! let tab = group.table().unwrap();
+//! assert_eq!(
+//!     tab.fpowm(&e),
+//!     group.generator().clone().pow_mod(&e, group.modulus()).unwrap()
+//! );
+//! ```
+//!
+//! For the groups of RFC 3526, the generator is 2, which generates the subgroup of the quadratic
+//! residues of order `(p-1)/2`.
+//!
+//! The groups can be retrieved by name or by identifier (see [StandardGroup::from_name]), so that a
+//! configuration file can refer to `modp-3072` instead of embedding the parameters. The parameters
+//! are then validated with `crate::groups::GroupParams::standard`.
+//! ```
+//! use rug_gmpmee::standard_groups::StandardGroup;
+//! let group: StandardGroup = "modp-3072".parse().unwrap();
+//! assert_eq!(group, StandardGroup::Modp3072);
+//! assert_eq!(StandardGroup::from_name("rfc3526.15"), Some(group));
+//! ```
+
+#[cfg(feature = "cache")]
+use crate::fpowm::FPowmTable;
+use crate::{GmpMEEError, groups::GroupError};
+use rug::Integer;
+use std::{fmt, str::FromStr, sync::OnceLock};
+
+/// Standard MODP groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StandardGroup {
+    /// 2048-bit MODP group of RFC 3526 (group id 14)
+    Modp2048,
+    /// 3072-bit MODP group of RFC 3526 (group id 15)
+    Modp3072,
+    /// 4096-bit MODP group of RFC 3526 (group id 16)
+    Modp4096,
+    /// 1024-bit MODP group with 160-bit prime order subgroup of RFC 5114
+    Modp1024S160,
+    /// 2048-bit MODP group with 224-bit prime order subgroup of RFC 5114
+    Modp2048S224,
+    /// 2048-bit MODP group with 256-bit prime order subgroup of RFC 5114
+    Modp2048S256,
+}
+
+/// Modulus, order of the subgroup and generator of a group
+struct GroupParameters {
+    modulus: Integer,
+    order: Integer,
+    generator: Integer,
+}
+
+const NB_GROUPS: usize = 6;
+
+static PARAMETERS: [OnceLock<GroupParameters>; NB_GROUPS] = [const { OnceLock::new() }; NB_GROUPS];
+#[cfg(feature = "cache")]
+static TABLES: [OnceLock<FPowmTable>; NB_GROUPS] = [const { OnceLock::new() }; NB_GROUPS];
+
+fn from_hex(hex: &str) -> Integer {
+    Integer::from_str_radix(hex, 16).unwrap()
+}
+
+impl StandardGroup {
+    /// All the standard groups
+    pub const ALL: [StandardGroup; NB_GROUPS] = [
+        StandardGroup::Modp2048,
+        StandardGroup::Modp3072,
+        StandardGroup::Modp4096,
+        StandardGroup::Modp1024S160,
+        StandardGroup::Modp2048S224,
+        StandardGroup::Modp2048S256,
+    ];
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// Name of the group, e.g. `modp-2048` or `modp-2048-256` for the subgroup of 256 bits
+    pub fn name(&self) -> &'static str {
+        match self {
+            StandardGroup::Modp2048 => "modp-2048",
+            StandardGroup::Modp3072 => "modp-3072",
+            StandardGroup::Modp4096 => "modp-4096",
+            StandardGroup::Modp1024S160 => "modp-1024-160",
+            StandardGroup::Modp2048S224 => "modp-2048-224",
+            StandardGroup::Modp2048S256 => "modp-2048-256",
+        }
+    }
+
+    /// Identifier of the group made of the RFC and of the group id (RFC 3526) or of the section
+    /// (RFC 5114), e.g. `rfc3526.14` or `rfc5114.2.3`
+    pub fn identifier(&self) -> &'static str {
+        match self {
+            StandardGroup::Modp2048 => "rfc3526.14",
+            StandardGroup::Modp3072 => "rfc3526.15",
+            StandardGroup::Modp4096 => "rfc3526.16",
+            StandardGroup::Modp1024S160 => "rfc5114.2.1",
+            StandardGroup::Modp2048S224 => "rfc5114.2.2",
+            StandardGroup::Modp2048S256 => "rfc5114.2.3",
+        }
+    }
+
+    /// Group with the given name or identifier, ignoring the case, or `None` if there is none
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|group| {
+            group.name().eq_ignore_ascii_case(name) || group.identifier().eq_ignore_ascii_case(name)
+        })
+    }
+
+    fn parameters(&self) -> &'static GroupParameters {
+        PARAMETERS[self.index()].get_or_init(|| {
+            let safe_prime = |hex: &str| {
+                let modulus = from_hex(hex);
+                let order = Integer::from(&modulus - 1u32) >> 1u32;
+                GroupParameters {
+                    modulus,
+                    order,
+                    generator: Integer::from(2),
+                }
+            };
+            let subgroup = |p: &str, q: &str, g: &str| GroupParameters {
+                modulus: from_hex(p),
+                order: from_hex(q),
+                generator: from_hex(g),
+            };
+            match self {
+                StandardGroup::Modp2048 => safe_prime(MODP_2048_P),
+                StandardGroup::Modp3072 => safe_prime(MODP_3072_P),
+                StandardGroup::Modp4096 => safe_prime(MODP_4096_P),
+                StandardGroup::Modp1024S160 => {
+                    subgroup(RFC5114_1024_160_P, RFC5114_1024_160_Q, RFC5114_1024_160_G)
+                }
+                StandardGroup::Modp2048S224 => {
+                    subgroup(RFC5114_2048_224_P, RFC5114_2048_224_Q, RFC5114_2048_224_G)
+                }
+                StandardGroup::Modp2048S256 => {
+                    subgroup(RFC5114_2048_256_P, RFC5114_2048_256_Q, RFC5114_2048_256_G)
+                }
+            }
+        })
+    }
+
+    /// Prime modulus of the group
+    pub fn modulus(&self) -> &'static Integer {
+        &self.parameters().modulus
+    }
+
+    /// Prime order of the subgroup generated by the generator
+    pub fn order(&self) -> &'static Integer {
+        &self.parameters().order
+    }
+
+    /// Generator of the subgroup
+    pub fn generator(&self) -> &'static Integer {
+        &self.parameters().generator
+    }
+
+    /// Precomputed table for the generator, calculated at the first call (only with the feature
+    /// `cache`)
+    #[cfg(feature = "cache")]
+    pub fn table(&self) -> Result<&'static FPowmTable, GmpMEEError> {
+        let cell = &TABLES[self.index()];
+        if let Some(table) = cell.get() {
+            return Ok(table);
+        }
+        let table = FPowmTable::for_subgroup(self.generator(), self.modulus(), self.order())?;
+        Ok(cell.get_or_init(|| table))
+    }
+}
+
+impl fmt::Display for StandardGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for StandardGroup {
+    type Err = GmpMEEError;
+
+    /// Group with the given name or identifier (see [StandardGroup::from_name])
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::from_name(name).ok_or_else(|| {
+            GroupError::UnknownGroup {
+                name: name.to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+/// Prime of the 2048-bit MODP group of RFC 3526
+const MODP_2048_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AACAA68FFFFFFFFFFFFFFFF",
+);
+
+/// Prime of the 3072-bit MODP group of RFC 3526
+const MODP_3072_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+    "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+    "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+    "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+    "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A93AD2CAFFFFFFFFFFFFFFFF",
+);
+
+/// Prime of the 4096-bit MODP group of RFC 3526
+const MODP_4096_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+    "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+    "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+    "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+    "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7",
+    "88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8",
+    "DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2",
+    "233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9",
+    "93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C934063199FFFFFFFFFFFFFFFF",
+);
+
+/// Prime of the 1024-bit MODP group with 160-bit subgroup (RFC 5114, section 2.1)
+const RFC5114_1024_160_P: &str = concat!(
+    "B10B8F96A080E01DDE92DE5EAE5D54EC52C99FBCFB06A3C69A6A9DCA52D23B61",
+    "6073E28675A23D189838EF1E2EE652C013ECB4AEA906112324975C3CD49B83BF",
+    "ACCBDD7D90C4BD7098488E9C219A73724EFFD6FAE5644738FAA31A4FF55BCCC0",
+    "A151AF5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371",
+);
+
+/// Generator of the 1024-bit MODP group with 160-bit subgroup (RFC 5114, section 2.1)
+const RFC5114_1024_160_G: &str = concat!(
+    "A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D31",
+    "266FEA1E5C41564B777E690F5504F213160217B4B01B886A5E91547F9E2749F4",
+    "D7FBD7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28A",
+    "D662A4D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5",
+);
+
+/// Order of the subgroup of the 1024-bit MODP group with 160-bit subgroup (RFC 5114, section 2.1)
+const RFC5114_1024_160_Q: &str = "F518AA8781A8DF278ABA4E7D64B7CB9D49462353";
+
+/// Prime of the 2048-bit MODP group with 224-bit subgroup (RFC 5114, section 2.2)
+const RFC5114_2048_224_P: &str = concat!(
+    "AD107E1E9123A9D0D660FAA79559C51FA20D64E5683B9FD1B54B1597B61D0A75",
+    "E6FA141DF95A56DBAF9A3C407BA1DF15EB3D688A309C180E1DE6B85A1274A0A6",
+    "6D3F8152AD6AC2129037C9EDEFDA4DF8D91E8FEF55B7394B7AD5B7D0B6C12207",
+    "C9F98D11ED34DBF6C6BA0B2C8BBC27BE6A00E0A0B9C49708B3BF8A3170918836",
+    "81286130BC8985DB1602E714415D9330278273C7DE31EFDC7310F7121FD5A074",
+    "15987D9ADC0A486DCDF93ACC44328387315D75E198C641A480CD86A1B9E587E8",
+    "BE60E69CC928B2B9C52172E413042E9B23F10B0E16E79763C9B53DCF4BA80A29",
+    "E3FB73C16B8E75B97EF363E2FFA31F71CF9DE5384E71B81C0AC4DFFE0C10E64F",
+);
+
+/// Generator of the 2048-bit MODP group with 224-bit subgroup (RFC 5114, section 2.2)
+const RFC5114_2048_224_G: &str = concat!(
+    "AC4032EF4F2D9AE39DF30B5C8FFDAC506CDEBE7B89998CAF74866A08CFE4FFE3",
+    "A6824A4E10B9A6F0DD921F01A70C4AFAAB739D7700C29F52C57DB17C620A8652",
+    "BE5E9001A8D66AD7C17669101999024AF4D027275AC1348BB8A762D0521BC98A",
+    "E247150422EA1ED409939D54DA7460CDB5F6C6B250717CBEF180EB34118E98D1",
+    "19529A45D6F834566E3025E316A330EFBB77A86F0C1AB15B051AE3D428C8F8AC",
+    "B70A8137150B8EEB10E183EDD19963DDD9E263E4770589EF6AA21E7F5F2FF381",
+    "B539CCE3409D13CD566AFBB48D6C019181E1BCFE94B30269EDFE72FE9B6AA4BD",
+    "7B5A0F1C71CFFF4C19C418E1F6EC017981BC087F2A7065B384B890D3191F2BFA",
+);
+
+/// Order of the subgroup of the 2048-bit MODP group with 224-bit subgroup (RFC 5114, section 2.2)
+const RFC5114_2048_224_Q: &str = "801C0D34C58D93FE997177101F80535A4738CEBCBF389A99B36371EB";
+
+/// Prime of the 2048-bit MODP group with 256-bit subgroup (RFC 5114, section 2.3)
+const RFC5114_2048_256_P: &str = concat!(
+    "87A8E61DB4B6663CFFBBD19C651959998CEEF608660DD0F25D2CEED4435E3B00",
+    "E00DF8F1D61957D4FAF7DF4561B2AA3016C3D91134096FAA3BF4296D830E9A7C",
+    "209E0C6497517ABD5A8A9D306BCF67ED91F9E6725B4758C022E0B1EF4275BF7B",
+    "6C5BFC11D45F9088B941F54EB1E59BB8BC39A0BF12307F5C4FDB70C581B23F76",
+    "B63ACAE1CAA6B7902D52526735488A0EF13C6D9A51BFA4AB3AD8347796524D8E",
+    "F6A167B5A41825D967E144E5140564251CCACB83E6B486F6B3CA3F7971506026",
+    "C0B857F689962856DED4010ABD0BE621C3A3960A54E710C375F26375D7014103",
+    "A4B54330C198AF126116D2276E11715F693877FAD7EF09CADB094AE91E1A1597",
+);
+
+/// Generator of the 2048-bit MODP group with 256-bit subgroup (RFC 5114, section 2.3)
+const RFC5114_2048_256_G: &str = concat!(
+    "3FB32C9B73134D0B2E77506660EDBD484CA7B18F21EF205407F4793A1A0BA125",
+    "10DBC15077BE463FFF4FED4AAC0BB555BE3A6C1B0C6B47B1BC3773BF7E8C6F62",
+    "901228F8C28CBB18A55AE31341000A650196F931C77A57F2DDF463E5E9EC144B",
+    "777DE62AAAB8A8628AC376D282D6ED3864E67982428EBC831D14348F6F2F9193",
+    "B5045AF2767164E1DFC967C1FB3F2E55A4BD1BFFE83B9C80D052B985D182EA0A",
+    "DB2A3B7313D3FE14C8484B1E052588B9B7D2BBD2DF016199ECD06E1557CD0915",
+    "B3353BBB64E0EC377FD028370DF92B52C7891428CDC67EB6184B523D1DB246C3",
+    "2F63078490F00EF8D647D148D47954515E2327CFEF98C582664B4C0F6CC41659",
+);
+
+/// Order of the subgroup of the 2048-bit MODP group with 256-bit subgroup (RFC 5114, section 2.3)
+const RFC5114_2048_256_Q: &str = "8CF83642A709A097B447997640129DA299B1A47D1EB3750BA308B0FE64F5FBD3";
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rug::integer::IsPrime;
+
+    #[test]
+    fn test_parameters() {
+        for group in StandardGroup::ALL {
+            let p = group.modulus();
+            let q = group.order();
+            let g = group.generator();
+            assert_ne!(p.is_probably_prime(20), IsPrime::No);
+            assert_ne!(q.is_probably_prime(20), IsPrime::No);
+            assert!(Integer::from(p - 1u32).is_divisible(q));
+            assert_eq!(g.clone().pow_mod(q, p).unwrap(), 1);
+        }
+        assert_eq!(StandardGroup::Modp2048.modulus().significant_bits(), 2048);
+        assert_eq!(StandardGroup::Modp3072.modulus().significant_bits(), 3072);
+        assert_eq!(StandardGroup::Modp4096.modulus().significant_bits(), 4096);
+        assert_eq!(StandardGroup::Modp1024S160.order().significant_bits(), 160);
+        assert_eq!(StandardGroup::Modp2048S224.order().significant_bits(), 224);
+        assert_eq!(StandardGroup::Modp2048S256.order().significant_bits(), 256);
+    }
+
+    #[test]
+    fn test_names() {
+        for group in StandardGroup::ALL {
+            assert_eq!(StandardGroup::from_name(group.name()), Some(group));
+            assert_eq!(StandardGroup::from_name(group.identifier()), Some(group));
+            assert_eq!(group.to_string().parse::<StandardGroup>(), Ok(group));
+            let upper = group.name().to_uppercase();
+            assert_eq!(upper.parse::<StandardGroup>(), Ok(group));
+        }
+        assert_eq!(StandardGroup::from_name("modp-1536"), None);
+        assert_eq!(
+            "rfc3526.5".parse::<StandardGroup>(),
+            Err(GroupError::UnknownGroup {
+                name: "rfc3526.5".to_string()
+            }
+            .into())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cache")]
+    fn test_table() {
+        let group = StandardGroup::Modp1024S160;
+        let tab = group.table().unwrap();
+        assert!(std::ptr::eq(tab, group.table().unwrap()));
+        let e = Integer::from(987654321);
+        assert_eq!(
+            tab.fpowm(&e),
+            group
+                .generator()
+                .clone()
+                .pow_mod(&e, group.modulus())
+                .unwrap()
+        );
+    }
+}
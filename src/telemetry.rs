@@ -0,0 +1,322 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Metrics of the operations published through the facade of the crate `metrics`, emitted only
+//! with the feature `metrics`
+//!
+//! The metrics are:
+//! - `rug_gmpmee_exponentiations_total` (counter, label `operation`): number of exponentiations,
+//!   one for each call of `fpowm`, `fpowm_inv` and `fpowm_zeroizing`, and one for each base of
+//!   `spowm`
+//! - `rug_gmpmee_batch_size` (histogram, label `operation`): number of elements of each `spowm`
+//!   and of each batch of ElGamal or Pedersen
+//! - `rug_gmpmee_precomp_seconds` (histogram): duration of the precomputations of the tables
+//! - `rug_gmpmee_cache_lookups_total` (counter, label `result` with `hit` or `miss`): lookups in
+//!   the caches of tables
+//!
+//! The handles of the metrics are registered once, at the first operation, with the recorder
+//! installed at that time: the application must install its recorder (e.g. the Prometheus
+//! exporter) before using the crate. Without the feature, the functions do nothing.
+
+#[cfg(feature = "metrics")]
+use metrics::{Counter, Histogram, counter, histogram};
+#[cfg(feature = "metrics")]
+use std::{sync::OnceLock, time::Instant};
+
+/// Operation counted in `rug_gmpmee_exponentiations_total`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Exponentiation {
+    Fpowm,
+    FpowmInv,
+    Spowm,
+    #[cfg(feature = "zeroize")]
+    FpowmZeroizing,
+}
+
+/// Operation whose batches are recorded in `rug_gmpmee_batch_size`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Batch {
+    Spowm,
+    ElGamalEncrypt,
+    ElGamalDecrypt,
+    ElGamalReencrypt,
+    ElGamalReencryptMix,
+    PedersenCommit,
+}
+
+#[cfg(feature = "metrics")]
+impl Exponentiation {
+    /// All the operations, in the order of their declaration
+    const ALL: &[Self] = &[
+        Self::Fpowm,
+        Self::FpowmInv,
+        Self::Spowm,
+        #[cfg(feature = "zeroize")]
+        Self::FpowmZeroizing,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Fpowm => "fpowm",
+            Self::FpowmInv => "fpowm_inv",
+            Self::Spowm => "spowm",
+            #[cfg(feature = "zeroize")]
+            Self::FpowmZeroizing => "fpowm_zeroizing",
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Batch {
+    /// All the operations, in the order of their declaration
+    const ALL: &[Self] = &[
+        Self::Spowm,
+        Self::ElGamalEncrypt,
+        Self::ElGamalDecrypt,
+        Self::ElGamalReencrypt,
+        Self::ElGamalReencryptMix,
+        Self::PedersenCommit,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Spowm => "spowm",
+            Self::ElGamalEncrypt => "elgamal_encrypt",
+            Self::ElGamalDecrypt => "elgamal_decrypt",
+            Self::ElGamalReencrypt => "elgamal_reencrypt",
+            Self::ElGamalReencryptMix => "elgamal_reencrypt_mix",
+            Self::PedersenCommit => "pedersen_commit",
+        }
+    }
+}
+
+/// Handles of the metrics, indexed by the operations
+#[cfg(feature = "metrics")]
+struct Telemetry {
+    exponentiations: Vec<Counter>,
+    batch_sizes: Vec<Histogram>,
+    precomp_seconds: Histogram,
+    #[cfg(feature = "cache")]
+    cache_hits: Counter,
+    #[cfg(feature = "cache")]
+    cache_misses: Counter,
+}
+
+#[cfg(feature = "metrics")]
+impl Telemetry {
+    /// Register the handles with the current recorder
+    fn register() -> Self {
+        Self {
+            exponentiations: Exponentiation::ALL
+                .iter()
+                .map(|op| counter!("rug_gmpmee_exponentiations_total", "operation" => op.label()))
+                .collect(),
+            batch_sizes: Batch::ALL
+                .iter()
+                .map(|op| histogram!("rug_gmpmee_batch_size", "operation" => op.label()))
+                .collect(),
+            precomp_seconds: histogram!("rug_gmpmee_precomp_seconds"),
+            #[cfg(feature = "cache")]
+            cache_hits: counter!("rug_gmpmee_cache_lookups_total", "result" => "hit"),
+            #[cfg(feature = "cache")]
+            cache_misses: counter!("rug_gmpmee_cache_lookups_total", "result" => "miss"),
+        }
+    }
+
+    fn record_exponentiations(&self, operation: Exponentiation, count: usize) {
+        self.exponentiations[operation as usize].increment(count as u64);
+    }
+
+    fn record_batch(&self, operation: Batch, size: usize) {
+        self.batch_sizes[operation as usize].record(size as f64);
+    }
+
+    fn record_precomp<T>(&self, precomp: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let res = precomp();
+        self.precomp_seconds.record(start.elapsed().as_secs_f64());
+        res
+    }
+
+    #[cfg(feature = "cache")]
+    fn record_cache_lookup(&self, hit: bool) {
+        match hit {
+            true => self.cache_hits.increment(1),
+            false => self.cache_misses.increment(1),
+        }
+    }
+}
+
+/// Handles registered at the first use
+#[cfg(feature = "metrics")]
+fn telemetry() -> &'static Telemetry {
+    static TELEMETRY: OnceLock<Telemetry> = OnceLock::new();
+    TELEMETRY.get_or_init(Telemetry::register)
+}
+
+/// Count the exponentiations of the operation
+#[cfg(feature = "metrics")]
+pub(crate) fn record_exponentiations(operation: Exponentiation, count: usize) {
+    telemetry().record_exponentiations(operation, count);
+}
+
+/// Record the size of a batch of the operation
+#[cfg(feature = "metrics")]
+pub(crate) fn record_batch(operation: Batch, size: usize) {
+    telemetry().record_batch(operation, size);
+}
+
+/// Run the precomputation of a table and record its duration
+#[cfg(feature = "metrics")]
+pub(crate) fn record_precomp<T>(precomp: impl FnOnce() -> T) -> T {
+    telemetry().record_precomp(precomp)
+}
+
+/// Count a lookup in a cache of tables
+#[cfg(all(feature = "metrics", feature = "cache"))]
+pub(crate) fn record_cache_lookup(hit: bool) {
+    telemetry().record_cache_lookup(hit);
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn record_exponentiations(_: Exponentiation, _: usize) {}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn record_batch(_: Batch, _: usize) {}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn record_precomp<T>(precomp: impl FnOnce() -> T) -> T {
+    precomp()
+}
+
+#[cfg(all(not(feature = "metrics"), feature = "cache"))]
+pub(crate) fn record_cache_lookup(_: bool) {}
+
+#[cfg(all(test, feature = "metrics"))]
+mod test {
+    use super::*;
+    use metrics::{
+        Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+        SharedString, Unit,
+    };
+    use std::sync::{Arc, Mutex};
+
+    /// Recorder keeping the values of the metrics with their key
+    #[derive(Default, Clone)]
+    struct Collector(Arc<Mutex<Vec<(String, f64)>>>);
+
+    struct Handle(Collector, Key);
+
+    impl Handle {
+        fn push(&self, value: f64) {
+            let labels = self
+                .1
+                .labels()
+                .map(|l| format!("{}={}", l.key(), l.value()))
+                .collect::<Vec<_>>();
+            let key = format!("{}{{{}}}", self.1.name(), labels.join(","));
+            self.0.0.lock().unwrap().push((key, value));
+        }
+    }
+
+    impl CounterFn for Handle {
+        fn increment(&self, value: u64) {
+            self.push(value as f64);
+        }
+
+        fn absolute(&self, value: u64) {
+            self.push(value as f64);
+        }
+    }
+
+    impl HistogramFn for Handle {
+        fn record(&self, value: f64) {
+            self.push(value);
+        }
+    }
+
+    impl Recorder for Collector {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(Arc::new(Handle(self.clone(), key.clone())))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(Arc::new(Handle(self.clone(), key.clone())))
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let collector = Collector::default();
+        let telemetry = metrics::with_local_recorder(&collector, Telemetry::register);
+        telemetry.record_exponentiations(Exponentiation::Spowm, 2);
+        telemetry.record_exponentiations(Exponentiation::FpowmInv, 1);
+        telemetry.record_batch(Batch::PedersenCommit, 3);
+        assert_eq!(telemetry.record_precomp(|| 7), 7);
+        let values = collector.0.lock().unwrap().clone();
+        assert_eq!(
+            values[..3],
+            [
+                (
+                    "rug_gmpmee_exponentiations_total{operation=spowm}".to_string(),
+                    2.0
+                ),
+                (
+                    "rug_gmpmee_exponentiations_total{operation=fpowm_inv}".to_string(),
+                    1.0
+                ),
+                (
+                    "rug_gmpmee_batch_size{operation=pedersen_commit}".to_string(),
+                    3.0
+                ),
+            ]
+        );
+        assert_eq!(values[3].0, "rug_gmpmee_precomp_seconds{}");
+        assert_eq!(values.len(), 4);
+    }
+
+    #[cfg(feature = "cache")]
+    #[test]
+    fn test_cache_lookups() {
+        let collector = Collector::default();
+        let telemetry = metrics::with_local_recorder(&collector, Telemetry::register);
+        telemetry.record_cache_lookup(true);
+        telemetry.record_cache_lookup(false);
+        assert_eq!(
+            collector.0.lock().unwrap().clone(),
+            [
+                (
+                    "rug_gmpmee_cache_lookups_total{result=hit}".to_string(),
+                    1.0
+                ),
+                (
+                    "rug_gmpmee_cache_lookups_total{result=miss}".to_string(),
+                    1.0
+                ),
+            ]
+        );
+    }
+}
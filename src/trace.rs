@@ -0,0 +1,142 @@
+// Copyright © 2024 Denis Morel
+
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU Lesser General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU Lesser General Public License and
+// a copy of the GNU General Public License along with this program. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Spans of the long-running operations, emitted only with the feature `tracing`
+//!
+//! The macro [timed_span] enters a span at the level DEBUG until the end of the enclosing block,
+//! and records the duration of the block in microseconds in the field `elapsed_us`. Without the
+//! feature, the macro expands to nothing and its fields are not evaluated.
+
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+/// Entered span recording its duration when it is dropped
+#[cfg(feature = "tracing")]
+pub(crate) struct TimedSpan {
+    span: tracing::span::EnteredSpan,
+    start: Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl TimedSpan {
+    pub(crate) fn new(span: tracing::Span) -> Self {
+        Self {
+            span: span.entered(),
+            start: Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for TimedSpan {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed().as_micros() as u64;
+        self.span.record("elapsed_us", elapsed);
+    }
+}
+
+/// Enter a span with the name and the fields until the end of the block (see the module)
+#[cfg(feature = "tracing")]
+macro_rules! timed_span {
+    ($name:literal $(, $($fields:tt)+)?) => {
+        let _timed_span = $crate::trace::TimedSpan::new(tracing::debug_span!(
+            $name,
+            elapsed_us = tracing::field::Empty
+            $(, $($fields)+)?
+        ));
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! timed_span {
+    ($($tokens:tt)*) => {};
+}
+
+pub(crate) use timed_span;
+
+#[cfg(all(test, feature = "tracing"))]
+mod test {
+    use crate::{miller_rabin::next_prime, spown::spowm};
+    use rug::Integer;
+    use std::sync::{Arc, Mutex};
+    use tracing::{
+        Event, Id, Metadata, Subscriber,
+        field::{Field, Visit},
+        span::{Attributes, Record},
+    };
+
+    /// Subscriber keeping the names of the spans and the recorded fields
+    #[derive(Default, Clone)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    struct FieldNames<'a>(&'a mut Vec<String>);
+
+    impl Visit for FieldNames<'_> {
+        fn record_debug(&mut self, field: &Field, _: &dyn std::fmt::Debug) {
+            self.0.push(field.name().to_string());
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut names = self.0.lock().unwrap();
+            names.push(span.metadata().name().to_string());
+            span.record(&mut FieldNames(&mut names));
+            Id::from_u64(names.len() as u64)
+        }
+
+        fn record(&self, _: &Id, values: &Record<'_>) {
+            values.record(&mut FieldNames(&mut self.0.lock().unwrap()));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_spans() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            spowm(
+                &[Integer::from(2)],
+                &[Integer::from(5)],
+                &Integer::from(1019),
+            )
+            .unwrap();
+        });
+        let names = recorder.0.lock().unwrap().clone();
+        assert_eq!(names, ["spowm", "count", "modulus_bits", "elapsed_us"]);
+    }
+
+    #[test]
+    fn test_prime_search_span() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            assert_eq!(next_prime(&Integer::from(1000)), 1009);
+        });
+        let names = recorder.0.lock().unwrap().clone();
+        assert_eq!(names, ["next_prime", "bits", "elapsed_us"]);
+    }
+}